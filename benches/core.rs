@@ -0,0 +1,169 @@
+//! Timings for the game logic, Criterion-style but hand-rolled, since Criterion isn't among the dependencies.
+//!
+//! ```text
+//! cargo bench
+//! cargo bench -- can_place
+//! ```
+//!
+//! Each benchmark warms up, then takes SAMPLES samples of as many iterations as fill SAMPLE_TIME;
+//! the per-iteration median and spread go to stdout, with the change since the last run, which is kept in
+//! the target directory.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use tetris_clone_rust::headless;
+use tetris_clone_rust::notation;
+use tetris_clone_rust::rng;
+use tetris_clone_rust::tetris;
+
+// HARDCODE
+const WARM_UP_TIME: Duration = Duration::from_millis(300);
+const SAMPLE_TIME: Duration = Duration::from_millis(50);
+const SAMPLES: usize = 30;
+
+const LAST_RUN_FILE: &str = concat!(env!("CARGO_TARGET_TMPDIR"), "/bench-core.txt");
+
+struct Timing {
+	name: &'static str,
+	/// Nanoseconds per iteration, fastest first.
+	samples: Vec<f64>,
+}
+
+impl Timing {
+	fn median(&self) -> f64 {
+		self.samples[self.samples.len() / 2]
+	}
+}
+
+/// Times `f`, which does one iteration per call.
+fn bench(name: &'static str, mut f: impl FnMut()) -> Timing {
+	let started = Instant::now();
+	let mut warm_up_iterations = 0_u64;
+	while started.elapsed() < WARM_UP_TIME {
+		f();
+		warm_up_iterations += 1;
+	}
+	let per_iteration = WARM_UP_TIME.as_secs_f64() / warm_up_iterations as f64;
+	let iterations = ((SAMPLE_TIME.as_secs_f64() / per_iteration) as u64).max(1);
+	let mut samples: Vec<f64> = (0 .. SAMPLES)
+		.map(|_| {
+			let started = Instant::now();
+			for _ in 0 .. iterations {
+				f();
+			}
+			started.elapsed().as_nanos() as f64 / iterations as f64
+		})
+		.collect();
+	samples.sort_by(f64::total_cmp);
+	Timing { name, samples }
+}
+
+fn format_ns(ns: f64) -> String {
+	match ns {
+		ns if ns >= 1e9 => format!("{:.3} s", ns / 1e9),
+		ns if ns >= 1e6 => format!("{:.3} ms", ns / 1e6),
+		ns if ns >= 1e3 => format!("{:.3} µs", ns / 1e3),
+		ns => format!("{ns:.1} ns"),
+	}
+}
+
+/// A fresh game as the headless runner starts them.
+fn new_game(seed: u64) -> tetris::GameState {
+	headless::Settings { width: 10, height: 20, ..Default::default() }.new_game_state(seed)
+}
+
+/// Four rows short of their left column, and a vertical I piece to finish them.
+fn tetris_ready() -> tetris::GameState {
+	let mut game_state = new_game(0);
+	let stack = ["10"; 16].into_iter().chain([".#########"; 4]).collect::<Vec<_>>().join("/");
+	notation::restore(&format!("{stack} #|#|#|# - - 0 0"), &mut game_state).expect("Position should fit a 10x20 board");
+	for _ in 0 .. game_state.cell_matrix_width {
+		game_state.apply(tetris::Action::MoveLeft);
+	}
+	game_state
+}
+
+fn main() {
+	// `cargo bench` passes --bench; anything else narrows down which ones run.
+	let filters: Vec<String> = std::env::args().skip(1).filter(|arg| !arg.starts_with("--")).collect();
+	let wanted = |name: &str| filters.is_empty() || filters.iter().any(|f| name.contains(f.as_str()));
+	let mut timings = Vec::new();
+
+	if wanted("can_place") {
+		let game_state = {
+			let mut g = new_game(1);
+			let stack = format!("{}/{}", ["10"; 12].join("/"), ["#.#.#.#.#.", ".#.#.#.#.#"].repeat(4).join("/"));
+			notation::restore(&format!("{stack} - - - 0 0"), &mut g)
+				.expect("Position should fit a 10x20 board");
+			g
+		};
+		let pieces = tetris::Piece::all_shapes();
+		timings.push(bench("can_place, every shape everywhere", || {
+			let mut fits = 0;
+			for p in pieces.iter() {
+				for y in 0 .. game_state.cell_matrix.len() as i32 {
+					for x in 0 .. game_state.cell_matrix_width as i32 {
+						fits += usize::from(game_state.can_place(black_box(p), (x, y)));
+					}
+				}
+			}
+			black_box(fits);
+		}));
+	}
+
+	if wanted("clear_rows") {
+		let ready = tetris_ready();
+		// clear_lines() is private, so this times the hard drop and the line clear delay's ticks around it too.
+		timings.push(bench("clear_rows, four at once", || {
+			let mut game_state = ready.clone();
+			game_state.apply(tetris::Action::HardDrop);
+			while game_state.rows_cleared == 0 {
+				game_state.tick();
+			}
+			black_box(game_state.drain_events().count());
+		}));
+	}
+
+	if wanted("generate_piece") {
+		let mut rng = rng::RandomNumberGenerator::new(2);
+		let mut randomizer = tetris::Randomizer::new(tetris::RandomizerStrategy::History { size: 4, rerolls: 4 });
+		timings.push(bench("generate_piece", || {
+			black_box(tetris::Piece::generate_new(&mut rng, &mut randomizer, tetris::PieceSet::Polyominoes));
+		}));
+	}
+
+	if wanted("bot_game") {
+		let settings = headless::Settings { games: 1, max_pieces: 200, ..Default::default() };
+		timings.push(bench("bot_game, greedy, 200 pieces", || {
+			let mut simulation = headless::simulation(&settings);
+			simulation.threads = 1;
+			simulation.run(|lane| lane.pieces_locked >= settings.max_pieces);
+			black_box(simulation.lanes[0].game_state.rows_cleared);
+		}));
+	}
+
+	let last_run = std::fs::read_to_string(LAST_RUN_FILE).unwrap_or_default();
+	let last_median = |name: &str| last_run.lines()
+		.filter_map(|line| line.split_once('\t'))
+		.find(|(n, _median)| *n == name)
+		.and_then(|(_name, median)| median.parse::<f64>().ok());
+	let mut this_run = String::new();
+	for timing in timings.iter() {
+		let median = timing.median();
+		let change = match last_median(timing.name) {
+			Some(last) => format!("{:+.1}% since last run", (median / last - 1.0) * 100.0),
+			None => String::new(),
+		};
+		println!("{:<36} {:>12}  [{} .. {}]  {change}",
+			timing.name, format_ns(median), format_ns(timing.samples[0]), format_ns(timing.samples[timing.samples.len() - 1]));
+		this_run += &format!("{}\t{median}\n", timing.name);
+	}
+	// Ones that didn't run this time keep their last result.
+	for line in last_run.lines().filter(|line| !timings.iter().any(|t| line.starts_with(&format!("{}\t", t.name)))) {
+		this_run += &format!("{line}\n");
+	}
+	if let Err(e) = std::fs::write(LAST_RUN_FILE, this_run) {
+		eprintln!("Could not save results to {LAST_RUN_FILE}: {e}");
+	}
+}
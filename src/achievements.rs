@@ -0,0 +1,148 @@
+use crate::log;
+use crate::profile;
+use crate::storage;
+use crate::tetris;
+
+const ACHIEVEMENTS_FILE: &str = "achievements.txt";
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Achievement {
+	FirstQuad,
+	TenCombo,
+	SurviveTenMinutes,
+	ThousandLines,
+}
+
+impl Achievement {
+	pub const ALL: [Achievement; 4] = [
+		Achievement::FirstQuad,
+		Achievement::TenCombo,
+		Achievement::SurviveTenMinutes,
+		Achievement::ThousandLines,
+	];
+
+	/// Stable; written to disk.
+	fn id(&self) -> &'static str {
+		match self {
+			Achievement::FirstQuad => "first-quad",
+			Achievement::TenCombo => "ten-combo",
+			Achievement::SurviveTenMinutes => "survive-ten-minutes",
+			Achievement::ThousandLines => "thousand-lines",
+		}
+	}
+
+	pub fn title(&self) -> &'static str {
+		match self {
+			Achievement::FirstQuad => "Quad",
+			Achievement::TenCombo => "Combo breaker",
+			Achievement::SurviveTenMinutes => "Survivor",
+			Achievement::ThousandLines => "Millennium",
+		}
+	}
+
+	pub fn description(&self) -> &'static str {
+		match self {
+			Achievement::FirstQuad => "Clear four rows with one piece",
+			Achievement::TenCombo => "Clear rows with ten pieces in a row",
+			Achievement::SurviveTenMinutes => "Stay alive for ten minutes",
+			Achievement::ThousandLines => "Clear 1000 rows, all time",
+		}
+	}
+}
+
+/// Unlocks and lifetime counters, plus just enough per-game state to notice new unlocks.
+#[derive(Debug,Default)]
+pub struct Achievements {
+	unlocked: Vec<Achievement>,
+	lifetime_rows: u64,
+	/// Consecutive pieces that cleared rows.
+	combo: u32,
+	ticks_alive: u64,
+	/// For toasts; see drain_new_unlocks().
+	new_unlocks: Vec<Achievement>,
+}
+
+impl Achievements {
+	/// Lines are `unlocked <id>` or `lifetime_rows <n>`; unreadable lines are skipped.
+	pub fn load() -> Achievements {
+		let mut achievements = Achievements::default();
+		for line in storage::load(&profile::file(ACHIEVEMENTS_FILE)).unwrap_or_default().lines() {
+			match line.split_once(' ') {
+				Some(("unlocked", id)) => {
+					if let Some(a) = Achievement::ALL.iter().find(|a| a.id() == id) {
+						achievements.unlocked.push(*a);
+					}
+				}
+				Some(("lifetime_rows", n)) => achievements.lifetime_rows = n.parse().unwrap_or_default(),
+				_ => {}
+			}
+		}
+		achievements
+	}
+
+	fn save(&self) {
+		let mut contents = format!("lifetime_rows {}\n", self.lifetime_rows);
+		for a in self.unlocked.iter() {
+			contents += &format!("unlocked {}\n", a.id());
+		}
+		if let Err(e) = storage::save(&profile::file(ACHIEVEMENTS_FILE), &contents) {
+			log::error!("Could not save achievements: {e}");
+		}
+	}
+
+	pub fn is_unlocked(&self, a: Achievement) -> bool {
+		self.unlocked.contains(&a)
+	}
+
+	/// Call whenever a new game begins.
+	pub fn start_game(&mut self) {
+		self.combo = 0;
+		self.ticks_alive = 0;
+	}
+
+	/// Call after every GameState::tick().
+	pub fn tick(&mut self) {
+		self.ticks_alive += 1;
+		if self.ticks_alive >= 10 * 60 * tetris::TICKS_PER_SECOND as u64 {
+			self.unlock(Achievement::SurviveTenMinutes);
+		}
+	}
+
+	pub fn observe(&mut self, event: &tetris::Event, game_state: &tetris::GameState) {
+		match event {
+			tetris::Event::PieceLocked => {
+				if !matches!(game_state.phase, tetris::Phase::LineClear { .. }) {
+					self.combo = 0;
+				}
+			}
+			&tetris::Event::RowsCleared(n) => {
+				self.combo += 1;
+				self.lifetime_rows += n as u64;
+				if n >= 4 {
+					self.unlock(Achievement::FirstQuad);
+				}
+				if self.combo >= 10 {
+					self.unlock(Achievement::TenCombo);
+				}
+				if self.lifetime_rows >= 1000 {
+					self.unlock(Achievement::ThousandLines);
+				}
+				self.save();
+			}
+			tetris::Event::GravityChanged(_) | tetris::Event::ItemUsed(_) | tetris::Event::Exploded { .. } | tetris::Event::Danger(_) | tetris::Event::LevelUp(_) | tetris::Event::ToppedOut(_) => {}
+		}
+	}
+
+	fn unlock(&mut self, a: Achievement) {
+		if self.is_unlocked(a) {
+			return;
+		}
+		self.unlocked.push(a);
+		self.new_unlocks.push(a);
+		self.save();
+	}
+
+	pub fn drain_new_unlocks(&mut self) -> std::vec::Drain<'_, Achievement> {
+		self.new_unlocks.drain(..)
+	}
+}
@@ -0,0 +1,188 @@
+//! Replay review: plays a recording back without a window, as fast as it goes, and keeps what a player would want to
+//! look over afterwards, a sample per piece locked. Heatmap works on live games too.
+
+use crate::finesse;
+use crate::replay;
+use crate::tetris;
+
+/// HARDCODE Pieces per second is over the last this many seconds, so a slow start doesn't drag down the whole run.
+pub const PPS_WINDOW_SECONDS: u64 = 10;
+
+/// The board just after a piece locked.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Sample {
+	pub ticks: u64,
+	/// Over PPS_WINDOW_SECONDS, or since the start if that's sooner.
+	pub pps: f32,
+	/// Empty cells with something above them in their column.
+	pub holes: usize,
+	pub stack_height: usize,
+	/// Moves and rotations beyond the fewest that would have placed this piece; see finesse::Verdict.
+	pub extra_presses: u32,
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct Analysis {
+	pub mode: String,
+	pub samples: Vec<Sample>,
+	/// Pieces between one line piece and the next, in order; the last is the one still going when the game ended.
+	pub droughts: Vec<u32>,
+	pub rows_cleared: u32,
+	pub score: u32,
+	pub ticks: u64,
+	/// See replay::Playback::is_in_sync(); stats from a replay that went out of sync aren't the game that was played.
+	pub in_sync: Option<bool>,
+	pub heatmap: Heatmap,
+}
+
+/// How many pieces locked over each cell of the visible board; counted as they lock, before any rows clear.
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub struct Heatmap {
+	pub width: usize,
+	pub height: usize,
+	/// Row by row, top to bottom.
+	counts: Vec<u32>,
+	pub pieces: u32,
+}
+
+impl Heatmap {
+	/// Call after each tetris::Event::PieceLocked. A board of another size starts the count over.
+	pub fn observe(&mut self, game_state: &tetris::GameState) {
+		let visible = &game_state.cell_matrix[game_state.hidden_rows ..];
+		if (self.width, self.height) != (game_state.cell_matrix_width, visible.len()) {
+			*self = Self { width: game_state.cell_matrix_width, height: visible.len(), counts: vec![0; game_state.cell_matrix_width * visible.len()], pieces: 0 };
+		}
+		self.pieces += 1;
+		for (y, row) in visible.iter().enumerate() {
+			for (x, cell) in row.cells.iter().enumerate() {
+				if cell.as_ref().is_some_and(|c| c.piece == game_state.pieces_locked) {
+					self.counts[y * self.width + x] += 1;
+				}
+			}
+		}
+	}
+
+	pub fn count(&self, x: usize, y: usize) -> u32 {
+		self.counts.get(y * self.width + x).copied().unwrap_or(0)
+	}
+
+	pub fn max(&self) -> u32 {
+		self.counts.iter().copied().max().unwrap_or(0)
+	}
+
+	/// Of all cells counted, the share in the left half of the board; a middle column counts half to each side.
+	pub fn left_share(&self) -> f32 {
+		let (mut left, mut total) = (0.0, 0.0);
+		for (i, &count) in self.counts.iter().enumerate() {
+			let x = i % self.width.max(1);
+			let weight = match (2 * x + 1).cmp(&self.width) {
+				std::cmp::Ordering::Less => 1.0,
+				std::cmp::Ordering::Equal => 0.5,
+				std::cmp::Ordering::Greater => 0.0,
+			};
+			left += weight * count as f32;
+			total += count as f32;
+		}
+		if total == 0.0 { 0.5 } else { left / total }
+	}
+}
+
+impl Analysis {
+	/// Pieces placed with no more presses than needed.
+	pub fn clean_pieces(&self) -> usize {
+		self.samples.iter().filter(|s| s.extra_presses == 0).count()
+	}
+
+	pub fn extra_presses(&self) -> u32 {
+		self.samples.iter().map(|s| s.extra_presses).sum()
+	}
+
+	pub fn longest_drought(&self) -> u32 {
+		self.droughts.iter().copied().max().unwrap_or(0)
+	}
+
+	/// Over the whole game.
+	pub fn mean_pps(&self) -> f32 {
+		match self.ticks {
+			0 => 0.0,
+			ticks => self.samples.len() as f32 * tetris::TICKS_PER_SECOND as f32 / ticks as f32,
+		}
+	}
+}
+
+/// Plays `replay` until the game ends, or for a second past its last action, for a recording that stopped mid-game.
+pub fn analyze(replay: &replay::Replay) -> Analysis {
+	let mut game_state = replay.new_game_state();
+	let mut playback = replay::Playback::new(replay.clone());
+	let mut trainer = finesse::Trainer::new();
+	let last_action_tick = replay.actions.last().map_or(0, |&(tick, _action)| tick);
+	let mut samples = Vec::new();
+	let mut droughts = vec![0];
+	let mut heatmap = Heatmap::default();
+	let mut falling: Option<tetris::Piece> = None;
+	let mut pieces_locked = game_state.pieces_locked;
+	while game_state.is_alive && game_state.ticks <= last_action_tick + tetris::TICKS_PER_SECOND as u64 {
+		let mut verdict = None;
+		for action in playback.actions_due(game_state.ticks) {
+			game_state.apply(action);
+			trainer.record(action);
+			verdict = verdict.or(trainer.observe(&game_state));
+		}
+		game_state.tick();
+		verdict = verdict.or(trainer.observe(&game_state));
+		game_state.drain_events().for_each(drop);
+		if game_state.pieces_locked != pieces_locked {
+			pieces_locked = game_state.pieces_locked;
+			heatmap.observe(&game_state);
+			if falling.as_ref().is_some_and(is_line_piece) {
+				droughts.push(0);
+			} else if let Some(drought) = droughts.last_mut() {
+				*drought += 1;
+			}
+			samples.push(Sample {
+				ticks: game_state.ticks,
+				pps: 0.0,
+				holes: holes(&game_state),
+				stack_height: game_state.stack_height(),
+				extra_presses: verdict.map_or(0, |v| v.extra_presses()),
+			});
+		}
+		if let Some(piece) = game_state.current_piece.as_ref() {
+			falling = Some(piece.clone());
+		}
+	}
+	let window_ticks = PPS_WINDOW_SECONDS * tetris::TICKS_PER_SECOND as u64;
+	let locked_at: Vec<u64> = samples.iter().map(|s| s.ticks).collect();
+	for (i, sample) in samples.iter_mut().enumerate() {
+		let since = sample.ticks.saturating_sub(window_ticks);
+		let pieces = locked_at[..= i].iter().filter(|&&t| t > since).count();
+		let seconds = (sample.ticks - since).max(1) as f32 / tetris::TICKS_PER_SECOND as f32;
+		sample.pps = pieces as f32 / seconds;
+	}
+	Analysis {
+		mode: replay.mode.clone(),
+		samples,
+		droughts,
+		rows_cleared: game_state.rows_cleared,
+		score: game_state.score,
+		ticks: game_state.ticks,
+		in_sync: playback.is_in_sync(&game_state),
+		heatmap,
+	}
+}
+
+/// The long bar: four or more cells in one row or column. Droughts are counted between these.
+fn is_line_piece(piece: &tetris::Piece) -> bool {
+	let in_one = |coordinate: fn(&tetris::CellWithRelativePosition) -> i32| piece.cells.iter().all(|c| coordinate(c) == coordinate(&piece.cells[0]));
+	piece.cells.len() >= 4 && (in_one(|c| c.x) || in_one(|c| c.y))
+}
+
+fn holes(game_state: &tetris::GameState) -> usize {
+	let visible = &game_state.cell_matrix[game_state.hidden_rows ..];
+	(0 .. game_state.cell_matrix_width)
+		.map(|x| {
+			let top = visible.iter().position(|row| row.cells[x].is_some()).unwrap_or(visible.len());
+			visible[top ..].iter().filter(|row| row.cells[x].is_none()).count()
+		})
+		.sum()
+}
@@ -0,0 +1,156 @@
+//! Fonts, textures, and sounds from assets/, loaded at startup behind a loading screen.
+//!
+//! Every file is optional. One that is missing or won't decode is logged and left out, and whoever wanted it
+//! gets None and carries on as before: macroquad's own font, flat colors, quiet.
+
+use std::collections::VecDeque;
+
+use macroquad::prelude::*;
+use tetris_clone_rust::bridge;
+use tetris_clone_rust::log;
+use tetris_clone_rust::tetris;
+
+/// HARDCODE Drawn over every cell, tinted with its color; white where the cell's color should show through.
+pub const BLOCK_TEXTURE: &str = "assets/textures/block.png";
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Sound {
+	Lock,
+	Clear,
+	/// Four rows at once; instead of Clear.
+	Tetris,
+	LevelUp,
+	TopOut,
+}
+
+impl Sound {
+	/// In the order the embedding page numbers them; see www/index.html.
+	pub const ALL: [Sound; 5] = [Sound::Lock, Sound::Clear, Sound::Tetris, Sound::LevelUp, Sound::TopOut];
+
+	pub fn path(self) -> &'static str {
+		match self {
+			Sound::Lock => "assets/sounds/lock.ogg",
+			Sound::Clear => "assets/sounds/clear.ogg",
+			Sound::Tetris => "assets/sounds/tetris.ogg",
+			Sound::LevelUp => "assets/sounds/level-up.ogg",
+			Sound::TopOut => "assets/sounds/top-out.ogg",
+		}
+	}
+
+	pub fn for_event(event: &tetris::Event) -> Option<Sound> {
+		match event {
+			tetris::Event::PieceLocked => Some(Sound::Lock),
+			tetris::Event::RowsCleared(4 ..) => Some(Sound::Tetris),
+			tetris::Event::RowsCleared(_) => Some(Sound::Clear),
+			tetris::Event::LevelUp(_) => Some(Sound::LevelUp),
+			tetris::Event::ToppedOut(_) => Some(Sound::TopOut),
+			tetris::Event::GravityChanged(_) | tetris::Event::ItemUsed(_) | tetris::Event::Exploded { .. } | tetris::Event::Danger(_) => None,
+		}
+	}
+}
+
+/// A sound that loaded; see Assets::sound().
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct SoundHandle(Sound);
+
+impl SoundHandle {
+	pub fn sound(self) -> Sound {
+		self.0
+	}
+
+	/// Macroquad's audio feature is off, since its backend isn't among the dependencies, so natively this is quiet;
+	/// the embedding page plays `Sound::path()` when told over the bridge, as it does music.
+	pub fn play(self) {
+		let index = Sound::ALL.iter().position(|&s| s == self.0).unwrap_or_default();
+		bridge::emit("sound", index as u64);
+	}
+}
+
+/// Where a file goes once loaded.
+#[derive(Clone,Debug)]
+enum Request {
+	Font(String),
+	Texture(String),
+	Sound(Sound),
+}
+
+impl Request {
+	fn path(&self) -> &str {
+		match self {
+			Request::Font(path) | Request::Texture(path) => path,
+			Request::Sound(sound) => sound.path(),
+		}
+	}
+}
+
+/// What loaded, by path. Font and Texture2D are already handles into macroquad's context, so they're handed out as
+/// clones; sounds are handed out as SoundHandle.
+#[derive(Default)]
+pub struct Assets {
+	fonts: Vec<(String, Font)>,
+	textures: Vec<(String, Texture2D)>,
+	sounds: Vec<(Sound, Vec<u8>)>,
+}
+
+impl Assets {
+	pub fn font(&self, path: &str) -> Option<Font> {
+		self.fonts.iter().find(|(p, _font)| p == path).map(|(_path, font)| font.clone())
+	}
+
+	pub fn texture(&self, path: &str) -> Option<Texture2D> {
+		self.textures.iter().find(|(p, _texture)| p == path).map(|(_path, texture)| texture.clone())
+	}
+
+	pub fn sound(&self, sound: Sound) -> Option<SoundHandle> {
+		self.sounds.iter().any(|(s, _bytes)| *s == sound).then_some(SoundHandle(sound))
+	}
+
+	async fn load(&mut self, request: Request) -> Result<(), String> {
+		match request {
+			Request::Font(path) => {
+				let font = load_ttf_font(&path).await.map_err(|e| e.to_string())?;
+				self.fonts.push((path, font));
+			}
+			Request::Texture(path) => {
+				let texture = load_texture(&path).await.map_err(|e| e.to_string())?;
+				texture.set_filter(FilterMode::Nearest);
+				self.textures.push((path, texture));
+			}
+			Request::Sound(sound) => {
+				let bytes = load_file(sound.path()).await.map_err(|e| e.to_string())?;
+				self.sounds.push((sound, bytes));
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Everything the game knows to look for, plus `fonts`; a frame per file, with a progress bar in between.
+pub async fn load(fonts: &[&str]) -> Assets {
+	let mut queue: VecDeque<Request> = fonts.iter().map(|path| Request::Font(path.to_string()))
+		.chain([Request::Texture(BLOCK_TEXTURE.to_string())])
+		.chain(Sound::ALL.map(Request::Sound))
+		.collect();
+	let total = queue.len();
+	let mut assets = Assets::default();
+	while let Some(request) = queue.pop_front() {
+		render_loading_screen(request.path(), (total - queue.len() - 1) as f32 / total as f32);
+		next_frame().await;
+		let path = request.path().to_string();
+		if let Err(e) = assets.load(request).await {
+			log::warn!("Going without {path}: {e}");
+		}
+	}
+	log::info!("Loaded {} of {total} assets", assets.fonts.len() + assets.textures.len() + assets.sounds.len());
+	assets
+}
+
+fn render_loading_screen(path: &str, progress: f32) {
+	clear_background(BLACK);
+	let (w, h) = (screen_width(), screen_height());
+	let bar = Rect::new(w * 0.1, h / 2.0, w * 0.8, 8.0); // HARDCODE
+	draw_rectangle(bar.x, bar.y, bar.w * progress, bar.h, LIGHTGRAY);
+	draw_rectangle_lines(bar.x, bar.y, bar.w, bar.h, 1.0, GRAY);
+	draw_text("Loading", bar.x, bar.y - 12.0, 24.0, LIGHTGRAY);
+	draw_text(path, bar.x, bar.bottom() + 20.0, 16.0, GRAY);
+}
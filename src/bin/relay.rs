@@ -0,0 +1,209 @@
+//! The relay netplay talks to: forwards each WebSocket message to everybody else connected to the same path,
+//! so players behind NAT only ever connect out. It knows nothing about the game beyond room codes.
+//!
+//! ```text
+//! cargo run --bin relay -- --listen 0.0.0.0:9001 --record matches
+//! ```
+//!
+//! A plain HTTP GET, without the upgrade, lists the open rooms. With `--record`, each room's messages go to
+//! `<dir>/<CODE>-<unix time>.txt`, one per line after milliseconds and a connection number; the hellos and ticks
+//! in there are enough to replay both games, the way netplay::Spectator does.
+//!
+//! A thread per connection rather than async: a room holds two players and the odd spectator.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use tetris_clone_rust::log;
+use tetris_clone_rust::websocket::{self, Frame};
+
+/// HARDCODE Two players, with room to watch.
+const MAX_CLIENTS_PER_ROOM: usize = 8;
+/// HARDCODE Somebody who stops reading shouldn't hold up the rest of the room for longer.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Client {
+	id: u64,
+	stream: Arc<Mutex<TcpStream>>,
+}
+
+struct Room {
+	clients: Vec<Client>,
+	opened_at: Instant,
+	recording: Option<std::fs::File>,
+}
+
+struct Relay {
+	rooms: Mutex<HashMap<String, Room>>,
+	/// See `--record`.
+	record_dir: Option<std::path::PathBuf>,
+}
+
+impl Relay {
+	fn rooms(&self) -> std::sync::MutexGuard<'_, HashMap<String, Room>> {
+		self.rooms.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+	}
+
+	fn join(&self, code: &str, client: Client) -> Result<(), String> {
+		let mut rooms = self.rooms();
+		let room = rooms.entry(code.to_string()).or_insert_with(|| Room {
+			clients: Vec::new(),
+			opened_at: Instant::now(),
+			recording: self.record_dir.as_ref().and_then(|dir| {
+				let seconds = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+				let path = dir.join(format!("{code}-{seconds}.txt"));
+				std::fs::create_dir_all(dir).and_then(|()| std::fs::File::create(&path))
+					.inspect_err(|e| log::warn!("Not recording room {code} to {}: {e}", path.display()))
+					.ok()
+			}),
+		});
+		if room.clients.len() >= MAX_CLIENTS_PER_ROOM {
+			return Err(format!("Room {code} is full"));
+		}
+		log::info!("Connection {} joined room {code}, now {} in it", client.id, room.clients.len() + 1);
+		room.clients.push(client);
+		Ok(())
+	}
+
+	fn leave(&self, code: &str, id: u64) {
+		let mut rooms = self.rooms();
+		let Some(room) = rooms.get_mut(code)
+			else { return };
+		room.clients.retain(|client| client.id != id);
+		log::info!("Connection {id} left room {code}, now {} in it", room.clients.len());
+		if room.clients.is_empty() {
+			rooms.remove(code);
+			log::info!("Closed room {code}");
+		}
+	}
+
+	/// To everybody in the room but `from`; and to the recording, if any.
+	fn forward(&self, code: &str, from: u64, text: &str) {
+		let others: Vec<(u64, Arc<Mutex<TcpStream>>)> = {
+			let mut rooms = self.rooms();
+			let Some(room) = rooms.get_mut(code)
+				else { return };
+			if let Some(file) = room.recording.as_mut() {
+				let millis = room.opened_at.elapsed().as_millis();
+				if let Err(e) = writeln!(file, "{millis} {from} {text}") {
+					log::warn!("Stopped recording room {code}: {e}");
+					room.recording = None;
+				}
+			}
+			room.clients.iter()
+				.filter(|client| client.id != from)
+				.map(|client| (client.id, client.stream.clone()))
+				.collect()
+		};
+		// Outside the lock, so one slow client doesn't stall every room.
+		for (id, stream) in others {
+			let mut stream = stream.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+			if let Err(e) = websocket::write_frame(&mut *stream, &Frame::Text(text.to_string()), false) {
+				log::warn!("Could not forward to connection {id}: {e}");
+				// Its own thread notices and leaves.
+				let _ = stream.shutdown(std::net::Shutdown::Both);
+			}
+		}
+	}
+
+	/// `CODE clients` per line, for anybody curious which rooms are open.
+	fn list(&self) -> String {
+		let rooms = self.rooms();
+		let mut codes: Vec<&String> = rooms.keys().collect();
+		codes.sort();
+		codes.iter().map(|code| format!("{code} {}\n", rooms[*code].clients.len())).collect()
+	}
+}
+
+fn main() {
+	log::init(log::Level::Info, None);
+	let mut listen = "0.0.0.0:9001".to_string(); // HARDCODE Matches config::Config::netplay_url.
+	let mut record_dir = None;
+	let mut args = std::env::args().skip(1);
+	while let Some(flag) = args.next() {
+		let Some(value) = args.next()
+			else {
+				eprintln!("Missing value for {flag}");
+				std::process::exit(2);
+			};
+		match flag.as_str() {
+			"--listen" => listen = value,
+			"--record" => record_dir = Some(std::path::PathBuf::from(value)),
+			other => {
+				eprintln!("Unknown flag {other}; expected --listen or --record");
+				std::process::exit(2);
+			}
+		}
+	}
+	let listener = match TcpListener::bind(&listen) {
+		Ok(listener) => listener,
+		Err(e) => {
+			eprintln!("Could not listen on {listen}: {e}");
+			std::process::exit(1);
+		}
+	};
+	log::info!("Relaying on {listen}");
+	let relay = Arc::new(Relay { rooms: Mutex::new(HashMap::new()), record_dir });
+	for (id, stream) in listener.incoming().enumerate() {
+		let id = id as u64 + 1;
+		match stream {
+			Ok(stream) => {
+				let relay = relay.clone();
+				std::thread::spawn(move || {
+					if let Err(e) = serve(&relay, id, stream) {
+						log::info!("Connection {id}: {e}");
+					}
+				});
+			}
+			Err(e) => log::warn!("Could not accept a connection: {e}"),
+		}
+	}
+}
+
+fn serve(relay: &Relay, id: u64, mut stream: TcpStream) -> Result<(), String> {
+	stream.set_nodelay(true).map_err(|e| e.to_string())?;
+	stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(|e| e.to_string())?;
+	let head = websocket::read_head(&mut stream).map_err(|e| e.to_string())?;
+	let path = head.split_whitespace().nth(1).unwrap_or_default();
+	let code = path.trim_matches('/').to_ascii_uppercase();
+	let is_upgrade = websocket::header(&head, "Upgrade").is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+	if !is_upgrade {
+		let body = relay.list();
+		let response = format!(
+			"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nAccess-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+			body.len());
+		return stream.write_all(response.as_bytes()).map_err(|e| e.to_string());
+	}
+	if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric()) {
+		let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+		return Err(format!("No room at {path}"));
+	}
+	websocket::server_handshake(&mut stream, &head)?;
+	let writer = Arc::new(Mutex::new(stream.try_clone().map_err(|e| e.to_string())?));
+	relay.join(&code, Client { id, stream: writer.clone() })?;
+	let result = relay_frames(relay, &code, id, &mut stream, &writer);
+	relay.leave(&code, id);
+	result
+}
+
+fn relay_frames(relay: &Relay, code: &str, id: u64, stream: &mut TcpStream, writer: &Mutex<TcpStream>) -> Result<(), String> {
+	let reply = |frame: &Frame| {
+		let mut writer = writer.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		websocket::write_frame(&mut *writer, frame, false).map_err(|e| e.to_string())
+	};
+	loop {
+		match websocket::read_frame(stream).map_err(|e| e.to_string())? {
+			Frame::Text(text) => relay.forward(code, id, &text),
+			Frame::Ping(_data) => reply(&Frame::Pong)?,
+			Frame::Pong => (),
+			Frame::Close => {
+				// Already leaving either way.
+				let _ = reply(&Frame::Close);
+				return Ok(());
+			}
+		}
+	}
+}
@@ -0,0 +1,431 @@
+//! Computer players; they drive a GameState through the same Actions a person would.
+
+use crate::log;
+use crate::rng;
+use crate::storage;
+use crate::tetris;
+
+/// Written by `--train`; see training.rs.
+const WEIGHTS_FILE: &str = "bot-weights.txt";
+
+pub trait Bot: std::fmt::Debug + Send {
+	/// Called once per tick, before GameState::tick(); everything returned gets applied this tick.
+	fn think(&mut self, game_state: &tetris::GameState) -> Vec<tetris::Action>;
+}
+
+pub const NAMES: [&str; 3] = ["greedy", "beam", "random"];
+
+/// See NAMES; `seed` only matters for bots that roll dice, `strength` for bots that search.
+pub fn by_name(name: &str, seed: u64, strength: Strength) -> Option<Box<dyn Bot>> {
+	match name {
+		"greedy" => Some(Box::new(GreedyBot::new(Weights::load()))),
+		"beam" => Some(Box::new(BeamBot::new(Weights::load(), strength))),
+		"random" => Some(Box::new(RandomBot::new(seed))),
+		_ => None,
+	}
+}
+
+/// How hard BeamBot thinks.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum Strength {
+	Easy,
+	#[default]
+	Normal,
+	Hard,
+}
+
+impl Strength {
+	pub const ALL: [Strength; 3] = [Strength::Easy, Strength::Normal, Strength::Hard];
+
+	pub fn parse(text: &str) -> Option<Strength> {
+		Self::ALL.into_iter().find(|s| s.name().eq_ignore_ascii_case(text))
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Strength::Easy => "easy",
+			Strength::Normal => "normal",
+			Strength::Hard => "hard",
+		}
+	}
+
+	/// Pieces searched, the falling one included; never further than the next queue shows.
+	pub fn depth(self) -> usize {
+		// HARDCODE
+		match self {
+			Strength::Easy => 1,
+			Strength::Normal => 2,
+			Strength::Hard => 4,
+		}
+	}
+
+	/// Boards kept after each piece; the rest are never looked at again.
+	pub fn beam_width(self) -> usize {
+		// HARDCODE
+		match self {
+			Strength::Easy => 1,
+			Strength::Normal => 6,
+			Strength::Hard => 16,
+		}
+	}
+
+	pub fn uses_hold(self) -> bool {
+		self != Strength::Easy
+	}
+}
+
+/// Mashes buttons; a baseline for the others.
+#[derive(Debug)]
+pub struct RandomBot {
+	rng: rng::RandomNumberGenerator,
+}
+
+impl RandomBot {
+	pub fn new(seed: u64) -> RandomBot {
+		Self { rng: rng::RandomNumberGenerator::new(seed) }
+	}
+}
+
+impl Bot for RandomBot {
+	fn think(&mut self, _game_state: &tetris::GameState) -> Vec<tetris::Action> {
+		let action = match self.rng.uniform(0, 16) { // HARDCODE Mostly idle, like people
+			0 => tetris::Action::MoveLeft,
+			1 => tetris::Action::MoveRight,
+			2 => tetris::Action::RotateClockwise,
+			3 => tetris::Action::RotateCounterClockwise,
+			4 => tetris::Action::SoftDropStart,
+			_ => return Vec::new(),
+		};
+		vec![action]
+	}
+}
+
+/// How GreedyBot and BeamBot score a board; higher is better, so penalties are negative.
+#[derive(Clone,Copy,Debug)]
+pub struct Weights {
+	/// Per cell of column height, summed over columns.
+	pub aggregate_height: f32,
+	/// Per empty cell with a filled cell somewhere above it.
+	pub holes: f32,
+	/// Per cell of height difference between neighboring columns.
+	pub bumpiness: f32,
+	/// Per row the placement would clear.
+	pub rows_cleared: f32,
+}
+
+impl Default for Weights {
+	/// Well-known hand-tuned values for the classic four-feature heuristic.
+	fn default() -> Self {
+		Self {
+			aggregate_height: -0.51,
+			holes: -0.36,
+			bumpiness: -0.18,
+			rows_cleared: 0.76,
+		}
+	}
+}
+
+impl Weights {
+	pub const COUNT: usize = 4;
+
+	/// Trained weights if there are any, else the defaults.
+	pub fn load() -> Weights {
+		let Some(text) = storage::load(WEIGHTS_FILE)
+			else { return Weights::default(); };
+		let mut weights = Weights::default();
+		for line in text.lines() {
+			let Some((key, value)) = line.split_once(' ')
+				else { continue; };
+			let Ok(value) = value.trim().parse()
+				else {
+					log::warn!("Ignoring bad bot weight `{line}`");
+					continue;
+				};
+			match key {
+				"aggregate_height" => weights.aggregate_height = value,
+				"holes" => weights.holes = value,
+				"bumpiness" => weights.bumpiness = value,
+				"rows_cleared" => weights.rows_cleared = value,
+				other => log::warn!("Ignoring unknown bot weight `{other}`"),
+			}
+		}
+		weights
+	}
+
+	pub fn save(&self) -> std::io::Result<()> {
+		storage::save(WEIGHTS_FILE, &format!(
+			"aggregate_height {}\nholes {}\nbumpiness {}\nrows_cleared {}\n",
+			self.aggregate_height, self.holes, self.bumpiness, self.rows_cleared))
+	}
+
+	/// In a fixed order, for treating the weights as a vector.
+	pub fn to_array(&self) -> [f32; Self::COUNT] {
+		[self.aggregate_height, self.holes, self.bumpiness, self.rows_cleared]
+	}
+
+	pub fn from_array([aggregate_height, holes, bumpiness, rows_cleared]: [f32; Self::COUNT]) -> Weights {
+		Self { aggregate_height, holes, bumpiness, rows_cleared }
+	}
+}
+
+/// Where a piece should end up, and how far along getting there the bot is.
+#[derive(Clone,Copy,Debug)]
+struct Plan {
+	/// Swap the piece for the held one first; the rest of the plan is for that one.
+	hold_first: bool,
+	rotations_left: u32,
+	target_x: i32,
+	/// Center of mass x before the last move, to notice being stuck.
+	last_x: Option<i32>,
+	is_dropping: bool,
+}
+
+/// Looks one piece ahead: tries every rotation and column, then steers towards the best-scoring landing spot.
+#[derive(Debug)]
+pub struct GreedyBot {
+	pub weights: Weights,
+	plan: Option<Plan>,
+}
+
+impl GreedyBot {
+	pub fn new(weights: Weights) -> GreedyBot {
+		Self { weights, plan: None }
+	}
+
+	fn make_plan(&self, game_state: &tetris::GameState, piece: &tetris::Piece) -> Plan {
+		let board = Board::new(game_state);
+		let mut best: Option<(f32, Plan)> = None;
+		for (rotations, rotated, xy) in placements(&board, piece, game_state.current_piece_mass_xy) {
+			let mut placed = board.clone();
+			let rows_cleared = placed.place(&rotated, xy);
+			let score = placed.score(&self.weights, rows_cleared);
+			if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+				best = Some((score, Plan { hold_first: false, rotations_left: rotations, target_x: xy.0, last_x: None, is_dropping: false }));
+			}
+		}
+		// Nothing fits anywhere; just let it fall.
+		best.map(|(_score, plan)| plan)
+			.unwrap_or(Plan { hold_first: false, rotations_left: 0, target_x: game_state.current_piece_mass_xy.0, last_x: None, is_dropping: false })
+	}
+}
+
+impl Bot for GreedyBot {
+	fn think(&mut self, game_state: &tetris::GameState) -> Vec<tetris::Action> {
+		let Some(piece) = game_state.current_piece.as_ref()
+			else {
+				self.plan = None;
+				return Vec::new();
+			};
+		let plan = match self.plan.as_mut() {
+			Some(plan) => plan,
+			None => self.plan.insert(self.make_plan(game_state, piece)),
+		};
+		steer(plan, game_state)
+	}
+}
+
+/// A board partway through BeamBot's search.
+#[derive(Clone,Debug)]
+struct Node {
+	board: Board,
+	/// By every piece placed so far.
+	rows_cleared: usize,
+	score: f32,
+	/// Rotations and target x of the falling piece, on the way to this board.
+	first: Option<(u32, i32)>,
+}
+
+/// Looks ahead through the next queue, and hold, keeping only the best few boards after each piece; see Strength.
+#[derive(Debug)]
+pub struct BeamBot {
+	pub weights: Weights,
+	pub strength: Strength,
+	plan: Option<Plan>,
+}
+
+impl BeamBot {
+	pub fn new(weights: Weights, strength: Strength) -> BeamBot {
+		Self { weights, strength, plan: None }
+	}
+
+	fn make_plan(&self, game_state: &tetris::GameState, piece: &tetris::Piece) -> Plan {
+		let board = Board::new(game_state);
+		let spawn_xy = game_state.current_piece_mass_xy;
+		let queue: Vec<&tetris::Piece> = game_state.next_pieces().collect();
+		// Play the piece as it comes, or hold it and play the held one, or the next one if nothing is held yet.
+		let mut starts = vec![(false, piece, &queue[..])];
+		if self.strength.uses_hold() && game_state.can_hold() {
+			match (game_state.held_piece.as_ref(), queue.split_first()) {
+				(Some(held), _) => starts.push((true, held, &queue[..])),
+				(None, Some((next, rest))) => starts.push((true, *next, rest)),
+				(None, None) => {}
+			}
+		}
+		let mut best: Option<(f32, Plan)> = None;
+		for (hold_first, first, rest) in starts {
+			let mut beam = vec![Node { board: board.clone(), rows_cleared: 0, score: 0.0, first: None }];
+			for piece in std::iter::once(first).chain(rest.iter().copied()).take(self.strength.depth()) {
+				let mut next = Vec::new();
+				for node in &beam {
+					for (rotations, rotated, xy) in placements(&node.board, piece, spawn_xy) {
+						let mut board = node.board.clone();
+						let rows_cleared = node.rows_cleared + board.place(&rotated, xy);
+						let score = board.score(&self.weights, rows_cleared);
+						next.push(Node { board, rows_cleared, score, first: node.first.or(Some((rotations, xy.0))) });
+					}
+				}
+				if next.is_empty() {
+					break;
+				}
+				next.sort_by(|a, b| b.score.total_cmp(&a.score));
+				next.truncate(self.strength.beam_width());
+				beam = next;
+			}
+			let Some((score, (rotations, target_x))) = beam.first().and_then(|node| Some((node.score, node.first?)))
+				else { continue; };
+			if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+				best = Some((score, Plan { hold_first, rotations_left: rotations, target_x, last_x: None, is_dropping: false }));
+			}
+		}
+		// Nothing fits anywhere; just let it fall.
+		best.map(|(_score, plan)| plan)
+			.unwrap_or(Plan { hold_first: false, rotations_left: 0, target_x: spawn_xy.0, last_x: None, is_dropping: false })
+	}
+}
+
+impl Bot for BeamBot {
+	fn think(&mut self, game_state: &tetris::GameState) -> Vec<tetris::Action> {
+		let Some(piece) = game_state.current_piece.as_ref()
+			else {
+				self.plan = None;
+				return Vec::new();
+			};
+		let plan = match self.plan.as_mut() {
+			Some(plan) => plan,
+			None => self.plan.insert(self.make_plan(game_state, piece)),
+		};
+		steer(plan, game_state)
+	}
+}
+
+/// The next step of `plan`: hold, then rotate, then move, then drop.
+fn steer(plan: &mut Plan, game_state: &tetris::GameState) -> Vec<tetris::Action> {
+	if std::mem::take(&mut plan.hold_first) {
+		return vec![tetris::Action::Hold];
+	}
+	if plan.is_dropping {
+		return Vec::new();
+	}
+	if plan.rotations_left > 0 {
+		plan.rotations_left -= 1;
+		return vec![tetris::Action::RotateClockwise];
+	}
+	let x = game_state.current_piece_mass_xy.0;
+	let is_stuck = plan.last_x == Some(x);
+	if x == plan.target_x || is_stuck {
+		plan.is_dropping = true;
+		return vec![tetris::Action::SoftDropStart];
+	}
+	plan.last_x = Some(x);
+	if x < plan.target_x {
+		vec![tetris::Action::MoveRight]
+	} else {
+		vec![tetris::Action::MoveLeft]
+	}
+}
+
+/// Every rotation and column `piece` can fall into from the height of `xy`: how many turns clockwise, the piece so
+/// turned, and where it lands.
+fn placements(board: &Board, piece: &tetris::Piece, (_x, y): (i32, i32)) -> Vec<(u32, tetris::Piece, (i32, i32))> {
+	let width = board.width() as i32;
+	let mut found = Vec::new();
+	let mut rotated = piece.clone();
+	for rotations in 0 .. 4 {
+		for x in -width ..= 2 * width {
+			if let Some(landed_y) = board.landing_y(&rotated, (x, y)) {
+				found.push((rotations, rotated.clone(), (x, landed_y)));
+			}
+		}
+		rotated = rotated.rotated(true);
+	}
+	found
+}
+
+/// Which cells are filled, hidden rows and all; cheaper to try placements on than a whole GameState.
+#[derive(Clone,Debug,PartialEq,Eq)]
+struct Board {
+	filled: Vec<Vec<bool>>,
+}
+
+impl Board {
+	fn new(game_state: &tetris::GameState) -> Board {
+		Self {
+			filled: game_state.cell_matrix.iter()
+				.map(|row| row.cells.iter().map(Option::is_some).collect())
+				.collect(),
+		}
+	}
+
+	fn width(&self) -> usize {
+		self.filled.first().map_or(0, Vec::len)
+	}
+
+	fn fits(&self, piece: &tetris::Piece, xy: (i32, i32)) -> bool {
+		piece.iter_global_space(xy).all(|(_c, cx, cy)| {
+			cx >= 0 && cy >= 0
+				&& self.filled.get(cy as usize)
+					.and_then(|row| row.get(cx as usize))
+					.is_some_and(|filled| !filled)
+		})
+	}
+
+	/// Lowest y the piece can fall to from `xy`, or None if it doesn't fit there to begin with.
+	fn landing_y(&self, piece: &tetris::Piece, (x, y): (i32, i32)) -> Option<i32> {
+		if !self.fits(piece, (x, y)) {
+			return None;
+		}
+		let mut y = y;
+		while self.fits(piece, (x, y + 1)) {
+			y += 1;
+		}
+		Some(y)
+	}
+
+	/// Locks `piece` at `xy` and clears the rows it fills; returns how many.
+	fn place(&mut self, piece: &tetris::Piece, xy: (i32, i32)) -> usize {
+		for (_c, x, y) in piece.iter_global_space(xy) {
+			self.filled[y as usize][x as usize] = true;
+		}
+		let height = self.filled.len();
+		self.filled.retain(|row| !row.iter().all(|f| *f));
+		let rows_cleared = height - self.filled.len();
+		let empty = vec![false; self.width()];
+		self.filled.splice(0 .. 0, std::iter::repeat_n(empty, rows_cleared));
+		rows_cleared
+	}
+
+	/// Higher is better; see Weights.
+	fn score(&self, weights: &Weights, rows_cleared: usize) -> f32 {
+		let filled = &self.filled;
+		let width = self.width();
+		let column_heights: Vec<usize> = (0 .. width)
+			.map(|x| {
+				let top = filled.iter().position(|row| row[x]).unwrap_or(filled.len());
+				filled.len() - top
+			})
+			.collect();
+		let holes: usize = (0 .. width)
+			.map(|x| {
+				let column_height = column_heights[x];
+				(filled.len() - column_height .. filled.len()).filter(|&y| !filled[y][x]).count()
+			})
+			.sum();
+		let aggregate_height: usize = column_heights.iter().sum();
+		let bumpiness: usize = column_heights.windows(2).map(|w| w[0].abs_diff(w[1])).sum();
+		let w = weights;
+		w.aggregate_height * aggregate_height as f32
+			+ w.holes * holes as f32
+			+ w.bumpiness * bumpiness as f32
+			+ w.rows_cleared * rows_cleared as f32
+	}
+}
@@ -0,0 +1,69 @@
+//! Talking to a page that embeds the wasm build: game events out, commands in.
+//!
+//! Events reach the page as `window.parent.postMessage({ source: "tetris-clone-rust", event, value })`.
+//! Commands come in through `window.tetris_clone_rust.pause()` and friends, or a matching `postMessage()`; see www/index.html.
+//! Natively there is no page, so nothing is sent and nothing arrives.
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Command {
+	Pause,
+	Resume,
+	/// Start the current mode over.
+	Restart,
+	/// Start over on this seed.
+	SetSeed(u64),
+}
+
+#[cfg(not(target_family="wasm"))]
+pub fn emit(_event: &str, _value: u64) {}
+
+#[cfg(not(target_family="wasm"))]
+pub fn poll_command() -> Option<Command> {
+	None
+}
+
+/// The browser's `prefers-reduced-motion`; natively, see config::Config::reduced_motion instead.
+#[cfg(not(target_family="wasm"))]
+pub fn prefers_reduced_motion() -> bool {
+	false
+}
+
+// Matches www/index.html -> register_plugin
+#[cfg(target_family="wasm")]
+extern "C" {
+	fn bridge_emit(event: *const u8, event_len: usize, value: f64);
+	/// 0 when the queue is empty; otherwise see poll_command().
+	fn bridge_poll() -> u32;
+	/// Halves of the seed for the last command polled.
+	fn bridge_seed_high() -> u32;
+	fn bridge_seed_low() -> u32;
+	fn bridge_prefers_reduced_motion() -> u32;
+}
+
+/// `value` is a JS number on the other side, so stay below 2^53.
+#[cfg(target_family="wasm")]
+pub fn emit(event: &str, value: u64) {
+	unsafe { // SAFETY: the JS side copies the buffer before returning
+		bridge_emit(event.as_ptr(), event.len(), value as f64);
+	}
+}
+
+/// Call until it yields None, once per frame.
+#[cfg(target_family="wasm")]
+pub fn poll_command() -> Option<Command> {
+	match unsafe { bridge_poll() } {
+		1 => Some(Command::Pause),
+		2 => Some(Command::Resume),
+		3 => Some(Command::Restart),
+		4 => {
+			let (high, low) = unsafe { (bridge_seed_high(), bridge_seed_low()) };
+			Some(Command::SetSeed((high as u64) << 32 | low as u64))
+		}
+		_ => None,
+	}
+}
+
+#[cfg(target_family="wasm")]
+pub fn prefers_reduced_motion() -> bool {
+	unsafe { bridge_prefers_reduced_motion() != 0 }
+}
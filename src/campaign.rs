@@ -0,0 +1,124 @@
+//! The built-in campaign: hand-made puzzles in order, each opened by clearing the one before, with up to MAX_STARS
+//! for each. Levels ship inside the binary, so the campaign is there even where assets don't load.
+
+use std::collections::BTreeMap;
+
+use crate::log;
+use crate::profile;
+use crate::puzzle;
+use crate::storage;
+use crate::tetris;
+
+/// Levels with `---` lines between; each is a puzzle as puzzle::Puzzle::parse() reads it, plus a
+/// `par: PIECES SECONDS` key for the second and third stars.
+const LEVELS_TEXT: &str = include_str!("../assets/campaign.txt");
+/// Per profile; `stars name` lines.
+const PROGRESS_FILE: &str = "campaign.txt";
+
+pub const MAX_STARS: u32 = 3;
+
+#[derive(Clone,Debug)]
+pub struct Level {
+	pub puzzle: puzzle::Puzzle,
+	/// The second star is for clearing it in this many pieces or fewer.
+	pub par_pieces: u32,
+	/// The third, for clearing it within this many seconds.
+	pub par_seconds: u32,
+}
+
+impl Level {
+	/// One for clearing it, one for par pieces, one for par time; see MAX_STARS.
+	pub fn stars(&self, pieces_used: u32, ticks: u64) -> u32 {
+		1 + u32::from(pieces_used <= self.par_pieces) + u32::from(ticks <= self.par_seconds as u64 * tetris::TICKS_PER_SECOND as u64)
+	}
+
+	/// What it takes for each star past the first.
+	pub fn par_description(&self) -> String {
+		format!("Par: {} pieces, {} s", self.par_pieces, self.par_seconds)
+	}
+}
+
+/// All of them, in order.
+pub fn levels() -> Vec<Level> {
+	LEVELS_TEXT.split("\n---\n")
+		.enumerate()
+		.filter_map(|(i, text)| match parse_level(text) {
+			Ok(level) => Some(level),
+			Err(e) => {
+				log::warn!("Skipping campaign level {}: {e}", i + 1);
+				None
+			}
+		})
+		.collect()
+}
+
+fn parse_level(text: &str) -> Result<Level, String> {
+	let mut par = None;
+	let mut puzzle_text = String::new();
+	for line in text.lines() {
+		match line.trim().strip_prefix("par:") {
+			Some(value) => {
+				let [pieces, seconds] = value.split_whitespace().collect::<Vec<_>>()[..]
+					else { return Err(format!("Expected `par: PIECES SECONDS`, got `{line}`")); };
+				let parse = |n: &str| n.parse::<u32>().map_err(|_| format!("Bad par `{n}`"));
+				par = Some((parse(pieces)?, parse(seconds)?));
+			}
+			None => {
+				puzzle_text += line;
+				puzzle_text.push('\n');
+			}
+		}
+	}
+	let (par_pieces, par_seconds) = par.ok_or("Missing `par`")?;
+	Ok(Level { puzzle: puzzle::Puzzle::parse(&puzzle_text)?, par_pieces, par_seconds })
+}
+
+/// The most stars earned on each level, by name, so reordering levels keeps them.
+#[derive(Debug,Default)]
+pub struct Progress {
+	stars: BTreeMap<String, u32>,
+}
+
+impl Progress {
+	/// Unreadable lines are skipped.
+	pub fn load() -> Progress {
+		let stars = storage::load(&profile::file(PROGRESS_FILE))
+			.unwrap_or_default()
+			.lines()
+			.filter_map(|line| {
+				let (stars, name) = line.split_once(' ')?;
+				Some((name.to_string(), stars.parse().ok()?))
+			})
+			.collect();
+		Self { stars }
+	}
+
+	/// 0 for a level not yet cleared.
+	pub fn stars(&self, name: &str) -> u32 {
+		self.stars.get(name).copied().unwrap_or(0)
+	}
+
+	pub fn total_stars(&self) -> u32 {
+		self.stars.values().sum()
+	}
+
+	/// The first level is always open; the rest, once the one before is cleared.
+	pub fn is_unlocked(&self, levels: &[Level], index: usize) -> bool {
+		index == 0 || levels.get(index - 1).is_some_and(|before| self.stars(&before.puzzle.name) > 0)
+	}
+
+	/// Keeps the better of this and what was there; returns whether this was better.
+	pub fn submit(&mut self, name: &str, stars: u32) -> bool {
+		if self.stars(name) >= stars {
+			return false;
+		}
+		self.stars.insert(name.to_string(), stars);
+		let contents: String = self.stars.iter()
+			.map(|(name, stars)| format!("{stars} {name}\n"))
+			.collect();
+		if let Err(e) = storage::save(&profile::file(PROGRESS_FILE), &contents) {
+			log::error!("Could not save campaign progress: {e}");
+		}
+		true
+	}
+}
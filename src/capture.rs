@@ -0,0 +1,253 @@
+//! Turning frames into image files; the frontend grabs the pixels.
+
+use crate::records;
+use crate::tetris::CellKind;
+
+/// `rgba` is row-major from the top, four bytes per pixel.
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, String> {
+	let mut bytes = Vec::new();
+	let mut encoder = png::Encoder::new(&mut bytes, width, height);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(png::BitDepth::Eight);
+	let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+	writer.write_image_data(rgba).map_err(|e| e.to_string())?;
+	writer.finish().map_err(|e| e.to_string())?;
+	Ok(bytes)
+}
+
+/// E.g. `screenshot-2026-10-15-134502.png`, UTC.
+pub fn timestamped_file_name(prefix: &str, extension: &str, unix_secs: u64) -> String {
+	let secs_of_day = unix_secs % 86_400;
+	format!("{prefix}-{}-{:02}{:02}{:02}.{extension}",
+		records::date_string(unix_secs), secs_of_day / 3600, secs_of_day / 60 % 60, secs_of_day % 60)
+}
+
+/// 256 colors, indexed; frames are one index per pixel, row-major from the top. Loops forever.
+pub fn encode_gif(width: u16, height: u16, palette: &[[u8; 3]; 256], frames: &[Vec<u8>], delay_centiseconds: u16) -> Vec<u8> {
+	let mut bytes = Vec::new();
+	bytes.extend_from_slice(b"GIF89a");
+	bytes.extend_from_slice(&width.to_le_bytes());
+	bytes.extend_from_slice(&height.to_le_bytes());
+	// Global color table of 2^(7+1) entries, 8 bits per primary.
+	bytes.extend_from_slice(&[0xf7, 0, 0]);
+	bytes.extend(palette.iter().flatten());
+	// NETSCAPE2.0 application extension: loop forever.
+	bytes.extend_from_slice(&[0x21, 0xff, 0x0b]);
+	bytes.extend_from_slice(b"NETSCAPE2.0");
+	bytes.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+	for frame in frames {
+		// Graphic control extension: just the delay.
+		bytes.extend_from_slice(&[0x21, 0xf9, 0x04, 0x00]);
+		bytes.extend_from_slice(&delay_centiseconds.to_le_bytes());
+		bytes.extend_from_slice(&[0x00, 0x00]);
+		// Image descriptor: the whole canvas, no local color table.
+		bytes.push(0x2c);
+		bytes.extend_from_slice(&[0, 0, 0, 0]);
+		bytes.extend_from_slice(&width.to_le_bytes());
+		bytes.extend_from_slice(&height.to_le_bytes());
+		bytes.push(0x00);
+		bytes.push(8); // LZW minimum code size
+		for block in lzw_encode(frame).chunks(255) {
+			bytes.push(block.len() as u8);
+			bytes.extend_from_slice(block);
+		}
+		bytes.push(0x00);
+	}
+	bytes.push(0x3b);
+	bytes
+}
+
+/// GIF-flavored LZW with 8-bit symbols: variable-width codes packed least significant bit first.
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+	const CLEAR: u16 = 256;
+	const END: u16 = 257;
+	const MAX_CODES: u16 = 4096;
+	let mut out = Vec::new();
+	let (mut buffer, mut buffered_bits) = (0_u32, 0_u32);
+	let mut emit = |code: u16, code_size: u32, out: &mut Vec<u8>| {
+		buffer |= (code as u32) << buffered_bits;
+		buffered_bits += code_size;
+		while buffered_bits >= 8 {
+			out.push(buffer as u8);
+			buffer >>= 8;
+			buffered_bits -= 8;
+		}
+	};
+	let mut dictionary: std::collections::HashMap<(u16, u8), u16> = std::collections::HashMap::new();
+	let mut next_code = END + 1;
+	let mut code_size = 9;
+	emit(CLEAR, code_size, &mut out);
+	let Some((&first, rest)) = indices.split_first()
+		else {
+			emit(END, code_size, &mut out);
+			emit(0, 7, &mut out); // flush
+			return out;
+		};
+	let mut prefix = first as u16;
+	for &k in rest {
+		if let Some(&code) = dictionary.get(&(prefix, k)) {
+			prefix = code;
+			continue;
+		}
+		emit(prefix, code_size, &mut out);
+		if next_code < MAX_CODES {
+			if next_code == 1 << code_size {
+				code_size += 1;
+			}
+			dictionary.insert((prefix, k), next_code);
+			next_code += 1;
+		} else {
+			emit(CLEAR, code_size, &mut out);
+			dictionary.clear();
+			next_code = END + 1;
+			code_size = 9;
+		}
+		prefix = k as u16;
+	}
+	emit(prefix, code_size, &mut out);
+	emit(END, code_size, &mut out);
+	emit(0, 7, &mut out); // flush
+	out
+}
+
+/// Same math as macroquad's `color::hsl_to_rgb()`, without the dependency.
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> [u8; 3] {
+	let channel = |n: f32| {
+		let k = (n + h * 12.0) % 12.0;
+		let a = s * l.min(1.0 - l);
+		let v = l - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0);
+		(v * 255.0).round() as u8
+	};
+	[channel(0.0), channel(8.0), channel(4.0)]
+}
+
+const BACKGROUND: u8 = 0;
+const FLASH: u8 = 1;
+const GRID: u8 = 2;
+const GARBAGE: u8 = 3;
+const FROZEN: u8 = 4;
+const BEDROCK: u8 = 5;
+/// Hues are quantized this finely; the stack and the falling piece each get a set.
+const HUES: usize = 64;
+const FIRST_STACK: usize = 6;
+const FIRST_PIECE: usize = FIRST_STACK + HUES;
+
+fn board_palette() -> [[u8; 3]; 256] {
+	let mut palette = [[0_u8; 3]; 256];
+	palette[FLASH as usize] = [255, 255, 255];
+	palette[GRID as usize] = [80, 80, 80];
+	// HARDCODE Matches gui.rs
+	palette[GARBAGE as usize] = [130, 130, 130];
+	palette[FROZEN as usize] = [178, 217, 255];
+	palette[BEDROCK as usize] = [51, 51, 51];
+	for i in 0 .. HUES {
+		let hue = i as f32 / HUES as f32;
+		// HARDCODE Matches gui.rs
+		palette[FIRST_STACK + i] = hsl_to_rgb(hue, 0.5, 0.3);
+		palette[FIRST_PIECE + i] = hsl_to_rgb(hue, 1.0, 0.5);
+	}
+	palette
+}
+
+fn hue_index(hue: f32) -> usize {
+	((hue.rem_euclid(1.0) * HUES as f32) as usize).min(HUES - 1)
+}
+
+/// Rolling buffer of what the board looked like, for saving the last few seconds as a GIF.
+/// Draws from GameState rather than grabbing the screen, so it costs next to nothing per frame.
+#[derive(Debug)]
+pub struct BoardRecorder {
+	/// One palette index per visible cell, row-major from the top.
+	frames: std::collections::VecDeque<Vec<u8>>,
+	width: usize,
+	height: usize,
+	last_tick: u64,
+}
+
+impl BoardRecorder {
+	pub const FRAMES_PER_SECOND: u32 = 10;
+	pub const SECONDS: u32 = 10;
+
+	pub fn new() -> BoardRecorder {
+		Self { frames: std::collections::VecDeque::new(), width: 0, height: 0, last_tick: 0 }
+	}
+
+	/// Call after every GameState::tick().
+	pub fn observe(&mut self, game_state: &crate::tetris::GameState) {
+		let width = game_state.cell_matrix_width;
+		let height = game_state.cell_matrix.len() - game_state.hidden_rows;
+		if game_state.ticks < self.last_tick || (width, height) != (self.width, self.height) {
+			// New game
+			self.frames.clear();
+			(self.width, self.height) = (width, height);
+		}
+		let ticks_per_frame = (crate::tetris::TICKS_PER_SECOND / Self::FRAMES_PER_SECOND) as u64;
+		if game_state.ticks == self.last_tick || !game_state.ticks.is_multiple_of(ticks_per_frame) {
+			self.last_tick = game_state.ticks;
+			return;
+		}
+		self.last_tick = game_state.ticks;
+		let hidden_rows = game_state.hidden_rows;
+		let mut frame: Vec<u8> = game_state.cell_matrix.iter()
+			.skip(hidden_rows)
+			.flat_map(|row| row.cells.iter().map(|c| match c {
+				Some(c) => match c.kind {
+					CellKind::Garbage => GARBAGE,
+					CellKind::Frozen => FROZEN,
+					CellKind::Bedrock => BEDROCK,
+					CellKind::Normal | CellKind::Bomb => (FIRST_STACK + hue_index(c.hue)) as u8,
+				},
+				None => BACKGROUND,
+			}))
+			.collect();
+		if let crate::tetris::Phase::LineClear { rows, columns, .. } = &game_state.phase {
+			for &y in rows.iter().filter(|&&y| y >= hidden_rows) {
+				frame[(y - hidden_rows) * width .. (y - hidden_rows + 1) * width].fill(FLASH);
+			}
+			for &x in columns.iter() {
+				frame.iter_mut().skip(x).step_by(width).for_each(|pixel| *pixel = FLASH);
+			}
+		}
+		if let Some(p) = game_state.current_piece.as_ref() {
+			for (c, x, y) in p.iter_global_space(game_state.current_piece_mass_xy) {
+				let y = y - hidden_rows as i32;
+				if y >= 0 && (x as usize) < width {
+					frame[y as usize * width + x as usize] = (FIRST_PIECE + hue_index(c.hue)) as u8;
+				}
+			}
+		}
+		self.frames.push_back(frame);
+		if self.frames.len() > (Self::FRAMES_PER_SECOND * Self::SECONDS) as usize {
+			self.frames.pop_front();
+		}
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.frames.is_empty()
+	}
+
+	/// Everything still buffered, with each cell drawn `cell_px` pixels square.
+	pub fn to_gif(&self, cell_px: usize) -> Vec<u8> {
+		let (width_px, height_px) = (self.width * cell_px, self.height * cell_px);
+		let frames: Vec<Vec<u8>> = self.frames.iter()
+			.map(|cells| {
+				let mut pixels = vec![BACKGROUND; width_px * height_px];
+				for (y, row) in pixels.chunks_exact_mut(width_px).enumerate() {
+					for (x, pixel) in row.iter_mut().enumerate() {
+						let cell = cells[(y / cell_px) * self.width + x / cell_px];
+						let is_grid_line = x % (4 * cell_px) == 0 && x > 0;
+						*pixel = if cell == BACKGROUND && is_grid_line { GRID } else { cell };
+					}
+				}
+				pixels
+			})
+			.collect();
+		encode_gif(width_px as u16, height_px as u16, &board_palette(), &frames, (100 / Self::FRAMES_PER_SECOND) as u16)
+	}
+}
+
+impl Default for BoardRecorder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
@@ -0,0 +1,66 @@
+//! Cheese race: the well starts with rows of garbage, each with its own hole, and more rise from below as they're
+//! dug out, until `target` garbage rows in all are gone. The time that takes is the score; see records::CheeseRecord.
+
+use crate::garbage;
+use crate::tetris;
+
+/// HARDCODE At most this many garbage rows are in the well at once.
+pub const MAX_GARBAGE_ROWS: usize = 10;
+
+#[derive(Debug)]
+pub struct CheeseRace {
+	/// Garbage rows to dig through in all.
+	pub target: u32,
+	/// Garbage rows put in the well so far.
+	added: u32,
+	/// Garbage rows cleared so far.
+	pub dug: u32,
+	pub finished_at_tick: Option<u64>,
+	/// Seeded from the game on its first tick, so the same seed digs through the same cheese.
+	generator: Option<garbage::RandomPerRow>,
+}
+
+impl CheeseRace {
+	pub fn new(target: u32) -> CheeseRace {
+		Self { target, added: 0, dug: 0, finished_at_tick: None, generator: None }
+	}
+
+	/// For a new attempt; the garbage goes in on the first top_up().
+	pub fn restart(&mut self) {
+		*self = Self::new(self.target);
+	}
+
+	/// Call before every tick: counts what's been dug out, then tops the well back up to as many garbage rows as
+	/// there's room for, or as are left to go.
+	pub fn top_up(&mut self, game_state: &mut tetris::GameState) {
+		let on_board = garbage_rows(game_state);
+		self.dug = self.added.saturating_sub(on_board);
+		if self.finished_at_tick.is_none() && self.dug >= self.target {
+			self.finished_at_tick = Some(game_state.ticks);
+			return;
+		}
+		// Anything rising mid-clear would shift the rows about to go.
+		if matches!(game_state.phase, tetris::Phase::LineClear { .. }) || !game_state.is_alive {
+			return;
+		}
+		let visible = game_state.cell_matrix.len() - game_state.hidden_rows;
+		let room = MAX_GARBAGE_ROWS.min(visible / 2) as u32;
+		let count = room.saturating_sub(on_board).min(self.target - self.added);
+		if count > 0 {
+			let generator = self.generator.get_or_insert_with(|| garbage::RandomPerRow::new(game_state.seed));
+			game_state.add_garbage(count as usize, generator);
+			self.added += count;
+		}
+	}
+
+	pub fn is_finished(&self) -> bool {
+		self.finished_at_tick.is_some()
+	}
+}
+
+/// Rows with any garbage in them; only garbage rows do, outside rules that move cells about.
+fn garbage_rows(game_state: &tetris::GameState) -> u32 {
+	game_state.cell_matrix.iter()
+		.filter(|row| row.cells.iter().flatten().any(|c| c.kind == tetris::CellKind::Garbage))
+		.count() as u32
+}
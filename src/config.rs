@@ -0,0 +1,327 @@
+use crate::difficulty;
+use crate::input;
+use crate::log;
+use crate::netplay;
+use crate::profile;
+use crate::ruleset;
+use crate::speed;
+use crate::storage;
+use crate::tetris;
+use crate::theme;
+
+const CONFIG_FILE: &str = "config.txt";
+/// Modes that take `previews.<mode>` and `hold.<mode>`; the rest play with the defaults, versus so that both
+/// copies of each game agree.
+pub const QUEUE_OPTION_MODES: [&str; 6] = ["marathon", "sprint", "daily", "practice", "finesse", "cheese"];
+/// HARDCODE The longest cheese race there are records for.
+const MAX_CHEESE_ROWS: u32 = 100;
+
+/// User settings from `config.txt`; one `key = value` per line, `#` for comments.
+#[derive(Clone,Debug)]
+pub struct Config {
+	pub player_name: String,
+	/// Base URL of an online leaderboard; see the leaderboard module. None to stay offline.
+	pub leaderboard_url: Option<String>,
+	/// Base URL to sync the profile's files with, and the token to do it with; see the sync module. None to keep them
+	/// on this machine.
+	pub sync_url: Option<String>,
+	pub sync_token: String,
+	/// Where to find opponents; see the netplay module. The versus screen can change it for the session.
+	pub netplay_url: String,
+	pub log_level: log::Level,
+	/// Relative to the storage directory; None to log to the console only.
+	pub log_file: Option<String>,
+	/// For marathons; see ruleset::NAMES.
+	pub ruleset: String,
+	/// Ditto; `polyominoes` or `classic`.
+	pub piece_set: tetris::PieceSet,
+	/// How each new piece relates to the ones before it; `memoryless`, or `history <size> <rerolls>`.
+	pub randomizer: tetris::RandomizerStrategy,
+	pub grid: theme::GridStyle,
+	pub blocks: theme::BlockStyle,
+	/// `--capture` on the command line wins over this.
+	pub capture: theme::Capture,
+	/// From the `scanlines`, `bloom`, and `vignette` keys.
+	pub effects: theme::Effects,
+	/// In versus, the height and surface of the opponent's stack, under their board.
+	pub stack_gauge: bool,
+	/// Stars drifting behind the well.
+	pub animated_background: bool,
+	/// Turns off anything that moves on its own, like the animated background.
+	/// The web build also follows the browser's setting; see bridge::prefers_reduced_motion().
+	pub reduced_motion: bool,
+	/// Starting gravity and tick rate; changeable mid-game.
+	pub speed: speed::Preset,
+	/// Overrides the speed's gravity until it changes; see tetris::FallSpeed::parse(). None for the speed's.
+	pub gravity: Option<tetris::FallSpeed>,
+	/// For practice and versus; the versus screen can change it for the session.
+	pub difficulty: difficulty::Preset,
+	/// Games in a versus set; odd, so somebody wins it. The versus screen can change it for the session.
+	pub best_of: u32,
+	/// Garbage rows a cheese race digs through; records are kept per count. See the cheese module.
+	pub cheese_rows: u32,
+	/// Saves every key press and release of each game under `inputs/`; see input::InputLog.
+	pub input_log: bool,
+	/// Saves every tick of each game, what was on the board, what was pressed, and the reward, under `training/` as
+	/// newline-delimited JSON; see env::TrainingLog.
+	pub training_log: bool,
+	/// Where to serve the live feed for stream overlays, e.g. `127.0.0.1:7373`; see the overlay module. None for no feed.
+	pub overlay_listen: Option<String>,
+	/// Bindings to start from; see input::Profile.
+	pub controls: input::Profile,
+	/// From `keys.<command> = key, key, ...` lines, e.g. `keys.rotate_ccw = z, left_control`; each replaces
+	/// the keys controls has for that command.
+	pub key_overrides: Vec<(input::Command, Vec<input::Key>)>,
+	/// From `previews.<mode> = 0` to 6 and `hold.<mode> = false` lines, e.g. for marathons the classic way;
+	/// see QUEUE_OPTION_MODES.
+	pub queue_options: Vec<(String, tetris::QueueOptions)>,
+	/// From the `das`, `arr`, and `das_cut` keys, in ticks.
+	pub handling: input::Handling,
+	/// From the `soft_drop_locks`, `rotation_resets_lock`, and `max_lock_resets` keys; versus plays with the defaults.
+	pub lock_options: tetris::LockOptions,
+	/// Play with the mouse too: the piece follows the cursor, left click turns, right click hard drops.
+	pub mouse_controls: bool,
+	/// A TTF file for the score, menus, and game over screen, e.g. `assets/fonts/main.ttf`; None for the built-in font,
+	/// which is also what a missing file gets.
+	pub font: Option<String>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			player_name: "anonymous".to_string(),
+			leaderboard_url: None,
+			sync_url: None,
+			sync_token: String::new(),
+			netplay_url: "ws://localhost:9001/".to_string(),
+			log_level: log::Level::Info,
+			log_file: Some("log.txt".to_string()),
+			ruleset: "standard".to_string(),
+			piece_set: tetris::PieceSet::Polyominoes,
+			randomizer: tetris::RandomizerStrategy::History { size: 4, rerolls: 4 },
+			grid: theme::GridStyle::Lattice,
+			blocks: theme::BlockStyle::Flat,
+			capture: theme::Capture::Off,
+			effects: theme::Effects::default(),
+			stack_gauge: false,
+			animated_background: true,
+			reduced_motion: false,
+			speed: speed::Preset::Normal,
+			gravity: None,
+			difficulty: difficulty::Preset::Standard,
+			best_of: 3,
+			cheese_rows: 18,
+			input_log: false,
+			training_log: false,
+			overlay_listen: None,
+			controls: input::Profile::Classic,
+			key_overrides: Vec::new(),
+			queue_options: Vec::new(),
+			handling: input::Handling::default(),
+			lock_options: tetris::LockOptions::default(),
+			mouse_controls: false,
+			font: Some("assets/fonts/main.ttf".to_string()),
+		}
+	}
+}
+
+impl Config {
+	/// Falls back to defaults for anything missing or unreadable.
+	pub fn load() -> Config {
+		let mut config = Config::default();
+		// A profile plays under its own name, unless its config says otherwise.
+		if let Some(name) = profile::current() {
+			config.player_name = name;
+		}
+		let text = storage::load(&profile::file(CONFIG_FILE)).or_else(|| storage::load(CONFIG_FILE)).unwrap_or_default();
+		for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+			let Some((key, value)) = line.split_once('=')
+				else { continue; };
+			let value = value.trim();
+			match key.trim() {
+				"player_name" => config.player_name = value.to_string(),
+				"leaderboard_url" => config.leaderboard_url = Some(value.to_string()).filter(|v| !v.is_empty()),
+				"sync_url" => config.sync_url = Some(value.to_string()).filter(|v| !v.is_empty()),
+				"sync_token" => config.sync_token = value.to_string(),
+				"netplay_url" => config.netplay_url = value.to_string(),
+				"log_level" => match log::Level::parse(value) {
+					Some(level) => config.log_level = level,
+					None => log::warn!("Ignoring unknown log level `{value}`"),
+				},
+				"log_file" => config.log_file = Some(value.to_string()).filter(|v| !v.is_empty()),
+				"ruleset" => match ruleset::by_name(value) {
+					Some(ruleset) => config.ruleset = ruleset.name().to_string(),
+					None => log::warn!("Ignoring unknown ruleset `{value}`"),
+				},
+				"pieces" => match tetris::PieceSet::parse(value) {
+					Some(piece_set) => config.piece_set = piece_set,
+					None => log::warn!("Ignoring unknown piece set `{value}`; expected polyominoes or classic"),
+				},
+				"randomizer" => match tetris::RandomizerStrategy::parse(value) {
+					Some(strategy) => config.randomizer = strategy,
+					None => log::warn!("Ignoring `{value}` for randomizer; expected memoryless or history <size> <rerolls>"),
+				},
+				"grid" => match theme::GridStyle::parse(value) {
+					Some(style) => config.grid = style,
+					None => log::warn!("Ignoring unknown grid style `{value}`; expected off, sparse, lattice, or outlines"),
+				},
+				"blocks" => match theme::BlockStyle::parse(value) {
+					Some(style) => config.blocks = style,
+					None => log::warn!("Ignoring unknown block style `{value}`; expected flat, beveled, or connected"),
+				},
+				"capture" => match theme::Capture::parse(value) {
+					Some(capture) => config.capture = capture,
+					None => log::warn!("Ignoring unknown capture background `{value}`; expected off, transparent, or chroma"),
+				},
+				"scanlines" => match parse_bool(value) {
+					Some(b) => config.effects.scanlines = b,
+					None => log::warn!("Ignoring `{value}` for scanlines; expected true or false"),
+				},
+				"bloom" => match parse_bool(value) {
+					Some(b) => config.effects.bloom = b,
+					None => log::warn!("Ignoring `{value}` for bloom; expected true or false"),
+				},
+				"vignette" => match parse_bool(value) {
+					Some(b) => config.effects.vignette = b,
+					None => log::warn!("Ignoring `{value}` for vignette; expected true or false"),
+				},
+				"stack_gauge" => match parse_bool(value) {
+					Some(b) => config.stack_gauge = b,
+					None => log::warn!("Ignoring `{value}` for stack_gauge; expected true or false"),
+				},
+				"animated_background" => match parse_bool(value) {
+					Some(b) => config.animated_background = b,
+					None => log::warn!("Ignoring `{value}` for animated_background; expected true or false"),
+				},
+				"reduced_motion" => match parse_bool(value) {
+					Some(b) => config.reduced_motion = b,
+					None => log::warn!("Ignoring `{value}` for reduced_motion; expected true or false"),
+				},
+				"speed" => match speed::Preset::parse(value) {
+					Some(preset) => config.speed = preset,
+					None => log::warn!("Ignoring unknown speed `{value}`; expected easy, normal, hard, or insane"),
+				},
+				"gravity" => match tetris::FallSpeed::parse(value) {
+					Some(speed) => config.gravity = Some(speed),
+					None => log::warn!("Ignoring `{value}` for gravity; expected cells per second, like 2.5, or G, like 20G"),
+				},
+				"difficulty" => match difficulty::Preset::parse(value) {
+					Some(preset) => config.difficulty = preset,
+					None => log::warn!("Ignoring unknown difficulty `{value}`; expected beginner, standard, expert, or master"),
+				},
+				"cheese_rows" => match value.parse::<u32>() {
+					Ok(n) if (1 ..= MAX_CHEESE_ROWS).contains(&n) => config.cheese_rows = n,
+					_ => log::warn!("Ignoring `{value}` for cheese_rows; expected a number from 1 to {MAX_CHEESE_ROWS}"),
+				},
+				"best_of" => match value.parse::<u32>() {
+					Ok(n) if n % 2 == 1 && n <= netplay::MAX_BEST_OF => config.best_of = n,
+					_ => log::warn!("Ignoring `{value}` for best_of; expected an odd number from 1 to {}", netplay::MAX_BEST_OF),
+				},
+				"overlay_listen" => config.overlay_listen = Some(value.to_string()).filter(|v| !v.is_empty()),
+				"input_log" => match parse_bool(value) {
+					Some(b) => config.input_log = b,
+					None => log::warn!("Ignoring `{value}` for input_log; expected true or false"),
+				},
+				"training_log" => match parse_bool(value) {
+					Some(b) => config.training_log = b,
+					None => log::warn!("Ignoring `{value}` for training_log; expected true or false"),
+				},
+				"mouse_controls" => match parse_bool(value) {
+					Some(b) => config.mouse_controls = b,
+					None => log::warn!("Ignoring `{value}` for mouse_controls; expected true or false"),
+				},
+				"das" => match value.parse::<u32>() {
+					Ok(n) => config.handling.das = n,
+					_ => log::warn!("Ignoring `{value}` for das; expected a number of ticks, 0 for no auto-repeat"),
+				},
+				"arr" => match value.parse::<u32>() {
+					Ok(n) => config.handling.arr = n,
+					_ => log::warn!("Ignoring `{value}` for arr; expected a number of ticks"),
+				},
+				"das_cut" => match value.parse::<u32>() {
+					Ok(n) => config.handling.das_cut = n,
+					_ => log::warn!("Ignoring `{value}` for das_cut; expected a number of ticks"),
+				},
+				"soft_drop_locks" => match parse_bool(value) {
+					Some(b) => config.lock_options.soft_drop_locks = b,
+					None => log::warn!("Ignoring `{value}` for soft_drop_locks; expected true or false"),
+				},
+				"rotation_resets_lock" => match parse_bool(value) {
+					Some(b) => config.lock_options.rotation_resets = b,
+					None => log::warn!("Ignoring `{value}` for rotation_resets_lock; expected true or false"),
+				},
+				"max_lock_resets" => match value.parse::<u32>() {
+					Ok(n) => config.lock_options.max_resets = Some(n),
+					_ if value.is_empty() => config.lock_options.max_resets = None,
+					_ => log::warn!("Ignoring `{value}` for max_lock_resets; expected a number, or nothing for the rules' own"),
+				},
+				"font" => config.font = Some(value.to_string()).filter(|v| !v.is_empty()),
+				"controls" => match input::Profile::parse(value) {
+					Some(profile) => config.controls = profile,
+					None => log::warn!("Ignoring unknown controls `{value}`; expected classic, guideline, or left-handed"),
+				},
+				other if other.starts_with("keys.") => {
+					let Some(command) = input::Command::parse(&other["keys.".len() ..])
+						else {
+							log::warn!("Ignoring `{other}`; expected one of move_left, move_right, rotate_cw, rotate_ccw, soft_drop, hold, hard_drop, or sonic_drop after `keys.`");
+							continue;
+						};
+					let mut keys = Vec::new();
+					for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+						match input::Key::parse(name) {
+							Some(key) => keys.push(key),
+							None => log::warn!("Ignoring unknown key `{name}` for {other}"),
+						}
+					}
+					config.key_overrides.push((command, keys));
+				}
+				other if other.starts_with("previews.") || other.starts_with("hold.") => {
+					let (option, mode) = other.split_once('.').expect("Should have a dot");
+					if !QUEUE_OPTION_MODES.contains(&mode) {
+						log::warn!("Ignoring `{other}`; expected one of {} after `{option}.`", QUEUE_OPTION_MODES.join(", "));
+						continue;
+					}
+					let mut options = config.queue_options(mode);
+					match option {
+						"previews" => match value.parse::<usize>() {
+							Ok(n) if n <= tetris::MAX_PREVIEW_PIECES => options.previews = n,
+							_ => log::warn!("Ignoring `{value}` for {other}; expected 0 to {}", tetris::MAX_PREVIEW_PIECES),
+						},
+						_ => match parse_bool(value) {
+							Some(b) => options.hold = b,
+							None => log::warn!("Ignoring `{value}` for {other}; expected true or false"),
+						},
+					}
+					config.queue_options.retain(|(m, _options)| m != mode);
+					config.queue_options.push((mode.to_string(), options));
+				}
+				other => log::warn!("Ignoring unknown config key `{other}`"),
+			}
+		}
+		config
+	}
+
+	/// The controls profile with key_overrides applied.
+	pub fn bindings(&self) -> Vec<(input::Control, input::Command)> {
+		let mut bindings = self.controls.bindings();
+		for (command, keys) in self.key_overrides.iter() {
+			input::rebind_keys(&mut bindings, *command, keys);
+		}
+		bindings
+	}
+
+	/// For `mode`, one of QUEUE_OPTION_MODES; the defaults for anything else.
+	pub fn queue_options(&self, mode: &str) -> tetris::QueueOptions {
+		self.queue_options.iter()
+			.find(|(m, _options)| m == mode)
+			.map_or_else(tetris::QueueOptions::default, |(_mode, options)| *options)
+	}
+}
+
+fn parse_bool(text: &str) -> Option<bool> {
+	match text.to_ascii_lowercase().as_str() {
+		"true" | "yes" | "on" | "1" => Some(true),
+		"false" | "no" | "off" | "0" => Some(false),
+		_ => None,
+	}
+}
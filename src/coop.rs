@@ -0,0 +1,49 @@
+//! Co-op: two players at one keyboard, each with a falling piece of their own on one board twice as wide, sharing
+//! the next queue, the hold box, and the score; see tetris::GameState::set_players(). Pieces can't pass through
+//! each other, so players keep out of each other's way or help each other along.
+//! The twin variant is the same with one player at both sets of keys.
+
+use std::collections::BTreeSet;
+
+use crate::input;
+use crate::tetris;
+
+pub const PLAYERS: usize = 2;
+
+/// Each player's keys, and what they've pressed that the game hasn't had yet.
+#[derive(Debug)]
+pub struct Coop {
+	inputs: Vec<input::Input>,
+	pending: Vec<Vec<tetris::Action>>,
+}
+
+impl Coop {
+	/// Keys per input::split_bindings(), the first player's on the left.
+	pub fn new(handling: input::Handling) -> Coop {
+		let inputs: Vec<input::Input> = input::split_bindings().into_iter()
+			.map(|bindings| input::Input::new(bindings, handling))
+			.collect();
+		Self { pending: vec![Vec::new(); inputs.len()], inputs }
+	}
+
+	/// Forget anything pressed but not yet applied, e.g. for a restart.
+	pub fn clear(&mut self) {
+		self.pending.iter_mut().for_each(Vec::clear);
+	}
+
+	/// Call whenever input is polled, with everything held right now; see input::Input::actions().
+	pub fn poll(&mut self, held: &BTreeSet<input::Control>) {
+		for (input, pending) in self.inputs.iter_mut().zip(self.pending.iter_mut()) {
+			pending.extend(input.actions(held));
+		}
+	}
+
+	/// Call before each tick: hands each player's actions to their own piece.
+	pub fn apply(&mut self, game_state: &mut tetris::GameState) {
+		for (player, pending) in self.pending.iter_mut().enumerate() {
+			for action in pending.drain(..) {
+				game_state.apply_as(player, action);
+			}
+		}
+	}
+}
@@ -0,0 +1,93 @@
+//! Handicaps: presets that make the game easier or harder on top of the ruleset, so players of different skill
+//! can race each other fairly in versus, each on their own preset.
+//!
+//! Standard changes nothing. The others adjust gravity and its curve, the lock delay, how far ahead the next queue
+//! shows, and how many rows of garbage the well starts with.
+
+use crate::garbage;
+use crate::tetris;
+
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum Preset {
+	Beginner,
+	#[default]
+	Standard,
+	Expert,
+	Master,
+}
+
+/// HARDCODE Moves and rotations that restart a preset's lock delay, per piece; as in the guideline rules.
+pub const MAX_LOCK_RESETS: u32 = 15;
+
+impl Preset {
+	pub const ALL: [Preset; 4] = [Preset::Beginner, Preset::Standard, Preset::Expert, Preset::Master];
+
+	pub fn parse(text: &str) -> Option<Preset> {
+		Self::ALL.into_iter().find(|p| p.name().eq_ignore_ascii_case(text))
+	}
+
+	/// Stable; sent over netplay.
+	pub fn name(self) -> &'static str {
+		match self {
+			Preset::Beginner => "beginner",
+			Preset::Standard => "standard",
+			Preset::Expert => "expert",
+			Preset::Master => "master",
+		}
+	}
+
+	/// The one after, wrapping around; for menus that cycle through them.
+	pub fn next(self) -> Preset {
+		let i = Self::ALL.iter().position(|&p| p == self).expect("Should be listed");
+		Self::ALL[(i + 1) % Self::ALL.len()]
+	}
+
+	/// The gravity curve: from the ruleset's ticks per drop at `level`, before soft drop, to this preset's.
+	pub fn ticks_per_drop(self, ruleset_ticks: u32, level: u32) -> u32 {
+		// HARDCODE
+		match self {
+			Preset::Beginner => ruleset_ticks * 2,
+			Preset::Standard => ruleset_ticks,
+			// One tick faster every level, on top of whatever the ruleset does.
+			Preset::Expert => ruleset_ticks.saturating_sub(level).max(1),
+			Preset::Master => (ruleset_ticks / 2).saturating_sub(level).max(1),
+		}
+	}
+
+	/// Replaces Ruleset::lock_delay() when Some, with up to MAX_LOCK_RESETS resets.
+	pub fn lock_delay(self) -> Option<u32> {
+		// HARDCODE
+		match self {
+			Preset::Beginner => Some(60),
+			Preset::Standard | Preset::Expert => None,
+			Preset::Master => Some(0),
+		}
+	}
+
+	/// How much of the next queue shows, from as much as the mode would show.
+	pub fn preview_pieces(self, mode_previews: usize) -> usize {
+		// HARDCODE
+		match self {
+			Preset::Beginner => (mode_previews + 2).min(tetris::MAX_PREVIEW_PIECES),
+			Preset::Standard => mode_previews,
+			Preset::Expert => mode_previews.min(2),
+			Preset::Master => mode_previews.min(1),
+		}
+	}
+
+	/// Rows already in the well when the game starts.
+	pub fn garbage_rows(self) -> usize {
+		// HARDCODE
+		match self {
+			Preset::Beginner | Preset::Standard => 0,
+			Preset::Expert => 4,
+			Preset::Master => 8,
+		}
+	}
+
+	/// Where the holes in the starting garbage go. The same every game rather than drawn from the seed,
+	/// so a copy of the game, like the opponent's in versus, needs no more than the preset to start out alike.
+	pub fn garbage_generator() -> garbage::Repeating {
+		garbage::Repeating::new(vec![1, 5, 2, 6, 0, 4, 3, 7]) // HARDCODE
+	}
+}
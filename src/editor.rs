@@ -0,0 +1,81 @@
+//! Painting a starting position cell by cell, to play from or to keep as a puzzle.
+
+use crate::layout;
+use crate::puzzle;
+use crate::storage;
+use crate::tetris::CellKind;
+
+/// Puzzles saved from the editor, one storage name per line; the bundled ones live in the assets instead.
+pub const SAVED_PUZZLES_INDEX: &str = "puzzles/index.txt";
+
+#[derive(Clone,Debug)]
+pub struct Editor {
+	/// Visible rows only, top to bottom, like puzzle::Puzzle::stack.
+	pub cells: Vec<Vec<Option<CellKind>>>,
+	/// What painting puts down.
+	pub brush: CellKind,
+}
+
+impl Editor {
+	/// Brushes in number-key order.
+	pub const BRUSHES: [CellKind; 4] = [CellKind::Normal, CellKind::Garbage, CellKind::Frozen, CellKind::Bedrock];
+
+	pub fn new(width: usize, height: usize) -> Editor {
+		Self { cells: vec![vec![None; width]; height], brush: CellKind::Normal }
+	}
+
+	pub fn clear(&mut self) {
+		self.cells.iter_mut().flatten().for_each(|c| *c = None);
+	}
+
+	/// None erases.
+	pub fn paint(&mut self, (x, y): (usize, usize), kind: Option<CellKind>) {
+		if let Some(cell) = self.cells.get_mut(y).and_then(|row| row.get_mut(x)) {
+			*cell = kind;
+		}
+	}
+
+	/// Which cell of a board drawn in `board` is under the pixel `(px, py)`, if any.
+	pub fn cell_at(&self, board: layout::Rect, (px, py): (f32, f32)) -> Option<(usize, usize)> {
+		let height = self.cells.len();
+		let width = self.cells.first()?.len();
+		if px < board.x || py < board.y || px >= board.right() || py >= board.bottom() {
+			return None;
+		}
+		let x = ((px - board.x) / board.w * width as f32) as usize;
+		let y = ((py - board.y) / board.h * height as f32) as usize;
+		Some((x.min(width - 1), y.min(height - 1)))
+	}
+
+	/// Empty rows above the highest filled cell are left off, since puzzle stacks sit on the floor anyway.
+	pub fn to_puzzle(&self, name: &str, goal: puzzle::Goal) -> puzzle::Puzzle {
+		let first_filled = self.cells.iter().position(|row| row.iter().any(Option::is_some)).unwrap_or(self.cells.len());
+		puzzle::Puzzle {
+			name: name.to_string(),
+			goal,
+			piece_limit: None,
+			stack: self.cells[first_filled ..].to_vec(),
+		}
+	}
+}
+
+/// Writes `puzzle` to storage under a fresh name and lists it in SAVED_PUZZLES_INDEX.
+pub fn save_puzzle(puzzle: &puzzle::Puzzle, unix_secs: u64) -> std::io::Result<String> {
+	let name = format!("puzzles/custom-{unix_secs}.txt");
+	storage::save(&name, &puzzle.to_text())?;
+	let mut index = storage::load(SAVED_PUZZLES_INDEX).unwrap_or_default();
+	index += &name;
+	index.push('\n');
+	storage::save(SAVED_PUZZLES_INDEX, &index)?;
+	Ok(name)
+}
+
+/// Everything save_puzzle() has kept that still parses.
+pub fn load_saved_puzzles() -> Vec<puzzle::Puzzle> {
+	storage::load(SAVED_PUZZLES_INDEX)
+		.unwrap_or_default()
+		.lines()
+		.filter_map(|name| storage::load(name.trim()))
+		.filter_map(|text| puzzle::Puzzle::parse(&text).ok())
+		.collect()
+}
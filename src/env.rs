@@ -0,0 +1,262 @@
+//! A gym-style environment over a headless game, for training agents from outside this crate: reset(), then
+//! step() once per tick with an index into ACTIONS until it's done.
+//!
+//! `--env` serves the same over stdin and stdout, a JSON line out for each line in, for agents in other languages:
+//!
+//! ```text
+//! reset 42
+//! {"observation":{"width":8,"height":20,"board":[0,0,...],"pieces":[0,1,...],"can_hold":true},"reward":0,"done":false}
+//! step 8
+//! {"observation":{...},"reward":0,"done":false}
+//! ```
+//!
+//! The rest of the arguments are as for `--headless`, for the board, ruleset, and Settings::max_pieces.
+//!
+//! TrainingLog keeps the same observations of games played some other way, by a bot or a person, with the actions
+//! taken and the reward, for offline analysis and imitation learning; so ACTIONS has every tetris::Action, sonic
+//! drop included, whatever a person might have bound.
+
+use crate::headless;
+use crate::tetris;
+
+/// Any piece fits a square this wide, however it's turned.
+pub const PIECE_GRID: usize = tetris::MAX_PIECE_CELLS;
+/// The falling piece, the next queue as far as it can show, and the held piece.
+pub const PIECE_SLOTS: usize = 1 + tetris::MAX_PREVIEW_PIECES + 1;
+/// What each index passed to Env::step() does; None lets the tick go by. New actions go on the end, so the indices
+/// agents learned keep their meaning.
+pub const ACTIONS: [Option<tetris::Action>; 10] = [
+	None,
+	Some(tetris::Action::MoveLeft),
+	Some(tetris::Action::MoveRight),
+	Some(tetris::Action::RotateClockwise),
+	Some(tetris::Action::RotateCounterClockwise),
+	Some(tetris::Action::SoftDropStart),
+	Some(tetris::Action::SoftDropStop),
+	Some(tetris::Action::Hold),
+	Some(tetris::Action::HardDrop),
+	Some(tetris::Action::SonicDrop),
+];
+/// HARDCODE On topping out, on top of the rows that step cleared; worth more than a few rows, so living pays.
+pub const TOP_OUT_REWARD: f32 = -10.0;
+
+/// Sized the same every tick for a given board, so it flattens straight into a fixed-length vector.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct Observation {
+	pub width: usize,
+	pub height: usize,
+	/// The visible board, row by row from the top: 0 empty, 1 stack, 2 the falling piece.
+	pub board: Vec<u8>,
+	/// PIECE_SLOTS squares of PIECE_GRID × PIECE_GRID, row by row, 1 where the piece has a cell, pushed up and left;
+	/// all 0 for a slot with nothing in it, or further ahead than the queue shows.
+	pub pieces: Vec<u8>,
+	pub can_hold: bool,
+}
+
+impl Observation {
+	pub fn new(game_state: &tetris::GameState) -> Observation {
+		let visible = &game_state.cell_matrix[game_state.hidden_rows ..];
+		let (width, height) = (game_state.cell_matrix_width, visible.len());
+		let mut board: Vec<u8> = visible.iter()
+			.flat_map(|row| row.cells.iter().map(|c| c.is_some() as u8))
+			.collect();
+		if let Some(piece) = game_state.current_piece.as_ref() {
+			for (_c, x, y) in piece.iter_global_space(game_state.current_piece_mass_xy) {
+				let y = y - game_state.hidden_rows as i32;
+				if (0 .. width as i32).contains(&x) && (0 .. height as i32).contains(&y) {
+					board[y as usize * width + x as usize] = 2;
+				}
+			}
+		}
+		let mut slots = vec![game_state.current_piece.as_ref()];
+		slots.extend(game_state.next_pieces().map(Some));
+		slots.resize(PIECE_SLOTS - 1, None);
+		slots.push(game_state.held_piece.as_ref());
+		Self {
+			width,
+			height,
+			board,
+			pieces: slots.into_iter().flat_map(encode_piece).collect(),
+			can_hold: game_state.can_hold(),
+		}
+	}
+
+	/// Everything in one vector, board first.
+	pub fn to_vec(&self) -> Vec<f32> {
+		self.board.iter().chain(self.pieces.iter()).map(|&v| v as f32)
+			.chain(std::iter::once(self.can_hold as u8 as f32))
+			.collect()
+	}
+
+	pub fn to_json(&self) -> String {
+		let list = |values: &[u8]| values.iter().map(u8::to_string).collect::<Vec<_>>().join(",");
+		format!("{{\"width\":{},\"height\":{},\"board\":[{}],\"pieces\":[{}],\"can_hold\":{}}}",
+			self.width, self.height, list(&self.board), list(&self.pieces), self.can_hold)
+	}
+}
+
+fn encode_piece(piece: Option<&tetris::Piece>) -> Vec<u8> {
+	let mut grid = vec![0; PIECE_GRID * PIECE_GRID];
+	if let Some(piece) = piece {
+		let min_x = piece.cells.iter().map(|c| c.x).min().unwrap_or(0);
+		let min_y = piece.cells.iter().map(|c| c.y).min().unwrap_or(0);
+		for c in piece.cells.iter() {
+			let (x, y) = ((c.x - min_x) as usize, (c.y - min_y) as usize);
+			if x < PIECE_GRID && y < PIECE_GRID {
+				grid[y * PIECE_GRID + x] = 1;
+			}
+		}
+	}
+	grid
+}
+
+/// What one step() did.
+#[derive(Clone,Debug,PartialEq)]
+pub struct Step {
+	pub observation: Observation,
+	/// Rows cleared this tick, plus TOP_OUT_REWARD if it topped out.
+	pub reward: f32,
+	/// Topped out, or reached Settings::max_pieces; reset() to go again.
+	pub done: bool,
+}
+
+impl Step {
+	pub fn to_json(&self) -> String {
+		format!("{{\"observation\":{},\"reward\":{},\"done\":{}}}", self.observation.to_json(), self.reward, self.done)
+	}
+}
+
+#[derive(Debug)]
+pub struct Env {
+	pub settings: headless::Settings,
+	pub game_state: tetris::GameState,
+	reward: f32,
+}
+
+impl Env {
+	/// Ready to play a game seeded Settings::seed; call reset() for another.
+	pub fn new(settings: headless::Settings) -> Env {
+		let game_state = settings.new_game_state(settings.seed);
+		Self { settings, game_state, reward: 0.0 }
+	}
+
+	pub fn reset(&mut self, seed: u64) -> Observation {
+		self.game_state = self.settings.new_game_state(seed);
+		self.reward = 0.0;
+		self.observe()
+	}
+
+	/// Applies ACTIONS[action], then ticks once.
+	pub fn step(&mut self, action: usize) -> Result<Step, String> {
+		let Some(&action) = ACTIONS.get(action)
+			else { return Err(format!("No action {action}; expected 0 to {}", ACTIONS.len() - 1)); };
+		if self.done() {
+			return Err("The game is over; reset first".to_string());
+		}
+		if let Some(action) = action {
+			self.game_state.apply(action);
+		}
+		let rows_before = self.game_state.rows_cleared;
+		self.game_state.tick();
+		self.game_state.drain_events().for_each(drop);
+		self.reward = reward(rows_before, &self.game_state);
+		Ok(Step { observation: self.observe(), reward: self.reward, done: self.done() })
+	}
+
+	pub fn observe(&self) -> Observation {
+		Observation::new(&self.game_state)
+	}
+
+	/// Of the last step; 0 after a reset.
+	pub fn reward(&self) -> f32 {
+		self.reward
+	}
+
+	pub fn done(&self) -> bool {
+		!self.game_state.is_alive || self.game_state.pieces_locked >= self.settings.max_pieces
+	}
+}
+
+/// For a tick that started with `rows_before` cleared; see Step::reward.
+fn reward(rows_before: u32, game_state: &tetris::GameState) -> f32 {
+	let rows = game_state.rows_cleared.saturating_sub(rows_before) as f32;
+	if game_state.is_alive { rows } else { rows + TOP_OUT_REWARD }
+}
+
+/// Where `action` is in ACTIONS.
+pub fn action_index(action: tetris::Action) -> usize {
+	ACTIONS.iter().position(|a| *a == Some(action)).expect("Should list every action")
+}
+
+/// Every tick of a game as Env would have seen it; see to_ndjson().
+#[derive(Clone,Debug,Default)]
+pub struct TrainingLog {
+	lines: Vec<String>,
+	/// Taken by before_tick(), for after_tick() to finish the line with.
+	pending: Option<(u64, Observation, Vec<usize>, u32)>,
+}
+
+impl TrainingLog {
+	pub fn new() -> TrainingLog {
+		Self::default()
+	}
+
+	/// Starts over, e.g. for a new game.
+	pub fn clear(&mut self) {
+		*self = Self::default();
+	}
+
+	/// Call before applying this tick's actions; then after_tick().
+	pub fn before_tick(&mut self, game_state: &tetris::GameState, actions: &[tetris::Action]) {
+		let actions = actions.iter().map(|&a| action_index(a)).collect();
+		self.pending = Some((game_state.ticks, Observation::new(game_state), actions, game_state.rows_cleared));
+	}
+
+	pub fn after_tick(&mut self, game_state: &tetris::GameState) {
+		let Some((tick, observation, actions, rows_before)) = self.pending.take()
+			else { return; };
+		let actions: Vec<String> = actions.iter().map(usize::to_string).collect();
+		self.lines.push(format!(
+			"{{\"seed\":{},\"tick\":{},\"observation\":{},\"actions\":[{}],\"reward\":{},\"done\":{}}}",
+			game_state.seed, tick, observation.to_json(), actions.join(","), reward(rows_before, game_state), !game_state.is_alive));
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.lines.is_empty()
+	}
+
+	/// A JSON object per tick, one per line: `seed`, `tick`, `observation` as in Observation::to_json() from before
+	/// the tick, `actions` as indices into ACTIONS, and `reward` and `done` as in Step.
+	pub fn to_ndjson(&self) -> String {
+		self.lines.iter().map(|line| line.clone() + "\n").collect()
+	}
+}
+
+/// Entry point for `--env`; `args` are whatever followed it. Runs until stdin closes.
+pub fn run(args: &[String]) -> Result<String, String> {
+	use std::io::{BufRead, Write};
+
+	let mut env = Env::new(headless::Settings::parse(args)?);
+	let mut episodes = 0;
+	let mut stdout = std::io::stdout().lock();
+	for line in std::io::stdin().lock().lines() {
+		let line = line.map_err(|e| e.to_string())?;
+		let mut words = line.split_whitespace();
+		let reply = match (words.next(), words.next().map(str::parse::<u64>)) {
+			(Some("reset"), seed) => {
+				episodes += 1;
+				let seed = match seed {
+					Some(Ok(seed)) => seed,
+					_ => env.settings.seed.wrapping_add(episodes),
+				};
+				Ok(Step { observation: env.reset(seed), reward: 0.0, done: false }.to_json())
+			}
+			(Some("step"), Some(Ok(action))) => env.step(action as usize).map(|step| step.to_json()),
+			(None, _) => continue,
+			_ => Err(format!("Unknown command `{line}`; expected `reset [seed]` or `step <action>`")),
+		};
+		let reply = reply.unwrap_or_else(|e| format!("{{\"error\":\"{}\"}}", e.replace('"', "'")));
+		writeln!(stdout, "{reply}").map_err(|e| e.to_string())?;
+	}
+	Ok(format!("{{\"episodes\":{episodes}}}"))
+}
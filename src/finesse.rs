@@ -0,0 +1,134 @@
+//! Finesse: placing each piece with as few key presses as possible.
+//! The trainer counts moves and rotations per piece, and compares them with the fewest that
+//! reach the same spot from the spawn, on an empty board with room to turn.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::tetris;
+
+/// How one piece went.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct Verdict {
+	pub presses: u32,
+	pub minimum: u32,
+}
+
+impl Verdict {
+	pub fn extra_presses(&self) -> u32 {
+		self.presses.saturating_sub(self.minimum)
+	}
+}
+
+/// Feed it every action and every state; running totals are for the whole session, across restarts.
+#[derive(Debug,Default)]
+pub struct Trainer {
+	tracking: Option<Tracked>,
+	pub pieces: u32,
+	/// Placed with no more presses than needed.
+	pub clean_pieces: u32,
+	pub extra_presses: u32,
+}
+
+#[derive(Debug)]
+struct Tracked {
+	spawn: tetris::Piece,
+	spawn_x: i32,
+	/// As of the last observe(); where it locks, give or take gravity.
+	last: tetris::Piece,
+	last_x: i32,
+	width: usize,
+	presses: u32,
+}
+
+impl Trainer {
+	pub fn new() -> Trainer {
+		Self::default()
+	}
+
+	/// Forget the piece in play, e.g. for a restart; the totals stay.
+	pub fn abandon_piece(&mut self) {
+		self.tracking = None;
+	}
+
+	/// Call for each action as it is applied, then observe().
+	pub fn record(&mut self, action: tetris::Action) {
+		match action {
+			tetris::Action::MoveLeft | tetris::Action::MoveRight
+				| tetris::Action::RotateClockwise | tetris::Action::RotateCounterClockwise => {
+				if let Some(t) = self.tracking.as_mut() {
+					t.presses += 1;
+				}
+			}
+			// A different piece, so start counting again.
+			tetris::Action::Hold => self.tracking = None,
+			// Dropping is how a piece gets placed, not a way of getting it there.
+			tetris::Action::SoftDropStart | tetris::Action::SoftDropStop | tetris::Action::HardDrop | tetris::Action::SonicDrop => {}
+		}
+	}
+
+	/// Call after applying actions and after every tick; returns how the last piece went once it locks.
+	pub fn observe(&mut self, game_state: &tetris::GameState) -> Option<Verdict> {
+		let Some(p) = game_state.current_piece.as_ref()
+			else {
+				let t = self.tracking.take()?;
+				let target = footprint(&t.last, t.last_x);
+				let minimum = minimum_presses(&t.spawn, t.spawn_x, t.width, &target)?;
+				let verdict = Verdict { presses: t.presses, minimum };
+				self.pieces += 1;
+				if verdict.extra_presses() == 0 {
+					self.clean_pieces += 1;
+				}
+				self.extra_presses += verdict.extra_presses();
+				return Some(verdict);
+			};
+		let x = game_state.current_piece_mass_xy.0;
+		match self.tracking.as_mut() {
+			Some(t) => {
+				t.last = p.clone();
+				t.last_x = x;
+			}
+			None => self.tracking = Some(Tracked {
+				spawn: p.clone(),
+				spawn_x: x,
+				last: p.clone(),
+				last_x: x,
+				width: game_state.cell_matrix_width,
+				presses: 0,
+			}),
+		}
+		None
+	}
+}
+
+/// Columns and shape of `piece` with its center of mass in column `x`; rows count up from its top cell.
+pub fn footprint(piece: &tetris::Piece, x: i32) -> Vec<(i32, i32)> {
+	let cells: Vec<(i32, i32)> = piece.iter_global_space((x, 0)).map(|(_c, x, y)| (x, y)).collect();
+	let min_y = cells.iter().map(|xy| xy.1).min().unwrap_or(0);
+	let mut cells: Vec<(i32, i32)> = cells.into_iter().map(|(x, y)| (x, y - min_y)).collect();
+	cells.sort();
+	cells
+}
+
+/// Breadth first over column and rotation; None if `target` can't be reached in a well `width` wide.
+pub fn minimum_presses(spawn: &tetris::Piece, spawn_x: i32, width: usize, target: &[(i32, i32)]) -> Option<u32> {
+	// Index i is i clockwise turns from the spawn orientation.
+	let mut rotations = vec![spawn.clone()];
+	for i in 1 .. 4 {
+		let next = rotations[i - 1].rotated(true);
+		rotations.push(next);
+	}
+	let fits = |rotation: usize, x: i32| rotations[rotation].iter_global_space((x, 0)).all(|(_c, x, _y)| x >= 0 && (x as usize) < width);
+	let mut seen = BTreeSet::from([(spawn_x, 0)]);
+	let mut queue = VecDeque::from([(spawn_x, 0_usize, 0_u32)]);
+	while let Some((x, rotation, presses)) = queue.pop_front() {
+		if footprint(&rotations[rotation], x) == target {
+			return Some(presses);
+		}
+		for (next_x, next_rotation) in [(x - 1, rotation), (x + 1, rotation), (x, (rotation + 1) % 4), (x, (rotation + 3) % 4)] {
+			if fits(next_rotation, next_x) && seen.insert((next_x, next_rotation)) {
+				queue.push_back((next_x, next_rotation, presses + 1));
+			}
+		}
+	}
+	None
+}
@@ -0,0 +1,12 @@
+//! What a way of playing the game has to provide; gui.rs has the macroquad one, headless.rs the windowless one.
+
+use crate::tetris;
+
+pub trait Frontend {
+	/// Called once per tick, before GameState::tick(); everything returned gets applied this tick.
+	fn poll_input(&mut self, game_state: &tetris::GameState) -> Vec<tetris::Action>;
+	/// Draw the board and whatever is falling.
+	fn render(&mut self, game_state: &tetris::GameState);
+	/// Called for each event drained from the game, e.g. to play a sound.
+	fn play_event(&mut self, event: &tetris::Event);
+}
@@ -0,0 +1,87 @@
+use std::fmt::Debug;
+
+use crate::rng;
+
+/// Chooses where the hole goes in each garbage row; see GameState::add_garbage().
+pub trait GarbageGenerator: Debug {
+	/// `is_new_batch` is set for the first row of each add_garbage() call.
+	fn next_hole(&mut self, width: usize, is_new_batch: bool) -> usize;
+}
+
+/// Every row of a batch shares one hole, so a batch can be dug out with a single well.
+#[derive(Debug,Default)]
+pub struct SameColumnPerBatch {
+	rng: rng::RandomNumberGenerator,
+	column: usize,
+}
+
+impl GarbageGenerator for SameColumnPerBatch {
+	fn next_hole(&mut self, width: usize, is_new_batch: bool) -> usize {
+		if is_new_batch {
+			self.column = self.rng.uniform(0, width);
+		}
+		self.column
+	}
+}
+
+/// Cheese: every row has its own hole.
+#[derive(Debug,Default)]
+pub struct RandomPerRow {
+	rng: rng::RandomNumberGenerator,
+}
+
+impl RandomPerRow {
+	pub fn new(seed: u64) -> RandomPerRow {
+		Self { rng: rng::RandomNumberGenerator::new(seed) }
+	}
+}
+
+impl GarbageGenerator for RandomPerRow {
+	fn next_hole(&mut self, width: usize, _is_new_batch: bool) -> usize {
+		self.rng.uniform(0, width)
+	}
+}
+
+/// Cycles through fixed columns, e.g. `[0, 1, 2, 1]` for a zig-zag; columns wrap around the well width.
+/// An empty pattern puts every hole in column 0.
+#[derive(Debug)]
+pub struct Repeating {
+	pattern: Vec<usize>,
+	next: usize,
+}
+
+impl Repeating {
+	pub fn new(pattern: Vec<usize>) -> Repeating {
+		let pattern = if pattern.is_empty() { vec![0] } else { pattern };
+		Self { pattern, next: 0 }
+	}
+}
+
+impl GarbageGenerator for Repeating {
+	fn next_hole(&mut self, width: usize, _is_new_batch: bool) -> usize {
+		let column = self.pattern[self.next] % width;
+		self.next = (self.next + 1) % self.pattern.len();
+		column
+	}
+}
+
+/// Holes land in each column with probability proportional to its weight, e.g. to favor the edges.
+/// Columns past the end of `weights` never get a hole; if none can, the hole goes in column 0.
+#[derive(Debug)]
+pub struct Weighted {
+	rng: rng::RandomNumberGenerator,
+	weights: Vec<f64>,
+}
+
+impl Weighted {
+	pub fn new(seed: u64, weights: Vec<f64>) -> Weighted {
+		Self { rng: rng::RandomNumberGenerator::new(seed), weights }
+	}
+}
+
+impl GarbageGenerator for Weighted {
+	fn next_hole(&mut self, width: usize, _is_new_batch: bool) -> usize {
+		let weights = &self.weights[.. self.weights.len().min(width)];
+		self.rng.weighted_index(weights).unwrap_or(0)
+	}
+}
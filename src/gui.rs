@@ -0,0 +1,2951 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use macroquad::prelude::*;
+use macroquad::color;
+use macroquad::ui;
+use macroquad::ui::hash; // bugged; must be imported with no prefix
+use macroquad::ui::widgets;
+use miniquad::window::set_window_size;
+use crate::assets;
+use crate::postprocess;
+use tetris_clone_rust::achievements;
+use tetris_clone_rust::analysis;
+use tetris_clone_rust::bot;
+use tetris_clone_rust::bridge;
+use tetris_clone_rust::campaign;
+use tetris_clone_rust::capture;
+use tetris_clone_rust::cheese;
+use tetris_clone_rust::config;
+use tetris_clone_rust::coop;
+use tetris_clone_rust::difficulty;
+use tetris_clone_rust::editor;
+use tetris_clone_rust::env;
+use tetris_clone_rust::finesse;
+use tetris_clone_rust::frontend::Frontend;
+use tetris_clone_rust::input;
+use tetris_clone_rust::layout;
+use tetris_clone_rust::leaderboard;
+use tetris_clone_rust::log;
+use tetris_clone_rust::netplay;
+use tetris_clone_rust::notation;
+use tetris_clone_rust::overlay;
+use tetris_clone_rust::practice;
+use tetris_clone_rust::profile;
+use tetris_clone_rust::puzzle;
+use tetris_clone_rust::records;
+use tetris_clone_rust::replay;
+use tetris_clone_rust::rng;
+use tetris_clone_rust::ruleset;
+use tetris_clone_rust::script;
+use tetris_clone_rust::speed;
+use tetris_clone_rust::storage;
+use tetris_clone_rust::sync;
+use tetris_clone_rust::tetris;
+use tetris_clone_rust::theme;
+use tetris_clone_rust::tween;
+
+#[cfg(not(target_family="wasm"))]
+fn quit() {
+	std::process::exit(0);
+}
+
+/// Pages can't close their own tabs, so the caller carries on to the title menu instead.
+#[cfg(target_family="wasm")]
+fn quit() {
+	log::info!("Close the tab to quit");
+}
+
+#[cfg(not(target_family="wasm"))]
+const QUIT_LABEL: &str = "Quit";
+#[cfg(target_family="wasm")]
+const QUIT_LABEL: &str = "Back to menu";
+
+enum Scene {
+	Title,
+	/// Picking the level a marathon starts at, and whether to take on mirrored pieces.
+	MarathonSetup { starting_level: u32, mirror_pieces: bool },
+	/// Picking one of the modes that keep no score: practice, finesse, and cheese races.
+	Training,
+	LevelSelect,
+	/// The campaign's levels along a path, with the stars earned on each; see the campaign module.
+	Campaign,
+	/// Filters index into LocalResults::mode_kinds() and ::board_sizes(), offset by one for "all".
+	Results { mode_filter: usize, size_filter: usize, page: usize },
+	Achievements,
+	/// Are you sure? Cancelling goes back to the game over screen if that is where it came from.
+	ConfirmQuit { from_game: bool },
+	/// Painting a starting position; see the editor module.
+	Editor,
+	/// Hosting or joining a room to play versus in; see netplay::Lobby.
+	Lobby,
+	/// Picking who's playing, at startup once anybody has made a profile; see the profile module.
+	Profiles,
+	/// Graphs of the replay being watched, from App::analysis; back goes on watching.
+	Analysis,
+	Playing,
+}
+
+enum Mode {
+	/// Starting further up gives a head start on score too; see Ruleset::starting_score(). Mirrored pieces are a
+	/// challenge with a leaderboard of its own; see GameState::mirror_pieces.
+	Marathon { starting_level: u32, mirror_pieces: bool },
+	/// Race to clear a fixed number of rows, optionally against the personal best.
+	Sprint { ghost: Option<Box<replay::Ghost>>, finished_at_tick: Option<u64> },
+	/// Dig through garbage against the clock; see the cheese module. No replays, since the garbage isn't in them.
+	Cheese(cheese::CheeseRace),
+	/// Days since the Unix epoch, UTC.
+	Daily { day: u64 },
+	Puzzle(puzzle::PuzzleRun),
+	/// A puzzle from the campaign; `level` indexes App::campaign.
+	Campaign { level: usize, run: puzzle::PuzzleRun },
+	/// A marathon that keeps no records, so mistakes can be taken back.
+	Practice(practice::Practice),
+	/// Standard rules, no records, and a word after every piece placed with more key presses than needed.
+	Finesse(finesse::Trainer),
+	/// Watching a recording rather than playing.
+	Replay(replay::Playback),
+	/// Against somebody else over the network, on standard rules and speed, each on their own difficulty;
+	/// garbage goes both ways.
+	Versus(Box<netplay::Versus>),
+	/// Watching a versus room; both boards, no input.
+	Spectate(Box<netplay::Spectator>),
+	/// Watching a bot, one of bot::NAMES, play a marathon; no input and no records.
+	Bot { name: &'static str, bot: Box<dyn bot::Bot> },
+	/// Two players on one wide board; see the coop module. No records.
+	Coop(coop::Coop),
+	/// Co-op's two pieces and key clusters, both for one player. No records, as a replay only has the one piece's moves.
+	Twin(coop::Coop),
+}
+
+impl Mode {
+	/// Also resets the game.
+	fn start(&mut self, game_state: &mut tetris::GameState) {
+		match self {
+			Mode::Marathon { .. } => {
+				game_state.reset();
+				game_state.reseed(miniquad::date::now().to_bits());
+			}
+			Mode::Practice(practice) => {
+				game_state.reset();
+				game_state.reseed(miniquad::date::now().to_bits());
+				practice.start(game_state);
+			}
+			Mode::Finesse(trainer) => {
+				game_state.reset();
+				game_state.reseed(miniquad::date::now().to_bits());
+				trainer.abandon_piece();
+			}
+			Mode::Sprint { ghost, finished_at_tick } => {
+				game_state.reset();
+				game_state.reseed(miniquad::date::now().to_bits());
+				if let Some(g) = ghost.as_mut() {
+					g.restart();
+				}
+				*finished_at_tick = None;
+			}
+			Mode::Daily { day } => {
+				game_state.reset();
+				game_state.reseed(rng::daily_seed(*day));
+			}
+			Mode::Cheese(race) => {
+				game_state.reset();
+				game_state.reseed(miniquad::date::now().to_bits());
+				race.restart();
+			}
+			Mode::Puzzle(run) | Mode::Campaign { run, .. } => {
+				run.puzzle.set_up(game_state);
+				game_state.reseed(miniquad::date::now().to_bits());
+				*run = puzzle::PuzzleRun::new(run.puzzle.clone());
+			}
+			Mode::Replay(playback) => {
+				*game_state = playback.replay.new_game_state();
+				playback.rewind();
+			}
+			Mode::Versus(versus) => {
+				game_state.reset();
+				game_state.reseed(versus.seed);
+				// Both copies of each game must fall alike.
+				speed::Preset::Normal.apply(&mut game_state.timing);
+				versus.start(game_state);
+			}
+			Mode::Spectate(spectator) => {
+				game_state.reset();
+				speed::Preset::Normal.apply(&mut game_state.timing);
+				spectator.template = game_state.clone();
+			}
+			Mode::Bot { name, bot } => {
+				game_state.reset();
+				game_state.reseed(miniquad::date::now().to_bits());
+				// A fresh one, so no plan carries over from the last game.
+				*bot = bot::by_name(name, game_state.seed, bot::Strength::default()).expect("Should be one of bot::NAMES");
+			}
+			Mode::Coop(coop) | Mode::Twin(coop) => {
+				game_state.reset();
+				game_state.reseed(miniquad::date::now().to_bits());
+				coop.clear();
+			}
+		}
+	}
+
+	/// Nobody's at the controls, so there's nothing to lose by skipping ahead; see App::fast_forward.
+	fn can_fast_forward(&self) -> bool {
+		matches!(self, Mode::Replay(_) | Mode::Bot { .. })
+	}
+
+	/// For the online leaderboard and local results; puzzles don't have one.
+	/// Marathons under other rules get their own board, e.g. `marathon.classic`, as do mirrored ones, e.g.
+	/// `marathon.mirror` or `marathon.classic.mirror`.
+	fn leaderboard_name(&self, ruleset: &str) -> Option<String> {
+		match self {
+			Mode::Marathon { mirror_pieces, .. } => {
+				let mut name = "marathon".to_string();
+				if ruleset != "standard" {
+					name += &format!(".{ruleset}");
+				}
+				if *mirror_pieces {
+					name += ".mirror";
+				}
+				Some(name)
+			}
+			Mode::Daily { day } => Some(format!("daily-{day}")),
+			Mode::Sprint { .. } | Mode::Cheese(_) | Mode::Puzzle(_) | Mode::Campaign { .. } | Mode::Practice(_) | Mode::Finesse(_) | Mode::Replay(_) | Mode::Versus(_) | Mode::Spectate(_) | Mode::Bot { .. } | Mode::Coop(_) | Mode::Twin(_) => None,
+		}
+	}
+
+	/// Modes worth recording; those with leaderboards, plus sprints for their ghosts.
+	fn recording_name(&self, ruleset: &str) -> Option<String> {
+		match self {
+			Mode::Sprint { .. } => Some("sprint".to_string()),
+			_ => self.leaderboard_name(ruleset),
+		}
+	}
+
+	/// Which of config::QUEUE_OPTION_MODES this is, if any.
+	fn queue_option_name(&self) -> Option<&'static str> {
+		match self {
+			Mode::Marathon { .. } => Some("marathon"),
+			Mode::Sprint { .. } => Some("sprint"),
+			Mode::Cheese(_) => Some("cheese"),
+			Mode::Daily { .. } => Some("daily"),
+			Mode::Practice(_) => Some("practice"),
+			Mode::Finesse(_) => Some("finesse"),
+			Mode::Puzzle(_) | Mode::Campaign { .. } | Mode::Replay(_) | Mode::Versus(_) | Mode::Spectate(_) | Mode::Bot { .. } | Mode::Coop(_) | Mode::Twin(_) => None,
+		}
+	}
+
+	fn from_leaderboard_name(name: &str) -> Option<Mode> {
+		match name.split_once('-') {
+			None if name == "marathon" => Some(Mode::Marathon { starting_level: 0, mirror_pieces: false }),
+			Some(("daily", day)) => Some(Mode::Daily { day: day.parse().ok()? }),
+			_ => None,
+		}
+	}
+}
+
+fn render_leaderboard(client: &leaderboard::LeaderboardClient, top: f32, width: usize) {
+	let font_size = 24.0;
+	let heading = match client.status {
+		leaderboard::Status::Offline => "Leaderboard offline",
+		leaderboard::Status::Loading if client.top.is_empty() => "Loading leaderboard...",
+		_ => "Top scores",
+	};
+	draw_text(heading, 8.0, top, font_size, DARKGRAY);
+	for (i, entry) in client.top.iter().take(10).enumerate() { // HARDCODE
+		let y = top + (i + 1) as f32 * font_size;
+		draw_text(&format!("{}. {}", i + 1, entry.name), 8.0, y, font_size, LIGHTGRAY);
+		let score = entry.score.to_string();
+		let score_dims = measure_text(&score, None, font_size as u16, 1.0);
+		draw_text(&score, width as f32 - 8.0 - score_dims.width, y, font_size, LIGHTGRAY);
+	}
+}
+
+/// Newest at the bottom; each disappears after its deadline, in seconds since the Unix epoch.
+fn render_toasts(toasts: &[(achievements::Achievement, f64)], width: usize) {
+	let font_size = 20.0;
+	let toast_height_px = font_size * 2.5;
+	for (i, (a, _deadline)) in toasts.iter().enumerate() {
+		let y = 8.0 + i as f32 * (toast_height_px + 4.0);
+		draw_rectangle(8.0, y, width as f32 - 16.0, toast_height_px, Color::new(0.1, 0.1, 0.1, 0.9));
+		draw_rectangle_lines(8.0, y, width as f32 - 16.0, toast_height_px, 2.0, GOLD);
+		draw_text(&format!("Unlocked: {}", a.title()), 16.0, y + font_size, font_size, GOLD);
+		draw_text(a.description(), 16.0, y + font_size * 2.0, font_size * 0.8, LIGHTGRAY);
+	}
+}
+
+fn today() -> u64 {
+	(miniquad::date::now() / (60.0 * 60.0 * 24.0)) as u64
+}
+
+/// Start or restart `mode`, resetting everything that follows a single game.
+/// Returns a fresh recording for modes that keep results.
+/// Only marathons and practice follow the configured ruleset and piece set; the other modes compare records,
+/// so they keep the standard rules and polyominoes.
+/// Likewise only practice follows the configured difficulty, and versus the one picked for it.
+fn start_game(mode: &mut Mode, seed: Option<u64>, game_state: &mut tetris::GameState, achievements: &mut achievements::Achievements, script_run: &mut script::ScriptRun, config: &config::Config, frontend: &mut MacroquadFrontend) -> Option<replay::Replay> {
+	let ruleset = if matches!(mode, Mode::Marathon { .. } | Mode::Practice(_)) { config.ruleset.as_str() } else { "standard" };
+	game_state.set_ruleset(ruleset::by_name(ruleset).unwrap_or_else(|| Box::new(ruleset::Standard)));
+	game_state.piece_set = if matches!(mode, Mode::Marathon { .. } | Mode::Practice(_)) { config.piece_set } else { tetris::PieceSet::Polyominoes };
+	game_state.difficulty = match mode {
+		Mode::Practice(_) => config.difficulty,
+		Mode::Versus(versus) => versus.difficulty,
+		_ => difficulty::Preset::Standard,
+	};
+	game_state.starting_level = match mode {
+		Mode::Marathon { starting_level, .. } => *starting_level,
+		_ => 0,
+	};
+	game_state.mirror_pieces = matches!(mode, Mode::Marathon { mirror_pieces: true, .. });
+	// A lane each, as wide as the usual board.
+	let players = if matches!(mode, Mode::Coop(_) | Mode::Twin(_)) { coop::PLAYERS } else { 1 };
+	if game_state.cell_matrix_width != frontend.width_cells * players {
+		game_state.set_width(frontend.width_cells * players);
+	}
+	game_state.set_players(players);
+	game_state.queue_options = mode.queue_option_name().map_or_else(tetris::QueueOptions::default, |name| config.queue_options(name));
+	// Both copies of a versus game have to lock alike.
+	game_state.lock_options = match mode {
+		Mode::Versus(_) | Mode::Spectate(_) | Mode::Bot { .. } => tetris::LockOptions::default(),
+		_ => config.lock_options,
+	};
+	script_run.start_game(game_state);
+	mode.start(game_state);
+	if let Some(seed) = seed {
+		game_state.reseed(seed);
+	}
+	bridge::emit("music", theme::for_level(game_state.level()).music as u64);
+	bridge::emit("music_rate", 100);
+	frontend.danger_since = None;
+	let recording_name = mode.recording_name(game_state.ruleset_name());
+	log::info!("Starting {} with seed {}", recording_name.as_deref().unwrap_or("a game"), game_state.seed);
+	achievements.start_game();
+	if let Some(input_log) = frontend.input_log.as_mut() {
+		input_log.clear();
+	}
+	recording_name.map(|name| replay::Replay::new(name, game_state))
+}
+
+/// Side-by-side bars of rows cleared so far, yours on the right.
+fn render_sprint_progress(game_state: &tetris::GameState, ghost: Option<&replay::Ghost>, goal_rows: u32, width: usize, height: usize) {
+	let bar_width_px = 6.0;
+	let height = height as f32;
+	let fraction = |rows: u32| (rows as f32 / goal_rows as f32).min(1.0);
+	let x = width as f32 - bar_width_px;
+	let mine = fraction(game_state.rows_cleared) * height;
+	draw_rectangle(x, height - mine, bar_width_px, mine, WHITE);
+	if let Some(g) = ghost {
+		let theirs = fraction(g.game_state.rows_cleared) * height;
+		draw_rectangle(x - bar_width_px, height - theirs, bar_width_px, theirs, Color::new(0.4, 0.6, 1.0, 0.5));
+	}
+}
+
+fn load_replay(bytes: &[u8]) -> Option<replay::Replay> {
+	match replay::Replay::parse(&String::from_utf8_lossy(bytes)) {
+		Ok(r) => Some(r),
+		Err(e) => {
+			log::warn!("Could not load replay: {e}");
+			None
+		}
+	}
+}
+
+enum GameOverChoice {
+	Restart,
+	Menu,
+	Export,
+	Quit,
+}
+
+/// For the score, menus, and game over screen; see config::Config::font.
+#[derive(Clone)]
+struct Typeface {
+	/// None for macroquad's own.
+	font: Option<Font>,
+	/// Cell size over CELL_PX; sizes given to size() are for cells that size.
+	scale: f32,
+}
+
+impl Typeface {
+	const CELL_PX: f32 = 32.0;
+
+	/// `px` at CELL_PX cells, scaled to the actual ones.
+	fn size(&self, px: f32) -> u16 {
+		(px * self.scale).round() as u16
+	}
+
+	fn measure(&self, text: &str, font_size: u16) -> TextDimensions {
+		measure_text(text, self.font.as_ref(), font_size, 1.0)
+	}
+
+	fn draw(&self, text: &str, x: f32, y: f32, font_size: u16, color: Color) {
+		draw_text_ex(text, x, y, TextParams { font: self.font.as_ref(), font_size, color, ..Default::default() });
+	}
+
+	/// For macroquad's UI skins, so buttons match.
+	fn style_builder(&self, ui: &ui::Ui) -> ui::StyleBuilder {
+		match self.font.as_ref() {
+			Some(font) => ui.style_builder().with_font(font).unwrap_or_else(|_e| ui.style_builder()),
+			None => ui.style_builder(),
+		}
+	}
+}
+
+fn game_over(typeface: &Typeface, title: &str, title_color: Color, subtitle: Option<&str>, can_export: bool, width: usize) -> Option<GameOverChoice> {
+	let width = width as f32;
+	let font_size = typeface.size(48.0);
+	let dims_game_over = typeface.measure(title, font_size);
+	typeface.draw(title, (width - dims_game_over.width) / 2.0, dims_game_over.offset_y, font_size, title_color);
+	let button_bar_size = Vec2::new(width, font_size as f32);
+	let button_padding_px = 4.0;
+	let mut choice = None;
+	ui::root_ui().window(hash!(), Vec2::new(0.0, dims_game_over.offset_y), button_bar_size, |ui| {
+		let skin = ui::Skin {
+			button_style: typeface.style_builder(ui)
+				.font_size(font_size / 2)
+				.text_color(LIGHTGRAY)
+				.color(DARKGRAY)
+				.build(),
+			..ui.default_skin()
+		};
+		ui.push_skin(&skin);
+		let button_width = button_bar_size.x / 3.0;
+		let button_size = Vec2::new(button_width - (button_padding_px * 2.0), button_bar_size.y - (button_padding_px * 2.0));
+		let button_restart = widgets::Button::new("Restart")
+			.position(Vec2::new(button_padding_px, button_padding_px))
+			.size(button_size);
+		if button_restart.ui(ui) {
+			choice = Some(GameOverChoice::Restart);
+		}
+		let button_menu = widgets::Button::new("Menu")
+			.position(Vec2::new(button_width + button_padding_px, button_padding_px))
+			.size(button_size);
+		if button_menu.ui(ui) {
+			choice = Some(GameOverChoice::Menu);
+		}
+		let button_quit = widgets::Button::new("Quit")
+			.position(Vec2::new(button_width * 2.0 + button_padding_px, button_padding_px))
+			.size(button_size);
+		if button_quit.ui(ui) {
+			choice = Some(GameOverChoice::Quit);
+		}
+		ui.pop_skin();
+	});
+	let subtitle_font_size = font_size / 2;
+	if let Some(subtitle) = subtitle {
+		let dims_subtitle = typeface.measure(subtitle, subtitle_font_size);
+		let subtitle_y = dims_game_over.offset_y + button_bar_size.y + dims_subtitle.offset_y;
+		typeface.draw(subtitle, (width - dims_subtitle.width) / 2.0, subtitle_y, subtitle_font_size, title_color);
+	}
+	if can_export {
+		let mut ui = ui::root_ui();
+		let skin = ui::Skin {
+			button_style: typeface.style_builder(&ui)
+				.font_size(subtitle_font_size)
+				.text_color(LIGHTGRAY)
+				.color(DARKGRAY)
+				.build(),
+			..ui.default_skin()
+		};
+		ui.push_skin(&skin);
+		let y = dims_game_over.offset_y + button_bar_size.y * 2.0;
+		let button_export = widgets::Button::new("Export replay")
+			.position(Vec2::new(button_padding_px, y + button_padding_px))
+			.size(Vec2::new(width - (button_padding_px * 2.0), button_bar_size.y - (button_padding_px * 2.0)));
+		if button_export.ui(&mut ui) {
+			choice = Some(GameOverChoice::Export);
+		}
+		ui.pop_skin();
+	}
+	choice
+}
+
+/// A title over a column of buttons; returns the index of the clicked one.
+fn menu(typeface: &Typeface, title: &str, labels: &[&str], width: usize) -> Option<usize> {
+	let width = width as f32;
+	let font_size = typeface.size(48.0);
+	let dims_title = typeface.measure(title, font_size);
+	typeface.draw(title, (width - dims_title.width) / 2.0, dims_title.offset_y * 2.0, font_size, LIGHTGRAY);
+	let button_padding_px = 4.0;
+	let button_size = Vec2::new(width - (button_padding_px * 2.0), font_size as f32 - (button_padding_px * 2.0));
+	let mut ui = ui::root_ui();
+	let skin = ui::Skin {
+		button_style: typeface.style_builder(&ui)
+			.font_size(font_size / 2)
+			.text_color(LIGHTGRAY)
+			.color(DARKGRAY)
+			.build(),
+		..ui.default_skin()
+	};
+	ui.push_skin(&skin);
+	let mut clicked = None;
+	for (i, label) in labels.iter().enumerate() {
+		let y = dims_title.offset_y * 3.0 + i as f32 * font_size as f32;
+		let button = widgets::Button::new(*label)
+			.position(Vec2::new(button_padding_px, y + button_padding_px))
+			.size(button_size);
+		if button.ui(&mut ui) {
+			clicked = Some(i);
+		}
+	}
+	ui.pop_skin();
+	clicked
+}
+
+async fn load_puzzles() -> Vec<puzzle::Puzzle> {
+	let index = match load_string("assets/puzzles/index.txt").await {
+		Ok(index) => index,
+		Err(e) => {
+			log::error!("Could not load the puzzle index: {e}");
+			return Vec::new();
+		}
+	};
+	let mut puzzles = Vec::new();
+	for file_name in index.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+		let text = match load_string(&format!("assets/puzzles/{file_name}")).await {
+			Ok(text) => text,
+			Err(e) => {
+				log::error!("Could not load puzzle {file_name}: {e}");
+				continue;
+			}
+		};
+		match puzzle::Puzzle::parse(&text) {
+			Ok(p) => puzzles.push(p),
+			Err(e) => log::warn!("Skipping puzzle {file_name}: {e}"),
+		}
+	}
+	puzzles
+}
+
+fn render_grid(style: theme::GridStyle, color: Color, width_cells: usize, height_cells: usize, cell_px: f32) {
+	let (width_px, height_px) = (width_cells as f32 * cell_px, height_cells as f32 * cell_px);
+	match style {
+		theme::GridStyle::Off => {}
+		theme::GridStyle::Sparse => {
+			for column in (0 .. width_cells).step_by(4).skip(1) {
+				let column_px = column as f32 * cell_px;
+				draw_line(column_px, 0.0, column_px, height_px, 1.0, color);
+			}
+		}
+		theme::GridStyle::Lattice => {
+			let color = Color { a: 0.5, ..color }; // HARDCODE Subtle
+			for column in 1 .. width_cells {
+				let column_px = column as f32 * cell_px;
+				draw_line(column_px, 0.0, column_px, height_px, 1.0, color);
+			}
+			for row in 1 .. height_cells {
+				let row_px = row as f32 * cell_px;
+				draw_line(0.0, row_px, width_px, row_px, 1.0, color);
+			}
+		}
+		theme::GridStyle::Outlines => {
+			let color = Color { a: 0.5, ..color };
+			for row in 0 .. height_cells {
+				for column in 0 .. width_cells {
+					draw_rectangle_lines(column as f32 * cell_px + 2.0, row as f32 * cell_px + 2.0, cell_px - 4.0, cell_px - 4.0, 1.0, color);
+				}
+			}
+		}
+	}
+}
+
+/// Slowly falling, twinkling stars, tinted like the grid; the same every run.
+fn render_starfield(time: f32, tint: [f32; 3], width: f32, height: f32) {
+	let [h, s, _l] = tint;
+	for i in 0 .. 48_u32 { // HARDCODE
+		// Cheap hash, so each star keeps its place without storing anything.
+		let hash = |salt: u32| {
+			// lowbias32
+			let mut x = i.wrapping_mul(0x9e37_79b9) ^ salt.wrapping_mul(0x85eb_ca6b);
+			x ^= x >> 16;
+			x = x.wrapping_mul(0x7feb_352d);
+			x ^= x >> 15;
+			x = x.wrapping_mul(0x846c_a68b);
+			x ^= x >> 16;
+			(x >> 8) as f32 / (1 << 24) as f32
+		};
+		let speed = 4.0 + 12.0 * hash(1); // px per second
+		let x = hash(2) * width;
+		let y = (hash(3) * height + time * speed).rem_euclid(height);
+		let twinkle = 0.5 + 0.5 * (time * (0.5 + hash(4)) + hash(5) * std::f32::consts::TAU).sin();
+		let color = color::hsl_to_rgb(h, s, 0.5 + 0.3 * hash(6));
+		draw_circle(x, y, 1.0 + hash(7), Color { a: 0.2 + 0.4 * twinkle, ..color });
+	}
+}
+
+/// Falling cells are brighter than the stack.
+fn cell_color(cell: &tetris::Cell, is_falling: bool) -> Color {
+	if let (tetris::CellKind::Normal, Some(shape)) = (cell.kind, cell.shape) {
+		let color = classic_color(shape);
+		let shade = if is_falling { 1.0 } else { 0.55 }; // HARDCODE
+		return Color::new(color.r * shade, color.g * shade, color.b * shade, 1.0);
+	}
+	match cell.kind {
+		tetris::CellKind::Normal | tetris::CellKind::Bomb if is_falling => color::hsl_to_rgb(cell.hue, 1.0, 0.5), // HARDCODE Saturation?
+		tetris::CellKind::Normal | tetris::CellKind::Bomb => color::hsl_to_rgb(cell.hue, 0.5, 0.3), // HARDCODE Maybe less saturated?
+		tetris::CellKind::Garbage => GRAY,
+		tetris::CellKind::Frozen => Color::new(0.7, 0.85, 1.0, 1.0), // HARDCODE Ice
+		tetris::CellKind::Bedrock => Color::new(0.2, 0.2, 0.2, 1.0),
+	}
+}
+
+/// The usual colors for the classic pieces; in the stack they are only darkened, where hues also get desaturated.
+fn classic_color(shape: tetris::Shape) -> Color {
+	// HARDCODE
+	match shape {
+		tetris::Shape::I => Color::from_rgba(0, 240, 240, 255),
+		tetris::Shape::O => Color::from_rgba(240, 240, 0, 255),
+		tetris::Shape::T => Color::from_rgba(160, 0, 240, 255),
+		tetris::Shape::S => Color::from_rgba(0, 240, 0, 255),
+		tetris::Shape::Z => Color::from_rgba(240, 0, 0, 255),
+		tetris::Shape::J => Color::from_rgba(0, 0, 240, 255),
+		tetris::Shape::L => Color::from_rgba(240, 160, 0, 255),
+	}
+}
+
+/// Which sides of a cell run on into the same piece, in JOIN_OFFSETS order.
+type Joins = [bool; 4];
+
+/// Up, right, down, left.
+const JOIN_OFFSETS: [(i32, i32); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+
+fn stack_joins(game_state: &tetris::GameState, cell: &tetris::Cell, (x, y): (usize, usize)) -> Joins {
+	JOIN_OFFSETS.map(|(dx, dy)| {
+		let (Some(nx), Some(ny)) = (x.checked_add_signed(dx as isize), y.checked_add_signed(dy as isize))
+			else { return false; };
+		game_state.cell_matrix.get(ny)
+			.and_then(|row| row.cells.get(nx))
+			.and_then(Option::as_ref)
+			.is_some_and(|neighbor| cell.same_piece(neighbor))
+	})
+}
+
+/// Flat, outlined in a darker shade on the sides that don't join.
+fn render_connected_cell(x: f32, y: f32, size: f32, color: Color, joins: Joins) {
+	draw_rectangle(x, y, size, size, color);
+	let edge = (size / 10.0).max(1.0); // HARDCODE
+	let outline = Color::new(color.r * 0.5, color.g * 0.5, color.b * 0.5, color.a);
+	let sides = [
+		(x, y, size, edge),
+		(x + size - edge, y, edge, size),
+		(x, y + size - edge, size, edge),
+		(x, y, edge, size),
+	];
+	for ((sx, sy, sw, sh), joined) in sides.into_iter().zip(joins) {
+		if !joined {
+			draw_rectangle(sx, sy, sw, sh, outline);
+		}
+	}
+}
+
+/// A square face with four slanted edges around it, shaded as though lit from the top left.
+fn render_beveled_cell(x: f32, y: f32, size: f32, color: Color) {
+	let edge = (size / 6.0).max(1.0); // HARDCODE
+	let shade = |light: f32| if light > 0.0 {
+		Color::new(color.r + (1.0 - color.r) * light, color.g + (1.0 - color.g) * light, color.b + (1.0 - color.b) * light, color.a)
+	} else {
+		Color::new(color.r * (1.0 + light), color.g * (1.0 + light), color.b * (1.0 + light), color.a)
+	};
+	// HARDCODE How much lighter or darker each edge is than the face.
+	let (top, left, bottom, right) = (shade(0.45), shade(0.25), shade(-0.45), shade(-0.25));
+	let (x1, y1) = (x + size, y + size);
+	let (ix, iy, ix1, iy1) = (x + edge, y + edge, x1 - edge, y1 - edge);
+	let trapezoid = |a: Vec2, b: Vec2, c: Vec2, d: Vec2, color: Color| {
+		draw_triangle(a, b, c, color);
+		draw_triangle(a, c, d, color);
+	};
+	trapezoid(Vec2::new(x, y), Vec2::new(x1, y), Vec2::new(ix1, iy), Vec2::new(ix, iy), top);
+	trapezoid(Vec2::new(x, y), Vec2::new(ix, iy), Vec2::new(ix, iy1), Vec2::new(x, y1), left);
+	trapezoid(Vec2::new(x, y1), Vec2::new(ix, iy1), Vec2::new(ix1, iy1), Vec2::new(x1, y1), bottom);
+	trapezoid(Vec2::new(x1, y), Vec2::new(x1, y1), Vec2::new(ix1, iy1), Vec2::new(ix1, iy), right);
+	draw_rectangle(ix, iy, ix1 - ix, iy1 - iy, color);
+}
+
+/// A letter over cells carrying a power-up.
+fn render_item(cell: &tetris::Cell, x: f32, y: f32, size: f32) {
+	let Some(item) = cell.item
+		else { return; };
+	let letter = match item {
+		tetris::Item::ClearColumn => "C",
+		tetris::Item::SlowTime => "T",
+		tetris::Item::ShrinkNextPiece => "S",
+	};
+	let font_size = (size * 0.8) as u16;
+	let dims = measure_text(letter, None, font_size, 1.0);
+	draw_text(letter, x + (size - dims.width) / 2.0, y + (size + dims.offset_y) / 2.0, font_size as f32, WHITE);
+}
+
+/// Centered in `slot`, whichever way it is turned.
+fn render_preview(piece: &tetris::Piece, slot: layout::Rect, cell_px: f32) {
+	let (min_x, max_x) = piece.cells.iter().fold((i32::MAX, i32::MIN), |(lo, hi), c| (lo.min(c.x), hi.max(c.x)));
+	let (min_y, max_y) = piece.cells.iter().fold((i32::MAX, i32::MIN), |(lo, hi), c| (lo.min(c.y), hi.max(c.y)));
+	let x0 = slot.x + (slot.w - (max_x - min_x + 1) as f32 * cell_px) / 2.0;
+	let y0 = slot.y + (slot.h - (max_y - min_y + 1) as f32 * cell_px) / 2.0;
+	for c in piece.cells.iter() {
+		let (x, y) = (x0 + (c.x - min_x) as f32 * cell_px, y0 + (c.y - min_y) as f32 * cell_px);
+		draw_rectangle(x, y, cell_px, cell_px, cell_color(&c.cell, true));
+		render_item(&c.cell, x, y, cell_px);
+	}
+}
+
+/// Another player's stack and falling piece, shrunk into `board`, with their name above.
+/// With render_stack_gauge() into `gauge`, if there is room for one.
+fn render_opponent(name: &str, game_state: &tetris::GameState, board: layout::Rect, gauge: Option<layout::Rect>, label_px: f32) {
+	let cell_px = board.w / game_state.cell_matrix_width as f32;
+	let hidden_rows = game_state.hidden_rows as i32;
+	draw_text(name, board.x, board.y - label_px * 0.2, label_px * 0.8, GRAY);
+	for (y, row) in game_state.cell_matrix.iter().skip(game_state.hidden_rows).enumerate() {
+		for (x, cell) in row.cells.iter().enumerate() {
+			if let Some(c) = cell {
+				draw_rectangle(board.x + x as f32 * cell_px, board.y + y as f32 * cell_px, cell_px, cell_px, cell_color(c, false));
+			}
+		}
+	}
+	if let Some(p) = game_state.current_piece.as_ref() {
+		for (c, x, y) in p.iter_global_space(game_state.current_piece_mass_xy).filter(|&(_c, _x, y)| y >= hidden_rows) {
+			draw_rectangle(board.x + x as f32 * cell_px, board.y + (y - hidden_rows) as f32 * cell_px, cell_px, cell_px, cell_color(c, true));
+		}
+	}
+	draw_rectangle_lines(board.x, board.y, board.w, board.h, 1.0, DARKGRAY);
+	if let Some(gauge) = gauge {
+		render_stack_gauge(game_state, gauge, label_px);
+	}
+}
+
+/// A bar of the tallest column, red once in danger, then a bar per column: the stack's surface at a glance.
+fn render_stack_gauge(game_state: &tetris::GameState, rect: layout::Rect, label_px: f32) {
+	let room = game_state.stack_room().max(1) as f32;
+	let height = game_state.stack_height();
+	let color = if game_state.is_in_danger() { RED } else { LIGHTGRAY };
+	draw_text(&format!("Height {height}"), rect.x, rect.y + label_px * 0.6, label_px * 0.8, GRAY);
+	let strip = layout::Rect::new(rect.x, rect.y + label_px, rect.w, rect.h - label_px);
+	let bar_w = strip.w / 8.0; // HARDCODE
+	let bar_h = strip.h * height as f32 / room;
+	draw_rectangle(strip.x, strip.bottom() - bar_h, bar_w, bar_h, color);
+	let surface = game_state.surface();
+	let column_w = (strip.w - bar_w * 1.5) / surface.len().max(1) as f32;
+	let left = strip.x + bar_w * 1.5;
+	for (i, &column) in surface.iter().enumerate() {
+		let h = strip.h * column as f32 / room;
+		draw_rectangle(left + i as f32 * column_w, strip.bottom() - h, column_w - 1.0, h, Color { a: 0.7, ..color });
+	}
+	draw_rectangle_lines(strip.x, strip.y, strip.w, strip.h, 1.0, DARKGRAY);
+}
+
+/// Versus: the opponent's name, and the set's score so far when it's longer than a game; ours first.
+fn opponent_label(versus: &netplay::Versus) -> String {
+	match versus.set.best_of {
+		1 => versus.opponent_name.clone(),
+		_ => format!("{} ({}-{})", versus.opponent_name, versus.set.wins(), versus.set.losses()),
+	}
+}
+
+/// Versus: a box per game of the set, centered on `y`: won, lost, drawn, and still to play while it's undecided.
+fn render_set_results(set: &netplay::SetScore, typeface: &Typeface, width: f32, y: f32) {
+	// HARDCODE
+	let (box_px, gap_px) = (32.0, 8.0);
+	let unplayed = if set.winner().is_none() { (set.best_of as usize).saturating_sub(set.games.len()) } else { 0 };
+	let count = set.games.len() + unplayed;
+	let mut x = (width - count as f32 * (box_px + gap_px) + gap_px) / 2.0;
+	let font_size = typeface.size(20.0);
+	for game in set.games.iter().map(Some).chain(std::iter::repeat_n(None, unplayed)) {
+		let (text, color) = match game {
+			Some(Some(netplay::Outcome::Won)) => ("W", GREEN),
+			Some(Some(netplay::Outcome::Lost)) => ("L", RED),
+			Some(None) => ("D", GRAY),
+			None => ("", DARKGRAY),
+		};
+		draw_rectangle(x, y - box_px / 2.0, box_px, box_px, Color { a: 0.8, ..BLACK });
+		draw_rectangle_lines(x, y - box_px / 2.0, box_px, box_px, 2.0, color);
+		let dims = typeface.measure(text, font_size);
+		typeface.draw(text, x + (box_px - dims.width) / 2.0, y + dims.offset_y / 2.0, font_size, color);
+		x += box_px + gap_px;
+	}
+}
+
+/// Versus: garbage on its way in, as a bar up the right wall of the board, a cell per row and a notch between attacks,
+/// oldest at the bottom. Rows canceled or risen since `shown_rows` drain away in white.
+fn render_incoming_meter(incoming: &VecDeque<u32>, shown_rows: f32, board: layout::Rect, cell_px: f32) {
+	let rows: u32 = incoming.iter().sum();
+	// HARDCODE Yellow for a little, orange for a lot, red from the height of a quad, pulsing once it'd fill half the well.
+	let color = match rows {
+		0 ..= 2 => YELLOW,
+		3 => ORANGE,
+		_ => RED,
+	};
+	let alpha = if rows as f32 * cell_px * 2.0 >= board.h { 0.6 + 0.4 * (get_time() * 8.0).sin().abs() as f32 } else { 1.0 };
+	let (x, w) = (board.right() + 1.0, 6.0);
+	let to_px = |rows: f32| (rows * cell_px).min(board.h);
+	if shown_rows > rows as f32 {
+		let top = board.bottom() - to_px(shown_rows);
+		draw_rectangle(x, top, w, to_px(shown_rows) - to_px(rows as f32), Color::new(1.0, 1.0, 1.0, 0.5));
+	}
+	let mut bottom = board.bottom();
+	for &attack in incoming.iter() {
+		let h = to_px(attack as f32).min(bottom - board.y);
+		if h <= 0.0 {
+			break;
+		}
+		draw_rectangle(x, bottom - h, w, (h - 1.0).max(1.0), Color { a: alpha, ..color });
+		bottom -= h;
+	}
+	if rows > 0 {
+		draw_text(&rows.to_string(), x, board.bottom() - to_px(rows as f32) - 2.0, 14.0, color);
+	}
+}
+
+/// Where the points came from, one line a part; nothing when they all came from the one place.
+fn render_score_breakdown(breakdown: &tetris::ScoreBreakdown, score: u32, board: layout::Rect, top: f32) {
+	let parts: Vec<(&str, u32)> = breakdown.parts().into_iter().filter(|&(_label, points)| points > 0).collect();
+	if parts.len() < 2 {
+		return;
+	}
+	let font_size = 18.0;
+	draw_rectangle(board.x + 8.0, top - font_size, board.w - 16.0, font_size * (parts.len() + 1) as f32 + 8.0, Color::new(0.0, 0.0, 0.0, 0.6));
+	for (i, (label, points)) in std::iter::once(("Score", score)).chain(parts).enumerate() {
+		let y = top + i as f32 * font_size;
+		let color = if i == 0 { WHITE } else { LIGHTGRAY };
+		draw_text(label, board.x + 16.0, y, font_size, color);
+		let value = points.to_string();
+		let dims = measure_text(&value, None, font_size as u16, 1.0);
+		draw_text(&value, board.right() - 16.0 - dims.width, y, font_size, color);
+	}
+}
+
+/// F4; each cell tinted by how many pieces locked over it, with the left/right split below the board.
+fn render_heatmap(heatmap: &analysis::Heatmap, board: layout::Rect, cell_px: f32) {
+	let max = heatmap.max();
+	if max == 0 {
+		draw_text("No pieces placed yet", board.x + 4.0, board.bottom() - 4.0, 14.0, LIGHTGRAY);
+		return;
+	}
+	for y in 0 .. heatmap.height {
+		for x in 0 .. heatmap.width {
+			let heat = heatmap.count(x, y) as f32 / max as f32;
+			// HARDCODE Blue through red, see-through enough to show the stack under it.
+			let color = Color::new(heat, 0.2, 1.0 - heat, 0.15 + 0.5 * heat);
+			draw_rectangle(board.x + x as f32 * cell_px, board.y + y as f32 * cell_px, cell_px, cell_px, color);
+		}
+	}
+	let left = heatmap.left_share();
+	let split = format!("{} pieces; {:.0}% left, {:.0}% right", heatmap.pieces, left * 100.0, (1.0 - left) * 100.0);
+	draw_text(&split, board.x + 4.0, board.bottom() - 4.0, 14.0, WHITE);
+}
+
+/// One value per step left to right, scaled to the largest; bars for counts, a line for the rest.
+fn render_graph(rect: layout::Rect, title: &str, values: &[f32], color: Color, bars: bool) {
+	draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, DARKGRAY);
+	let max = values.iter().copied().fold(0.0, f32::max);
+	draw_text(&format!("{title} (max {})", (max * 100.0).round() / 100.0), rect.x + 4.0, rect.y + 14.0, 14.0, LIGHTGRAY);
+	if values.is_empty() || max <= 0.0 {
+		return;
+	}
+	let step = rect.w / values.len() as f32;
+	let y_of = |v: f32| rect.y + rect.h - v / max * (rect.h - 18.0); // HARDCODE Clear of the title
+	if bars {
+		for (i, &v) in values.iter().enumerate() {
+			let y = y_of(v);
+			draw_rectangle(rect.x + i as f32 * step, y, (step - 1.0).max(1.0), rect.y + rect.h - y, color);
+		}
+	} else {
+		for (i, pair) in values.windows(2).enumerate() {
+			let x = rect.x + (i as f32 + 0.5) * step;
+			draw_line(x, y_of(pair[0]), x + step, y_of(pair[1]), 2.0, color);
+		}
+	}
+}
+
+/// T opens the chat line, Enter sends it, Escape drops it; returns what to send.
+fn chat_input(draft: &mut Option<String>) -> Option<String> {
+	let Some(text) = draft.as_mut()
+		else {
+			if is_key_pressed(KeyCode::T) {
+				*draft = Some(String::new());
+			}
+			return None;
+		};
+	while let Some(c) = get_char_pressed() {
+		if !c.is_control() && text.chars().count() < netplay::CHAT_MAX_CHARS {
+			text.push(c);
+		}
+	}
+	if is_key_pressed(KeyCode::Backspace) {
+		text.pop();
+	}
+	if is_key_pressed(KeyCode::Escape) {
+		*draft = None;
+	} else if is_key_pressed(KeyCode::Enter) {
+		return draft.take();
+	}
+	None
+}
+
+/// Versus chat, oldest first, wrapped to fit `rect`, with the line being typed, or how to start one, at the bottom.
+fn render_chat(chat: &netplay::Chat, draft: Option<&str>, hint: &str, rect: layout::Rect, font_px: f32) {
+	let mut lines: Vec<(String, Color)> = chat.lines.iter().map(|line| (format!("{}: {}", line.name, line.text), LIGHTGRAY)).collect();
+	lines.push(match draft {
+		Some(draft) => (format!("> {draft}_"), WHITE),
+		None => (hint.to_string(), GRAY),
+	});
+	let mut rows: Vec<(String, Color)> = Vec::new();
+	for (line, color) in lines {
+		let mut row = String::new();
+		for word in line.split(' ') {
+			let longer = if row.is_empty() { word.to_string() } else { format!("{row} {word}") };
+			if !row.is_empty() && measure_text(&longer, None, font_px as u16, 1.0).width > rect.w {
+				rows.push((std::mem::replace(&mut row, word.to_string()), color));
+			} else {
+				row = longer;
+			}
+		}
+		rows.push((row, color));
+	}
+	let fit = (rect.h / font_px) as usize;
+	for (i, (row, color)) in rows.iter().skip(rows.len().saturating_sub(fit)).enumerate() {
+		draw_text(row, rect.x, rect.y + (i + 1) as f32 * font_px, font_px, *color);
+	}
+}
+
+/// Every shape in a grid over the board; returns the one clicked, if any.
+fn piece_picker(shapes: &[tetris::Piece], sequence_len: usize, board: layout::Rect) -> Option<usize> {
+	let columns = 4; // HARDCODE
+	let slot_px = board.w / columns as f32;
+	let top = 48.0;
+	draw_rectangle(board.x, board.y, board.w, board.h, Color::new(0.0, 0.0, 0.0, 0.85));
+	draw_text("PICK A PIECE", board.x + 8.0, top - 16.0, 24.0, LIGHTGRAY);
+	let (mouse_x, mouse_y) = mouse_position();
+	let mut clicked = None;
+	for (i, p) in shapes.iter().enumerate() {
+		let slot = layout::Rect::new(board.x + (i % columns) as f32 * slot_px, top + (i / columns) as f32 * slot_px, slot_px, slot_px);
+		let is_hovered = (slot.x .. slot.right()).contains(&mouse_x) && (slot.y .. slot.bottom()).contains(&mouse_y);
+		draw_rectangle_lines(slot.x + 2.0, slot.y + 2.0, slot.w - 4.0, slot.h - 4.0, 1.0, if is_hovered { WHITE } else { DARKGRAY });
+		render_preview(p, slot, slot_px / (tetris::MAX_PIECE_CELLS + 1) as f32);
+		if is_hovered && is_mouse_button_pressed(MouseButton::Left) {
+			clicked = Some(i);
+		}
+	}
+	let hints = [
+		"Click: spawn it next".to_string(),
+		"Shift+click: add to sequence".to_string(),
+		format!("Delete: clear sequence ({sequence_len})"),
+		"Tab: back to the game".to_string(),
+	];
+	for (i, hint) in hints.iter().enumerate() {
+		draw_text(hint, board.x + 8.0, board.bottom() - 8.0 - (hints.len() - 1 - i) as f32 * 20.0, 16.0, LIGHTGRAY);
+	}
+	clicked
+}
+
+/// The sidebar: score, level, lines, and time, then the hold box and the next queue.
+fn render_hud(layout: &layout::Layout, typeface: &Typeface, game_state: &tetris::GameState) {
+	let label_px = layout.label_px;
+	let stats = [
+		("SCORE", layout.score, game_state.score.to_string()),
+		("LEVEL", layout.level, game_state.level().to_string()),
+		("LINES", layout.lines, game_state.rows_cleared.to_string()),
+		("TIME", layout.time, records::format_ticks(game_state.ticks)),
+	];
+	for (label, slot, value) in stats {
+		typeface.draw(label, slot.x, slot.y + label_px * 0.8, (label_px * 0.8) as u16, GRAY);
+		typeface.draw(&value, slot.x, slot.bottom() - label_px * 0.1, (label_px * 1.2) as u16, WHITE);
+	}
+	if let Some(hold) = layout.hold {
+		typeface.draw("HOLD", hold.x, hold.y - label_px * 0.2, (label_px * 0.8) as u16, GRAY);
+		draw_rectangle_lines(hold.x, hold.y, hold.w, hold.h, 1.0, DARKGRAY);
+		if let Some(p) = game_state.held_piece.as_ref() {
+			render_preview(p, hold, layout.preview_cell_px);
+		}
+	}
+	if let Some(first) = layout.next.first() {
+		typeface.draw("NEXT", first.x, first.y - label_px * 0.2, (label_px * 0.8) as u16, GRAY);
+	}
+	for (p, &slot) in game_state.next_pieces().zip(layout.next.iter()) {
+		render_preview(p, slot, layout.preview_cell_px);
+	}
+	// Shrunk to fit, since seeds off the clock run to twenty digits.
+	let seed = format!("#{}", game_state.seed);
+	let seed_px = (layout.seed.h as u16).min((layout.seed.h * layout.seed.w / typeface.measure(&seed, layout.seed.h as u16).width.max(1.0)) as u16);
+	typeface.draw(&seed, layout.seed.x, layout.seed.bottom(), seed_px, GRAY);
+}
+
+/// F12; into the working directory natively, or as a download in the browser.
+fn save_screenshot() {
+	let screen = get_screen_data();
+	let row_bytes = screen.width as usize * 4;
+	// GL reads bottom-up.
+	let rgba: Vec<u8> = screen.bytes.chunks_exact(row_bytes).rev().flatten().copied().collect();
+	let file_name = capture::timestamped_file_name("screenshot", "png", miniquad::date::now() as u64);
+	let saved = capture::encode_png(screen.width as u32, screen.height as u32, &rgba)
+		.and_then(|png| storage::export(&file_name, &png).map_err(|e| e.to_string()));
+	match saved {
+		Ok(()) => log::info!("Saved {file_name}"),
+		Err(e) => log::error!("Could not save screenshot: {e}"),
+	}
+}
+
+/// Call instead of next_frame(), once everything is drawn.
+async fn end_frame() {
+	if is_key_pressed(KeyCode::F12) {
+		save_screenshot();
+	}
+	next_frame().await
+}
+
+/// Measured ticks per second, averaged over about a second.
+struct TickRate {
+	since: f64,
+	ticks_then: u64,
+	per_second: f64,
+}
+
+impl TickRate {
+	fn sample(&mut self, now: f64, ticks: u64) {
+		let elapsed = now - self.since;
+		if ticks < self.ticks_then {
+			// A new game started.
+			(self.since, self.ticks_then) = (now, ticks);
+		} else if elapsed >= 1.0 {
+			self.per_second = (ticks - self.ticks_then) as f64 / elapsed;
+			(self.since, self.ticks_then) = (now, ticks);
+		}
+	}
+}
+
+/// F3; drawn over everything else.
+fn render_debug_overlay(game_state: &tetris::GameState, tick_rate: &TickRate) {
+	let font_size = 16.0;
+	let (x, y) = game_state.current_piece_mass_xy;
+	let lines = [
+		format!("FPS {}", get_fps()),
+		format!("frame {:.2} ms", get_frame_time() * 1000.0),
+		format!("ticks/s {:.1} (nominal {})", tick_rate.per_second, tetris::TICKS_PER_SECOND),
+		format!("tick {}", game_state.ticks),
+		format!("piece at ({x}, {y})"),
+		format!("phase {:?}", game_state.phase),
+		format!("seed {}", game_state.seed),
+	];
+	let width = lines.iter().map(|l| measure_text(l, None, font_size as u16, 1.0).width).fold(0.0, f32::max);
+	draw_rectangle(0.0, 0.0, width + 8.0, font_size * lines.len() as f32 + 8.0, Color::new(0.0, 0.0, 0.0, 0.7));
+	for (i, line) in lines.iter().enumerate() {
+		draw_text(line, 4.0, font_size * (i + 1) as f32, font_size, GREEN);
+	}
+}
+
+/// Keyboard in, board and sidebar out; menus and overlays are App's.
+struct MacroquadFrontend {
+	width_cells: usize,
+	cell_sidelength_px: f32,
+	layout: layout::Layout,
+	particles: Vec<Particle>,
+	/// For effects like particles, apart from the game's; see rng.rs.
+	effects_rng: rng::RandomNumberGenerator,
+	popups: Vec<ScorePopup>,
+	/// As of the last frame a piece was falling, so the next clear can say what it was worth.
+	score_before_clear: u32,
+	/// Where the last falling piece was; clears happen where it locked.
+	last_piece_xy: (i32, i32),
+	/// Each counter swells when it goes up, then shrinks back.
+	combo_pulse: Option<tween::Tween>,
+	back_to_back_pulse: Option<tween::Tween>,
+	last_combo: u32,
+	last_back_to_back: u32,
+	grid_style: theme::GridStyle,
+	block_style: theme::BlockStyle,
+	input: input::Input,
+	/// Per config::Config::input_log; cleared by start_game().
+	input_log: Option<input::InputLog>,
+	/// Per config::Config::mouse_controls.
+	mouse_controls: bool,
+	/// Draw buttons to play with; see touch_buttons().
+	touch_overlay: bool,
+	typeface: Typeface,
+	/// From assets::Assets; None if it didn't load.
+	block_texture: Option<Texture2D>,
+	/// Since the last Event::Danger(true), in get_time() seconds; None while the stack is clear of the top.
+	danger_since: Option<f64>,
+	sounds: Vec<assets::SoundHandle>,
+}
+
+/// Macroquad's names for the keys input::Key covers.
+const KEYS: [(KeyCode, input::Key); 18] = [
+	(KeyCode::Left, input::Key::Left),
+	(KeyCode::Right, input::Key::Right),
+	(KeyCode::Up, input::Key::Up),
+	(KeyCode::Down, input::Key::Down),
+	(KeyCode::Space, input::Key::Space),
+	(KeyCode::LeftShift, input::Key::LeftShift),
+	(KeyCode::RightShift, input::Key::RightShift),
+	(KeyCode::LeftControl, input::Key::LeftControl),
+	(KeyCode::RightControl, input::Key::RightControl),
+	(KeyCode::A, input::Key::A),
+	(KeyCode::C, input::Key::C),
+	(KeyCode::D, input::Key::D),
+	(KeyCode::E, input::Key::E),
+	(KeyCode::Q, input::Key::Q),
+	(KeyCode::S, input::Key::S),
+	(KeyCode::W, input::Key::W),
+	(KeyCode::X, input::Key::X),
+	(KeyCode::Z, input::Key::Z),
+];
+
+/// Everything held down this frame. Macroquad has no gamepad support, so just keys for now.
+fn held_controls() -> BTreeSet<input::Control> {
+	KEYS.iter()
+		.filter(|(code, _key)| is_key_down(*code))
+		.map(|&(_code, key)| input::Control::Key(key))
+		.collect()
+}
+
+/// The on-screen buttons, sized to the canvas as it is now.
+fn touch_buttons() -> Vec<(input::TouchButton, layout::Rect)> {
+	input::touch_buttons(layout::Rect::new(0.0, 0.0, screen_width(), screen_height()))
+}
+
+/// On-screen buttons under a finger this frame.
+fn touched_controls() -> BTreeSet<input::Control> {
+	let fingers: Vec<(f32, f32)> = touches().into_iter()
+		.filter(|t| !matches!(t.phase, TouchPhase::Ended | TouchPhase::Cancelled))
+		.map(|t| (t.position.x, t.position.y))
+		.collect();
+	input::touched(&touch_buttons(), &fingers)
+}
+
+/// Translucent, so the stack shows through; brighter while pressed.
+fn render_touch_buttons(pressed: &BTreeSet<input::Control>) {
+	for (button, r) in touch_buttons() {
+		let alpha = if pressed.contains(&input::Control::Touch(button)) { 0.5 } else { 0.2 }; // HARDCODE
+		let inset = r.w * 0.05;
+		draw_rectangle(r.x + inset, r.y + inset, r.w - 2.0 * inset, r.h - 2.0 * inset, Color::new(1.0, 1.0, 1.0, alpha));
+		let font_size = r.h * 0.3;
+		let dims = measure_text(button.label(), None, font_size as u16, 1.0);
+		draw_text(button.label(), r.x + (r.w - dims.width) / 2.0, r.y + (r.h + dims.offset_y) / 2.0, font_size, Color::new(1.0, 1.0, 1.0, 0.8));
+	}
+}
+
+impl MacroquadFrontend {
+	/// Everything held right now, on screen too once it's been touched; logged if the config says so.
+	fn held(&mut self, game_state: &tetris::GameState) -> BTreeSet<input::Control> {
+		// The web build finds out it is on a touch screen once somebody touches it.
+		if cfg!(target_family="wasm") && !touches().is_empty() {
+			self.touch_overlay = true;
+		}
+		let mut held = held_controls();
+		if self.touch_overlay {
+			held.extend(touched_controls());
+		}
+		if let Some(input_log) = self.input_log.as_mut() {
+			input_log.observe(get_time(), game_state.ticks, &held);
+		}
+		held
+	}
+
+	fn pointer(&self) -> input::Pointer {
+		let (x, y) = mouse_position();
+		let board = self.layout.board;
+		let is_over_board = (board.x .. board.right()).contains(&x) && (board.y .. board.bottom()).contains(&y);
+		input::Pointer {
+			column: is_over_board.then(|| ((x - board.x) / self.cell_sidelength_px) as i32),
+			left_clicked: is_mouse_button_pressed(MouseButton::Left),
+			right_clicked: is_mouse_button_pressed(MouseButton::Right),
+		}
+	}
+
+	/// A cell of the board at `x, y` in pixels, in `color`; see theme::BlockStyle. Only the connected style
+	/// looks at `joins`.
+	fn render_cell(&self, x: f32, y: f32, color: Color, joins: Joins) {
+		let size = self.cell_sidelength_px;
+		match (self.block_style, self.block_texture.as_ref()) {
+			(theme::BlockStyle::Beveled, _) => render_beveled_cell(x, y, size, color),
+			(theme::BlockStyle::Connected, _) => render_connected_cell(x, y, size, color, joins),
+			(theme::BlockStyle::Flat, Some(texture)) => draw_texture_ex(texture, x, y, color, DrawTextureParams { dest_size: Some(Vec2::new(size, size)), ..Default::default() }),
+			(theme::BlockStyle::Flat, None) => draw_rectangle(x, y, size, size, color),
+		}
+	}
+
+	fn render_streaks(&mut self, game_state: &tetris::GameState) {
+		let now = get_time();
+		let pulse = || Some(tween::Tween::new(1.6, 1.0, now, 0.3, tween::Easing::OutBack)); // HARDCODE
+		if game_state.combo > self.last_combo {
+			self.combo_pulse = pulse();
+		}
+		if game_state.back_to_back > self.last_back_to_back {
+			self.back_to_back_pulse = pulse();
+		}
+		(self.last_combo, self.last_back_to_back) = (game_state.combo, game_state.back_to_back);
+		let slot = self.layout.streaks;
+		let lines = [
+			// The first clear of a run doesn't count yet.
+			(game_state.combo.checked_sub(1).filter(|&n| n > 0).map(|n| format!("COMBO {n}")), self.combo_pulse, ORANGE),
+			(game_state.back_to_back.checked_sub(1).filter(|&n| n > 0).map(|n| format!("B2B {n}")), self.back_to_back_pulse, SKYBLUE),
+		];
+		for (i, (text, pulse, color)) in lines.into_iter().enumerate() {
+			let Some(text) = text
+				else { continue; };
+			let scale = pulse.map_or(1.0, |t| t.value(now));
+			let font_size = self.layout.label_px * scale;
+			let baseline = slot.y + slot.h / 2.0 * (i + 1) as f32 - self.layout.label_px * 0.1;
+			draw_text(&text, slot.x, baseline, font_size, color);
+		}
+	}
+}
+
+impl Frontend for MacroquadFrontend {
+	fn poll_input(&mut self, game_state: &tetris::GameState) -> Vec<tetris::Action> {
+		let held = self.held(game_state);
+		let mut actions = self.input.actions(&held);
+		if self.mouse_controls {
+			actions.extend(input::pointer_actions(&self.pointer(), game_state));
+		}
+		actions
+	}
+
+	fn render(&mut self, game_state: &tetris::GameState) {
+		let cell_sidelength_px_f32 = self.cell_sidelength_px;
+		let (width_px, height_px) = (self.layout.board.w, self.layout.board.h);
+		let [h, s, l] = theme::for_level(game_state.level()).grid;
+		let grid_color = color::hsl_to_rgb(h, s, l);
+		let visible_rows = game_state.cell_matrix.len() - game_state.hidden_rows;
+		render_grid(self.grid_style, grid_color, game_state.cell_matrix_width, visible_rows, cell_sidelength_px_f32);
+
+		render_hud(&self.layout, &self.typeface, game_state);
+		self.render_streaks(game_state);
+
+		// Points arrive a frame after RowsCleared, once the score has caught up.
+		for popup in self.popups.iter_mut().filter(|popup| popup.points.is_none()) {
+			popup.points = Some(game_state.score.saturating_sub(self.score_before_clear));
+		}
+		if let tetris::Phase::Falling = game_state.phase {
+			self.score_before_clear = game_state.score;
+		}
+		if game_state.current_piece.is_some() {
+			self.last_piece_xy = game_state.current_piece_mass_xy;
+		}
+
+		let hidden_rows = game_state.hidden_rows as i32;
+		let (mut x, mut y) = (0.0, 0.0);
+		for (row_index, row) in game_state.cell_matrix.iter().enumerate().skip(game_state.hidden_rows) {
+			for (column, cell) in row.cells.iter().enumerate() {
+				if let Some(c) = cell {
+					self.render_cell(x, y, cell_color(c, false), stack_joins(game_state, c, (column, row_index)));
+					if c.kind == tetris::CellKind::Bedrock {
+						draw_line(x, y, x + cell_sidelength_px_f32, y + cell_sidelength_px_f32, 2.0, GRAY);
+						draw_line(x + cell_sidelength_px_f32, y, x, y + cell_sidelength_px_f32, 2.0, GRAY);
+					}
+					render_item(c, x, y, cell_sidelength_px_f32);
+				}
+				x += cell_sidelength_px_f32;
+			}
+			x = 0.0;
+			y += cell_sidelength_px_f32;
+		}
+
+		if let tetris::Phase::LineClear { ticks_left, rows, columns } = &game_state.phase {
+			// Fade out as the rows disappear.
+			let alpha = (*ticks_left as f32 / game_state.timing.line_clear_delay.max(1) as f32).min(1.0);
+			for &row in rows {
+				let y_px = (row as i32 - hidden_rows) as f32 * cell_sidelength_px_f32;
+				draw_rectangle(0.0, y_px, width_px, cell_sidelength_px_f32, Color::new(1.0, 1.0, 1.0, alpha));
+			}
+			for &column in columns {
+				let x_px = column as f32 * cell_sidelength_px_f32;
+				draw_rectangle(x_px, 0.0, cell_sidelength_px_f32, height_px, Color::new(1.0, 1.0, 1.0, alpha));
+			}
+		}
+
+		let current = game_state.current_piece.as_ref().map(|p| (p, game_state.current_piece_mass_xy));
+		for (p, mass_xy) in current.into_iter().chain(game_state.other_pieces()) {
+			let cells: Vec<(i32, i32)> = p.iter_global_space(mass_xy).map(|(_cell, x, y)| (x, y)).collect();
+			for (c, x, y) in p.iter_global_space(mass_xy) {
+				let (x_px, y_px) = (x as f32 * cell_sidelength_px_f32, (y - hidden_rows) as f32 * cell_sidelength_px_f32);
+				if c.kind == tetris::CellKind::Bomb {
+					let radius = cell_sidelength_px_f32 / 2.0;
+					draw_circle(x_px + radius, y_px + radius, radius, DARKGRAY);
+					draw_circle(x_px + radius * 1.6, y_px + radius * 0.4, radius * 0.25, ORANGE); // HARDCODE The fuse
+					continue;
+				}
+				let joins = JOIN_OFFSETS.map(|(dx, dy)| cells.contains(&(x + dx, y + dy)));
+				self.render_cell(x_px, y_px, cell_color(c, true), joins);
+				render_item(c, x_px, y_px, cell_sidelength_px_f32);
+			}
+			let com_x = (mass_xy.0 as f32 + 0.5) * cell_sidelength_px_f32;
+			let com_y = ((mass_xy.1 - hidden_rows) as f32 + 0.5) * cell_sidelength_px_f32;
+			draw_circle(com_x, com_y, 8.0, BLACK); // HARDCODE
+			draw_circle(com_x, com_y, 4.0, WHITE); // HARDCODE
+		}
+
+		// The walls and floor, drawn over anything poking out of the well.
+		draw_rectangle_lines(0.0, 0.0, width_px, height_px, 2.0, Color { a: 1.0, ..grid_color }); // HARDCODE
+		if let Some(since) = self.danger_since {
+			let pulse = 0.5 + 0.5 * ((get_time() - since) * std::f64::consts::TAU * 1.5).cos() as f32; // HARDCODE 1.5 Hz
+			draw_rectangle_lines(0.0, 0.0, width_px, height_px, 6.0, Color::new(1.0, 0.1, 0.1, pulse));
+		}
+		// Particles move in cell-spaces per second, so they scale with the board.
+		let dt = get_frame_time();
+		for particle in self.particles.iter_mut() {
+			particle.x += particle.vx * dt;
+			particle.y += particle.vy * dt;
+			particle.vy += 20.0 * dt; // HARDCODE Their own gravity
+			particle.life -= dt;
+			let (x_px, y_px) = (particle.x * cell_sidelength_px_f32, (particle.y - hidden_rows as f32) * cell_sidelength_px_f32);
+			let color = Color { a: particle.life.clamp(0.0, 1.0), ..particle.color };
+			draw_rectangle(x_px - 2.0, y_px - 2.0, 4.0, 4.0, color);
+		}
+		self.particles.retain(|particle| particle.life > 0.0);
+		let now = get_time();
+		for popup in self.popups.iter() {
+			let Some(points) = popup.points
+				else { continue; };
+			let text = format!("+{points} {}", popup.label);
+			let font_size = (cell_sidelength_px_f32 * 0.75) as u16; // HARDCODE
+			let dims = self.typeface.measure(&text, font_size);
+			let x_px = ((popup.x + 0.5) * cell_sidelength_px_f32 - dims.width / 2.0).clamp(0.0, (width_px - dims.width).max(0.0));
+			let y_px = (popup.y - hidden_rows as f32) * cell_sidelength_px_f32 - popup.rise.value(now);
+			let alpha = 1.0 - popup.rise.progress(now);
+			self.typeface.draw(&text, x_px, y_px, font_size, Color { a: alpha, ..GOLD });
+		}
+		self.popups.retain(|popup| !popup.rise.is_done(now));
+		if self.touch_overlay {
+			render_touch_buttons(&touched_controls());
+		}
+	}
+
+	fn play_event(&mut self, event: &tetris::Event) {
+		if let Some(handle) = assets::Sound::for_event(event).and_then(|sound| self.sounds.iter().find(|h| h.sound() == sound)) {
+			handle.play();
+		}
+		if let &tetris::Event::RowsCleared(n) = event {
+			let label = match n {
+				1 => "SINGLE",
+				2 => "DOUBLE",
+				3 => "TRIPLE",
+				4 => "TETRIS",
+				_ => "LINES",
+			};
+			let rise_px = self.cell_sidelength_px * 2.0; // HARDCODE
+			self.popups.push(ScorePopup {
+				label,
+				points: None,
+				x: self.last_piece_xy.0 as f32,
+				y: self.last_piece_xy.1 as f32,
+				rise: tween::Tween::new(0.0, rise_px, get_time(), 1.0, tween::Easing::OutCubic),
+			});
+		}
+		if let &tetris::Event::Danger(is_in_danger) = event {
+			self.danger_since = is_in_danger.then(get_time);
+			// Percent of normal speed.
+			bridge::emit("music_rate", if is_in_danger { 125 } else { 100 }); // HARDCODE
+		}
+		if let &tetris::Event::Exploded { x, y } = event {
+			for _ in 0 .. 32 { // HARDCODE
+				let angle = self.effects_rng.uniform(0.0, std::f32::consts::TAU);
+				let speed = self.effects_rng.uniform(2.0, 8.0);
+				self.particles.push(Particle {
+					x: x as f32 + 0.5,
+					y: y as f32 + 0.5,
+					vx: angle.cos() * speed,
+					vy: angle.sin() * speed,
+					life: self.effects_rng.uniform(0.5, 1.0),
+					color: if self.effects_rng.uniform(0, 2) == 0 { ORANGE } else { YELLOW },
+				});
+			}
+		}
+	}
+}
+
+/// "+800 DOUBLE", floating up from where rows cleared; x and y in board coordinates.
+#[derive(Clone,Debug)]
+struct ScorePopup {
+	label: &'static str,
+	/// Filled in by the next render(); see MacroquadFrontend::score_before_clear.
+	points: Option<u32>,
+	x: f32,
+	y: f32,
+	/// In pixels; it fades out as it goes.
+	rise: tween::Tween,
+}
+
+/// A spark from an explosion, in board coordinates.
+#[derive(Clone,Debug)]
+struct Particle {
+	x: f32,
+	y: f32,
+	vx: f32,
+	vy: f32,
+	/// Seconds left; also the opacity.
+	life: f32,
+	color: Color,
+}
+
+/// Everything that lasts from frame to frame: settings, records, the game, and whichever scene is up.
+///
+/// Each frame is update(), for input and game logic, then draw(). Macroquad's menus are immediate mode,
+/// drawn and clicked in the same call, so menus and what their buttons do live on the draw() side.
+struct App {
+	width_cells: usize,
+	height_cells: usize,
+	cell_sidelength_px: usize,
+	cell_sidelength_px_f32: f32,
+	height_px: usize,
+	layout: layout::Layout,
+	/// Menus and overlays span the sidebar too.
+	window_width_px: usize,
+	/// Versus widens the window for the other board.
+	versus_layout: layout::Layout,
+	config: config::Config,
+	/// Gravity and tick rate come from the speed preset; [ and ] change it mid-game.
+	speed: speed::Preset,
+	/// The fraction of a tick owed from earlier frames.
+	tick_debt: f64,
+	speed_hint_until: f64,
+	/// While watching, an index into FAST_FORWARD_TICKS; F steps through them and back to None, normal speed.
+	fast_forward: Option<usize>,
+	pending_actions: Vec<tetris::Action>,
+	/// The game started on a seed somebody picked, which would let them shop for easy ones; it sets no records.
+	/// The daily's seed is everybody's, so it doesn't count.
+	seeded: bool,
+	animate_background: bool,
+	leaderboard: Option<leaderboard::LeaderboardClient>,
+	game_state: tetris::GameState,
+	frontend: MacroquadFrontend,
+	typeface: Typeface,
+	/// None with every effect off.
+	post_process: Option<postprocess::PostProcess>,
+	puzzles: Vec<puzzle::Puzzle>,
+	campaign: Vec<campaign::Level>,
+	campaign_progress: campaign::Progress,
+	board_editor: editor::Editor,
+	daily_records: records::DailyRecords,
+	sprint_record: records::SprintRecord,
+	cheese_record: records::CheeseRecord,
+	local_results: records::LocalResults,
+	achievements: achievements::Achievements,
+	script_run: script::ScriptRun,
+	toasts: Vec<(achievements::Achievement, f64)>,
+	highlights: capture::BoardRecorder,
+	/// After a big clear, remind the player they can keep it; seconds since the Unix epoch.
+	gif_hint_until: f64,
+	scene: Scene,
+	mode: Mode,
+	/// Of the current game, or the last one once it ends.
+	recording: Option<replay::Replay>,
+	/// Contents of the last file dragged onto the window.
+	last_dropped: Option<Vec<u8>>,
+	/// Why the last game ended, for the game over screen.
+	top_out: Option<tetris::TopOut>,
+	/// Only the embedding page can pause; see the bridge module.
+	paused: bool,
+	/// See RESTART_HOLD_S.
+	restart_held_since: Option<f64>,
+	/// Until R comes back up, so holding it doesn't restart over and over.
+	restart_fired: bool,
+	/// Practice only; the game waits while it is open.
+	picker_open: bool,
+	practice_shapes: Vec<tetris::Piece>,
+	/// The last piece placed with extra presses, and until when to say so; seconds since the Unix epoch.
+	finesse_fault: Option<(finesse::Verdict, f64)>,
+	show_debug_overlay: bool,
+	/// Where this session's pieces locked, replays aside; F4 shows it over the board.
+	heatmap: analysis::Heatmap,
+	show_heatmap: bool,
+	/// Versus: the relay, the code typed in to join a room, and the room once hosted or joined.
+	netplay_url: String,
+	room_code_input: String,
+	/// Marathon: a seed to play rather than a fresh one, to share a run of pieces; blank for fresh.
+	seed_input: String,
+	lobby: Option<netplay::Lobby>,
+	/// Versus: the chat line being typed, while open; see chat_input().
+	chat_draft: Option<String>,
+	/// The name typed in for a new profile.
+	profile_input: String,
+	/// Per config::Config::training_log; cleared by start_game().
+	training_log: Option<env::TrainingLog>,
+	/// Of the replay last analyzed; see Scene::Analysis.
+	analysis: Option<analysis::Analysis>,
+	/// Off unless configured; see the sync module.
+	sync: Option<sync::SyncClient>,
+	/// Fixed at startup; see capture_setting().
+	capture: theme::Capture,
+	/// Off unless configured; see the overlay module. The last tick it was told about.
+	overlay: Option<overlay::OverlayServer>,
+	overlay_ticks: Option<u64>,
+	/// A sync pulled files for the profile, which load again once no game is under way.
+	profile_stale: bool,
+	/// Versus: the incoming meter's height in rows, easing down to the queue's as it's canceled or rises.
+	incoming_shown: f32,
+	tick_rate: TickRate,
+}
+
+impl App {
+	// HARDCODE
+	const SPRINT_ROWS: u32 = 40;
+	const RESULTS_PER_PAGE: usize = 8;
+	const TOAST_DURATION_S: f64 = 4.0;
+	/// Quick restart: R held this long starts the mode over; with Alt, which no binding uses, on the same seed, which
+	/// sets no records.
+	const RESTART_HOLD_S: f64 = 0.4;
+	/// As far up as the marathon menu goes.
+	const MAX_STARTING_LEVEL: u32 = 19;
+	/// Ticks per frame drawn while fast-forwarding; the last is as many as fit in FAST_FORWARD_BUDGET_S.
+	const FAST_FORWARD_TICKS: [u32; 4] = [8, 32, 128, u32::MAX];
+	/// Fast-forward stops ticking after this long in a frame, in seconds, so drawing and input keep up.
+	const FAST_FORWARD_BUDGET_S: f64 = 1.0 / 120.0;
+
+	async fn new() -> App {
+		// HARDCODE Do a proper config system later
+		let width_cells = 8;
+		let height_cells = 24;
+		let cell_sidelength_px = 32;
+		// derived config
+		let cell_sidelength_px_f32 = cell_sidelength_px as f32;
+		let layout = layout::Layout::new(width_cells, height_cells, cell_sidelength_px_f32, tetris::PREVIEW_PIECES, true);
+		// </config>
+		let config = config::Config::load();
+		log::init(config.log_level, config.log_file.as_deref());
+		let assets = assets::load(config.font.as_deref().as_slice()).await;
+		let typeface = Typeface {
+			font: config.font.as_deref().and_then(|path| assets.font(path)),
+			scale: cell_sidelength_px_f32 / Typeface::CELL_PX,
+		};
+		let speed = config.speed;
+		let timing = tetris::Timing {
+			ticks_per_drop_slow: speed.ticks_per_drop(),
+			ticks_per_drop_fast: 1,
+			entry_delay: 10,
+			line_clear_delay: 20,
+			fall_speed: config.gravity,
+		};
+		let mut leaderboard = config.leaderboard_url.as_deref().map(leaderboard::LeaderboardClient::new);
+		if let Some(client) = leaderboard.as_mut() {
+			client.refresh("marathon");
+		}
+		let frontend = MacroquadFrontend { width_cells, cell_sidelength_px: cell_sidelength_px_f32, layout: layout.clone(), particles: Vec::new(), effects_rng: rng::RandomNumberGenerator::new(miniquad::date::now().to_bits()), popups: Vec::new(), score_before_clear: 0, last_piece_xy: (0, 0), combo_pulse: None, back_to_back_pulse: None, last_combo: 0, last_back_to_back: 0, grid_style: config.grid, block_style: config.blocks, input: input::Input::new(config.bindings(), config.handling), input_log: config.input_log.then(input::InputLog::new), mouse_controls: config.mouse_controls, touch_overlay: false, typeface: typeface.clone(), block_texture: assets.texture(assets::BLOCK_TEXTURE), danger_since: None, sounds: assets::Sound::ALL.into_iter().filter_map(|sound| assets.sound(sound)).collect() };
+		let mut puzzles = load_puzzles().await;
+		puzzles.extend(editor::load_saved_puzzles());
+		let mut app = Self {
+			width_cells,
+			height_cells,
+			cell_sidelength_px,
+			cell_sidelength_px_f32,
+			height_px: height_cells * cell_sidelength_px,
+			window_width_px: layout.window.w as usize,
+			versus_layout: layout.with_opponent(),
+			layout,
+			speed,
+			tick_debt: 0.0,
+			speed_hint_until: 0.0,
+			fast_forward: None,
+			pending_actions: Vec::new(),
+			seeded: false,
+			animate_background: config.animated_background && !config.reduced_motion && !bridge::prefers_reduced_motion(),
+			leaderboard,
+			game_state: tetris::GameState::new(height_cells, width_cells, config.randomizer, timing),
+			frontend,
+			typeface,
+			post_process: postprocess::PostProcess::new(config.effects),
+			puzzles,
+			campaign: campaign::levels(),
+			campaign_progress: campaign::Progress::load(),
+			board_editor: editor::Editor::new(width_cells, height_cells),
+			daily_records: records::DailyRecords::load(),
+			sprint_record: records::SprintRecord::load(),
+			cheese_record: records::CheeseRecord::load(),
+			local_results: records::LocalResults::load(),
+			achievements: achievements::Achievements::load(),
+			script_run: script::ScriptRun::new(script::Script::load()),
+			toasts: Vec::new(),
+			highlights: capture::BoardRecorder::new(),
+			gif_hint_until: 0.0,
+			scene: if profile::list().is_empty() { Scene::Title } else { Scene::Profiles },
+			mode: Mode::Marathon { starting_level: 0, mirror_pieces: false },
+			recording: None,
+			last_dropped: None,
+			top_out: None,
+			paused: false,
+			restart_held_since: None,
+			restart_fired: false,
+			picker_open: false,
+			practice_shapes: tetris::Piece::all_shapes(),
+			finesse_fault: None,
+			show_debug_overlay: false,
+			heatmap: analysis::Heatmap::default(),
+			show_heatmap: false,
+			netplay_url: config.netplay_url.clone(),
+			room_code_input: String::new(),
+			seed_input: String::new(),
+			lobby: None,
+			chat_draft: None,
+			profile_input: String::new(),
+			analysis: None,
+			training_log: config.training_log.then(env::TrainingLog::new),
+			sync: config.sync_url.as_deref().map(|url| sync::SyncClient::new(url, &config.sync_token)),
+			profile_stale: false,
+			overlay: config.overlay_listen.as_deref().and_then(|address| overlay::OverlayServer::start(address)
+				.inspect_err(|e| log::error!("No overlay feed: {e}"))
+				.ok()),
+			overlay_ticks: None,
+			capture: capture_setting(),
+			incoming_shown: 0.0,
+			tick_rate: TickRate { since: miniquad::date::now(), ticks_then: 0, per_second: 0.0 },
+			config,
+		};
+		#[cfg(not(target_family="wasm"))]
+		// A replay to watch; anything but the flags.
+		let replay_path = std::env::args().skip(1).scan(false, |after_flag, arg| {
+			let is_value = std::mem::replace(after_flag, arg == "--capture");
+			Some((!is_value && !arg.starts_with("--")).then_some(arg))
+		}).flatten().next();
+		if let Some(path) = replay_path {
+			match std::fs::read(&path) {
+				Ok(bytes) => if let Some(r) = load_replay(&bytes) {
+					app.play(Mode::Replay(replay::Playback::new(r)));
+				},
+				Err(e) => log::error!("Could not read {path}: {e}"),
+			}
+		}
+		// Otherwise it waits for somebody to be picked.
+		if let (Scene::Title, Some(client)) = (&app.scene, app.sync.as_mut()) {
+			client.sync(miniquad::date::now());
+		}
+		app
+	}
+
+	/// Plays as `name`, or as a guest for None: their config, bindings and all, and their records and achievements.
+	/// The font, log, and leaderboard stay as they were loaded at startup.
+	fn use_profile(&mut self, name: Option<&str>) {
+		profile::select(name);
+		log::info!("Playing as {}", name.unwrap_or("a guest"));
+		self.reload_profile();
+		self.sync = self.config.sync_url.as_deref().map(|url| sync::SyncClient::new(url, &self.config.sync_token));
+		if let Some(client) = self.sync.as_mut() {
+			client.sync(miniquad::date::now());
+		}
+	}
+
+	/// The current profile's files, again; see use_profile().
+	fn reload_profile(&mut self) {
+		self.profile_stale = false;
+		let config = config::Config::load();
+		self.frontend.input = input::Input::new(config.bindings(), config.handling);
+		self.frontend.input_log = config.input_log.then(input::InputLog::new);
+		self.training_log = config.training_log.then(env::TrainingLog::new);
+		(self.frontend.grid_style, self.frontend.block_style, self.frontend.mouse_controls) = (config.grid, config.blocks, config.mouse_controls);
+		self.post_process = postprocess::PostProcess::new(config.effects);
+		self.animate_background = config.animated_background && !config.reduced_motion && !bridge::prefers_reduced_motion();
+		self.speed = config.speed;
+		self.netplay_url = config.netplay_url.clone();
+		self.daily_records = records::DailyRecords::load();
+		self.campaign_progress = campaign::Progress::load();
+		self.sprint_record = records::SprintRecord::load();
+		self.cheese_record = records::CheeseRecord::load();
+		self.local_results = records::LocalResults::load();
+		self.achievements = achievements::Achievements::load();
+		self.config = config;
+	}
+
+	/// See start_game().
+	fn start_game(&mut self, seed: Option<u64>) {
+		self.recording = start_game(&mut self.mode, seed, &mut self.game_state, &mut self.achievements, &mut self.script_run, &self.config, &mut self.frontend);
+		self.seeded = seed.is_some() && !matches!(self.mode, Mode::Daily { .. });
+		if self.seeded {
+			log::info!("Playing a chosen seed; this game will not count for records");
+		}
+		self.incoming_shown = 0.0;
+		self.fast_forward = None;
+		if let Some(training_log) = self.training_log.as_mut() {
+			training_log.clear();
+		}
+		self.relayout();
+	}
+
+	/// Fits the sidebar to the game's next queue and hold box, which depend on the mode and difficulty.
+	fn relayout(&mut self) {
+		let layout = layout::Layout::new(self.game_state.cell_matrix_width, self.height_cells, self.cell_sidelength_px_f32, self.game_state.preview_count(), self.game_state.queue_options.hold);
+		self.versus_layout = layout.with_opponent();
+		if self.config.stack_gauge {
+			self.versus_layout = self.versus_layout.with_opponent_gauge();
+		}
+		self.frontend.layout = layout.clone();
+		self.layout = layout;
+	}
+
+	/// Starts `mode` on a fresh seed and goes to it.
+	fn play(&mut self, mode: Mode) {
+		self.mode = mode;
+		self.start_game(None);
+		self.scene = Scene::Playing;
+	}
+
+	/// Input and game logic; see draw() for the rest.
+	fn update(&mut self) {
+		if let Some(client) = self.leaderboard.as_mut() {
+			client.poll();
+		}
+		if let Some(client) = self.sync.as_mut() {
+			self.profile_stale |= client.poll();
+		}
+		if self.profile_stale && (!matches!(self.scene, Scene::Playing) || self.is_game_over()) {
+			log::info!("Loading what sync pulled");
+			self.reload_profile();
+		}
+		if miniquad::window::dropped_file_count() > 0 {
+			let bytes = miniquad::window::dropped_file_bytes(0);
+			if bytes.is_some() && bytes != self.last_dropped {
+				if let Some(r) = bytes.as_deref().and_then(load_replay) {
+					self.play(Mode::Replay(replay::Playback::new(r)));
+				}
+				self.last_dropped = bytes;
+			}
+		}
+		if is_key_pressed(KeyCode::F3) {
+			self.show_debug_overlay = !self.show_debug_overlay;
+		}
+		if is_key_pressed(KeyCode::F4) {
+			self.show_heatmap = !self.show_heatmap;
+		}
+		// Natively there is no word when the window loses focus, but a frame this long means it was hidden, dragged,
+		// or put to sleep; the web build hears about hidden tabs directly, through bridge::Command::Pause.
+		if matches!(self.scene, Scene::Playing) && get_frame_time() > 0.5 { // HARDCODE
+			self.paused = true;
+		}
+		while let Some(command) = bridge::poll_command() {
+			match command {
+				bridge::Command::Pause => self.paused = true,
+				bridge::Command::Resume => self.paused = false,
+				// Both sides would have to agree.
+				bridge::Command::Restart if matches!(self.mode, Mode::Versus(_)) => {}
+				bridge::Command::Restart => if let Scene::Playing = self.scene {
+					self.start_game(None);
+					self.paused = false;
+				},
+				bridge::Command::SetSeed(seed) => {
+					if !matches!(self.mode, Mode::Sprint { .. }) {
+						self.mode = Mode::Marathon { starting_level: 0, mirror_pieces: false };
+					}
+					self.start_game(Some(seed));
+					self.scene = Scene::Playing;
+					self.paused = false;
+				}
+			}
+		}
+		match self.scene {
+			Scene::ConfirmQuit { from_game } => if is_key_pressed(KeyCode::Escape) {
+				self.scene = if from_game { Scene::Playing } else { Scene::Title };
+			},
+			Scene::Editor => self.update_editor(),
+			Scene::Lobby => self.update_lobby(),
+			Scene::Playing => self.update_game(),
+			// The menu's button only counts up.
+			Scene::MarathonSetup { starting_level, mirror_pieces } => if is_key_pressed(KeyCode::Left) {
+				self.scene = Scene::MarathonSetup { starting_level: starting_level.saturating_sub(1), mirror_pieces };
+			} else if is_key_pressed(KeyCode::Right) {
+				self.scene = Scene::MarathonSetup { starting_level: (starting_level + 1).min(Self::MAX_STARTING_LEVEL), mirror_pieces };
+			},
+			Scene::Title | Scene::Training | Scene::LevelSelect | Scene::Campaign | Scene::Results { .. } | Scene::Achievements | Scene::Profiles | Scene::Analysis => {}
+		}
+	}
+
+	fn update_editor(&mut self) {
+		if let Some(xy) = self.board_editor.cell_at(self.layout.board, mouse_position()) {
+			if is_mouse_button_down(MouseButton::Left) {
+				self.board_editor.paint(xy, Some(self.board_editor.brush));
+			} else if is_mouse_button_down(MouseButton::Right) {
+				self.board_editor.paint(xy, None);
+			}
+		}
+		for (key, brush) in [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4].into_iter().zip(editor::Editor::BRUSHES) {
+			if is_key_pressed(key) {
+				self.board_editor.brush = brush;
+			}
+		}
+		if is_key_pressed(KeyCode::C) {
+			self.board_editor.clear();
+		}
+		if is_key_pressed(KeyCode::S) {
+			let now = miniquad::date::now() as u64;
+			let p = self.board_editor.to_puzzle(&format!("Custom {}", records::date_string(now)), puzzle::Goal::ClearBoard);
+			match editor::save_puzzle(&p, now) {
+				Ok(name) => {
+					log::info!("Saved {name}");
+					self.puzzles.push(p);
+				}
+				Err(e) => log::error!("Could not save puzzle: {e}"),
+			}
+		}
+		if is_key_pressed(KeyCode::Enter) {
+			self.play(Mode::Puzzle(puzzle::PuzzleRun::new(self.board_editor.to_puzzle("Custom", puzzle::Goal::ClearBoard))));
+		} else if is_key_pressed(KeyCode::Escape) {
+			self.scene = Scene::Title;
+		}
+	}
+
+	fn update_lobby(&mut self) {
+		let Some(room) = self.lobby.as_mut()
+			else {
+				self.chat_draft = None;
+				return;
+			};
+		if let Some(text) = chat_input(&mut self.chat_draft) {
+			room.say(&text);
+		}
+		if let Some(versus) = room.poll(miniquad::date::now()) {
+			log::info!("Playing {} in room {} with seed {}", versus.opponent_name, room.code, versus.seed);
+			self.lobby = None;
+			self.play(Mode::Versus(Box::new(versus)));
+			self.paused = false;
+		}
+	}
+
+	fn update_game(&mut self) {
+		if let Mode::Versus(versus) = &mut self.mode {
+			versus.poll();
+			versus.settle(&self.game_state);
+			if versus.take_next() {
+				log::info!("Playing the next game of the set against {} with seed {}", versus.opponent_name, versus.seed);
+				self.start_game(None);
+				self.paused = false;
+				return;
+			}
+			if let Some(text) = chat_input(&mut self.chat_draft) {
+				versus.say(&text);
+			}
+			if self.chat_draft.is_none() {
+				for (key, emote) in [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4].into_iter().zip(netplay::EMOTES) {
+					if is_key_pressed(key) {
+						versus.say(emote);
+					}
+				}
+			}
+		} else {
+			self.chat_draft = None;
+		}
+		if let Mode::Spectate(spectator) = &mut self.mode {
+			spectator.poll(miniquad::date::now());
+			if is_key_pressed(KeyCode::Escape) {
+				self.mode = Mode::Marathon { starting_level: 0, mirror_pieces: false };
+				self.scene = Scene::Lobby;
+			}
+			return;
+		}
+		self.observe_events();
+		if let Mode::Sprint { ghost, finished_at_tick } = &mut self.mode {
+			if finished_at_tick.is_none() && self.game_state.rows_cleared >= Self::SPRINT_ROWS {
+				*finished_at_tick = Some(self.game_state.ticks);
+				if let Some(r) = self.recording.as_mut() {
+					r.finish(&self.game_state);
+					if !self.seeded && self.sprint_record.submit(self.game_state.ticks, &r.to_text()) {
+						*ghost = Some(Box::new(replay::Ghost::new(r.clone())));
+					}
+				}
+			}
+		}
+		let now = miniquad::date::now();
+		self.toasts.extend(self.achievements.drain_new_unlocks().map(|a| (a, now + Self::TOAST_DURATION_S)));
+		self.toasts.retain(|(_a, deadline)| *deadline > now);
+		if is_key_pressed(KeyCode::G) && !self.highlights.is_empty() && self.chat_draft.is_none() {
+			let file_name = capture::timestamped_file_name("highlight", "gif", now as u64);
+			match storage::export(&file_name, &self.highlights.to_gif(self.cell_sidelength_px / 2)) {
+				Ok(()) => log::info!("Saved {file_name}"),
+				Err(e) => log::error!("Could not save GIF: {e}"),
+			}
+		}
+		if let Mode::Practice(practice) = &mut self.mode {
+			if is_key_pressed(KeyCode::Backspace) && practice.rewind(&mut self.game_state) {
+				log::debug!("Rewound to tick {}", self.game_state.ticks);
+				self.pending_actions.clear();
+			}
+			// Z too, unless the controls already use it.
+			let undo_pressed = is_key_pressed(KeyCode::U) || (is_key_pressed(KeyCode::Z) && !self.frontend.input.is_bound(input::Control::Key(input::Key::Z)));
+			if undo_pressed && practice.undo(&mut self.game_state) {
+				log::debug!("Took back a piece; now at tick {}", self.game_state.ticks);
+				self.pending_actions.clear();
+			}
+			if is_key_pressed(KeyCode::F7) {
+				let text = miniquad::window::clipboard_get().unwrap_or_default();
+				match notation::restore(&text, &mut self.game_state) {
+					Ok(()) => {
+						log::info!("Pasted position {}", text.trim());
+						practice.forget_history();
+						self.pending_actions.clear();
+					}
+					Err(e) => log::error!("Could not paste position: {e}"),
+				}
+			}
+		}
+		if let Mode::Replay(playback) = &self.mode {
+			if is_key_pressed(KeyCode::A) {
+				self.analysis = Some(analysis::analyze(&playback.replay));
+				self.scene = Scene::Analysis;
+				return;
+			}
+		}
+		if self.mode.can_fast_forward() && is_key_pressed(KeyCode::F) {
+			self.fast_forward = match self.fast_forward {
+				None => Some(0),
+				Some(i) => Some(i + 1).filter(|&i| i < Self::FAST_FORWARD_TICKS.len()),
+			};
+		}
+		if is_key_pressed(KeyCode::F6) {
+			let text = notation::encode(&self.game_state);
+			log::info!("Copied position {text}");
+			miniquad::window::clipboard_set(&text);
+		}
+		// Versus can't start over on one side only.
+		if !is_key_down(KeyCode::R) || matches!(self.mode, Mode::Versus(_)) {
+			self.restart_held_since = None;
+			self.restart_fired = false;
+		} else if !self.restart_fired {
+			let since = *self.restart_held_since.get_or_insert(get_time());
+			if get_time() - since >= Self::RESTART_HOLD_S {
+				let same_seed = is_key_down(KeyCode::LeftAlt) || is_key_down(KeyCode::RightAlt);
+				self.start_game(same_seed.then_some(self.game_state.seed));
+				self.restart_fired = true;
+				self.paused = false;
+				self.pending_actions.clear();
+			}
+		}
+		if self.is_game_over() {
+			return;
+		}
+		if is_key_pressed(KeyCode::P) && self.chat_draft.is_none() {
+			self.paused = !self.paused;
+		}
+		if self.paused {
+			return;
+		}
+		if matches!(self.mode, Mode::Practice(_)) {
+			if is_key_pressed(KeyCode::Tab) {
+				self.picker_open = !self.picker_open;
+			}
+			if self.picker_open {
+				return;
+			}
+		}
+		// Input; kept until the next tick, which may be a frame or two away.
+		if let Mode::Coop(coop) | Mode::Twin(coop) = &mut self.mode {
+			coop.poll(&self.frontend.held(&self.game_state));
+		} else {
+			let polled = self.frontend.poll_input(&self.game_state);
+			// Typing into the chat, the keys aren't for the game.
+			if !matches!(self.mode, Mode::Replay(_) | Mode::Bot { .. }) && self.chat_draft.is_none() {
+				self.pending_actions.extend(polled);
+			}
+		}
+		let new_speed = if is_key_pressed(KeyCode::LeftBracket) {
+			self.speed.slower()
+		} else if is_key_pressed(KeyCode::RightBracket) {
+			self.speed.faster()
+		} else {
+			self.speed
+		};
+		// Replays keep the timing they were recorded with; versus, the one both sides agreed on.
+		if new_speed != self.speed && !matches!(self.mode, Mode::Replay(_) | Mode::Versus(_) | Mode::Bot { .. }) {
+			self.speed = new_speed;
+			self.speed.apply(&mut self.game_state.timing);
+			self.speed_hint_until = miniquad::date::now() + 2.0;
+			// The replay format has the timing once, up front, so this game could not be played back.
+			if self.recording.take().is_some() {
+				log::info!("Speed changed to {} mid-game; this game is practice and will not be recorded", self.speed.name());
+			}
+		}
+		self.tick_game();
+		self.tick_rate.sample(miniquad::date::now(), self.game_state.ticks);
+		if let Some(server) = self.overlay.as_ref() {
+			if self.overlay_ticks != Some(self.game_state.ticks) {
+				let mode = self.mode.leaderboard_name(self.game_state.ruleset_name());
+				server.publish_state(overlay::state_json(&self.game_state, mode.as_deref(), &self.config.player_name));
+				self.overlay_ticks = Some(self.game_state.ticks);
+			}
+		}
+	}
+
+	/// Whatever the game sent since the last frame: sounds, achievements, records, and the mode's own bookkeeping.
+	fn observe_events(&mut self) {
+		let events: Vec<tetris::Event> = self.game_state.drain_events().collect();
+		for event in events {
+			log::debug!("{event:?} at tick {}", self.game_state.ticks);
+			if let Some(server) = self.overlay.as_ref() {
+				server.publish_event(overlay::event_json(&event, self.game_state.ticks));
+			}
+			self.frontend.play_event(&event);
+			if !matches!(self.mode, Mode::Bot { .. }) {
+				self.achievements.observe(&event, &self.game_state);
+			}
+			// Rules that change the game would only change one copy of it.
+			if !matches!(self.mode, Mode::Versus(_)) {
+				self.script_run.observe(&event, &mut self.game_state);
+			}
+			if let Mode::Puzzle(run) | Mode::Campaign { run, .. } = &mut self.mode {
+				run.observe(&event, &self.game_state);
+			}
+			if let tetris::Event::PieceLocked | tetris::Event::RowsCleared(_) = event {
+				self.record_campaign_win();
+			}
+			if let Mode::Versus(versus) = &mut self.mode {
+				versus.observe(&event, &self.game_state);
+			}
+			if matches!(event, tetris::Event::PieceLocked) && !matches!(self.mode, Mode::Replay(_) | Mode::Bot { .. }) {
+				self.heatmap.observe(&self.game_state);
+			}
+			if let tetris::Event::RowsCleared(n) = event {
+				if n >= 3 { // HARDCODE
+					self.gif_hint_until = miniquad::date::now() + 3.0;
+				}
+				bridge::emit("rows_cleared", n as u64);
+				if let Some(post_process) = self.post_process.as_mut() {
+					post_process.flash();
+				}
+			}
+			if let tetris::Event::LevelUp(level) = event {
+				let theme = theme::for_level(level);
+				log::debug!("Level {level}; music track {}", theme.music);
+				bridge::emit("level", level as u64);
+				bridge::emit("music", theme.music as u64);
+				bridge::emit("score", self.game_state.score as u64);
+			}
+			if let tetris::Event::ToppedOut(cause) = event {
+				self.game_over_recorded(cause);
+			}
+		}
+	}
+
+	/// Stars for a campaign level just won; does nothing otherwise, and nothing new for a win already recorded.
+	fn record_campaign_win(&mut self) {
+		let Mode::Campaign { level, run } = &self.mode
+			else { return; };
+		let Some(level) = self.campaign.get(*level).filter(|_| run.outcome == Some(puzzle::Outcome::Won))
+			else { return; };
+		let stars = level.stars(run.pieces_used, self.game_state.ticks);
+		if self.campaign_progress.submit(&level.puzzle.name, stars) {
+			log::info!("{} cleared with {stars} stars", level.puzzle.name);
+		}
+	}
+
+	/// Saves what there is to save of a game that just topped out.
+	fn game_over_recorded(&mut self, cause: tetris::TopOut) {
+		let game_state = &self.game_state;
+		self.top_out = Some(cause);
+		bridge::emit("game_over", game_state.score as u64);
+		if let Mode::Daily { day } = self.mode {
+			self.daily_records.submit(day, game_state.score);
+		}
+		let finished_at = miniquad::date::now() as u64;
+		let mut replay_hash = 0;
+		let mut replay_name = None;
+		if let Some(r) = self.recording.as_mut() {
+			r.finish(game_state);
+			replay_hash = r.hash();
+			let name = format!("replays/{finished_at}-{}.replay", r.seed);
+			match storage::save(&name, &r.to_text()) {
+				Ok(()) => replay_name = Some(name),
+				Err(e) => log::error!("Could not save replay: {e}"),
+			}
+		}
+		if let Some(input_log) = self.frontend.input_log.as_ref().filter(|l| !l.is_empty()) {
+			let name = format!("inputs/{finished_at}-{}.tsv", game_state.seed);
+			match storage::save(&name, &input_log.to_text()) {
+				Ok(()) => log::info!("Saved input log {name}"),
+				Err(e) => log::error!("Could not save input log: {e}"),
+			}
+		}
+		if let Some(training_log) = self.training_log.as_ref().filter(|l| !l.is_empty()) {
+			let name = format!("training/{finished_at}-{}.ndjson", game_state.seed);
+			match storage::save(&name, &training_log.to_ndjson()) {
+				Ok(()) => log::info!("Saved training log {name}"),
+				Err(e) => log::error!("Could not save training log: {e}"),
+			}
+		}
+		let ranked_name = if self.seeded { None } else { self.mode.leaderboard_name(game_state.ruleset_name()) };
+		if let Some(mode_name) = ranked_name.clone() {
+			self.local_results.add(records::GameResult {
+				mode: mode_name,
+				width: game_state.cell_matrix_width,
+				height: game_state.cell_matrix.len() - game_state.hidden_rows,
+				score: game_state.score,
+				seed: game_state.seed,
+				finished_at,
+				replay: replay_name,
+				ticks: game_state.ticks,
+			});
+		}
+		if let (Some(client), Some(mode_name)) = (self.leaderboard.as_mut(), ranked_name) {
+			client.submit(leaderboard::Submission {
+				name: self.config.player_name.clone(),
+				mode: mode_name.clone(),
+				seed: game_state.seed,
+				score: game_state.score,
+				replay_hash,
+			});
+			client.refresh(&mode_name);
+		}
+		if let Some(client) = self.sync.as_mut() {
+			client.sync(finished_at as f64);
+		}
+	}
+
+	/// As many ticks as the time since the last frame calls for, each with the actions that came in before it;
+	/// fast-forwarding, as many as FAST_FORWARD_TICKS says, or the game ends.
+	fn tick_game(&mut self) {
+		let fast_forward = self.fast_forward.filter(|_| self.mode.can_fast_forward()).map(|i| Self::FAST_FORWARD_TICKS[i]);
+		let ticks_due = match fast_forward {
+			Some(ticks) => {
+				self.tick_debt = 0.0;
+				ticks
+			}
+			None => {
+				self.tick_debt += get_frame_time() as f64 * self.speed.ticks_per_second() as f64;
+				let ticks_due = self.tick_debt as u32;
+				self.tick_debt -= ticks_due as f64;
+				// HARDCODE After a hitch, better to lose time than to lurch ahead.
+				ticks_due.min(4)
+			}
+		};
+		let started = miniquad::date::now();
+		for i in 0 .. ticks_due {
+			if let Mode::Cheese(race) = &mut self.mode {
+				if !race.is_finished() {
+					race.top_up(&mut self.game_state);
+					if let Some(ticks) = race.finished_at_tick.filter(|_| !self.seeded) {
+						self.cheese_record.submit(race.target, ticks);
+					}
+				}
+				if race.is_finished() {
+					break;
+				}
+			}
+			if fast_forward.is_some() && i > 0 && (self.is_game_over() || miniquad::date::now() - started > Self::FAST_FORWARD_BUDGET_S) {
+				break;
+			}
+			match &mut self.mode {
+				Mode::Replay(playback) => self.pending_actions = playback.actions_due(self.game_state.ticks),
+				Mode::Bot { bot, .. } => self.pending_actions = bot.think(&self.game_state),
+				_ => {}
+			}
+			if let Some(training_log) = self.training_log.as_mut() {
+				training_log.before_tick(&self.game_state, &self.pending_actions);
+			}
+			let mut applied = Vec::new();
+			for action in self.pending_actions.drain(..) {
+				applied.push(action);
+				self.game_state.apply(action);
+				if let Some(r) = self.recording.as_mut() {
+					r.record(self.game_state.ticks, action);
+				}
+				if let Mode::Finesse(trainer) = &mut self.mode {
+					trainer.record(action);
+					trainer.observe(&self.game_state);
+				}
+			}
+			if let Mode::Coop(coop) | Mode::Twin(coop) = &mut self.mode {
+				coop.apply(&mut self.game_state);
+			}
+			// In the same order as netplay::play_tick() on the other side.
+			if let Mode::Versus(versus) = &mut self.mode {
+				let rising = versus.take_rising();
+				netplay::add_garbage(&mut self.game_state, &rising);
+				versus.send_tick(self.game_state.ticks, applied, rising);
+			}
+			self.game_state.tick();
+			if let Some(training_log) = self.training_log.as_mut() {
+				training_log.after_tick(&self.game_state);
+			}
+			if let Mode::Versus(versus) = &mut self.mode {
+				versus.after_tick(&self.game_state);
+			}
+			if !matches!(self.mode, Mode::Bot { .. }) {
+				self.achievements.tick();
+			}
+			if let Mode::Sprint { ghost: Some(g), .. } = &mut self.mode {
+				g.tick();
+			}
+			self.highlights.observe(&self.game_state);
+			if let Mode::Practice(practice) = &mut self.mode {
+				practice.observe(&self.game_state);
+			}
+			if let Mode::Finesse(trainer) = &mut self.mode {
+				if let Some(verdict) = trainer.observe(&self.game_state).filter(|v| v.extra_presses() > 0) {
+					self.finesse_fault = Some((verdict, miniquad::date::now() + 2.0));
+				}
+			}
+		}
+	}
+
+	/// Topped out, or the mode says it's over.
+	fn is_game_over(&self) -> bool {
+		let puzzle_over = matches!(&self.mode, Mode::Puzzle(run) | Mode::Campaign { run, .. } if run.outcome.is_some());
+		let sprint_over = matches!(self.mode, Mode::Sprint { finished_at_tick: Some(_), .. });
+		let cheese_over = matches!(&self.mode, Mode::Cheese(race) if race.is_finished());
+		let versus_over = matches!(&self.mode, Mode::Versus(versus) if versus.outcome(&self.game_state).is_some());
+		!self.game_state.is_alive || puzzle_over || sprint_over || cheese_over || versus_over
+	}
+
+	/// Everything on screen, menus included; see the struct docs.
+	fn draw(&mut self) {
+		let window_layout = if matches!((&self.scene, &self.mode), (Scene::Playing, Mode::Versus(_) | Mode::Spectate(_))) { &self.versus_layout } else { &self.layout };
+		set_window_size(window_layout.window.w as u32, self.height_px as u32);
+		if let Some(post_process) = self.post_process.as_mut() {
+			post_process.begin();
+		}
+		let theme = theme::for_level(self.game_state.level());
+		let [h, s, l] = theme.background;
+		let mut background = color::hsl_to_rgb(h, s, l);
+		if matches!(self.scene, Scene::Playing) && self.frontend.danger_since.is_some() {
+			// HARDCODE Halfway to a dark red.
+			background = Color::new((background.r + 0.4) / 2.0, background.g / 2.0, background.b / 2.0, 1.0);
+		}
+		let [r, g, b] = theme::CHROMA_KEY;
+		background = match self.capture {
+			theme::Capture::Off => background,
+			theme::Capture::Transparent => Color::new(0.0, 0.0, 0.0, 0.0),
+			theme::Capture::ChromaKey => Color::new(r, g, b, 1.0),
+		};
+		clear_background(background);
+		if self.animate_background && self.capture == theme::Capture::Off {
+			render_starfield(get_time() as f32, theme.grid, self.window_width_px as f32, self.height_px as f32);
+		}
+		match self.scene {
+			Scene::Title => self.draw_title(),
+			Scene::MarathonSetup { starting_level, mirror_pieces } => self.draw_marathon_setup(starting_level, mirror_pieces),
+			Scene::LevelSelect => self.draw_level_select(),
+			Scene::Campaign => self.draw_campaign(),
+			Scene::Training => self.draw_training(),
+			Scene::Results { mode_filter, size_filter, page } => self.draw_results(mode_filter, size_filter, page),
+			Scene::ConfirmQuit { from_game } => self.draw_confirm_quit(from_game),
+			Scene::Achievements => self.draw_achievements(),
+			Scene::Editor => self.draw_editor(),
+			Scene::Lobby => self.draw_lobby(),
+			Scene::Profiles => self.draw_profiles(),
+			Scene::Analysis => self.draw_analysis(),
+			Scene::Playing => self.draw_game(),
+		}
+		if let Some(post_process) = self.post_process.as_mut() {
+			post_process.end();
+		}
+	}
+
+	fn draw_title(&mut self) {
+		let player_label = format!("Player: {}", profile::current().as_deref().unwrap_or("guest"));
+		match menu(&self.typeface, "TETRIS", &["Marathon", "Sprint", "Daily", "Training", "Versus", "Co-op", "Campaign", "Puzzles", "Editor", "Results", "Achievements", &player_label, "Quit"], self.window_width_px) {
+			Some(0) => self.scene = Scene::MarathonSetup { starting_level: 0, mirror_pieces: false },
+			Some(1) => {
+				let ghost = self.sprint_record.best_replay()
+					.and_then(|text| load_replay(text.as_bytes()))
+					.map(|r| Box::new(replay::Ghost::new(r)));
+				self.play(Mode::Sprint { ghost, finished_at_tick: None });
+			}
+			Some(2) => self.play(Mode::Daily { day: today() }),
+			Some(3) => self.scene = Scene::Training,
+			Some(4) => self.scene = Scene::Lobby,
+			Some(5) => self.play(Mode::Coop(coop::Coop::new(self.config.handling))),
+			Some(6) => self.scene = Scene::Campaign,
+			Some(7) => self.scene = Scene::LevelSelect,
+			Some(8) => self.scene = Scene::Editor,
+			Some(9) => self.scene = Scene::Results { mode_filter: 0, size_filter: 0, page: 0 },
+			Some(10) => self.scene = Scene::Achievements,
+			Some(11) => self.scene = Scene::Profiles,
+			Some(_) => self.scene = Scene::ConfirmQuit { from_game: false },
+			None => {}
+		}
+		if let Some(client) = self.leaderboard.as_ref() {
+			render_leaderboard(client, self.height_px as f32 / 2.0, self.window_width_px);
+		}
+		let sync_status = match self.sync.as_ref().map(|client| client.status) {
+			Some(sync::Status::Syncing) => Some("Syncing..."),
+			Some(sync::Status::Offline) => Some("Sync offline"),
+			Some(sync::Status::Idle) | None => None,
+		};
+		if let Some(text) = sync_status {
+			draw_text(text, 4.0, self.height_px as f32 - 8.0, 16.0, GRAY);
+		}
+	}
+
+	fn draw_profiles(&mut self) {
+		let profiles = profile::list();
+		let mut labels: Vec<&str> = profiles.iter().map(String::as_str).collect();
+		labels.extend(["Guest", "New profile"]);
+		let font_size = 20.0;
+		let top = 48.0 * (labels.len() + 1) as f32; // HARDCODE Below the menu
+		match menu(&self.typeface, "PLAYERS", &labels, self.window_width_px) {
+			Some(i) if i < profiles.len() => {
+				self.use_profile(Some(&profiles[i]));
+				self.scene = Scene::Title;
+			}
+			Some(i) if i == profiles.len() => {
+				self.use_profile(None);
+				self.scene = Scene::Title;
+			}
+			Some(_) => match profile::create(&self.profile_input) {
+				Ok(name) => {
+					self.use_profile(Some(&name));
+					self.profile_input.clear();
+					self.scene = Scene::Title;
+				}
+				Err(e) => log::warn!("{e}"),
+			},
+			None => {}
+		}
+		ui::root_ui().window(hash!(), Vec2::new(4.0, top), Vec2::new(self.window_width_px as f32 - 8.0, font_size * 2.0), |ui| {
+			ui.input_text(hash!(), "Name", &mut self.profile_input);
+		});
+		draw_text("Each player's settings, records, and achievements are kept apart", 8.0, top + font_size * 3.0, font_size * 0.8, GRAY);
+		let hint = format!("Names are up to {} letters, digits, spaces, - or _", profile::MAX_NAME_CHARS);
+		draw_text(&hint, 8.0, top + font_size * 4.0, font_size * 0.8, GRAY);
+	}
+
+	fn draw_marathon_setup(&mut self, starting_level: u32, mirror_pieces: bool) {
+		let font_size = 20.0;
+		let level_label = format!("Starting level: {starting_level}");
+		let seed = match self.seed_input.trim() {
+			"" => Ok(None),
+			text => text.parse::<u64>().map(Some),
+		};
+		let mirror_label = format!("Mirrored pieces: {}", if mirror_pieces { "on" } else { "off" });
+		let labels = ["Start", &level_label, &mirror_label, "Twin pieces", "Watch the bot", "Back"];
+		let top = 48.0 * (labels.len() + 1) as f32; // HARDCODE Below the menu
+		match menu(&self.typeface, "MARATHON", &labels, self.window_width_px) {
+			Some(0) => match seed {
+				Ok(None) => self.play(Mode::Marathon { starting_level, mirror_pieces }),
+				Ok(Some(seed)) => {
+					self.mode = Mode::Marathon { starting_level, mirror_pieces };
+					self.start_game(Some(seed));
+					self.scene = Scene::Playing;
+				}
+				Err(_) => {}
+			},
+			Some(1) => self.scene = Scene::MarathonSetup { starting_level: (starting_level + 1) % (Self::MAX_STARTING_LEVEL + 1), mirror_pieces },
+			Some(2) => self.scene = Scene::MarathonSetup { starting_level, mirror_pieces: !mirror_pieces },
+			Some(3) => if let Ok(seed) = seed {
+				self.mode = Mode::Twin(coop::Coop::new(self.config.handling));
+				self.start_game(seed);
+				self.scene = Scene::Playing;
+			},
+			Some(4) => if let Ok(seed) = seed {
+				// HARDCODE The strongest bot that still keeps up at normal speed.
+				let name = "beam";
+				let bot = bot::by_name(name, 0, bot::Strength::default()).expect("Should be one of bot::NAMES");
+				self.mode = Mode::Bot { name, bot };
+				self.start_game(seed);
+				self.scene = Scene::Playing;
+			},
+			Some(_) => self.scene = Scene::Title,
+			None => {}
+		}
+		ui::root_ui().window(hash!(), Vec2::new(4.0, top), Vec2::new(self.window_width_px as f32 - 8.0, font_size * 2.0), |ui| {
+			ui.input_text(hash!(), "Seed", &mut self.seed_input);
+		});
+		let (hint, color) = match seed {
+			Ok(None) => ("Blank for fresh pieces; type a seed from somebody's game to play the same ones", GRAY),
+			Ok(Some(_)) => ("Same seed, same pieces; practice only, it sets no records", GRAY),
+			Err(_) => ("A seed is a whole number, as shown beside the next queue", RED),
+		};
+		draw_text(hint, 8.0, top + font_size * 3.0, font_size * 0.8, color);
+	}
+
+	fn draw_training(&mut self) {
+		let cheese_label = format!("Cheese race: {} rows", self.config.cheese_rows);
+		match menu(&self.typeface, "TRAINING", &["Practice", "Finesse", &cheese_label, "Back"], self.window_width_px) {
+			Some(0) => self.play(Mode::Practice(practice::Practice::new())),
+			Some(1) => self.play(Mode::Finesse(finesse::Trainer::new())),
+			Some(2) => self.play(Mode::Cheese(cheese::CheeseRace::new(self.config.cheese_rows))),
+			Some(_) => self.scene = Scene::Title,
+			None => {}
+		}
+		let best = match self.cheese_record.best_ticks(self.config.cheese_rows) {
+			Some(ticks) => format!("Cheese race best: {}; cheese_rows in the config sets the count", records::format_ticks(ticks)),
+			None => "Cheese race: dig out the garbage against the clock; cheese_rows in the config sets the count".to_string(),
+		};
+		draw_text(&best, 8.0, self.height_px as f32 - 8.0, 16.0, GRAY);
+	}
+
+	fn draw_campaign(&mut self) {
+		if menu(&self.typeface, "CAMPAIGN", &["Back"], self.window_width_px).is_some() {
+			self.scene = Scene::Title;
+			return;
+		}
+		let width = self.window_width_px as f32;
+		let top = 150.0; // HARDCODE Below the menu
+		let total = format!("{}/{} stars", self.campaign_progress.total_stars(), self.campaign.len() as u32 * campaign::MAX_STARS);
+		draw_text(&total, 8.0, top, 20.0, GOLD);
+		// HARDCODE Spacing between levels; rows run back and forth, like a path.
+		let spacing = 56.0;
+		let radius = 16.0;
+		let columns = (((width - 16.0) / spacing) as usize).max(1);
+		let center = |i: usize| {
+			let (row, column) = (i / columns, i % columns);
+			let column = if row % 2 == 0 { column } else { columns - 1 - column };
+			Vec2::new(8.0 + spacing * (column as f32 + 0.5), top + 24.0 + spacing * (row as f32 + 0.5))
+		};
+		for i in 1 .. self.campaign.len() {
+			let (a, b) = (center(i - 1), center(i));
+			draw_line(a.x, a.y, b.x, b.y, 3.0, DARKGRAY);
+		}
+		let (mouse_x, mouse_y) = mouse_position();
+		let mut hovered = None;
+		for (i, level) in self.campaign.iter().enumerate() {
+			let c = center(i);
+			let stars = self.campaign_progress.stars(&level.puzzle.name);
+			let is_unlocked = self.campaign_progress.is_unlocked(&self.campaign, i);
+			let color = match (is_unlocked, stars) {
+				(false, _) => DARKGRAY,
+				(true, 0) => SKYBLUE,
+				(true, _) => GOLD,
+			};
+			draw_circle(c.x, c.y, radius, color);
+			let number = (i + 1).to_string();
+			let dims = measure_text(&number, None, 18, 1.0);
+			draw_text(&number, c.x - dims.width / 2.0, c.y + dims.offset_y / 2.0, 18.0, BLACK);
+			let earned = "*".repeat(stars as usize);
+			let dims = measure_text(&earned, None, 18, 1.0);
+			draw_text(&earned, c.x - dims.width / 2.0, c.y + radius + 12.0, 18.0, GOLD);
+			if Vec2::new(mouse_x, mouse_y).distance(c) <= radius {
+				hovered = Some((i, is_unlocked));
+			}
+		}
+		let Some((i, is_unlocked)) = hovered
+			else { return; };
+		let level = &self.campaign[i];
+		let lines = [
+			format!("{}. {}", i + 1, level.puzzle.name),
+			if is_unlocked { level.puzzle.description() } else { "Clear the level before to open this one".to_string() },
+			level.par_description(),
+		];
+		for (n, line) in lines.iter().enumerate() {
+			draw_text(line, 8.0, self.height_px as f32 - 8.0 - (lines.len() - 1 - n) as f32 * 20.0, 18.0, LIGHTGRAY);
+		}
+		if is_unlocked && is_mouse_button_pressed(MouseButton::Left) {
+			self.play(Mode::Campaign { level: i, run: puzzle::PuzzleRun::new(level.puzzle.clone()) });
+		}
+	}
+
+	fn draw_level_select(&mut self) {
+		let mut labels: Vec<&str> = self.puzzles.iter().map(|p| p.name.as_str()).collect();
+		labels.push("Back");
+		match menu(&self.typeface, "PUZZLES", &labels, self.window_width_px) {
+			Some(i) if i < self.puzzles.len() => self.play(Mode::Puzzle(puzzle::PuzzleRun::new(self.puzzles[i].clone()))),
+			Some(_) => self.scene = Scene::Title,
+			None => {}
+		}
+	}
+
+	fn draw_results(&mut self, mode_filter: usize, size_filter: usize, page: usize) {
+		let mode_kinds = self.local_results.mode_kinds();
+		let board_sizes = self.local_results.board_sizes();
+		let mode_kind = mode_filter.checked_sub(1).and_then(|i| mode_kinds.get(i)).map(String::as_str);
+		let board_size = size_filter.checked_sub(1).and_then(|i| board_sizes.get(i)).map(String::as_str);
+		let shown: Vec<&records::GameResult> = self.local_results.filtered(mode_kind, board_size).collect();
+		let page_count = shown.len().div_ceil(Self::RESULTS_PER_PAGE).max(1);
+		let page = page.min(page_count - 1);
+		let page_results: Vec<records::GameResult> = shown.iter().skip(page * Self::RESULTS_PER_PAGE).take(Self::RESULTS_PER_PAGE).map(|&r| r.clone()).collect();
+		let mut labels = vec![
+			format!("Mode: {}", mode_kind.unwrap_or("all")),
+			format!("Size: {}", board_size.unwrap_or("all")),
+		];
+		labels.extend(page_results.iter().map(|r| format!("{} {} {} {} #{}", r.score, r.mode_kind(), records::format_ticks(r.ticks), records::date_string(r.finished_at), r.seed)));
+		labels.push(format!("Page {}/{page_count}", page + 1));
+		labels.push("Back".to_string());
+		let label_refs: Vec<&str> = labels.iter().map(String::as_str).collect();
+		let clicked = menu(&self.typeface, "RESULTS", &label_refs, self.window_width_px);
+		let first_entry = 2;
+		let page_button = first_entry + page_results.len();
+		match clicked {
+			Some(0) => self.scene = Scene::Results { mode_filter: (mode_filter + 1) % (mode_kinds.len() + 1), size_filter, page: 0 },
+			Some(1) => self.scene = Scene::Results { mode_filter, size_filter: (size_filter + 1) % (board_sizes.len() + 1), page: 0 },
+			Some(i) if i < page_button => {
+				let result = &page_results[i - first_entry];
+				let saved_replay = result.replay.as_deref()
+					.and_then(storage::load)
+					.and_then(|text| load_replay(text.as_bytes()));
+				if let Some(r) = saved_replay {
+					self.play(Mode::Replay(replay::Playback::new(r)));
+				} else if let Some(m) = Mode::from_leaderboard_name(&result.mode) {
+					// No recording, but the same seed still gives the same pieces.
+					self.mode = m;
+					self.start_game(Some(result.seed));
+					self.scene = Scene::Playing;
+				}
+			}
+			Some(i) if i == page_button => self.scene = Scene::Results { mode_filter, size_filter, page: (page + 1) % page_count },
+			Some(_) => self.scene = Scene::Title,
+			None => {}
+		}
+	}
+
+	fn draw_confirm_quit(&mut self, from_game: bool) {
+		match menu(&self.typeface, "QUIT?", &[QUIT_LABEL, "Cancel"], self.window_width_px) {
+			Some(0) => {
+				quit();
+				self.scene = Scene::Title;
+			}
+			Some(_) => self.scene = if from_game { Scene::Playing } else { Scene::Title },
+			None => {}
+		}
+	}
+
+	fn draw_achievements(&mut self) {
+		if menu(&self.typeface, "ACHIEVEMENTS", &["Back"], self.window_width_px).is_some() {
+			self.scene = Scene::Title;
+		}
+		let font_size = 20.0;
+		for (i, a) in achievements::Achievement::ALL.iter().enumerate() {
+			let y = 200.0 + i as f32 * font_size * 3.0; // HARDCODE Below the menu
+			let color = if self.achievements.is_unlocked(*a) { GOLD } else { DARKGRAY };
+			draw_text(a.title(), 8.0, y, font_size, color);
+			draw_text(a.description(), 8.0, y + font_size, font_size * 0.8, color);
+		}
+	}
+
+	fn draw_analysis(&mut self) {
+		if menu(&self.typeface, "ANALYSIS", &["Back"], self.window_width_px).is_some() {
+			self.scene = Scene::Playing;
+		}
+		let Some(analysis) = self.analysis.as_ref()
+			else { return; };
+		let width = self.window_width_px as f32;
+		let sync_note = match analysis.in_sync {
+			Some(true) => "checksum verified",
+			Some(false) => "DESYNC; not the game recorded",
+			None => "unverified",
+		};
+		let summary = [
+			format!("{}, {}; score {}, {} rows in {}", analysis.mode, sync_note, analysis.score, analysis.rows_cleared, records::format_ticks(analysis.ticks)),
+			format!("{} pieces, {:.2} per second; {}/{} clean, {} extra presses; longest drought {}",
+				analysis.samples.len(), analysis.mean_pps(), analysis.clean_pieces(), analysis.samples.len(), analysis.extra_presses(), analysis.longest_drought()),
+		];
+		for (i, line) in summary.iter().enumerate() {
+			draw_text(line, 8.0, 150.0 + i as f32 * 20.0, 16.0, LIGHTGRAY); // HARDCODE Below the menu
+		}
+		let pps: Vec<f32> = analysis.samples.iter().map(|s| s.pps).collect();
+		let holes: Vec<f32> = analysis.samples.iter().map(|s| s.holes as f32).collect();
+		let height: Vec<f32> = analysis.samples.iter().map(|s| s.stack_height as f32).collect();
+		let extra: Vec<f32> = analysis.samples.iter().map(|s| s.extra_presses as f32).collect();
+		let droughts: Vec<f32> = analysis.droughts.iter().map(|&d| d as f32).collect();
+		let top = 200.0;
+		let graph_h = ((self.height_px as f32 - top) / 5.0).max(24.0);
+		let heatmap = &analysis.heatmap;
+		// HARDCODE Beside the graphs, at most a quarter of the width.
+		let heat_cell_px = ((self.height_px as f32 - top - 24.0) / heatmap.height.max(1) as f32).min(width / 4.0 / heatmap.width.max(1) as f32);
+		let heat_board = layout::Rect { x: width - 8.0 - heat_cell_px * heatmap.width as f32, y: top, w: heat_cell_px * heatmap.width as f32, h: heat_cell_px * heatmap.height as f32 };
+		draw_rectangle_lines(heat_board.x, heat_board.y, heat_board.w, heat_board.h, 1.0, DARKGRAY);
+		render_heatmap(heatmap, heat_board, heat_cell_px);
+		let graphs = [
+			("Pieces per second", pps, SKYBLUE, false),
+			("Holes", holes, ORANGE, false),
+			("Stack height", height, GREEN, false),
+			("Extra presses per piece", extra, RED, true),
+			("Droughts between line pieces", droughts, GOLD, true),
+		];
+		for (i, (title, values, color, bars)) in graphs.iter().enumerate() {
+			let rect = layout::Rect { x: 8.0, y: top + i as f32 * graph_h, w: heat_board.x - 16.0, h: graph_h - 8.0 };
+			render_graph(rect, title, values, *color, *bars);
+		}
+	}
+
+	fn draw_editor(&mut self) {
+		let board = self.layout.board;
+		let cell_px = self.cell_sidelength_px_f32;
+		render_grid(self.config.grid, GRAY, self.width_cells, self.height_cells, cell_px);
+		for (y, row) in self.board_editor.cells.iter().enumerate() {
+			for (x, kind) in row.iter().enumerate() {
+				if let Some(kind) = kind {
+					let cell = tetris::Cell::with_kind(puzzle::STACK_HUE, *kind);
+					draw_rectangle(x as f32 * cell_px, y as f32 * cell_px, cell_px, cell_px, cell_color(&cell, false));
+				}
+			}
+		}
+		draw_rectangle_lines(board.x, board.y, board.w, board.h, 2.0, GRAY);
+		let hints = [
+			format!("Brush: {:?}", self.board_editor.brush),
+			"1-4: brush".to_string(),
+			"Left: paint".to_string(),
+			"Right: erase".to_string(),
+			"C: clear".to_string(),
+			"Enter: play".to_string(),
+			"S: save puzzle".to_string(),
+			"Esc: back".to_string(),
+		];
+		for (i, hint) in hints.iter().enumerate() {
+			draw_text(hint, self.layout.sidebar.x + 8.0, 24.0 + i as f32 * 20.0, 16.0, LIGHTGRAY);
+		}
+	}
+
+	fn draw_lobby(&mut self) {
+		let font_size = 20.0;
+		let top = 336.0; // HARDCODE Below the menu
+		let now = miniquad::date::now();
+		let Some(room) = self.lobby.as_mut()
+			else {
+				let has_code = self.room_code_input.trim().len() == netplay::ROOM_CODE_LENGTH;
+				let difficulty_label = format!("Difficulty: {}", self.config.difficulty.name());
+				let best_of_label = format!("Best of {}", self.config.best_of);
+				match menu(&self.typeface, "VERSUS", &["Host a room", "Join room", "Watch room", &difficulty_label, &best_of_label, "Back"], self.window_width_px) {
+					Some(0) => self.lobby = Some(netplay::Lobby::host(&self.netplay_url, &self.config.player_name, self.config.difficulty, self.config.best_of)),
+					Some(1) if has_code => self.lobby = Some(netplay::Lobby::join(&self.netplay_url, &self.room_code_input, &self.config.player_name, self.config.difficulty, self.config.best_of)),
+					Some(2) if has_code => {
+						let spectator = netplay::Spectator::join(&self.netplay_url, &self.room_code_input, self.game_state.clone());
+						self.play(Mode::Spectate(Box::new(spectator)));
+						self.paused = false;
+					}
+					Some(1 | 2) => {}
+					Some(3) => self.config.difficulty = self.config.difficulty.next(),
+					// Odd lengths only, wrapping around.
+					Some(4) => self.config.best_of = if self.config.best_of + 2 > netplay::MAX_BEST_OF { 1 } else { self.config.best_of + 2 },
+					Some(_) => self.scene = Scene::Title,
+					None => {}
+				}
+				ui::root_ui().window(hash!(), Vec2::new(4.0, top), Vec2::new(self.window_width_px as f32 - 8.0, font_size * 4.0), |ui| {
+					ui.input_text(hash!(), "Server", &mut self.netplay_url);
+					ui.input_text(hash!(), "Room code", &mut self.room_code_input);
+				});
+				draw_text(&format!("Playing as {}", self.config.player_name), 8.0, top + font_size * 5.0, font_size, LIGHTGRAY);
+				let hint = format!("To join or watch, type the {}-letter code the host shares", netplay::ROOM_CODE_LENGTH);
+				draw_text(&hint, 8.0, top + font_size * 6.0, font_size * 0.8, GRAY);
+				return;
+			};
+		let ready_label = if room.is_ready { "Not ready" } else { "Ready" };
+		match menu(&self.typeface, &format!("ROOM {}", room.code), &[ready_label, "Leave"], self.window_width_px) {
+			Some(0) => {
+				let is_ready = !room.is_ready;
+				room.set_ready(is_ready, now);
+			}
+			Some(_) => {
+				room.leave();
+				self.lobby = None;
+				return;
+			}
+			None => {}
+		}
+		let mark = |is_ready: bool| if is_ready { "ready" } else { "not ready" };
+		let status = match &room.status {
+			netplay::LobbyStatus::Connecting => "Connecting...".to_string(),
+			netplay::LobbyStatus::Waiting => format!("Waiting for someone to join with {}", room.code),
+			netplay::LobbyStatus::Ready => "Both ready starts the game".to_string(),
+			netplay::LobbyStatus::Countdown { until } => format!("Starting in {}", (until - now).ceil().max(1.0)),
+			netplay::LobbyStatus::Failed(e) => e.clone(),
+		};
+		draw_text(&status, 8.0, top, font_size, GOLD);
+		draw_text(&format!("Best of {}", room.set_length()), 8.0, top + font_size, font_size, LIGHTGRAY);
+		draw_text(&format!("{} ({}, {})", self.config.player_name, room.difficulty.name(), mark(room.is_ready)), 8.0, top + font_size * 2.0, font_size, LIGHTGRAY);
+		if let Some((name, _nonce)) = room.opponent.as_ref() {
+			draw_text(&format!("{name} ({}, {})", room.opponent_difficulty.name(), mark(room.opponent_is_ready)), 8.0, top + font_size * 3.0, font_size, LIGHTGRAY);
+		}
+		let chat = layout::Rect::new(8.0, top + font_size * 4.0, self.window_width_px as f32 - 16.0, font_size * 8.0);
+		render_chat(&room.chat, self.chat_draft.as_deref(), "T to chat", chat, font_size * 0.8);
+	}
+
+	fn draw_game(&mut self) {
+		let (layout, height_px) = (&self.layout, self.height_px);
+		if let Mode::Spectate(spectator) = &self.mode {
+			let [first, second] = &spectator.players;
+			match first {
+				Some(watched) => {
+					self.frontend.render(&watched.game_state);
+					draw_text(&watched.name, 4.0, height_px as f32 - 28.0, 16.0, LIGHTGRAY);
+				}
+				None => {
+					draw_text("Waiting for the game to start...", 4.0, height_px as f32 / 2.0, 20.0, LIGHTGRAY);
+				}
+			}
+			if let (Some(watched), Some(opponent)) = (second, self.versus_layout.opponent) {
+				render_opponent(&watched.name, &watched.game_state, opponent, self.versus_layout.opponent_gauge, layout.label_px);
+			}
+			if let Some(chat) = self.versus_layout.chat {
+				render_chat(&spectator.chat, None, "", chat, layout.label_px * 0.6);
+			}
+			if let Some(seat) = spectator.winner() {
+				let winner = spectator.players[seat as usize].as_ref().map_or("", |w| w.name.as_str());
+				let title = format!("{winner} WINS");
+				let font_size = self.typeface.size(48.0);
+				let dims = self.typeface.measure(&title, font_size);
+				self.typeface.draw(&title, (self.window_width_px as f32 - dims.width) / 2.0, dims.offset_y, font_size, GREEN);
+			}
+			let status = match spectator.error() {
+				Some(e) => format!("Room {}; {e}; Esc to leave", spectator.code),
+				None => format!("Watching room {}; Esc to leave", spectator.code),
+			};
+			draw_text(&status, 4.0, height_px as f32 - 8.0, 16.0, LIGHTGRAY);
+			return;
+		}
+		let now = miniquad::date::now();
+		let is_capturing = self.capture != theme::Capture::Off;
+		if now < self.speed_hint_until && !is_capturing {
+			draw_text(&format!("Speed: {}", self.speed.name()), 4.0, height_px as f32 - 48.0, 16.0, LIGHTGRAY);
+		}
+		if let (Some(i), true) = (self.fast_forward, self.mode.can_fast_forward()) {
+			let text = match Self::FAST_FORWARD_TICKS[i] {
+				u32::MAX => "Fast forward: as fast as it goes; F for normal speed".to_string(),
+				ticks => format!("Fast forward: {ticks} ticks a frame; F for faster"),
+			};
+			draw_text(&text, 4.0, height_px as f32 - 48.0, 16.0, GOLD);
+		}
+		if (!self.game_state.is_alive || now < self.gif_hint_until) && !is_capturing {
+			let hint = format!("G: save the last {} s as a GIF", capture::BoardRecorder::SECONDS);
+			draw_text(&hint, 4.0, height_px as f32 - 28.0, 16.0, GOLD);
+		}
+		if let (Some(since), false) = (self.restart_held_since, self.restart_fired) {
+			let progress = ((get_time() - since) / Self::RESTART_HOLD_S).min(1.0) as f32;
+			draw_rectangle(0.0, 0.0, self.window_width_px as f32 * progress, 4.0, GOLD);
+		}
+		if self.is_game_over() {
+			self.draw_game_over();
+			return;
+		}
+		if self.paused {
+			let font_size = self.typeface.size(48.0);
+			let dims = self.typeface.measure("PAUSED", font_size);
+			self.typeface.draw("PAUSED", (self.window_width_px as f32 - dims.width) / 2.0, dims.offset_y, font_size, LIGHTGRAY);
+			let hint = "P to resume";
+			let hint_dims = measure_text(hint, None, 16, 1.0);
+			draw_text(hint, (self.window_width_px as f32 - hint_dims.width) / 2.0, dims.height + 24.0, 16.0, LIGHTGRAY);
+			render_hud(&self.layout, &self.typeface, &self.game_state);
+			return;
+		}
+		if let Mode::Practice(practice) = &mut self.mode {
+			if self.picker_open {
+				if let Some(i) = piece_picker(&self.practice_shapes, practice.sequence.len(), self.layout.board) {
+					let p = self.practice_shapes[i].clone();
+					if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+						practice.add_to_sequence(p, &mut self.game_state);
+					} else {
+						self.game_state.push_next_piece(p);
+					}
+				}
+				if is_key_pressed(KeyCode::Delete) {
+					practice.clear_sequence(&mut self.game_state);
+				}
+				render_hud(&self.layout, &self.typeface, &self.game_state);
+				return;
+			}
+		}
+
+		self.frontend.render(&self.game_state);
+		if self.show_heatmap {
+			render_heatmap(&self.heatmap, self.layout.board, self.cell_sidelength_px_f32);
+		}
+		if let Mode::Versus(versus) = &self.mode {
+			// HARDCODE Drains at 12 rows a second; fills at once.
+			let rows = versus.incoming_rows() as f32;
+			self.incoming_shown = (self.incoming_shown - 12.0 * get_frame_time()).max(rows);
+		}
+		let (layout, width_px, height_px) = (&self.layout, self.layout.board.w as usize, self.height_px);
+		if let (Mode::Versus(versus), Some(opponent)) = (&self.mode, self.versus_layout.opponent) {
+			render_incoming_meter(&versus.incoming, self.incoming_shown, layout.board, self.cell_sidelength_px_f32);
+			render_opponent(&opponent_label(versus), &versus.opponent, opponent, self.versus_layout.opponent_gauge, layout.label_px);
+		}
+		if let (Mode::Versus(versus), Some(chat)) = (&self.mode, self.versus_layout.chat) {
+			let hint = format!("T: chat; 1-{}: {}", netplay::EMOTES.len(), netplay::EMOTES.join(", "));
+			render_chat(&versus.chat, self.chat_draft.as_deref(), &hint, chat, layout.label_px * 0.6);
+		}
+
+		let mode_text = match &self.mode {
+			Mode::Marathon { mirror_pieces: false, .. } if self.game_state.ruleset_name() == "standard" => None,
+			Mode::Marathon { mirror_pieces: true, .. } => Some(format!("Marathon, {} rules, mirrored pieces, level {}", self.game_state.ruleset_name(), self.game_state.level())),
+			Mode::Marathon { .. } => Some(format!("Marathon, {} rules, level {}", self.game_state.ruleset_name(), self.game_state.level())),
+			Mode::Finesse(trainer) => {
+				if let Some((verdict, _until)) = self.finesse_fault.filter(|(_v, until)| *until > miniquad::date::now()) {
+					let fault = format!("{} presses; {} would do", verdict.presses, verdict.minimum);
+					draw_text(&fault, 4.0, height_px as f32 - 68.0, 16.0, RED);
+				}
+				Some(format!("Finesse: {}/{} clean, {} extra presses", trainer.clean_pieces, trainer.pieces, trainer.extra_presses))
+			}
+			Mode::Practice(_) => Some(format!("Practice; Backspace rewinds {} s, U undoes, Tab picks pieces", practice::Rewind::SECONDS_PER_REWIND)),
+			Mode::Sprint { ghost, .. } => {
+				render_sprint_progress(&self.game_state, ghost.as_deref(), Self::SPRINT_ROWS, width_px, height_px);
+				Some(format!("Sprint {}/{} {}", self.game_state.rows_cleared, Self::SPRINT_ROWS, records::format_ticks(self.game_state.ticks)))
+			}
+			Mode::Cheese(race) => Some(match self.cheese_record.best_ticks(race.target) {
+				Some(best) => format!("Cheese {}/{} {}; best {}", race.dug, race.target, records::format_ticks(self.game_state.ticks), records::format_ticks(best)),
+				None => format!("Cheese {}/{} {}", race.dug, race.target, records::format_ticks(self.game_state.ticks)),
+			}),
+			Mode::Replay(playback) => Some(format!("Replay: {} #{}; A analyzes, F fast-forwards", playback.replay.mode, playback.replay.seed)),
+			Mode::Bot { name, .. } => Some(format!("Bot: {name} #{}; F fast-forwards", self.game_state.seed)),
+			Mode::Coop(_) => Some("Co-op: left player WASD, Space drops; right player arrows, Right Shift drops".to_string()),
+			Mode::Twin(_) => Some("Twin: left piece WASD, Space drops; right piece arrows, Right Shift drops".to_string()),
+			Mode::Versus(versus) => {
+				if let Some(tick) = versus.desync_at {
+					draw_text(&format!("DESYNC after tick {tick}; see desyncs/"), 4.0, height_px as f32 - 68.0, 16.0, RED);
+				}
+				Some(match versus.connection.error.as_deref() {
+					Some(e) => format!("Versus {}; {e}", versus.opponent_name),
+					None => format!("Versus {}", versus.opponent_name),
+				})
+			}
+			// Drawn with the rest of its screen, further up.
+			Mode::Spectate(_) => None,
+			Mode::Daily { day } => Some(match self.daily_records.best(*day) {
+				Some(best) => format!("Daily #{day}; best {best}"),
+				None => format!("Daily #{day}"),
+			}),
+			Mode::Puzzle(run) | Mode::Campaign { run, .. } => Some(match run.puzzle.piece_limit {
+				Some(limit) => format!("{} ({}/{limit})", run.puzzle.description(), run.pieces_used),
+				None => run.puzzle.description(),
+			}),
+		};
+		if let Some(mode_text) = mode_text.filter(|_| self.capture == theme::Capture::Off) {
+			draw_text(&mode_text, 4.0, height_px as f32 - 8.0, 16.0, LIGHTGRAY);
+		}
+		if self.script_run.bonus != 0 {
+			let bonus_text = format!("{:+} bonus", self.script_run.bonus);
+			let dims = measure_text(&bonus_text, None, 16, 1.0);
+			draw_text(&bonus_text, width_px as f32 - dims.width - 4.0, height_px as f32 - 8.0, 16.0, GOLD);
+		}
+
+		render_toasts(&self.toasts, self.window_width_px);
+		if self.show_debug_overlay {
+			render_debug_overlay(&self.game_state, &self.tick_rate);
+		}
+	}
+
+	fn draw_game_over(&mut self) {
+		let top_out_text = match self.top_out {
+			Some(tetris::TopOut::BlockOut) => "BLOCK OUT",
+			Some(tetris::TopOut::LockOut) => "LOCK OUT",
+			Some(tetris::TopOut::PushedOut) => "PUSHED OUT",
+			None => "",
+		};
+		let puzzle_outcome = match &self.mode {
+			Mode::Puzzle(run) | Mode::Campaign { run, .. } => run.outcome,
+			_ => None,
+		};
+		let versus_outcome = match &self.mode {
+			Mode::Versus(versus) => versus.outcome(&self.game_state),
+			_ => None,
+		};
+		let daily_best;
+		let sprint_time;
+		let campaign_stars;
+		let mut versus_result;
+		let (title, color, subtitle) = match (&self.mode, puzzle_outcome) {
+			(Mode::Sprint { finished_at_tick: Some(ticks), .. }, _) => {
+				sprint_time = match self.sprint_record.best_ticks {
+					Some(best) if best == *ticks => format!("{}; new best!", records::format_ticks(*ticks)),
+					Some(best) => format!("{}; best {}", records::format_ticks(*ticks), records::format_ticks(best)),
+					None => records::format_ticks(*ticks),
+				};
+				("FINISHED", GREEN, Some(sprint_time.as_str()))
+			}
+			(Mode::Cheese(cheese::CheeseRace { target, finished_at_tick: Some(ticks), .. }), _) => {
+				sprint_time = match self.cheese_record.best_ticks(*target) {
+					Some(best) if best == *ticks => format!("{target} rows dug in {}; new best!", records::format_ticks(*ticks)),
+					Some(best) => format!("{target} rows dug in {}; best {}", records::format_ticks(*ticks), records::format_ticks(best)),
+					None => format!("{target} rows dug in {}", records::format_ticks(*ticks)),
+				};
+				("FINISHED", GREEN, Some(sprint_time.as_str()))
+			}
+			(Mode::Campaign { level, run }, Some(puzzle::Outcome::Won)) => {
+				campaign_stars = match self.campaign.get(*level) {
+					Some(level) => format!("{}/{} stars; {}", level.stars(run.pieces_used, self.game_state.ticks), campaign::MAX_STARS, level.par_description()),
+					None => String::new(),
+				};
+				("CLEARED", GREEN, Some(campaign_stars.as_str()))
+			}
+			(_, Some(puzzle::Outcome::Won)) => ("CLEARED", GREEN, None),
+			(_, Some(puzzle::Outcome::Lost)) if self.game_state.is_alive => ("FAILED", RED, None),
+			(Mode::Replay(playback), _) => match playback.is_in_sync(&self.game_state) {
+				Some(true) => ("REPLAY", GREEN, Some("checksum verified")),
+				Some(false) => ("REPLAY", RED, Some("DESYNC; checksum differs")),
+				None => ("REPLAY", LIGHTGRAY, None),
+			},
+			(Mode::Practice(_), _) => ("GAME OVER", RED, Some("Backspace to rewind, U to undo")),
+			(Mode::Versus(versus), _) => {
+				let set = &versus.set;
+				versus_result = match versus_outcome {
+					Some(_) if versus.opponent_left && versus.opponent.is_alive => format!("{} left", versus.opponent_name),
+					Some(_) if set.winner().is_some() => format!("{}-{} against {}", set.wins(), set.losses(), versus.opponent_name),
+					Some(_) if set.best_of == 1 => format!("against {}", versus.opponent_name),
+					Some(_) if versus.has_asked_next() => format!("set {}-{}; waiting for {} to play on", set.wins(), set.losses(), versus.opponent_name),
+					Some(_) => format!("set {}-{} against {}, best of {}; Restart plays on", set.wins(), set.losses(), versus.opponent_name, set.best_of),
+					None => format!("{top_out_text}; waiting for {}", versus.opponent_name),
+				};
+				if let Some(tick) = versus.desync_at {
+					versus_result += &format!("; DESYNC after tick {tick}");
+				}
+				match (versus_outcome, set.winner()) {
+					(Some(_), Some(netplay::Outcome::Won)) if set.best_of > 1 => ("SET WON", GOLD, Some(versus_result.as_str())),
+					(Some(_), Some(netplay::Outcome::Lost)) if set.best_of > 1 => ("SET LOST", RED, Some(versus_result.as_str())),
+					(Some(netplay::Outcome::Won), _) => ("YOU WIN", GREEN, Some(versus_result.as_str())),
+					(Some(netplay::Outcome::Lost), _) => ("YOU LOSE", RED, Some(versus_result.as_str())),
+					(None, _) => ("GAME OVER", RED, Some(versus_result.as_str())),
+				}
+			}
+			(Mode::Daily { day }, _) => {
+				daily_best = format!("{top_out_text}; daily best {}", self.daily_records.best(*day).unwrap_or_default());
+				("GAME OVER", RED, Some(daily_best.as_str()))
+			}
+			_ => ("GAME OVER", RED, Some(top_out_text)),
+		};
+		let exportable = match &self.mode {
+			Mode::Replay(playback) => Some(&playback.replay),
+			_ => self.recording.as_ref().filter(|r| r.checksum.is_some()),
+		};
+		match game_over(&self.typeface, title, color, subtitle, exportable.is_some(), self.window_width_px) {
+			// The set plays on over the same connection; once it's over, a rematch means finding an opponent again.
+			Some(GameOverChoice::Restart) if matches!(&self.mode, Mode::Versus(versus) if versus.can_play_on()) => {
+				if let Mode::Versus(versus) = &mut self.mode {
+					versus.ask_next();
+				}
+			}
+			Some(GameOverChoice::Restart) if matches!(self.mode, Mode::Versus(_)) => {
+				self.mode = Mode::Marathon { starting_level: 0, mirror_pieces: false };
+				self.scene = Scene::Lobby;
+			}
+			Some(GameOverChoice::Restart) => self.start_game(None),
+			Some(GameOverChoice::Menu) => {
+				if matches!(self.mode, Mode::Versus(_)) {
+					self.mode = Mode::Marathon { starting_level: 0, mirror_pieces: false };
+				}
+				self.scene = if matches!(self.mode, Mode::Campaign { .. }) { Scene::Campaign } else { Scene::Title };
+			}
+			Some(GameOverChoice::Quit) => self.scene = Scene::ConfirmQuit { from_game: true },
+			Some(GameOverChoice::Export) => if let Some(r) = exportable {
+				let file_name = format!("{}-{}.replay", r.mode, r.seed);
+				if let Err(e) = storage::export(&file_name, r.to_text().as_bytes()) {
+					log::error!("Could not export replay: {e}");
+				}
+			},
+			None => {}
+		}
+		render_hud(&self.layout, &self.typeface, &self.game_state);
+		if let (Mode::Versus(versus), Some(opponent)) = (&self.mode, self.versus_layout.opponent) {
+			render_opponent(&opponent_label(versus), &versus.opponent, opponent, self.versus_layout.opponent_gauge, self.layout.label_px);
+		}
+		if let (Mode::Versus(versus), Some(chat)) = (&self.mode, self.versus_layout.chat) {
+			render_chat(&versus.chat, self.chat_draft.as_deref(), "T to chat", chat, self.layout.label_px * 0.6);
+		}
+		if let Mode::Versus(versus) = &self.mode {
+			if versus.set.best_of > 1 && versus_outcome.is_some() {
+				render_set_results(&versus.set, &self.typeface, self.window_width_px as f32, self.height_px as f32 / 2.0);
+			}
+		} else {
+			render_score_breakdown(&self.game_state.breakdown, self.game_state.score, self.layout.board, self.height_px as f32 / 2.0);
+		}
+		render_toasts(&self.toasts, self.window_width_px);
+	}
+}
+
+/// `--capture <off|transparent|chroma>`, anywhere on the command line.
+fn capture_flag() -> Option<theme::Capture> {
+	let args: Vec<String> = std::env::args().collect();
+	let value = args.iter().position(|arg| arg == "--capture").and_then(|i| args.get(i + 1))?;
+	let capture = theme::Capture::parse(value);
+	if capture.is_none() {
+		log::warn!("Ignoring unknown capture background `{value}`; expected off, transparent, or chroma");
+	}
+	capture
+}
+
+/// The capture background, settled before the window opens, since only then can it be asked for an alpha channel.
+fn capture_setting() -> theme::Capture {
+	capture_flag().unwrap_or_else(|| config::Config::load().capture)
+}
+
+pub fn window_conf() -> macroquad::conf::Conf {
+	let mut miniquad_conf = miniquad::conf::Conf { window_title: "Tetris clone in Rust".to_string(), ..Default::default() };
+	miniquad_conf.platform.framebuffer_alpha = capture_setting() == theme::Capture::Transparent;
+	macroquad::conf::Conf { miniquad_conf, ..Default::default() }
+}
+
+pub async fn main() {
+	let mut app = App::new().await;
+	loop {
+		app.update();
+		app.draw();
+		end_frame().await;
+	}
+}
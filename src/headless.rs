@@ -0,0 +1,210 @@
+//! `--headless`: bots play many games without a window, then statistics come out as JSON.
+//!
+//! ```text
+//! tetris-clone-rust --headless --games 100 --bot greedy --seed 1 --max-pieces 500
+//! tetris-clone-rust --headless --games 10 --bot beam --strength hard
+//! tetris-clone-rust --headless --games 10 --bot beam --export bot-games.ndjson
+//! tetris-clone-rust --headless --games 10 --gravity 20G
+//! ```
+//!
+//! Build with `--no-default-features` to leave out macroquad entirely.
+
+use crate::bot;
+use crate::env;
+use crate::frontend::Frontend;
+use crate::ruleset;
+use crate::simulation::{Lane, Simulation};
+use crate::storage;
+use crate::tetris;
+
+#[derive(Clone,Debug)]
+pub struct Settings {
+	pub games: u32,
+	/// See bot::NAMES.
+	pub bot: String,
+	/// For bots that search; see bot::Strength.
+	pub strength: bot::Strength,
+	/// Game `i` is seeded with `seed + i`.
+	pub seed: u64,
+	/// Games that go on this long count as survived; without a limit, a good bot never stops.
+	pub max_pieces: u32,
+	pub width: usize,
+	pub height: usize,
+	pub timing: tetris::Timing,
+	pub randomizer_strategy: tetris::RandomizerStrategy,
+	/// See ruleset::NAMES.
+	pub ruleset: String,
+	/// Where to write every tick of every game, as env::TrainingLog::to_ndjson() has it.
+	pub export: Option<String>,
+}
+
+impl Default for Settings {
+	/// HARDCODE Matches the game's own defaults; see gui.rs.
+	fn default() -> Self {
+		Self {
+			games: 10,
+			bot: "greedy".to_string(),
+			strength: bot::Strength::default(),
+			seed: 0,
+			max_pieces: 500,
+			width: 8,
+			height: 24,
+			timing: tetris::Timing {
+				ticks_per_drop_slow: 10,
+				ticks_per_drop_fast: 1,
+				entry_delay: 10,
+				line_clear_delay: 20,
+				fall_speed: None,
+			},
+			randomizer_strategy: tetris::RandomizerStrategy::History { size: 4, rerolls: 4 },
+			ruleset: "standard".to_string(),
+			export: None,
+		}
+	}
+}
+
+impl Settings {
+	pub fn parse(args: &[String]) -> Result<Settings, String> {
+		let mut settings = Settings::default();
+		let mut args = args.iter();
+		while let Some(flag) = args.next() {
+			let value = args.next().ok_or_else(|| format!("Missing value for {flag}"))?;
+			let number = || value.parse::<u64>().map_err(|_| format!("Bad number `{value}` for {flag}"));
+			match flag.as_str() {
+				"--games" => settings.games = number()? as u32,
+				"--bot" => settings.bot = value.clone(),
+				"--strength" => settings.strength = bot::Strength::parse(value)
+					.ok_or_else(|| format!("Unknown strength `{value}`; expected easy, normal, or hard"))?,
+				"--seed" => settings.seed = number()?,
+				"--max-pieces" => settings.max_pieces = number()? as u32,
+				"--width" => settings.width = number()? as usize,
+				"--height" => settings.height = number()? as usize,
+				"--ruleset" => settings.ruleset = value.clone(),
+				"--export" => settings.export = Some(value.clone()),
+				"--gravity" => settings.timing.fall_speed = Some(tetris::FallSpeed::parse(value)
+					.ok_or_else(|| format!("Bad gravity `{value}`; expected cells per second, like 2.5, or G, like 20G"))?),
+				other => return Err(format!("Unknown flag {other}; expected --games, --bot, --strength, --seed, --max-pieces, --width, --height, --ruleset, --export, or --gravity")),
+			}
+		}
+		if !bot::NAMES.contains(&settings.bot.as_str()) {
+			return Err(format!("Unknown bot `{}`; expected one of {}", settings.bot, bot::NAMES.join(", ")));
+		}
+		if ruleset::by_name(&settings.ruleset).is_none() {
+			return Err(format!("Unknown ruleset `{}`; expected one of {}", settings.ruleset, ruleset::NAMES.join(", ")));
+		}
+		Ok(settings)
+	}
+}
+
+/// A bot at the controls and nobody watching.
+#[derive(Debug)]
+pub struct BotFrontend {
+	pub bot: Box<dyn bot::Bot>,
+	/// Per Settings::export.
+	pub training_log: Option<env::TrainingLog>,
+}
+
+impl Frontend for BotFrontend {
+	fn poll_input(&mut self, game_state: &tetris::GameState) -> Vec<tetris::Action> {
+		let actions = self.bot.think(game_state);
+		if let Some(log) = self.training_log.as_mut() {
+			log.before_tick(game_state, &actions);
+		}
+		actions
+	}
+
+	/// Right after each tick.
+	fn render(&mut self, game_state: &tetris::GameState) {
+		if let Some(log) = self.training_log.as_mut() {
+			log.after_tick(game_state);
+		}
+	}
+
+	fn play_event(&mut self, _event: &tetris::Event) {}
+}
+
+#[derive(Clone,Debug)]
+pub struct GameStats {
+	pub seed: u64,
+	pub rows_cleared: u32,
+	/// Per the ruleset.
+	pub score: u32,
+	pub pieces: u32,
+	pub ticks: u64,
+	/// None if the game hit Settings::max_pieces first.
+	pub top_out: Option<tetris::TopOut>,
+}
+
+impl Settings {
+	/// Reset and seeded, ready for its first tick.
+	pub fn new_game_state(&self, seed: u64) -> tetris::GameState {
+		let mut game_state = tetris::GameState::new(self.height, self.width, self.randomizer_strategy, self.timing);
+		game_state.set_ruleset(ruleset::by_name(&self.ruleset).expect("Settings::parse() should have checked the ruleset"));
+		game_state.reset();
+		game_state.reseed(seed);
+		game_state
+	}
+}
+
+/// One lane per game, seeded `seed`, `seed + 1`, ...
+pub fn simulation(settings: &Settings) -> Simulation<BotFrontend> {
+	let lanes = (0 .. settings.games as u64)
+		.map(|i| {
+			let seed = settings.seed.wrapping_add(i);
+			let game_state = settings.new_game_state(seed);
+			let bot = bot::by_name(&settings.bot, seed, settings.strength)
+				.expect("Settings::parse() should have checked the bot name");
+			Lane::new(game_state, BotFrontend { bot, training_log: settings.export.is_some().then(env::TrainingLog::new) })
+		})
+		.collect();
+	Simulation::new(lanes)
+}
+
+/// Entry point for `--headless`; `args` are whatever followed it.
+pub fn run(args: &[String]) -> Result<String, String> {
+	let settings = Settings::parse(args)?;
+	let started = std::time::Instant::now();
+	let mut simulation = simulation(&settings);
+	simulation.run(|lane| lane.pieces_locked >= settings.max_pieces);
+	let games: Vec<GameStats> = simulation.lanes.iter()
+		.map(|lane| GameStats {
+			seed: lane.game_state.seed,
+			rows_cleared: lane.game_state.rows_cleared,
+			score: lane.game_state.score,
+			pieces: lane.pieces_locked,
+			ticks: lane.game_state.ticks,
+			top_out: lane.top_out,
+		})
+		.collect();
+	if let Some(file_name) = settings.export.as_deref() {
+		let ndjson: String = simulation.lanes.iter()
+			.filter_map(|lane| lane.player.training_log.as_ref())
+			.map(env::TrainingLog::to_ndjson)
+			.collect();
+		storage::export(file_name, ndjson.as_bytes()).map_err(|e| format!("Could not write {file_name}: {e}"))?;
+	}
+	Ok(to_json(&settings, &games, started.elapsed()))
+}
+
+fn to_json(settings: &Settings, games: &[GameStats], elapsed: std::time::Duration) -> String {
+	let count = games.len().max(1) as f64;
+	let mean = |f: fn(&GameStats) -> f64| games.iter().map(f).sum::<f64>() / count;
+	let rows = |g: &GameStats| g.rows_cleared;
+	let games_json: Vec<String> = games.iter()
+		.map(|g| format!(
+			"{{\"seed\":{},\"rows_cleared\":{},\"score\":{},\"pieces\":{},\"ticks\":{},\"top_out\":{}}}",
+			g.seed, g.rows_cleared, g.score, g.pieces, g.ticks,
+			match g.top_out {
+				Some(cause) => format!("\"{cause:?}\""),
+				None => "null".to_string(),
+			}))
+		.collect();
+	format!(
+		"{{\"bot\":\"{}\",\"strength\":\"{}\",\"ruleset\":\"{}\",\"seed\":{},\"games\":{},\"max_pieces\":{},\"board\":[{},{}],\"elapsed_ms\":{},\
+			\"rows_cleared\":{{\"mean\":{:.3},\"min\":{},\"max\":{}}},\"mean_score\":{:.3},\"mean_pieces\":{:.3},\"mean_ticks\":{:.3},\"survived\":{},\
+			\"results\":[{}]}}",
+		settings.bot, settings.strength.name(), settings.ruleset, settings.seed, games.len(), settings.max_pieces, settings.width, settings.height, elapsed.as_millis(),
+		mean(|g| g.rows_cleared as f64), games.iter().map(rows).min().unwrap_or(0), games.iter().map(rows).max().unwrap_or(0),
+		mean(|g| g.score as f64), mean(|g| g.pieces as f64), mean(|g| g.ticks as f64), games.iter().filter(|g| g.top_out.is_none()).count(),
+		games_json.join(","))
+}
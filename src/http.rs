@@ -0,0 +1,125 @@
+//! Just enough HTTP to talk to a leaderboard without blocking the frame loop.
+//!
+//! Natively this is plain `http://` over a background thread; in the browser it goes through `fetch()`.
+
+/// Poll once per frame until it yields.
+#[derive(Debug)]
+pub struct PendingResponse {
+	#[cfg(not(target_family="wasm"))]
+	receiver: std::sync::mpsc::Receiver<Result<String, String>>,
+	#[cfg(target_family="wasm")]
+	id: u32,
+}
+
+pub fn get(url: &str) -> PendingResponse {
+	request("GET", url, "")
+}
+
+/// `body` is sent as `application/x-www-form-urlencoded`; see form_encode().
+pub fn post(url: &str, body: &str) -> PendingResponse {
+	request("POST", url, body)
+}
+
+pub fn form_encode(pairs: &[(&str, &str)]) -> String {
+	pairs.iter()
+		.map(|(k, v)| format!("{}={}", percent_encode(k), percent_encode(v)))
+		.collect::<Vec<_>>()
+		.join("&")
+}
+
+pub fn percent_encode(text: &str) -> String {
+	text.bytes()
+		.map(|b| match b {
+			b'A' ..= b'Z' | b'a' ..= b'z' | b'0' ..= b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+			_ => format!("%{b:02X}"),
+		})
+		.collect()
+}
+
+#[cfg(not(target_family="wasm"))]
+fn request(method: &'static str, url: &str, body: &str) -> PendingResponse {
+	let (sender, receiver) = std::sync::mpsc::channel();
+	let url = url.to_string();
+	let body = body.to_string();
+	std::thread::spawn(move || {
+		// Nobody listening is fine.
+		let _ = sender.send(request_blocking(method, &url, &body));
+	});
+	PendingResponse { receiver }
+}
+
+#[cfg(not(target_family="wasm"))]
+fn request_blocking(method: &str, url: &str, body: &str) -> Result<String, String> {
+	use std::io::{Read, Write};
+
+	let rest = url.strip_prefix("http://")
+		.ok_or_else(|| format!("Only http:// URLs are supported natively, not {url}"))?;
+	let (host, path) = match rest.find('/') {
+		Some(i) => (&rest[.. i], &rest[i ..]),
+		None => (rest, "/"),
+	};
+	let address = if host.contains(':') { host.to_string() } else { format!("{host}:80") };
+	let timeout = Some(std::time::Duration::from_secs(5)); // HARDCODE
+	let mut stream = std::net::TcpStream::connect(&address).map_err(|e| e.to_string())?;
+	stream.set_read_timeout(timeout).map_err(|e| e.to_string())?;
+	stream.set_write_timeout(timeout).map_err(|e| e.to_string())?;
+	// HTTP/1.0 so the server can't reply chunked.
+	let request = format!(
+		"{method} {path} HTTP/1.0\r\nHost: {host}\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{body}",
+		body.len());
+	stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+	let mut response = String::new();
+	stream.read_to_string(&mut response).map_err(|e| e.to_string())?;
+	let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+	let status = head.split_whitespace().nth(1).unwrap_or_default();
+	if !status.starts_with('2') {
+		return Err(format!("HTTP status {status}"));
+	}
+	Ok(body.to_string())
+}
+
+#[cfg(not(target_family="wasm"))]
+impl PendingResponse {
+	pub fn poll(&mut self) -> Option<Result<String, String>> {
+		match self.receiver.try_recv() {
+			Ok(result) => Some(result),
+			Err(std::sync::mpsc::TryRecvError::Empty) => None,
+			Err(std::sync::mpsc::TryRecvError::Disconnected) => Some(Err("Request thread died".to_string())),
+		}
+	}
+}
+
+// Matches www/index.html -> register_plugin
+#[cfg(target_family="wasm")]
+extern "C" {
+	fn http_request(method: *const u8, method_len: usize, url: *const u8, url_len: usize, body: *const u8, body_len: usize) -> u32;
+	/// 0 while pending, 1 on success, 2 on failure; the text is the body or the error.
+	fn http_poll(id: u32) -> u32;
+	fn http_response_len(id: u32) -> usize;
+	/// Also frees the request.
+	fn http_response_read(id: u32, buffer: *mut u8);
+}
+
+#[cfg(target_family="wasm")]
+fn request(method: &'static str, url: &str, body: &str) -> PendingResponse {
+	let id = unsafe { // SAFETY: the JS side copies each buffer before returning
+		http_request(method.as_ptr(), method.len(), url.as_ptr(), url.len(), body.as_ptr(), body.len())
+	};
+	PendingResponse { id }
+}
+
+#[cfg(target_family="wasm")]
+impl PendingResponse {
+	pub fn poll(&mut self) -> Option<Result<String, String>> {
+		let status = unsafe { http_poll(self.id) };
+		if status == 0 {
+			return None;
+		}
+		let text = unsafe { // SAFETY: buffer is exactly as long as the JS side reported
+			let mut buffer = vec![0_u8; http_response_len(self.id)];
+			http_response_read(self.id, buffer.as_mut_ptr());
+			String::from_utf8_lossy(&buffer).into_owned()
+		};
+		Some(if status == 1 { Ok(text) } else { Err(text) })
+	}
+}
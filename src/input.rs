@@ -0,0 +1,501 @@
+//! Turning whatever the player is holding down into tetris::Actions, once per tick.
+//! No window library in here: frontends name what is held as Controls, and everything downstream
+//! (the game, replays, finesse) only ever sees the Actions, same as from a bot.
+
+use std::collections::BTreeSet;
+
+use crate::layout;
+use crate::tetris;
+
+/// Something the player can hold down.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Control {
+	Key(Key),
+	Gamepad(GamepadButton),
+	/// Drawn on screen, for touch screens.
+	Touch(TouchButton),
+}
+
+/// Keys worth binding; frontends map their own key codes onto these.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Key {
+	Left,
+	Right,
+	Up,
+	Down,
+	Space,
+	LeftShift,
+	RightShift,
+	LeftControl,
+	RightControl,
+	A,
+	C,
+	D,
+	E,
+	Q,
+	S,
+	W,
+	X,
+	Z,
+}
+
+impl Key {
+	pub const ALL: [Key; 18] = [
+		Key::Left, Key::Right, Key::Up, Key::Down, Key::Space,
+		Key::LeftShift, Key::RightShift, Key::LeftControl, Key::RightControl,
+		Key::A, Key::C, Key::D, Key::E, Key::Q, Key::S, Key::W, Key::X, Key::Z,
+	];
+
+	pub fn parse(text: &str) -> Option<Key> {
+		Self::ALL.into_iter().find(|k| k.name().eq_ignore_ascii_case(text))
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Key::Left => "left",
+			Key::Right => "right",
+			Key::Up => "up",
+			Key::Down => "down",
+			Key::Space => "space",
+			Key::LeftShift => "left_shift",
+			Key::RightShift => "right_shift",
+			Key::LeftControl => "left_control",
+			Key::RightControl => "right_control",
+			Key::A => "a",
+			Key::C => "c",
+			Key::D => "d",
+			Key::E => "e",
+			Key::Q => "q",
+			Key::S => "s",
+			Key::W => "w",
+			Key::X => "x",
+			Key::Z => "z",
+		}
+	}
+}
+
+/// Named by position, as on most pads: South is A on an Xbox pad, Cross on a PlayStation one.
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
+pub enum GamepadButton {
+	DpadLeft,
+	DpadRight,
+	DpadUp,
+	DpadDown,
+	South,
+	East,
+	West,
+	North,
+	LeftShoulder,
+	RightShoulder,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
+pub enum TouchButton {
+	Left,
+	Right,
+	RotateClockwise,
+	RotateCounterClockwise,
+	SoftDrop,
+	HardDrop,
+	Hold,
+}
+
+impl TouchButton {
+	/// Short enough to fit on the button.
+	pub fn label(self) -> &'static str {
+		match self {
+			TouchButton::Left => "<",
+			TouchButton::Right => ">",
+			TouchButton::RotateClockwise => "CW",
+			TouchButton::RotateCounterClockwise => "CCW",
+			TouchButton::SoftDrop => "v",
+			TouchButton::HardDrop => "DROP",
+			TouchButton::Hold => "HOLD",
+		}
+	}
+}
+
+/// Where the on-screen buttons go: two rows of four along the bottom of `screen`, which they scale with.
+pub fn touch_buttons(screen: layout::Rect) -> Vec<(TouchButton, layout::Rect)> {
+	// HARDCODE (column, row) from the bottom left; thumbs reach the corners easily.
+	let grid = [
+		(TouchButton::Left, 0, 0),
+		(TouchButton::SoftDrop, 1, 0),
+		(TouchButton::HardDrop, 2, 0),
+		(TouchButton::Right, 3, 0),
+		(TouchButton::Hold, 0, 1),
+		(TouchButton::RotateCounterClockwise, 2, 1),
+		(TouchButton::RotateClockwise, 3, 1),
+	];
+	let size = (screen.w / 4.0).min(screen.h / 8.0);
+	let left = screen.x + (screen.w - 4.0 * size) / 2.0;
+	grid.into_iter()
+		.map(|(button, column, row)| (button, layout::Rect::new(left + column as f32 * size, screen.bottom() - (row + 1) as f32 * size, size, size)))
+		.collect()
+}
+
+/// The on-screen buttons under any of `touches`, in the same pixels as `buttons`.
+pub fn touched(buttons: &[(TouchButton, layout::Rect)], touches: &[(f32, f32)]) -> BTreeSet<Control> {
+	buttons.iter()
+		.filter(|(_button, r)| touches.iter().any(|&(x, y)| (r.x .. r.right()).contains(&x) && (r.y .. r.bottom()).contains(&y)))
+		.map(|&(button, _r)| Control::Touch(button))
+		.collect()
+}
+
+/// What a control is bound to. Unlike an Action, soft drop lasts as long as it is held.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Command {
+	MoveLeft,
+	MoveRight,
+	RotateClockwise,
+	RotateCounterClockwise,
+	SoftDrop,
+	Hold,
+	HardDrop,
+	/// No profile binds it; see config::Config::key_overrides.
+	SonicDrop,
+}
+
+impl Command {
+	pub const ALL: [Command; 8] = [Command::MoveLeft, Command::MoveRight, Command::RotateClockwise, Command::RotateCounterClockwise, Command::SoftDrop, Command::Hold, Command::HardDrop, Command::SonicDrop];
+
+	pub fn parse(text: &str) -> Option<Command> {
+		Self::ALL.into_iter().find(|c| c.name().eq_ignore_ascii_case(text))
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Command::MoveLeft => "move_left",
+			Command::MoveRight => "move_right",
+			Command::RotateClockwise => "rotate_cw",
+			Command::RotateCounterClockwise => "rotate_ccw",
+			Command::SoftDrop => "soft_drop",
+			Command::Hold => "hold",
+			Command::HardDrop => "hard_drop",
+			Command::SonicDrop => "sonic_drop",
+		}
+	}
+}
+
+/// Named sets of bindings to start from; any control can have several commands, and any command several controls.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Profile {
+	/// What the game has always had: arrows, Space to drop, C or Shift to hold.
+	Classic,
+	/// As in most modern games: Up or X to turn clockwise, Z or Ctrl the other way, Down to drop, Space to hard drop.
+	Guideline,
+	/// WASD: A and D to move, W or E to turn clockwise, Q the other way, S to drop, Space to hard drop, Shift to hold.
+	LeftHanded,
+}
+
+impl Profile {
+	pub const ALL: [Profile; 3] = [Profile::Classic, Profile::Guideline, Profile::LeftHanded];
+
+	pub fn parse(text: &str) -> Option<Profile> {
+		Self::ALL.into_iter().find(|p| p.name().eq_ignore_ascii_case(text))
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Profile::Classic => "classic",
+			Profile::Guideline => "guideline",
+			Profile::LeftHanded => "left-handed",
+		}
+	}
+
+	/// Gamepads and the on-screen buttons get the same bindings whatever the profile.
+	pub fn bindings(self) -> Vec<(Control, Command)> {
+		let keys: &[(Key, Command)] = match self {
+			Profile::Classic => &[
+				(Key::Left, Command::MoveLeft),
+				(Key::Right, Command::MoveRight),
+				(Key::Up, Command::RotateCounterClockwise),
+				(Key::Down, Command::RotateClockwise),
+				(Key::Space, Command::SoftDrop),
+				(Key::C, Command::Hold),
+				(Key::LeftShift, Command::Hold),
+				(Key::RightShift, Command::Hold),
+			],
+			Profile::Guideline => &[
+				(Key::Left, Command::MoveLeft),
+				(Key::Right, Command::MoveRight),
+				(Key::Up, Command::RotateClockwise),
+				(Key::X, Command::RotateClockwise),
+				(Key::Z, Command::RotateCounterClockwise),
+				(Key::LeftControl, Command::RotateCounterClockwise),
+				(Key::RightControl, Command::RotateCounterClockwise),
+				(Key::Down, Command::SoftDrop),
+				(Key::Space, Command::HardDrop),
+				(Key::C, Command::Hold),
+				(Key::LeftShift, Command::Hold),
+				(Key::RightShift, Command::Hold),
+			],
+			Profile::LeftHanded => &[
+				(Key::A, Command::MoveLeft),
+				(Key::D, Command::MoveRight),
+				(Key::W, Command::RotateClockwise),
+				(Key::E, Command::RotateClockwise),
+				(Key::Q, Command::RotateCounterClockwise),
+				(Key::S, Command::SoftDrop),
+				(Key::Space, Command::HardDrop),
+				(Key::LeftShift, Command::Hold),
+			],
+		};
+		let buttons = [
+			(GamepadButton::DpadLeft, Command::MoveLeft),
+			(GamepadButton::DpadRight, Command::MoveRight),
+			(GamepadButton::DpadDown, Command::SoftDrop),
+			(GamepadButton::DpadUp, Command::HardDrop),
+			(GamepadButton::South, Command::RotateCounterClockwise),
+			(GamepadButton::East, Command::RotateClockwise),
+			(GamepadButton::LeftShoulder, Command::Hold),
+			(GamepadButton::RightShoulder, Command::Hold),
+		];
+		let touch = [
+			(TouchButton::Left, Command::MoveLeft),
+			(TouchButton::Right, Command::MoveRight),
+			(TouchButton::RotateClockwise, Command::RotateClockwise),
+			(TouchButton::RotateCounterClockwise, Command::RotateCounterClockwise),
+			(TouchButton::SoftDrop, Command::SoftDrop),
+			(TouchButton::HardDrop, Command::HardDrop),
+			(TouchButton::Hold, Command::Hold),
+		];
+		keys.iter().map(|&(key, command)| (Control::Key(key), command))
+			.chain(buttons.into_iter().map(|(button, command)| (Control::Gamepad(button), command)))
+			.chain(touch.into_iter().map(|(button, command)| (Control::Touch(button), command)))
+			.collect()
+	}
+}
+
+/// Two sets of keys that don't share any, for two pieces at once from one keyboard: WASD and the keys around them
+/// on the left, the arrows and the keys beside them on the right. The left set gets the gamepad too, and hold, there
+/// being no key to spare for it on the right.
+pub fn split_bindings() -> [Vec<(Control, Command)>; 2] {
+	let left = [
+		(Key::A, Command::MoveLeft),
+		(Key::D, Command::MoveRight),
+		(Key::W, Command::RotateClockwise),
+		(Key::E, Command::RotateClockwise),
+		(Key::Q, Command::RotateCounterClockwise),
+		(Key::S, Command::SoftDrop),
+		(Key::Space, Command::HardDrop),
+		(Key::LeftShift, Command::Hold),
+	];
+	let right = [
+		(Key::Left, Command::MoveLeft),
+		(Key::Right, Command::MoveRight),
+		(Key::Up, Command::RotateClockwise),
+		(Key::RightControl, Command::RotateCounterClockwise),
+		(Key::Down, Command::SoftDrop),
+		(Key::RightShift, Command::HardDrop),
+	];
+	let gamepad = Profile::Classic.bindings().into_iter().filter(|(control, _command)| matches!(control, Control::Gamepad(_)));
+	[
+		left.into_iter().map(|(key, command)| (Control::Key(key), command)).chain(gamepad).collect(),
+		right.into_iter().map(|(key, command)| (Control::Key(key), command)).collect(),
+	]
+}
+
+/// `bindings` with the keys for `command` swapped for `keys`; other controls for it, like gamepad buttons, stay.
+pub fn rebind_keys(bindings: &mut Vec<(Control, Command)>, command: Command, keys: &[Key]) {
+	bindings.retain(|&(control, c)| c != command || !matches!(control, Control::Key(_)));
+	bindings.extend(keys.iter().map(|&key| (Control::Key(key), command)));
+}
+
+/// Where the mouse is and what it clicked this frame, for playing with the mouse alone.
+#[derive(Clone,Copy,Debug,Default)]
+pub struct Pointer {
+	/// Board column under the cursor; None when it is off the board.
+	pub column: Option<i32>,
+	pub left_clicked: bool,
+	pub right_clicked: bool,
+}
+
+/// Steps the piece one column toward the cursor, turns it on a left click, and drops it on a right click.
+pub fn pointer_actions(pointer: &Pointer, game_state: &tetris::GameState) -> Vec<tetris::Action> {
+	let mut actions = Vec::new();
+	if pointer.left_clicked {
+		actions.push(tetris::Action::RotateClockwise);
+	}
+	let piece_column = game_state.current_piece.as_ref().map(|_p| game_state.current_piece_mass_xy.0);
+	if let (Some(column), Some(piece_column)) = (pointer.column, piece_column) {
+		match column.cmp(&piece_column) {
+			std::cmp::Ordering::Less => actions.push(tetris::Action::MoveLeft),
+			std::cmp::Ordering::Greater => actions.push(tetris::Action::MoveRight),
+			std::cmp::Ordering::Equal => {}
+		}
+	}
+	if pointer.right_clicked {
+		actions.push(tetris::Action::HardDrop);
+	}
+	actions
+}
+
+/// Only one of these goes through per tick, the first in this order.
+const EXCLUSIVE: [(Command, tetris::Action); 4] = [
+	(Command::RotateCounterClockwise, tetris::Action::RotateCounterClockwise),
+	(Command::RotateClockwise, tetris::Action::RotateClockwise),
+	(Command::MoveLeft, tetris::Action::MoveLeft),
+	(Command::MoveRight, tetris::Action::MoveRight),
+];
+
+/// How a held move repeats, in ticks. With das at 0, moves only happen on presses.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct Handling {
+	/// Delayed auto shift: how long a move is held before it starts repeating.
+	pub das: u32,
+	/// Auto repeat rate: time between repeats once they start; 0 repeats every tick.
+	pub arr: u32,
+	/// DAS cut delay: how long repeats hold off after a rotation, hold, or hard drop, so a charged move doesn't
+	/// drag the next piece along before the player means it to.
+	pub das_cut: u32,
+}
+
+/// Remembers what was held last time, so presses and releases stand out.
+#[derive(Clone,Debug)]
+pub struct Input {
+	bindings: Vec<(Control, Command)>,
+	handling: Handling,
+	held: BTreeSet<Control>,
+	/// How long the move in the lead has been held, in ticks.
+	move_held_ticks: u32,
+	/// The move in the lead; DAS starts over whenever it changes.
+	charging: Option<tetris::Action>,
+	/// Ticks left of Handling::das_cut.
+	cut_ticks_left: u32,
+}
+
+impl Input {
+	pub fn new(bindings: Vec<(Control, Command)>, handling: Handling) -> Input {
+		Self { bindings, handling, held: BTreeSet::new(), move_held_ticks: 0, charging: None, cut_ticks_left: 0 }
+	}
+
+	pub fn is_bound(&self, control: Control) -> bool {
+		self.bindings.iter().any(|&(c, _command)| c == control)
+	}
+
+	/// Call once per tick with everything held right now; returns what to apply this tick.
+	pub fn actions(&mut self, held: &BTreeSet<Control>) -> Vec<tetris::Action> {
+		let pressed: Vec<Command> = self.commands(held.difference(&self.held));
+		let released: Vec<Command> = self.commands(self.held.difference(held));
+		let still_held: Vec<Command> = self.commands(held.intersection(&self.held));
+		self.held = held.clone();
+		self.cut_ticks_left = self.cut_ticks_left.saturating_sub(1);
+		let mut actions = Vec::new();
+		if pressed.contains(&Command::SoftDrop) {
+			actions.push(tetris::Action::SoftDropStart);
+		} else if released.contains(&Command::SoftDrop) && !still_held.contains(&Command::SoftDrop) {
+			actions.push(tetris::Action::SoftDropStop);
+		}
+		match EXCLUSIVE.iter().find(|(command, _action)| pressed.contains(command)) {
+			Some(&(command, action)) => {
+				actions.push(action);
+				if matches!(command, Command::MoveLeft | Command::MoveRight) {
+					self.move_held_ticks = 0;
+					self.charging = Some(action);
+				} else {
+					self.cut_ticks_left = self.handling.das_cut;
+				}
+			}
+			None => {
+				if let Some(action) = self.repeated_move(&still_held) {
+					actions.push(action);
+				}
+			}
+		}
+		if pressed.contains(&Command::Hold) {
+			actions.push(tetris::Action::Hold);
+		}
+		if pressed.contains(&Command::HardDrop) {
+			actions.push(tetris::Action::HardDrop);
+		} else if pressed.contains(&Command::SonicDrop) {
+			actions.push(tetris::Action::SonicDrop);
+		}
+		if pressed.contains(&Command::Hold) || pressed.contains(&Command::HardDrop) {
+			self.cut_ticks_left = self.handling.das_cut;
+		}
+		actions
+	}
+
+	/// The held move, once it has charged and outside the DAS cut; both directions at once cancel out.
+	fn repeated_move(&mut self, still_held: &[Command]) -> Option<tetris::Action> {
+		let action = match (still_held.contains(&Command::MoveLeft), still_held.contains(&Command::MoveRight)) {
+			(true, false) => tetris::Action::MoveLeft,
+			(false, true) => tetris::Action::MoveRight,
+			_ => {
+				self.charging = None;
+				return None;
+			}
+		};
+		if self.charging != Some(action) {
+			self.charging = Some(action);
+			self.move_held_ticks = 0;
+		}
+		if self.handling.das == 0 {
+			return None;
+		}
+		self.move_held_ticks = self.move_held_ticks.saturating_add(1);
+		if self.cut_ticks_left > 0 {
+			return None;
+		}
+		let past_das = self.move_held_ticks.checked_sub(self.handling.das)?;
+		(past_das % self.handling.arr.max(1) == 0).then_some(action)
+	}
+
+	/// Everything bound to any of `controls`.
+	fn commands<'a>(&self, controls: impl Iterator<Item = &'a Control>) -> Vec<Command> {
+		let controls: Vec<&Control> = controls.collect();
+		self.bindings.iter()
+			.filter(|(control, _command)| controls.contains(&control))
+			.map(|&(_control, command)| command)
+			.collect()
+	}
+}
+
+/// Every press and release with when it happened, for looking into input latency and how fast keys repeat;
+/// not a replay, which only has the Actions that came of them.
+#[derive(Clone,Debug,Default)]
+pub struct InputLog {
+	lines: Vec<String>,
+	held: BTreeSet<Control>,
+	/// Seconds at the first observe(); times are from here.
+	started_at_s: Option<f64>,
+}
+
+impl InputLog {
+	pub fn new() -> InputLog {
+		Self::default()
+	}
+
+	/// Starts over, e.g. for a new game.
+	pub fn clear(&mut self) {
+		*self = Self::default();
+	}
+
+	/// Call whenever input is polled, with the wall clock in seconds and the game's tick.
+	pub fn observe(&mut self, now_s: f64, tick: u64, held: &BTreeSet<Control>) {
+		let started_at_s = *self.started_at_s.get_or_insert(now_s);
+		let seconds = now_s - started_at_s;
+		for control in held.difference(&self.held) {
+			self.lines.push(format!("{seconds:.4}\t{tick}\t{control:?}\tdown"));
+		}
+		for control in self.held.difference(held) {
+			self.lines.push(format!("{seconds:.4}\t{tick}\t{control:?}\tup"));
+		}
+		self.held = held.clone();
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.lines.is_empty()
+	}
+
+	/// Tab-separated: seconds since the first observe(), tick, control, and `down` or `up`; one line each.
+	pub fn to_text(&self) -> String {
+		let mut text = "# seconds\ttick\tcontrol\tstate\n".to_string();
+		for line in self.lines.iter() {
+			text += line;
+			text.push('\n');
+		}
+		text
+	}
+}
@@ -0,0 +1,112 @@
+//! Where things go on screen, in pixels: the board on the left, a sidebar of panels on its right.
+//! Just arithmetic, so frontends other than the macroquad one can share it.
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Rect {
+	pub x: f32,
+	pub y: f32,
+	pub w: f32,
+	pub h: f32,
+}
+
+impl Rect {
+	pub fn new(x: f32, y: f32, w: f32, h: f32) -> Rect {
+		Self { x, y, w, h }
+	}
+
+	pub fn bottom(&self) -> f32 {
+		self.y + self.h
+	}
+
+	pub fn right(&self) -> f32 {
+		self.x + self.w
+	}
+}
+
+#[derive(Clone,Debug)]
+pub struct Layout {
+	/// The whole window; wider than the board by the sidebar.
+	pub window: Rect,
+	pub board: Rect,
+	pub sidebar: Rect,
+	/// Each a label line with the value on the line below.
+	pub score: Rect,
+	pub level: Rect,
+	pub lines: Rect,
+	pub time: Rect,
+	/// Combo and back-to-back counters, one line each.
+	pub streaks: Rect,
+	/// Just the box the held piece goes in; its label sits label_px above. None where hold isn't allowed.
+	pub hold: Option<Rect>,
+	/// Ditto the next queue, soonest first; as many boxes as fit between the hold box, if any, and the seed.
+	pub next: Vec<Rect>,
+	/// A line at the foot of the sidebar for the game's seed.
+	pub seed: Rect,
+	/// Height of a line of label text.
+	pub label_px: f32,
+	/// Cell size in the hold and next boxes.
+	pub preview_cell_px: f32,
+	/// The other player's board in versus, at half scale, right of the sidebar; see with_opponent().
+	pub opponent: Option<Rect>,
+	/// Versus chat, under the opponent's board.
+	pub chat: Option<Rect>,
+	/// Between the two, how high the opponent's stack is; see with_opponent_gauge().
+	pub opponent_gauge: Option<Rect>,
+}
+
+impl Layout {
+	/// HARDCODE Sidebar width, in board cells.
+	const SIDEBAR_CELLS: f32 = 5.0;
+	/// HARDCODE Preview boxes fit pieces up to this many cells across.
+	const PREVIEW_CELLS: f32 = 5.0;
+
+	pub fn new(width_cells: usize, height_cells: usize, cell_px: f32, previews: usize, has_hold: bool) -> Layout {
+		let board = Rect::new(0.0, 0.0, width_cells as f32 * cell_px, height_cells as f32 * cell_px);
+		let sidebar = Rect::new(board.right(), 0.0, cell_px * Self::SIDEBAR_CELLS, board.h);
+		let window = Rect::new(0.0, 0.0, sidebar.right(), board.h);
+		let padding = cell_px / 4.0;
+		let label_px = cell_px * 0.75;
+		let preview_cell_px = cell_px / 2.0;
+		let (x, w) = (sidebar.x + padding, sidebar.w - 2.0 * padding);
+		let mut y = padding;
+		let mut stat = || {
+			let r = Rect::new(x, y, w, 2.0 * label_px);
+			y += r.h + padding;
+			r
+		};
+		let (score, level, lines, time, streaks) = (stat(), stat(), stat(), stat(), stat());
+		let box_px = preview_cell_px * Self::PREVIEW_CELLS;
+		let mut y = streaks.bottom() + padding + label_px;
+		let hold = has_hold.then(|| Rect::new(x, y, w, box_px));
+		if let Some(hold) = hold {
+			y = hold.bottom() + padding + label_px;
+		}
+		let seed = Rect::new(x, window.bottom() - padding - label_px * 0.6, w, label_px * 0.6);
+		let mut next = Vec::new();
+		while next.len() < previews && y + box_px <= seed.y {
+			next.push(Rect::new(x, y, w, box_px));
+			y += box_px;
+		}
+		Self { window, board, sidebar, score, level, lines, time, streaks, hold, next, seed, label_px, preview_cell_px, opponent: None, chat: None, opponent_gauge: None }
+	}
+
+	/// Widens the window for a half-size copy of the board, with room for a name above it and chat below.
+	pub fn with_opponent(&self) -> Layout {
+		let padding = self.preview_cell_px / 2.0;
+		let opponent = Rect::new(self.sidebar.right() + padding, self.label_px + padding, self.board.w / 2.0, self.board.h / 2.0);
+		let window = Rect::new(0.0, 0.0, opponent.right() + padding, self.window.h);
+		let chat_y = opponent.bottom() + padding;
+		let chat = Rect::new(opponent.x, chat_y, opponent.w, window.bottom() - chat_y - padding);
+		Self { window, opponent: Some(opponent), chat: Some(chat), ..self.clone() }
+	}
+
+	/// Takes the top of the chat for a strip about the opponent's stack, which is hard to read at half size.
+	pub fn with_opponent_gauge(&self) -> Layout {
+		let (Some(opponent), Some(chat)) = (self.opponent, self.chat)
+			else { return self.clone(); };
+		let padding = self.preview_cell_px / 2.0;
+		let gauge = Rect::new(chat.x, chat.y, chat.w, (opponent.h / 4.0).min(chat.h / 2.0));
+		let chat = Rect::new(chat.x, gauge.bottom() + padding, chat.w, chat.bottom() - gauge.bottom() - padding);
+		Self { chat: Some(chat), opponent_gauge: Some(gauge), ..self.clone() }
+	}
+}
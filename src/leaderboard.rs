@@ -0,0 +1,119 @@
+//! Optional online leaderboard.
+//!
+//! Protocol, relative to the configured endpoint:
+//! + `POST scores` with form fields `name`, `mode`, `seed`, `score`, and `replay` (a hash of the run)
+//! + `GET top?mode=...` answers with one `score<TAB>name` line per entry, best first
+//!
+//! Every failure just marks the board offline; scores that failed to send are retried with the next refresh.
+
+use crate::http;
+use crate::log;
+
+#[derive(Clone,Debug)]
+pub struct Entry {
+	pub name: String,
+	pub score: u32,
+}
+
+#[derive(Clone,Debug)]
+pub struct Submission {
+	pub name: String,
+	/// E.g. `marathon` or `daily-20000`.
+	pub mode: String,
+	pub seed: u64,
+	pub score: u32,
+	pub replay_hash: u64,
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Status {
+	Idle,
+	Loading,
+	Offline,
+}
+
+#[derive(Debug)]
+pub struct LeaderboardClient {
+	endpoint: String,
+	pub status: Status,
+	/// Best first; whatever the last successful fetch returned.
+	pub top: Vec<Entry>,
+	pending_top: Option<http::PendingResponse>,
+	pending_submissions: Vec<(Submission, http::PendingResponse)>,
+	/// Failed to send; retried by refresh().
+	outbox: Vec<Submission>,
+}
+
+impl LeaderboardClient {
+	pub fn new(endpoint: &str) -> LeaderboardClient {
+		Self {
+			endpoint: endpoint.trim_end_matches('/').to_string(),
+			status: Status::Idle,
+			top: Vec::new(),
+			pending_top: None,
+			pending_submissions: Vec::new(),
+			outbox: Vec::new(),
+		}
+	}
+
+	/// Fetch the top entries for `mode`, and retry any failed submissions.
+	pub fn refresh(&mut self, mode: &str) {
+		for submission in std::mem::take(&mut self.outbox) {
+			self.submit(submission);
+		}
+		let url = format!("{}/top?mode={}", self.endpoint, http::percent_encode(mode));
+		self.pending_top = Some(http::get(&url));
+		self.status = Status::Loading;
+	}
+
+	pub fn submit(&mut self, submission: Submission) {
+		let body = http::form_encode(&[
+			("name", &submission.name),
+			("mode", &submission.mode),
+			("seed", &submission.seed.to_string()),
+			("score", &submission.score.to_string()),
+			("replay", &format!("{:016x}", submission.replay_hash)),
+		]);
+		let pending = http::post(&format!("{}/scores", self.endpoint), &body);
+		self.pending_submissions.push((submission, pending));
+	}
+
+	/// Call once per frame.
+	pub fn poll(&mut self) {
+		if let Some(result) = self.pending_top.as_mut().and_then(http::PendingResponse::poll) {
+			self.pending_top = None;
+			match result {
+				Ok(body) => {
+					self.top = parse_top(&body);
+					self.status = Status::Idle;
+				}
+				Err(e) => {
+					log::warn!("Leaderboard unavailable: {e}");
+					self.status = Status::Offline;
+				}
+			}
+		}
+		let mut still_pending = Vec::new();
+		for (submission, mut pending) in self.pending_submissions.drain(..) {
+			match pending.poll() {
+				None => still_pending.push((submission, pending)),
+				Some(Ok(_)) => {}
+				Some(Err(e)) => {
+					log::warn!("Could not submit score: {e}");
+					self.status = Status::Offline;
+					self.outbox.push(submission);
+				}
+			}
+		}
+		self.pending_submissions = still_pending;
+	}
+}
+
+fn parse_top(body: &str) -> Vec<Entry> {
+	body.lines()
+		.filter_map(|line| {
+			let (score, name) = line.split_once('\t')?;
+			Some(Entry { name: name.to_string(), score: score.trim().parse().ok()? })
+		})
+		.collect()
+}
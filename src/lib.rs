@@ -0,0 +1,41 @@
+pub mod achievements;
+pub mod analysis;
+pub mod capture;
+pub mod bot;
+pub mod bridge;
+pub mod campaign;
+pub mod cheese;
+pub mod config;
+pub mod coop;
+pub mod difficulty;
+pub mod editor;
+pub mod env;
+pub mod finesse;
+pub mod frontend;
+pub mod garbage;
+pub mod headless;
+pub mod http;
+pub mod input;
+pub mod layout;
+pub mod leaderboard;
+pub mod log;
+pub mod netplay;
+pub mod notation;
+pub mod overlay;
+pub mod practice;
+pub mod profile;
+pub mod puzzle;
+pub mod records;
+pub mod replay;
+pub mod rng;
+pub mod ruleset;
+pub mod script;
+pub mod simulation;
+pub mod speed;
+pub mod storage;
+pub mod sync;
+pub mod tetris;
+pub mod theme;
+pub mod training;
+pub mod tween;
+pub mod websocket;
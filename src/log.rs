@@ -0,0 +1,112 @@
+//! Leveled logging to the console and, natively, a file; like the `log` crate's macros, minus the dependency.
+//!
+//! ```text
+//! [  12.345 INFO  tetris_clone_rust::leaderboard] Leaderboard unavailable: connection refused
+//! ```
+//!
+//! Configured by `log_level` and `log_file` in config.txt; see config::Config.
+
+use std::io::Write;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Level {
+	Error,
+	Warn,
+	Info,
+	Debug,
+	Trace,
+}
+
+impl Level {
+	pub fn parse(text: &str) -> Option<Level> {
+		match text.to_ascii_lowercase().as_str() {
+			"error" => Some(Level::Error),
+			"warn" => Some(Level::Warn),
+			"info" => Some(Level::Info),
+			"debug" => Some(Level::Debug),
+			"trace" => Some(Level::Trace),
+			_ => None,
+		}
+	}
+
+	fn label(&self) -> &'static str {
+		match self {
+			Level::Error => "ERROR",
+			Level::Warn => "WARN",
+			Level::Info => "INFO",
+			Level::Debug => "DEBUG",
+			Level::Trace => "TRACE",
+		}
+	}
+}
+
+struct Logger {
+	/// Anything less severe is dropped.
+	level: Level,
+	file: Option<Box<dyn Write + Send>>,
+}
+
+static LOGGER: std::sync::Mutex<Logger> = std::sync::Mutex::new(Logger { level: Level::Info, file: None });
+static STARTED: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+
+/// Until this is called, Info and worse go to the console only.
+/// `file_name` is relative to the storage directory and starts over each run.
+pub fn init(level: Level, file_name: Option<&str>) {
+	STARTED.get_or_init(std::time::Instant::now);
+	let file = file_name.and_then(|name| match crate::storage::create(name) {
+		Ok(f) => Some(Box::new(f) as Box<dyn Write + Send>),
+		Err(e) => {
+			log(Level::Warn, module_path!(), format_args!("Could not open log file {name}: {e}"));
+			None
+		}
+	});
+	let mut logger = LOGGER.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+	logger.level = level;
+	logger.file = file;
+}
+
+pub fn enabled(level: Level) -> bool {
+	level <= LOGGER.lock().unwrap_or_else(std::sync::PoisonError::into_inner).level
+}
+
+/// Use the macros instead; they fill in the module.
+pub fn log(level: Level, module: &str, args: std::fmt::Arguments) {
+	let mut logger = LOGGER.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+	if level > logger.level {
+		return;
+	}
+	let seconds = STARTED.get_or_init(std::time::Instant::now).elapsed().as_secs_f64();
+	let line = format!("[{seconds:8.3} {:<5} {module}] {args}", level.label());
+	eprintln!("{line}");
+	if let Some(file) = logger.file.as_mut() {
+		// Nowhere left to report a failure to.
+		let _ = writeln!(file, "{line}").and_then(|()| file.flush());
+	}
+}
+
+#[macro_export]
+macro_rules! error {
+	($($arg:tt)*) => { $crate::log::log($crate::log::Level::Error, module_path!(), format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! warn {
+	($($arg:tt)*) => { $crate::log::log($crate::log::Level::Warn, module_path!(), format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! info {
+	($($arg:tt)*) => { $crate::log::log($crate::log::Level::Info, module_path!(), format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! debug {
+	($($arg:tt)*) => { $crate::log::log($crate::log::Level::Debug, module_path!(), format_args!($($arg)*)) };
+}
+
+#[macro_export]
+macro_rules! trace {
+	($($arg:tt)*) => { $crate::log::log($crate::log::Level::Trace, module_path!(), format_args!($($arg)*)) };
+}
+
+pub use crate::{debug, error, info, trace, warn};
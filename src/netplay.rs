@@ -0,0 +1,1094 @@
+//! Versus over the network. Each side plays its own GameState and streams everything that went into each tick,
+//! so the other side can run an exact copy alongside; lines cleared turn into garbage for the other side.
+//!
+//! Messages are lines of text over a WebSocket. Clients never talk directly: a relay forwards each message
+//! to everybody else connected to the same path, which is the room code; see Lobby.
+
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+
+use crate::difficulty;
+use crate::garbage;
+use crate::log;
+use crate::notation;
+use crate::replay;
+use crate::rng;
+use crate::ruleset;
+use crate::storage;
+use crate::tetris;
+
+/// Everybody in a room must speak the same one; see Message::Hello and Message::Watch.
+pub const PROTOCOL_VERSION: u32 = 7;
+
+/// What went into one tick of a game: actions, then garbage rows by hole column, then GameState::tick();
+/// see play_tick().
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct TickInputs {
+	pub tick: u64,
+	pub actions: Vec<tetris::Action>,
+	pub garbage_holes: Vec<usize>,
+}
+
+impl TickInputs {
+	/// E.g. `120 Lw 3,3`, with `separator` between the fields. Action letters are as in replays;
+	/// `-` stands for no actions or no garbage.
+	fn to_text(&self, separator: char) -> String {
+		let actions: String = self.actions.iter().map(|&a| replay::action_code(a)).collect();
+		let holes: Vec<String> = self.garbage_holes.iter().map(usize::to_string).collect();
+		let or_dash = |text: String| if text.is_empty() { "-".to_string() } else { text };
+		format!("{}{separator}{}{separator}{}", self.tick, or_dash(actions), or_dash(holes.join(",")))
+	}
+
+	fn parse(text: &str, separator: char) -> Result<TickInputs, String> {
+		let [tick, actions, holes] = text.split(separator).collect::<Vec<_>>()[..]
+			else { return Err(format!("`{text}`: expected a tick, actions, and garbage")); };
+		let actions = match actions {
+			"-" => Vec::new(),
+			_ => actions.chars()
+				.map(|c| replay::action_from_code(&c.to_string()).ok_or_else(|| format!("`{text}`: unknown action `{c}`")))
+				.collect::<Result<_, _>>()?,
+		};
+		let garbage_holes = match holes {
+			"-" => Vec::new(),
+			_ => holes.split(',').map(|h| h.parse().map_err(|e| format!("`{text}`: {e}"))).collect::<Result<_, _>>()?,
+		};
+		let tick = tick.parse().map_err(|e| format!("`{text}`: {e}"))?;
+		Ok(TickInputs { tick, actions, garbage_holes })
+	}
+
+	fn is_empty(&self) -> bool {
+		self.actions.is_empty() && self.garbage_holes.is_empty()
+	}
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum Message {
+	/// First thing each player sends. The seed both games use comes from both nonces, so neither side picks it alone;
+	/// each plays on their own difficulty. The set is as long as the longer of the two `best_of`.
+	Hello { version: u32, nonce: u64, difficulty: difficulty::Preset, best_of: u32, name: String },
+	/// Ready to start, or not any more; see Lobby.
+	Ready { is_ready: bool },
+	/// One tick of the sender's game. Seats tell the players apart for spectators: 0 for the lower nonce, 1 for the higher.
+	Tick { seat: u8, inputs: TickInputs },
+	/// Rows of garbage for the receiver.
+	Attack { rows: u32 },
+	/// The sender's game as of `tick`: roll_checksum() over every tick so far, and the low 16 bits of
+	/// GameState::checksum() after each tick since the last Check, oldest first; see Versus::after_tick().
+	Check { seat: u8, tick: u64, rolling: u64, recent: Vec<u16> },
+	/// The receiver's game, as the sender copies it, stopped matching at `diverged_at`; found at the Check for `checked_at`.
+	Desync { checked_at: u64, diverged_at: u64 },
+	/// From a spectator, who would like a Snapshot from each player; see Spectator.
+	Watch { version: u32 },
+	/// The sender's game so far, for spectators: its seed and difficulty, and every tick with something in it,
+	/// up to but not including `ticks`.
+	Snapshot { seat: u8, seed: u64, difficulty: difficulty::Preset, ticks: u64, history: Vec<TickInputs>, name: String },
+	/// Something to say, from the player called `name`; see chat_text().
+	Chat { name: String, text: String },
+	/// Ready for the next game of the set; its seed comes from both sides' nonces, as the first game's does.
+	Next { nonce: u64 },
+	/// Leaving; the connection closes next.
+	Bye,
+}
+
+impl Message {
+	/// One line, no newline:
+	///
+	/// ```text
+	/// hello 6 1234 standard 3 Alice
+	/// ready 1
+	/// tick 0 120 Lw 3,3
+	/// attack 2
+	/// check 0 120 00c0ffee00c0ffee 1a2b,3c4d
+	/// desync 120 64
+	/// watch 2
+	/// snapshot 0 5678 expert 300 12:L:-;120:Lw:3,3 Alice
+	/// chat Alice\tgood luck
+	/// next 9012
+	/// bye
+	/// ```
+	pub fn to_line(&self) -> String {
+		match self {
+			Message::Hello { version, nonce, difficulty, best_of, name } => format!("hello {version} {nonce} {} {best_of} {name}", difficulty.name()),
+			Message::Ready { is_ready } => format!("ready {}", u8::from(*is_ready)),
+			Message::Tick { seat, inputs } => format!("tick {seat} {}", inputs.to_text(' ')),
+			Message::Attack { rows } => format!("attack {rows}"),
+			Message::Check { seat, tick, rolling, recent } => {
+				let recent: Vec<String> = recent.iter().map(|sum| format!("{sum:04x}")).collect();
+				let recent = if recent.is_empty() { "-".to_string() } else { recent.join(",") };
+				format!("check {seat} {tick} {rolling:016x} {recent}")
+			}
+			Message::Desync { checked_at, diverged_at } => format!("desync {checked_at} {diverged_at}"),
+			Message::Watch { version } => format!("watch {version}"),
+			Message::Snapshot { seat, seed, difficulty, ticks, history, name } => {
+				let history: Vec<String> = history.iter().map(|inputs| inputs.to_text(':')).collect();
+				let history = if history.is_empty() { "-".to_string() } else { history.join(";") };
+				format!("snapshot {seat} {seed} {} {ticks} {history} {name}", difficulty.name())
+			}
+			Message::Chat { name, text } => format!("chat {}\t{}", chat_text(name), chat_text(text)),
+			Message::Next { nonce } => format!("next {nonce}"),
+			Message::Bye => "bye".to_string(),
+		}
+	}
+
+	pub fn parse(line: &str) -> Result<Message, String> {
+		let mut words = line.trim().splitn(2, ' ');
+		let tag = words.next().unwrap_or_default();
+		let rest = words.next().unwrap_or_default();
+		let number = |text: &str| text.parse::<u64>().map_err(|e| format!("`{line}`: {e}"));
+		let difficulty = |text: &str| difficulty::Preset::parse(text).ok_or_else(|| format!("`{line}`: unknown difficulty `{text}`"));
+		match tag {
+			"hello" => {
+				let mut fields = rest.splitn(5, ' ');
+				let version = number(fields.next().unwrap_or_default())? as u32;
+				let nonce = number(fields.next().unwrap_or_default())?;
+				// Older versions had no difficulty or set length; they get turned away by version anyway.
+				if version != PROTOCOL_VERSION {
+					return Ok(Message::Hello { version, nonce, difficulty: difficulty::Preset::Standard, best_of: 1, name: String::new() });
+				}
+				let difficulty = difficulty(fields.next().unwrap_or_default())?;
+				let best_of = number(fields.next().unwrap_or_default())?.clamp(1, MAX_BEST_OF as u64) as u32;
+				let name = fields.next().unwrap_or_default().to_string();
+				Ok(Message::Hello { version, nonce, difficulty, best_of, name })
+			}
+			"ready" => Ok(Message::Ready { is_ready: number(rest)? != 0 }),
+			"tick" => {
+				let (seat, inputs) = rest.split_once(' ').unwrap_or_default();
+				Ok(Message::Tick { seat: number(seat)?.min(1) as u8, inputs: TickInputs::parse(inputs, ' ')? })
+			}
+			"attack" => Ok(Message::Attack { rows: number(rest)?.min(MAX_INCOMING_ROWS as u64) as u32 }),
+			"check" => {
+				let [seat, tick, rolling, recent] = rest.split(' ').collect::<Vec<_>>()[..]
+					else { return Err(format!("`{line}`: expected a seat, tick, and checksums")); };
+				let hex = |text: &str| u64::from_str_radix(text, 16).map_err(|e| format!("`{line}`: {e}"));
+				let recent = match recent {
+					"-" => Vec::new(),
+					_ => recent.split(',').map(|sum| hex(sum).map(|sum| sum as u16)).collect::<Result<_, _>>()?,
+				};
+				Ok(Message::Check { seat: number(seat)?.min(1) as u8, tick: number(tick)?, rolling: hex(rolling)?, recent })
+			}
+			"desync" => {
+				let (checked_at, diverged_at) = rest.split_once(' ').unwrap_or_default();
+				Ok(Message::Desync { checked_at: number(checked_at)?, diverged_at: number(diverged_at)? })
+			}
+			"watch" => Ok(Message::Watch { version: number(rest)? as u32 }),
+			"snapshot" => {
+				let mut fields = rest.splitn(6, ' ');
+				let mut field = || fields.next().unwrap_or_default();
+				let (seat, seed, preset, ticks, history, name) = (field(), field(), field(), field(), field(), field());
+				let history = match history {
+					"-" => Vec::new(),
+					_ => history.split(';').map(|inputs| TickInputs::parse(inputs, ':')).collect::<Result<_, _>>()?,
+				};
+				Ok(Message::Snapshot { seat: number(seat)?.min(1) as u8, seed: number(seed)?, difficulty: difficulty(preset)?, ticks: number(ticks)?, history, name: name.to_string() })
+			}
+			"chat" => {
+				let (name, text) = rest.split_once('\t').ok_or_else(|| format!("`{line}`: expected a name and text"))?;
+				Ok(Message::Chat { name: chat_text(name), text: chat_text(text) })
+			}
+			"next" => Ok(Message::Next { nonce: number(rest)? }),
+			"bye" => Ok(Message::Bye),
+			_ => Err(format!("Unknown message `{line}`")),
+		}
+	}
+}
+
+/// HARDCODE A line of chat, in characters; enough for a taunt, not an essay.
+pub const CHAT_MAX_CHARS: usize = 80;
+/// HARDCODE Lines of chat kept, and so shown.
+pub const CHAT_LINES: usize = 6;
+/// Sent by the number keys during a game, when there's no time to type.
+pub const EMOTES: [&str; 4] = ["Good luck!", "Nice!", "Oops", "Good game"];
+
+/// Fit to go through Message::Chat: one line, control characters turned to spaces, at most CHAT_MAX_CHARS.
+pub fn chat_text(text: &str) -> String {
+	text.chars().map(|c| if c.is_control() { ' ' } else { c }).take(CHAT_MAX_CHARS).collect::<String>().trim().to_string()
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct ChatLine {
+	pub name: String,
+	pub text: String,
+}
+
+/// The last CHAT_LINES said in a room, oldest first; ours too.
+#[derive(Clone,Debug,Default)]
+pub struct Chat {
+	pub lines: VecDeque<ChatLine>,
+}
+
+impl Chat {
+	pub fn push(&mut self, name: String, text: String) {
+		if self.lines.len() >= CHAT_LINES {
+			self.lines.pop_front();
+		}
+		self.lines.push_back(ChatLine { name, text });
+	}
+
+	/// Sends `text` as `name` and keeps it; nothing happens if it's blank.
+	fn say(&mut self, connection: &mut Connection, name: &str, text: &str) {
+		let text = chat_text(text);
+		if text.is_empty() {
+			return;
+		}
+		connection.send(&Message::Chat { name: name.to_string(), text: text.clone() });
+		self.push(name.to_string(), text);
+	}
+}
+
+/// The same steps in the same order on both sides, or the copies drift apart.
+pub fn play_tick(game_state: &mut tetris::GameState, actions: &[tetris::Action], garbage_holes: &[usize]) {
+	for &action in actions {
+		game_state.apply(action);
+	}
+	add_garbage(game_state, garbage_holes);
+	game_state.tick();
+}
+
+/// A row per hole; the frontend calls this itself between applying actions and ticking, like play_tick() does.
+pub fn add_garbage(game_state: &mut tetris::GameState, garbage_holes: &[usize]) {
+	if !garbage_holes.is_empty() {
+		game_state.add_garbage(garbage_holes.len(), &mut garbage::Repeating::new(garbage_holes.to_vec()));
+	}
+}
+
+/// Ticks between Checks; a desync shows up within about this many.
+pub const CHECK_EVERY_TICKS: u64 = 60; // HARDCODE
+
+/// Folds the game as it stands into a checksum of every tick before, so a difference anywhere along the way stays visible.
+pub fn roll_checksum(rolling: u64, game_state: &tetris::GameState) -> u64 {
+	// FNV-1a's prime, like GameState::checksum().
+	(rolling ^ game_state.checksum()).wrapping_mul(0x100000001b3)
+}
+
+/// Whether `a` lasted longer than `b`, once it's known: both games are over, or the one still going has passed the
+/// tick the other topped out on. Players and spectators both go by this, so they agree on who won.
+fn lasted_longer(a: &tetris::GameState, b: &tetris::GameState) -> Option<Ordering> {
+	match (a.is_alive, b.is_alive) {
+		(true, false) if a.ticks >= b.ticks => Some(Ordering::Greater),
+		(false, true) if b.ticks >= a.ticks => Some(Ordering::Less),
+		(false, false) => Some(a.ticks.cmp(&b.ticks)),
+		_ => None,
+	}
+}
+
+/// HARDCODE Taller than any board; one attack, or all of them waiting at once, can't be more than this. Anything
+/// like this many tops out whoever gets it, so there's no sense in more.
+pub const MAX_INCOMING_ROWS: u32 = 64;
+
+/// HARDCODE Extra rows for the second clear in a row, the third, and so on; the last goes for any longer combo.
+const COMBO_BONUS: [u32; 11] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+
+/// Garbage rows sent for clearing `rows_cleared` at once, given GameState::combo and ::back_to_back as they stand
+/// right after the clear.
+///
+/// Singles send nothing, doubles one, triples two, and quads or better (see ruleset::DIFFICULT_CLEAR_ROWS) every row;
+/// combos add COMBO_BONUS on top, and a difficult clear straight after another sends half as much again.
+pub fn attack_rows(rows_cleared: u32, combo: u32, back_to_back: u32) -> u32 {
+	let base = match rows_cleared {
+		0 => return 0,
+		1 => 0,
+		2 => 1,
+		3 => 2,
+		n => n,
+	};
+	let base = if rows_cleared >= ruleset::DIFFICULT_CLEAR_ROWS && back_to_back > 1 { base * 3 / 2 } else { base };
+	let combo_bonus = COMBO_BONUS[(combo.saturating_sub(1) as usize).min(COMBO_BONUS.len() - 1)];
+	base + combo_bonus
+}
+
+/// Spends `attack` rows cancelling garbage that has yet to rise, oldest first; returns what is left to send.
+pub fn cancel_incoming(incoming: &mut VecDeque<u32>, mut attack: u32) -> u32 {
+	while attack > 0 {
+		let Some(front) = incoming.front_mut()
+			else { break; };
+		let cancelled = attack.min(*front);
+		*front -= cancelled;
+		attack -= cancelled;
+		if *front == 0 {
+			incoming.pop_front();
+		}
+	}
+	attack
+}
+
+/// A WebSocket carrying Messages. Sending before it opens is fine; messages wait.
+#[derive(Debug)]
+pub struct Connection {
+	#[cfg(not(target_family="wasm"))]
+	receiver: std::sync::mpsc::Receiver<Incoming>,
+	#[cfg(not(target_family="wasm"))]
+	writer: Option<std::sync::Arc<std::sync::Mutex<std::net::TcpStream>>>,
+	#[cfg(not(target_family="wasm"))]
+	waiting: Vec<String>,
+	#[cfg(target_family="wasm")]
+	id: u32,
+	/// Set once the connection fails or closes; nothing arrives after.
+	pub error: Option<String>,
+}
+
+#[cfg(not(target_family="wasm"))]
+#[derive(Debug)]
+enum Incoming {
+	Open(std::sync::Arc<std::sync::Mutex<std::net::TcpStream>>),
+	Line(String),
+	Closed(String),
+}
+
+#[cfg(not(target_family="wasm"))]
+impl Connection {
+	pub fn open(url: &str) -> Connection {
+		use crate::websocket;
+
+		let (sender, receiver) = std::sync::mpsc::channel();
+		let url = url.to_string();
+		std::thread::spawn(move || {
+			let connected = websocket::connect(&url)
+				.and_then(|reader| reader.try_clone().map(|writer| (reader, writer)).map_err(|e| e.to_string()));
+			let (mut reader, writer) = match connected {
+				Ok((reader, writer)) => (reader, std::sync::Arc::new(std::sync::Mutex::new(writer))),
+				Err(e) => {
+					let _ = sender.send(Incoming::Closed(e));
+					return;
+				}
+			};
+			let _ = sender.send(Incoming::Open(writer.clone()));
+			loop {
+				let incoming = match websocket::read_frame(&mut reader) {
+					Ok(websocket::Frame::Text(line)) => Incoming::Line(line),
+					Ok(websocket::Frame::Ping(_)) => {
+						let mut writer = writer.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+						let _ = websocket::write_frame(&mut *writer, &websocket::Frame::Pong, true);
+						continue;
+					}
+					Ok(websocket::Frame::Pong) => continue,
+					Ok(websocket::Frame::Close) => Incoming::Closed("Closed by the server".to_string()),
+					Err(e) => Incoming::Closed(e.to_string()),
+				};
+				let is_closed = matches!(incoming, Incoming::Closed(_));
+				// Nobody listening is fine.
+				if sender.send(incoming).is_err() || is_closed {
+					return;
+				}
+			}
+		});
+		Self { receiver, writer: None, waiting: Vec::new(), error: None }
+	}
+
+	pub fn send(&mut self, message: &Message) {
+		let line = message.to_line();
+		let Some(writer) = self.writer.as_ref()
+			else {
+				self.waiting.push(line);
+				return;
+			};
+		let mut stream = writer.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		if let Err(e) = crate::websocket::write_frame(&mut *stream, &crate::websocket::Frame::Text(line), true) {
+			self.error.get_or_insert(e.to_string());
+		}
+	}
+
+	/// Call until it yields None, once per frame.
+	pub fn poll(&mut self) -> Option<Message> {
+		loop {
+			match self.receiver.try_recv().ok()? {
+				Incoming::Open(writer) => {
+					self.writer = Some(writer);
+					for line in std::mem::take(&mut self.waiting) {
+						let mut stream = self.writer.as_ref()?.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+						if let Err(e) = crate::websocket::write_frame(&mut *stream, &crate::websocket::Frame::Text(line), true) {
+							self.error.get_or_insert(e.to_string());
+						}
+					}
+				}
+				Incoming::Line(line) => match Message::parse(&line) {
+					Ok(message) => return Some(message),
+					Err(e) => log::warn!("Ignoring netplay message: {e}"),
+				},
+				Incoming::Closed(e) => {
+					self.error.get_or_insert(e);
+				}
+			}
+		}
+	}
+
+	/// Connected, and not closed since.
+	pub fn is_open(&self) -> bool {
+		self.writer.is_some() && self.error.is_none()
+	}
+
+	pub fn close(&mut self) {
+		if let Some(writer) = self.writer.take() {
+			let mut stream = writer.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+			let _ = crate::websocket::write_frame(&mut *stream, &crate::websocket::Frame::Close, true);
+			let _ = stream.shutdown(std::net::Shutdown::Both);
+		}
+	}
+}
+
+// Matches www/index.html -> register_plugin
+#[cfg(target_family="wasm")]
+extern "C" {
+	fn ws_open(url: *const u8, url_len: usize) -> u32;
+	fn ws_send(id: u32, text: *const u8, text_len: usize);
+	/// Byte length of the next message, which waits for ws_read(); -1 if there is none yet, -2 once closed.
+	fn ws_poll(id: u32) -> i32;
+	fn ws_is_open(id: u32) -> u32;
+	fn ws_read(id: u32, buffer: *mut u8);
+	fn ws_close(id: u32);
+}
+
+#[cfg(target_family="wasm")]
+impl Connection {
+	pub fn open(url: &str) -> Connection {
+		let id = unsafe { ws_open(url.as_ptr(), url.len()) }; // SAFETY: the JS side copies the buffer before returning
+		Self { id, error: None }
+	}
+
+	pub fn send(&mut self, message: &Message) {
+		let line = message.to_line();
+		unsafe { ws_send(self.id, line.as_ptr(), line.len()) }; // SAFETY: ditto
+	}
+
+	/// Call until it yields None, once per frame.
+	pub fn poll(&mut self) -> Option<Message> {
+		loop {
+			let len = unsafe { ws_poll(self.id) };
+			if len == -2 {
+				self.error.get_or_insert("Connection closed".to_string());
+				return None;
+			}
+			let len = usize::try_from(len).ok()?;
+			let line = unsafe { // SAFETY: buffer is exactly as long as the JS side reported
+				let mut buffer = vec![0_u8; len];
+				ws_read(self.id, buffer.as_mut_ptr());
+				String::from_utf8_lossy(&buffer).into_owned()
+			};
+			match Message::parse(&line) {
+				Ok(message) => return Some(message),
+				Err(e) => log::warn!("Ignoring netplay message: {e}"),
+			}
+		}
+	}
+
+	/// Connected, and not closed since.
+	pub fn is_open(&self) -> bool {
+		self.error.is_none() && unsafe { ws_is_open(self.id) } != 0
+	}
+
+	pub fn close(&mut self) {
+		unsafe { ws_close(self.id) };
+	}
+}
+
+/// Letters for room codes; none that read alike, like I and 1 or O and 0.
+const ROOM_CODE_LETTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ";
+/// HARDCODE
+pub const ROOM_CODE_LENGTH: usize = 4;
+/// From both sides being ready to the first tick.
+pub const COUNTDOWN_SECONDS: f64 = 3.0;
+/// HARDCODE The longest set either side can ask for.
+pub const MAX_BEST_OF: u32 = 9;
+
+/// A fresh room code, e.g. `KXQB`.
+pub fn new_room_code() -> String {
+	let mut rng = rng::RandomNumberGenerator::new(random_seed());
+	(0 .. ROOM_CODE_LENGTH).map(|_| *rng.choose(ROOM_CODE_LETTERS).unwrap_or(&b'A') as char).collect()
+}
+
+/// Where a room lives on the relay: its code as the last part of the path, so only its two players hear each other.
+pub fn room_url(server_url: &str, code: &str) -> String {
+	format!("{}/{}", server_url.trim_end_matches('/'), code.trim().to_ascii_uppercase())
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub enum LobbyStatus {
+	Connecting,
+	/// Connected; nobody else in the room yet.
+	Waiting,
+	/// Both in the room; waiting for both to be ready.
+	Ready,
+	/// Both ready; the game starts when it runs out, in seconds since the Unix epoch.
+	Countdown { until: f64 },
+	Failed(String),
+}
+
+/// A room two players meet in: one hosts and shares the code, the other joins with it, and once both are ready
+/// the same countdown runs on both sides.
+#[derive(Debug)]
+pub struct Lobby {
+	pub code: String,
+	/// None once poll() has handed it on to the game, or the lobby has been left.
+	connection: Option<Connection>,
+	nonce: u64,
+	name: String,
+	pub difficulty: difficulty::Preset,
+	/// Games in the set, as asked for.
+	best_of: u32,
+	/// Who else is in the room, and their nonce.
+	pub opponent: Option<(String, u64)>,
+	/// As of their last Hello.
+	pub opponent_difficulty: difficulty::Preset,
+	opponent_best_of: u32,
+	pub is_ready: bool,
+	pub opponent_is_ready: bool,
+	pub status: LobbyStatus,
+	/// Goes on into the game with the connection.
+	pub chat: Chat,
+}
+
+impl Lobby {
+	/// Opens a new room on the relay at `server_url`; share Lobby::code.
+	pub fn host(server_url: &str, name: &str, difficulty: difficulty::Preset, best_of: u32) -> Lobby {
+		Self::join(server_url, &new_room_code(), name, difficulty, best_of)
+	}
+
+	pub fn join(server_url: &str, code: &str, name: &str, difficulty: difficulty::Preset, best_of: u32) -> Lobby {
+		let code = code.trim().to_ascii_uppercase();
+		let mut connection = Connection::open(&room_url(server_url, &code));
+		let nonce = random_seed();
+		connection.send(&Message::Hello { version: PROTOCOL_VERSION, nonce, difficulty, best_of, name: name.to_string() });
+		Self {
+			code,
+			connection: Some(connection),
+			nonce,
+			name: name.to_string(),
+			difficulty,
+			best_of,
+			opponent: None,
+			opponent_difficulty: difficulty::Preset::Standard,
+			opponent_best_of: 1,
+			is_ready: false,
+			opponent_is_ready: false,
+			status: LobbyStatus::Connecting,
+			chat: Chat::default(),
+		}
+	}
+
+	pub fn set_ready(&mut self, is_ready: bool, now_s: f64) {
+		if matches!(self.status, LobbyStatus::Countdown { .. } | LobbyStatus::Failed(_)) || self.opponent.is_none() {
+			return;
+		}
+		self.is_ready = is_ready;
+		if let Some(connection) = self.connection.as_mut() {
+			connection.send(&Message::Ready { is_ready });
+		}
+		self.update_countdown(now_s);
+	}
+
+	pub fn say(&mut self, text: &str) {
+		if let Some(connection) = self.connection.as_mut() {
+			self.chat.say(connection, &self.name, text);
+		}
+	}
+
+	/// Call once per frame; yields the game once the countdown runs out, after which the lobby is spent.
+	pub fn poll(&mut self, now_s: f64) -> Option<Versus> {
+		let connection = self.connection.as_mut()?;
+		while let Some(message) = connection.poll() {
+			match message {
+				Message::Hello { version, .. } if version != PROTOCOL_VERSION => {
+					self.status = LobbyStatus::Failed(format!("The other side speaks netplay version {version}, not {PROTOCOL_VERSION}"));
+					connection.send(&Message::Bye);
+					connection.close();
+					self.connection = None;
+					return None;
+				}
+				// Say hello again, in case they joined after ours went out; the second time round, both already know.
+				Message::Hello { nonce, difficulty, best_of, name, .. } if self.opponent.is_none() => {
+					self.opponent = Some((name, nonce));
+					(self.opponent_difficulty, self.opponent_best_of) = (difficulty, best_of);
+					connection.send(&Message::Hello { version: PROTOCOL_VERSION, nonce: self.nonce, difficulty: self.difficulty, best_of: self.best_of, name: self.name.clone() });
+					// They missed any earlier word.
+					if self.is_ready {
+						connection.send(&Message::Ready { is_ready: true });
+					}
+				}
+				Message::Ready { is_ready } if self.opponent.is_some() => self.opponent_is_ready = is_ready,
+				Message::Chat { name, text } => self.chat.push(name, text),
+				Message::Bye if !matches!(self.status, LobbyStatus::Countdown { .. }) => {
+					self.opponent = None;
+					(self.is_ready, self.opponent_is_ready) = (false, false);
+				}
+				other => log::debug!("Ignoring {other:?} in the lobby"),
+			}
+		}
+		if let Some(e) = connection.error.clone() {
+			self.status = LobbyStatus::Failed(e);
+			self.connection = None;
+			return None;
+		}
+		self.update_countdown(now_s);
+		match self.status {
+			LobbyStatus::Countdown { until } if now_s >= until => {
+				let (name, nonce) = self.opponent.clone()?;
+				let seat = u8::from(self.nonce > nonce);
+				let mut versus = Versus::new(self.connection.take()?, self.name.clone(), seat, name, self.nonce ^ nonce);
+				(versus.difficulty, versus.opponent_difficulty) = (self.difficulty, self.opponent_difficulty);
+				versus.set = SetScore::new(self.set_length());
+				versus.chat = std::mem::take(&mut self.chat);
+				Some(versus)
+			}
+			_ => None,
+		}
+	}
+
+	/// Games in the set, once both sides have said: the longer of the two asked for.
+	pub fn set_length(&self) -> u32 {
+		if self.opponent.is_some() { self.best_of.max(self.opponent_best_of) } else { self.best_of }
+	}
+
+	fn update_countdown(&mut self, now_s: f64) {
+		self.status = match self.status {
+			LobbyStatus::Countdown { .. } | LobbyStatus::Failed(_) => return,
+			// Each side starts counting when it learns both are ready, so they are apart by the one-way latency.
+			_ if self.is_ready && self.opponent_is_ready => LobbyStatus::Countdown { until: now_s + COUNTDOWN_SECONDS },
+			_ if self.opponent.is_some() => LobbyStatus::Ready,
+			_ if self.connection.as_ref().is_some_and(Connection::is_open) => LobbyStatus::Waiting,
+			_ => LobbyStatus::Connecting,
+		};
+	}
+
+	pub fn leave(&mut self) {
+		if let Some(mut connection) = self.connection.take() {
+			connection.send(&Message::Bye);
+			connection.close();
+		}
+	}
+}
+
+fn random_seed() -> u64 {
+	let mut bytes = [0_u8; 8];
+	// The clock will do if the OS won't say.
+	if getrandom::getrandom(&mut bytes).is_err() {
+		return std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64);
+	}
+	u64::from_le_bytes(bytes)
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Outcome {
+	Won,
+	Lost,
+}
+
+/// Games won and lost so far in a best-of-N set, from our side.
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub struct SetScore {
+	pub best_of: u32,
+	/// Each game's outcome in order; None where both topped out on the same tick, which counts for neither.
+	pub games: Vec<Option<Outcome>>,
+}
+
+impl SetScore {
+	pub fn new(best_of: u32) -> SetScore {
+		Self { best_of: best_of.max(1), games: Vec::new() }
+	}
+
+	pub fn wins(&self) -> u32 {
+		self.games.iter().filter(|&&g| g == Some(Outcome::Won)).count() as u32
+	}
+
+	pub fn losses(&self) -> u32 {
+		self.games.iter().filter(|&&g| g == Some(Outcome::Lost)).count() as u32
+	}
+
+	/// Whoever has won more than half of the set's games, once somebody has.
+	pub fn winner(&self) -> Option<Outcome> {
+		let needed = self.best_of / 2 + 1;
+		match (self.wins() >= needed, self.losses() >= needed) {
+			(true, _) => Some(Outcome::Won),
+			(_, true) => Some(Outcome::Lost),
+			_ => None,
+		}
+	}
+}
+
+/// One game against somebody on the other end of `connection`.
+#[derive(Debug)]
+pub struct Versus {
+	pub connection: Connection,
+	/// Ours, for spectators.
+	name: String,
+	seat: u8,
+	pub opponent_name: String,
+	/// Both games play this seed.
+	pub seed: u64,
+	/// Ours and theirs, as each picked before joining.
+	pub difficulty: difficulty::Preset,
+	pub opponent_difficulty: difficulty::Preset,
+	/// A copy of the opponent's game, as far as their ticks have arrived.
+	pub opponent: tetris::GameState,
+	/// Garbage sent our way that has yet to rise, a batch per attack, oldest first.
+	pub incoming: VecDeque<u32>,
+	/// Holes of rows rising on the next tick; see take_rising().
+	rising: Vec<usize>,
+	/// Picks the holes; the other side learns them from Message::Tick, so this needn't match theirs.
+	rng: rng::RandomNumberGenerator,
+	/// They said goodbye, or the connection dropped.
+	pub opponent_left: bool,
+	/// Ticks of our game with anything in them, for spectators who join late.
+	history: Vec<TickInputs>,
+	ticks_sent: u64,
+	/// roll_checksum() over our game, and the short checksums since the last Check; see after_tick().
+	rolling: u64,
+	recent: Vec<u16>,
+	/// Ditto for the opponent's copy.
+	opponent_rolling: u64,
+	opponent_recent: Vec<u16>,
+	/// Our game as of the last few Checks, to dump if the other side says its copy went wrong there.
+	checkpoints: VecDeque<tetris::GameState>,
+	/// The first tick after which the two copies of either game stopped matching, once found.
+	pub desync_at: Option<u64>,
+	pub chat: Chat,
+	pub set: SetScore,
+	/// Whether this game's outcome has gone into `set`; see settle().
+	settled: bool,
+	/// Ours and theirs, once each side has asked for the next game of the set; see ask_next().
+	next_nonce: Option<u64>,
+	opponent_next_nonce: Option<u64>,
+}
+
+impl Versus {
+	pub fn new(connection: Connection, name: String, seat: u8, opponent_name: String, seed: u64) -> Versus {
+		let opponent_name = if opponent_name.trim().is_empty() { "opponent".to_string() } else { opponent_name };
+		Self {
+			connection,
+			name,
+			seat,
+			opponent_name,
+			seed,
+			difficulty: difficulty::Preset::Standard,
+			opponent_difficulty: difficulty::Preset::Standard,
+			opponent: tetris::GameState::new(1, 1, tetris::RandomizerStrategy::Memoryless, tetris::Timing::default()),
+			incoming: VecDeque::new(),
+			rising: Vec::new(),
+			rng: rng::RandomNumberGenerator::new(random_seed()),
+			opponent_left: false,
+			history: Vec::new(),
+			ticks_sent: 0,
+			rolling: 0,
+			recent: Vec::new(),
+			opponent_rolling: 0,
+			opponent_recent: Vec::new(),
+			checkpoints: VecDeque::new(),
+			desync_at: None,
+			chat: Chat::default(),
+			set: SetScore::new(1),
+			settled: false,
+			next_nonce: None,
+			opponent_next_nonce: None,
+		}
+	}
+
+	/// Call once `game_state` is reset and seeded with `seed`; the opponent starts out the same, but for their difficulty.
+	pub fn start(&mut self, game_state: &tetris::GameState) {
+		self.opponent = game_state.clone();
+		if self.opponent_difficulty != game_state.difficulty {
+			self.opponent.difficulty = self.opponent_difficulty;
+			self.opponent.reset();
+			self.opponent.reseed(self.seed);
+		}
+		self.incoming.clear();
+		self.rising.clear();
+		self.history.clear();
+		self.ticks_sent = 0;
+		(self.rolling, self.opponent_rolling) = (0, 0);
+		self.recent.clear();
+		self.opponent_recent.clear();
+		self.checkpoints.clear();
+		self.desync_at = None;
+		self.settled = false;
+	}
+
+	/// Call once per frame; catches the opponent's copy up with whatever has arrived.
+	pub fn poll(&mut self) {
+		while let Some(message) = self.connection.poll() {
+			match message {
+				Message::Tick { seat, inputs } if seat != self.seat => {
+					play_tick(&mut self.opponent, &inputs.actions, &inputs.garbage_holes);
+					self.opponent.drain_events().for_each(drop);
+					self.opponent_rolling = roll_checksum(self.opponent_rolling, &self.opponent);
+					self.opponent_recent.push(self.opponent.checksum() as u16);
+				}
+				Message::Check { seat, tick, rolling, recent } if seat != self.seat => self.check(tick, rolling, &recent),
+				Message::Desync { checked_at, diverged_at } => {
+					log::error!("Desync: {} says our game stopped matching their copy after tick {diverged_at}", self.opponent_name);
+					self.desync_at.get_or_insert(diverged_at);
+					match self.checkpoints.iter().find(|g| g.ticks == checked_at) {
+						Some(game_state) => dump_desync(&format!("seat-{}", self.seat), checked_at, diverged_at, game_state),
+						None => log::warn!("No checkpoint left for tick {checked_at} to dump"),
+					}
+				}
+				Message::Attack { rows } => {
+					let rows = rows.min(MAX_INCOMING_ROWS.saturating_sub(self.incoming_rows()));
+					if rows > 0 {
+						self.incoming.push_back(rows);
+					}
+				}
+				Message::Watch { version } if version == PROTOCOL_VERSION => {
+					let snapshot = Message::Snapshot { seat: self.seat, seed: self.seed, difficulty: self.difficulty, ticks: self.ticks_sent, history: self.history.clone(), name: self.name.clone() };
+					self.connection.send(&snapshot);
+				}
+				Message::Chat { name, text } => self.chat.push(name, text),
+				Message::Next { nonce } => {
+					self.opponent_next_nonce = Some(nonce);
+					// Their next game's ticks may follow; they're for after start().
+					if self.next_nonce.is_some() {
+						break;
+					}
+				}
+				// Only players say goodbye; spectators just go.
+				Message::Bye => self.opponent_left = true,
+				other => log::debug!("Ignoring {other:?} during versus"),
+			}
+		}
+		if self.connection.error.is_some() {
+			self.opponent_left = true;
+		}
+	}
+
+	/// Call for each event from our own game.
+	///
+	/// Clearing rows first cancels garbage on its way in, and only what is left over goes to the opponent;
+	/// garbage still queued rises after the next piece that locks without clearing anything, a well per batch.
+	pub fn observe(&mut self, event: &tetris::Event, game_state: &tetris::GameState) {
+		match *event {
+			tetris::Event::RowsCleared(n) => {
+				let attack = attack_rows(n, game_state.combo, game_state.back_to_back);
+				let rows = cancel_incoming(&mut self.incoming, attack);
+				if rows > 0 {
+					self.connection.send(&Message::Attack { rows });
+				}
+			}
+			// A piece that will clear rows goes to LineClear instead, and gets its chance to cancel first.
+			tetris::Event::PieceLocked if !matches!(game_state.phase, tetris::Phase::LineClear { .. }) => {
+				// A board's worth pushes out any stack, so that's all it takes to top out.
+				let room = game_state.cell_matrix.len();
+				for rows in self.incoming.drain(..) {
+					let hole = self.rng.uniform(0, game_state.cell_matrix_width);
+					let rows = (rows as usize).min(room.saturating_sub(self.rising.len()));
+					self.rising.extend(std::iter::repeat_n(hole, rows));
+				}
+			}
+			_ => {}
+		}
+	}
+
+	pub fn say(&mut self, text: &str) {
+		self.chat.say(&mut self.connection, &self.name, text);
+	}
+
+	/// Garbage queued to rise, in rows; what the meter beside the board shows.
+	pub fn incoming_rows(&self) -> u32 {
+		self.incoming.iter().sum()
+	}
+
+	/// Rows to add this tick, between applying actions and GameState::tick(); see add_garbage().
+	pub fn take_rising(&mut self) -> Vec<usize> {
+		std::mem::take(&mut self.rising)
+	}
+
+	/// Call after each tick of our own game, with what went into it.
+	pub fn send_tick(&mut self, tick: u64, actions: Vec<tetris::Action>, garbage_holes: Vec<usize>) {
+		let inputs = TickInputs { tick, actions, garbage_holes };
+		if !inputs.is_empty() {
+			self.history.push(inputs.clone());
+		}
+		self.ticks_sent = tick + 1;
+		self.connection.send(&Message::Tick { seat: self.seat, inputs });
+	}
+
+	/// Call after each tick of our own game; every CHECK_EVERY_TICKS, sends a Check so the other side can compare.
+	pub fn after_tick(&mut self, game_state: &tetris::GameState) {
+		self.rolling = roll_checksum(self.rolling, game_state);
+		self.recent.push(game_state.checksum() as u16);
+		if !game_state.ticks.is_multiple_of(CHECK_EVERY_TICKS) {
+			return;
+		}
+		let recent = std::mem::take(&mut self.recent);
+		self.connection.send(&Message::Check { seat: self.seat, tick: game_state.ticks, rolling: self.rolling, recent });
+		// HARDCODE Enough to outlast a second or so of latency.
+		if self.checkpoints.len() >= 8 {
+			self.checkpoints.pop_front();
+		}
+		self.checkpoints.push_back(game_state.clone());
+	}
+
+	/// Compares the opponent's Check against our copy of their game, which has just caught up to the same tick.
+	fn check(&mut self, tick: u64, rolling: u64, recent: &[u16]) {
+		let ours = std::mem::take(&mut self.opponent_recent);
+		if self.desync_at.is_some() || (self.opponent.ticks == tick && self.opponent_rolling == rolling) {
+			return;
+		}
+		// The first tick whose short checksum differs; failing that, the start of the window.
+		let first = tick + 1 - recent.len().max(1) as u64;
+		let offset = ours.iter().zip(recent).position(|(a, b)| a != b).unwrap_or(0);
+		let diverged_at = first + offset as u64;
+		log::error!("Desync: our copy of {}'s game stopped matching theirs after tick {diverged_at}", self.opponent_name);
+		self.desync_at = Some(diverged_at);
+		dump_desync(&format!("copy-of-seat-{}", 1 - self.seat), tick, diverged_at, &self.opponent);
+		self.connection.send(&Message::Desync { checked_at: tick, diverged_at });
+	}
+
+	/// Call once per frame; counts the outcome toward the set, the first time there is one.
+	pub fn settle(&mut self, game_state: &tetris::GameState) {
+		let Some(outcome) = self.outcome(game_state)
+			else { return; };
+		if self.settled {
+			return;
+		}
+		let is_draw = !game_state.is_alive && !self.opponent.is_alive && game_state.ticks == self.opponent.ticks;
+		self.set.games.push((!is_draw).then_some(outcome));
+		self.settled = true;
+	}
+
+	/// Whether there's another game to play: the set isn't decided, and the opponent hasn't left.
+	pub fn can_play_on(&self) -> bool {
+		self.settled && self.set.winner().is_none() && !self.opponent_left
+	}
+
+	/// Asks for the next game of the set; it starts once the opponent asks too, when take_next() says so.
+	pub fn ask_next(&mut self) {
+		if self.next_nonce.is_none() && self.can_play_on() {
+			let nonce = random_seed();
+			self.next_nonce = Some(nonce);
+			self.connection.send(&Message::Next { nonce });
+		}
+	}
+
+	pub fn has_asked_next(&self) -> bool {
+		self.next_nonce.is_some()
+	}
+
+	/// Once both sides have asked for the next game, moves `seed` on to it; call start() next.
+	pub fn take_next(&mut self) -> bool {
+		let (Some(ours), Some(theirs)) = (self.next_nonce, self.opponent_next_nonce)
+			else { return false; };
+		self.seed = ours ^ theirs;
+		(self.next_nonce, self.opponent_next_nonce) = (None, None);
+		true
+	}
+
+	/// Decided per lasted_longer(), or as soon as the opponent leaves; topping out on the same tick loses for both.
+	pub fn outcome(&self, game_state: &tetris::GameState) -> Option<Outcome> {
+		match lasted_longer(game_state, &self.opponent) {
+			Some(Ordering::Greater) => Some(Outcome::Won),
+			Some(_) => Some(Outcome::Lost),
+			None if self.opponent_left => Some(if game_state.is_alive { Outcome::Won } else { Outcome::Lost }),
+			None => None,
+		}
+	}
+}
+
+/// Saves `game_state` under `desyncs/` for comparing with the other side's dump of the same tick.
+fn dump_desync(whose: &str, tick: u64, diverged_at: u64, game_state: &tetris::GameState) {
+	let name = format!("desyncs/{}-{tick}-{whose}.txt", game_state.seed);
+	let text = format!("diverged after tick {diverged_at}\nchecksum {:016x}\n{}\n\n{game_state:#?}\n", game_state.checksum(), notation::encode(game_state));
+	match storage::save(&name, &text) {
+		Ok(()) => log::info!("Dumped {name}"),
+		Err(e) => log::error!("Could not dump {name}: {e}"),
+	}
+}
+
+/// Saying goodbye, so the other side hears of it before the connection drops.
+impl Drop for Versus {
+	fn drop(&mut self) {
+		self.connection.send(&Message::Bye);
+		self.connection.close();
+	}
+}
+
+/// One player's game as a spectator sees it.
+#[derive(Clone,Debug)]
+pub struct Watched {
+	pub name: String,
+	pub game_state: tetris::GameState,
+}
+
+/// Watching a room: a copy of each player's game, built from their Snapshot and kept up with their ticks.
+/// Spectators never send inputs, and players don't know or care how many are watching.
+#[derive(Debug)]
+pub struct Spectator {
+	pub code: String,
+	connection: Connection,
+	/// What a player's game looks like before its first tick, but for the seed; see Versus::start().
+	pub template: tetris::GameState,
+	/// By seat; None until that player's Snapshot arrives.
+	pub players: [Option<Watched>; 2],
+	/// When Watch last went out, in seconds since the Unix epoch; it goes out again until both players have answered,
+	/// since they only do once their game has started.
+	asked_at: Option<f64>,
+	/// What the players say; spectators only listen.
+	pub chat: Chat,
+}
+
+impl Spectator {
+	/// HARDCODE
+	const ASK_EVERY_SECONDS: f64 = 2.0;
+
+	pub fn join(server_url: &str, code: &str, template: tetris::GameState) -> Spectator {
+		let code = code.trim().to_ascii_uppercase();
+		let connection = Connection::open(&room_url(server_url, &code));
+		Self { code, connection, template, players: [None, None], asked_at: None, chat: Chat::default() }
+	}
+
+	pub fn error(&self) -> Option<&str> {
+		self.connection.error.as_deref()
+	}
+
+	/// Call once per frame.
+	pub fn poll(&mut self, now_s: f64) {
+		while let Some(message) = self.connection.poll() {
+			match message {
+				Message::Snapshot { seat, seed, difficulty, ticks, history, name } if self.players[seat as usize].is_none() => {
+					let mut game_state = self.template.clone();
+					game_state.difficulty = difficulty;
+					game_state.reset();
+					game_state.reseed(seed);
+					let mut history = history.into_iter().peekable();
+					while game_state.ticks < ticks {
+						let inputs = history.next_if(|inputs| inputs.tick == game_state.ticks);
+						let (actions, garbage_holes) = inputs.map(|i| (i.actions, i.garbage_holes)).unwrap_or_default();
+						play_tick(&mut game_state, &actions, &garbage_holes);
+					}
+					game_state.drain_events().for_each(drop);
+					log::info!("Watching {name} from tick {ticks}");
+					self.players[seat as usize] = Some(Watched { name, game_state });
+				}
+				Message::Tick { seat, inputs } => {
+					let Some(watched) = self.players[seat as usize].as_mut()
+						else { continue; };
+					// Anything older came with the snapshot.
+					if inputs.tick == watched.game_state.ticks {
+						play_tick(&mut watched.game_state, &inputs.actions, &inputs.garbage_holes);
+						watched.game_state.drain_events().for_each(drop);
+					}
+				}
+				Message::Chat { name, text } => self.chat.push(name, text),
+				other => log::debug!("Spectator ignoring {other:?}"),
+			}
+		}
+		let is_due = self.asked_at.is_none_or(|at| now_s - at >= Self::ASK_EVERY_SECONDS);
+		if self.players.iter().any(Option::is_none) && is_due {
+			self.connection.send(&Message::Watch { version: PROTOCOL_VERSION });
+			self.asked_at = Some(now_s);
+		}
+	}
+
+	/// The seat that lasted longer, per lasted_longer(); None for a tie, and while undecided.
+	pub fn winner(&self) -> Option<u8> {
+		let [Some(a), Some(b)] = &self.players
+			else { return None; };
+		match lasted_longer(&a.game_state, &b.game_state)? {
+			Ordering::Greater => Some(0),
+			Ordering::Less => Some(1),
+			Ordering::Equal => None,
+		}
+	}
+}
+
+impl Drop for Spectator {
+	fn drop(&mut self) {
+		self.connection.close();
+	}
+}
@@ -0,0 +1,162 @@
+//! One line of text for a whole position, to copy out of the game and paste back in; here, on a board 8 cells wide and 6 tall:
+//!
+//! ```text
+//! 8/8/8/8/3##3/GGG.GGGG ##.|.## #|#|#,###|.#. - 1200 14
+//! ```
+//!
+//! Space-separated fields:
+//! 1. The visible stack, rows top to bottom separated by `/`; a number is that many empty cells in a row,
+//!    anything else one cell in CellKind::to_char() characters.
+//! 2. The current piece: its cells' bounding box, rows separated by `|`, `#` for a cell and `.` for none; `*` for a bomb.
+//! 3. The next queue, soonest first, pieces separated by `,`.
+//! 4. The held piece.
+//! 5. Score.
+//! 6. Rows cleared.
+//!
+//! `-` stands for no piece, or no pieces. Items, hues, and where the current piece has got to are left out;
+//! a restored piece starts over from the spawn point.
+
+use crate::puzzle;
+use crate::tetris;
+
+/// The position `game_state` is in; see the module docs.
+pub fn encode(game_state: &tetris::GameState) -> String {
+	let board: Vec<String> = game_state.cell_matrix.iter()
+		.skip(game_state.hidden_rows)
+		.map(|row| encode_row(row.cells.iter().map(|c| c.as_ref().map(|c| c.kind))))
+		.collect();
+	let next: Vec<String> = game_state.next_pieces().map(encode_piece).collect();
+	let fields = [
+		board.join("/"),
+		game_state.current_piece.as_ref().map_or("-".to_string(), encode_piece),
+		if next.is_empty() { "-".to_string() } else { next.join(",") },
+		game_state.held_piece.as_ref().map_or("-".to_string(), encode_piece),
+		game_state.score.to_string(),
+		game_state.rows_cleared.to_string(),
+	];
+	fields.join(" ")
+}
+
+/// Puts `game_state` in the position `text` encodes, as if a fresh game had got there; leaves it alone on error.
+/// The board must be as big as game_state's. Gravity and the seed stay as reset() leaves them.
+pub fn restore(text: &str, game_state: &mut tetris::GameState) -> Result<(), String> {
+	let fields: Vec<&str> = text.split_whitespace().collect();
+	let [board, current, next, held, score, rows_cleared] = fields[..]
+		else { return Err(format!("Expected 6 fields, got {}", fields.len())); };
+	let height = game_state.cell_matrix.len() - game_state.hidden_rows;
+	let width = game_state.cell_matrix_width;
+	let stack: Vec<Vec<Option<tetris::CellKind>>> = board.split('/').map(|row| decode_row(row, width)).collect::<Result<_, _>>()?;
+	if stack.len() != height {
+		return Err(format!("Board has {} rows, not {height}", stack.len()));
+	}
+	if let Some(row) = stack.iter().find(|row| row.len() != width) {
+		return Err(format!("Row has {} cells, not {width}", row.len()));
+	}
+	let current = decode_optional_piece(current)?;
+	let next: Vec<tetris::Piece> = match next {
+		"-" => Vec::new(),
+		_ => next.split(',').map(decode_piece).collect::<Result<_, _>>()?,
+	};
+	let held = decode_optional_piece(held)?;
+	let score: u32 = score.parse().map_err(|e| format!("Score `{score}`: {e}"))?;
+	let rows_cleared: u32 = rows_cleared.parse().map_err(|e| format!("Rows cleared `{rows_cleared}`: {e}"))?;
+
+	game_state.reset();
+	for (y, row) in stack.iter().enumerate() {
+		for (x, &kind) in row.iter().enumerate() {
+			game_state.set_cell(x, y + game_state.hidden_rows, kind.map(|kind| tetris::Cell::with_kind(puzzle::STACK_HUE, kind)));
+		}
+	}
+	game_state.score = score;
+	game_state.breakdown = tetris::ScoreBreakdown { starting: score, ..tetris::ScoreBreakdown::default() };
+	game_state.rows_cleared = rows_cleared;
+	game_state.held_piece = held;
+	game_state.replace_next_pieces(next);
+	// With no current piece, the next one comes in after the entry delay.
+	if let Some(p) = current {
+		game_state.spawn(p);
+	}
+	Ok(())
+}
+
+fn encode_row(cells: impl Iterator<Item = Option<tetris::CellKind>>) -> String {
+	let mut text = String::new();
+	let mut empty = 0;
+	for kind in cells {
+		match kind {
+			None => empty += 1,
+			Some(kind) => {
+				if empty > 0 {
+					text += &empty.to_string();
+					empty = 0;
+				}
+				text.push(kind.to_char());
+			}
+		}
+	}
+	if empty > 0 {
+		text += &empty.to_string();
+	}
+	text
+}
+
+/// Pasted text can say anything, so nothing wider than `width` gets built.
+fn decode_row(text: &str, width: usize) -> Result<Vec<Option<tetris::CellKind>>, String> {
+	let mut row = Vec::new();
+	let mut empty = String::new();
+	for c in text.chars().chain(std::iter::once('/')) {
+		if c.is_ascii_digit() {
+			empty.push(c);
+			continue;
+		}
+		if !empty.is_empty() {
+			let count: usize = empty.parse().map_err(|e| format!("Row `{text}`: {e}"))?;
+			if count == 0 {
+				return Err(format!("Row `{text}`: a run of no empty cells"));
+			}
+			if row.len() + count > width {
+				return Err(format!("Row `{text}` is wider than {width} cells"));
+			}
+			row.extend(std::iter::repeat_n(None, count));
+			empty.clear();
+		}
+		if c != '/' {
+			if row.len() >= width {
+				return Err(format!("Row `{text}` is wider than {width} cells"));
+			}
+			row.push(tetris::CellKind::from_char(c));
+		}
+	}
+	Ok(row)
+}
+
+fn encode_piece(piece: &tetris::Piece) -> String {
+	if piece.kind == tetris::PieceKind::Bomb {
+		return "*".to_string();
+	}
+	let min_x = piece.cells.iter().map(|c| c.x).min().unwrap_or(0);
+	let max_x = piece.cells.iter().map(|c| c.x).max().unwrap_or(0);
+	let min_y = piece.cells.iter().map(|c| c.y).min().unwrap_or(0);
+	let max_y = piece.cells.iter().map(|c| c.y).max().unwrap_or(0);
+	let rows: Vec<String> = (min_y ..= max_y)
+		.map(|y| (min_x ..= max_x).map(|x| if piece.cells.iter().any(|c| c.x == x && c.y == y) { '#' } else { '.' }).collect())
+		.collect();
+	rows.join("|")
+}
+
+fn decode_piece(text: &str) -> Result<tetris::Piece, String> {
+	if text == "*" {
+		return Ok(tetris::Piece::bomb());
+	}
+	let xys: Vec<(i32, i32)> = text.split('|').enumerate()
+		.flat_map(|(y, row)| row.chars().enumerate().filter(|&(_x, c)| c == '#').map(move |(x, _c)| (x as i32, y as i32)))
+		.collect();
+	tetris::Piece::from_cells(&xys).ok_or_else(|| format!("Piece `{text}` is not one connected shape"))
+}
+
+fn decode_optional_piece(text: &str) -> Result<Option<tetris::Piece>, String> {
+	match text {
+		"-" => Ok(None),
+		_ => decode_piece(text).map(Some),
+	}
+}
@@ -0,0 +1,189 @@
+//! A live feed of the game for stream overlays and stat trackers, served locally while `overlay_listen` is set;
+//! see config::Config. Native only.
+//!
+//! + `GET /state` answers with the latest state message
+//! + a WebSocket to `/feed` gets the latest state message on connecting, then every state and event message as it
+//!   happens, one per text frame
+//!
+//! Messages are JSON objects; a state message goes out whenever the game has ticked since the last one:
+//!
+//! ```text
+//! {"type":"state","mode":"marathon","player":"Alice","ticks":1200,"alive":true,"score":4200,"level":2,"rows":21,
+//!  "pieces":57,"combo":0,"back_to_back":1,"board":["........","...@@...", ... ,"GG.GGGGG"],
+//!  "position":"8/8/... ##.|.## #|#|# - 4200 21"}
+//! ```
+//!
+//! `board` is the visible stack, rows top to bottom, a character per cell: `.` for empty, `@` for the falling piece,
+//! anything else as in tetris::CellKind::to_char(). `position` is the same position in notation; see that module.
+//! `mode` is null outside games that keep records.
+//!
+//! An event message follows each tetris::Event, with `rows`, `level`, `x`/`y`, `in_danger`, or `cause` as the event has:
+//!
+//! ```text
+//! {"type":"event","ticks":1210,"event":"rows_cleared","rows":4}
+//! ```
+
+use crate::notation;
+use crate::tetris;
+
+/// A state message; see the module docs.
+pub fn state_json(game_state: &tetris::GameState, mode: Option<&str>, player: &str) -> String {
+	let mut board: Vec<Vec<char>> = game_state.cell_matrix.iter()
+		.map(|row| row.cells.iter().map(|c| c.as_ref().map_or('.', |c| c.kind.to_char())).collect())
+		.collect();
+	if let Some(piece) = game_state.current_piece.as_ref() {
+		for (_cell, x, y) in piece.iter_global_space(game_state.current_piece_mass_xy) {
+			if let Some(c) = board.get_mut(y as usize).and_then(|row| row.get_mut(x as usize)) {
+				*c = '@';
+			}
+		}
+	}
+	let board: Vec<String> = board.iter().skip(game_state.hidden_rows).map(|row| json_string(&row.iter().collect::<String>())).collect();
+	format!(
+		"{{\"type\":\"state\",\"mode\":{},\"player\":{},\"ticks\":{},\"alive\":{},\"score\":{},\"level\":{},\"rows\":{},\
+			\"pieces\":{},\"combo\":{},\"back_to_back\":{},\"board\":[{}],\"position\":{}}}",
+		mode.map_or("null".to_string(), json_string), json_string(player), game_state.ticks, game_state.is_alive,
+		game_state.score, game_state.level(), game_state.rows_cleared, game_state.pieces_locked, game_state.combo,
+		game_state.back_to_back, board.join(","), json_string(&notation::encode(game_state)))
+}
+
+/// An event message; see the module docs.
+pub fn event_json(event: &tetris::Event, ticks: u64) -> String {
+	let (name, fields) = match event {
+		tetris::Event::PieceLocked => ("piece_locked", String::new()),
+		tetris::Event::RowsCleared(rows) => ("rows_cleared", format!(",\"rows\":{rows}")),
+		tetris::Event::GravityChanged(gravity) => ("gravity_changed", format!(",\"gravity\":{}", json_string(&format!("{gravity:?}")))),
+		tetris::Event::ItemUsed(item) => ("item_used", format!(",\"item\":{}", json_string(&format!("{item:?}")))),
+		tetris::Event::LevelUp(level) => ("level_up", format!(",\"level\":{level}")),
+		tetris::Event::Exploded { x, y } => ("exploded", format!(",\"x\":{x},\"y\":{y}")),
+		tetris::Event::Danger(in_danger) => ("danger", format!(",\"in_danger\":{in_danger}")),
+		tetris::Event::ToppedOut(cause) => ("topped_out", format!(",\"cause\":{}", json_string(&format!("{cause:?}")))),
+	};
+	format!("{{\"type\":\"event\",\"ticks\":{ticks},\"event\":\"{name}\"{fields}}}")
+}
+
+fn json_string(text: &str) -> String {
+	let mut quoted = String::with_capacity(text.len() + 2);
+	quoted.push('"');
+	for c in text.chars() {
+		match c {
+			'"' => quoted += "\\\"",
+			'\\' => quoted += "\\\\",
+			c if c.is_control() => quoted += &format!("\\u{:04x}", c as u32),
+			c => quoted.push(c),
+		}
+	}
+	quoted.push('"');
+	quoted
+}
+
+/// Serves the feed from background threads; publishing never waits on a client.
+#[cfg(not(target_family="wasm"))]
+#[derive(Debug)]
+pub struct OverlayServer {
+	shared: std::sync::Arc<std::sync::Mutex<Shared>>,
+}
+
+#[cfg(not(target_family="wasm"))]
+#[derive(Debug,Default)]
+struct Shared {
+	/// The latest state message.
+	state: String,
+	/// A writer thread per `/feed` client.
+	feeds: Vec<std::sync::mpsc::Sender<String>>,
+}
+
+#[cfg(not(target_family="wasm"))]
+impl OverlayServer {
+	/// Listens on `address`, e.g. `127.0.0.1:7373`.
+	pub fn start(address: &str) -> Result<OverlayServer, String> {
+		let listener = std::net::TcpListener::bind(address).map_err(|e| format!("Could not listen on {address}: {e}"))?;
+		let shared = std::sync::Arc::new(std::sync::Mutex::new(Shared::default()));
+		let accepting = shared.clone();
+		std::thread::spawn(move || {
+			for stream in listener.incoming().filter_map(Result::ok) {
+				let shared = accepting.clone();
+				std::thread::spawn(move || {
+					if let Err(e) = serve(&shared, stream) {
+						crate::log::debug!("Overlay client: {e}");
+					}
+				});
+			}
+		});
+		crate::log::info!("Serving the overlay feed on {address}");
+		Ok(Self { shared })
+	}
+
+	pub fn publish_state(&self, json: String) {
+		let mut shared = self.shared.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		shared.feeds.retain(|feed| feed.send(json.clone()).is_ok());
+		shared.state = json;
+	}
+
+	pub fn publish_event(&self, json: String) {
+		let mut shared = self.shared.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+		shared.feeds.retain(|feed| feed.send(json.clone()).is_ok());
+	}
+}
+
+/// The page can't listen for connections.
+#[cfg(target_family="wasm")]
+#[derive(Debug)]
+pub struct OverlayServer;
+
+#[cfg(target_family="wasm")]
+impl OverlayServer {
+	pub fn start(_address: &str) -> Result<OverlayServer, String> {
+		Err("No servers in the browser".to_string())
+	}
+
+	pub fn publish_state(&self, _json: String) {}
+
+	pub fn publish_event(&self, _json: String) {}
+}
+
+#[cfg(not(target_family="wasm"))]
+fn serve(shared: &std::sync::Mutex<Shared>, mut stream: std::net::TcpStream) -> Result<(), String> {
+	use std::io::Write;
+	use crate::websocket::{self, Frame};
+
+	let head = websocket::read_head(&mut stream).map_err(|e| e.to_string())?;
+	let path = head.split_whitespace().nth(1).unwrap_or_default();
+	let is_upgrade = websocket::header(&head, "Upgrade").is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+	let state = shared.lock().unwrap_or_else(std::sync::PoisonError::into_inner).state.clone();
+	match (path, is_upgrade) {
+		("/state", false) => {
+			let response = format!(
+				"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nAccess-Control-Allow-Origin: *\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{state}",
+				state.len());
+			stream.write_all(response.as_bytes()).map_err(|e| e.to_string())
+		}
+		("/feed", true) => {
+			websocket::server_handshake(&mut stream, &head)?;
+			let (sender, receiver) = std::sync::mpsc::channel::<String>();
+			let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+			if !state.is_empty() {
+				websocket::write_frame(&mut writer, &Frame::Text(state), false).map_err(|e| e.to_string())?;
+			}
+			shared.lock().unwrap_or_else(std::sync::PoisonError::into_inner).feeds.push(sender);
+			std::thread::spawn(move || {
+				for json in receiver {
+					if websocket::write_frame(&mut writer, &Frame::Text(json), false).is_err() {
+						break;
+					}
+				}
+			});
+			// Nothing to hear from clients but pings and goodbyes; once they go, the writer's next send fails.
+			loop {
+				match websocket::read_frame(&mut stream).map_err(|e| e.to_string())? {
+					Frame::Close => return stream.shutdown(std::net::Shutdown::Both).map_err(|e| e.to_string()),
+					Frame::Ping(_) | Frame::Text(_) | Frame::Pong => {}
+				}
+			}
+		}
+		_ => {
+			let _ = stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+			Err(format!("Nothing at {path}"))
+		}
+	}
+}
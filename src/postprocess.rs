@@ -0,0 +1,140 @@
+//! The optional pass over each finished frame for theme::Effects: the frame is drawn into a render target, which
+//! then goes to the screen through one shader that does whichever effects are on.
+//!
+//! Macroquad's UI draws after this, straight to the screen, so menu buttons come out clean.
+
+use macroquad::prelude::*;
+use tetris_clone_rust::log;
+use tetris_clone_rust::theme;
+
+/// HARDCODE How long the glow after a clear takes to fade.
+const BLOOM_FADE_S: f32 = 0.5;
+
+pub struct PostProcess {
+	effects: theme::Effects,
+	material: Material,
+	/// Sized to the screen; remade whenever the window changes size, like when versus widens it.
+	target: Option<RenderTarget>,
+	/// 1 right after a clear, fading to 0.
+	bloom: f32,
+}
+
+impl PostProcess {
+	/// None when every effect is off, or when the shader won't compile here; frames then go straight to the screen.
+	pub fn new(effects: theme::Effects) -> Option<PostProcess> {
+		if !effects.any() {
+			return None;
+		}
+		let params = MaterialParams {
+			uniforms: vec![
+				UniformDesc::new("Scanlines", UniformType::Float1),
+				UniformDesc::new("Bloom", UniformType::Float1),
+				UniformDesc::new("Vignette", UniformType::Float1),
+				UniformDesc::new("Resolution", UniformType::Float2),
+			],
+			..Default::default()
+		};
+		match load_material(ShaderSource::Glsl { vertex: VERTEX_SHADER, fragment: FRAGMENT_SHADER }, params) {
+			Ok(material) => Some(Self { effects, material, target: None, bloom: 0.0 }),
+			Err(e) => {
+				log::warn!("Going without effects: {e}");
+				None
+			}
+		}
+	}
+
+	/// On RowsCleared.
+	pub fn flash(&mut self) {
+		if self.effects.bloom {
+			self.bloom = 1.0;
+		}
+	}
+
+	/// Until end(), drawing goes into the render target.
+	pub fn begin(&mut self) {
+		let (w, h) = (screen_width(), screen_height());
+		let is_stale = self.target.as_ref().is_none_or(|t| t.texture.width() != w || t.texture.height() != h);
+		if is_stale {
+			let target = render_target(w.max(1.0) as u32, h.max(1.0) as u32);
+			target.texture.set_filter(FilterMode::Nearest);
+			self.target = Some(target);
+		}
+		let mut camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, w, h));
+		camera.render_target = self.target.clone();
+		set_camera(&camera);
+	}
+
+	/// The frame so far, to the screen, through the shader.
+	pub fn end(&mut self) {
+		set_default_camera();
+		let Some(target) = self.target.as_ref()
+			else { return; };
+		let flag = |on: bool| if on { 1.0_f32 } else { 0.0 };
+		self.material.set_uniform("Scanlines", flag(self.effects.scanlines));
+		self.material.set_uniform("Bloom", self.bloom);
+		self.material.set_uniform("Vignette", flag(self.effects.vignette));
+		self.material.set_uniform("Resolution", (screen_width(), screen_height()));
+		gl_use_material(&self.material);
+		// Render targets come out upside down.
+		let params = DrawTextureParams { dest_size: Some(Vec2::new(screen_width(), screen_height())), flip_y: true, ..Default::default() };
+		draw_texture_ex(&target.texture, 0.0, 0.0, WHITE, params);
+		gl_use_default_material();
+		self.bloom = (self.bloom - get_frame_time() / BLOOM_FADE_S).max(0.0);
+	}
+}
+
+const VERTEX_SHADER: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+	gl_Position = Projection * Model * vec4(position, 1);
+	color = color0 / 255.0;
+	uv = texcoord;
+}
+"#;
+
+// HARDCODE Strengths and sizes of the effects.
+const FRAGMENT_SHADER: &str = r#"#version 100
+precision mediump float;
+
+varying vec4 color;
+varying vec2 uv;
+
+uniform sampler2D Texture;
+uniform float Scanlines;
+uniform float Bloom;
+uniform float Vignette;
+uniform vec2 Resolution;
+
+void main() {
+	vec3 res = texture2D(Texture, uv).rgb;
+	if (Bloom > 0.0) {
+		// What is brighter than 0.6 spills into a blur around it.
+		vec2 step = 3.0 / Resolution;
+		vec3 glow = vec3(0.0);
+		for (int dx = -2; dx <= 2; dx++) {
+			for (int dy = -2; dy <= 2; dy++) {
+				glow += max(texture2D(Texture, uv + vec2(float(dx), float(dy)) * step).rgb - 0.6, 0.0);
+			}
+		}
+		res += glow / 10.0 * Bloom;
+	}
+	if (Scanlines > 0.0) {
+		// Darkest every other row of pixels.
+		res *= 0.8 + 0.2 * sin(uv.y * Resolution.y * 3.14159);
+	}
+	if (Vignette > 0.0) {
+		vec2 d = uv - 0.5;
+		res *= clamp(1.0 - dot(d, d) * 1.5, 0.0, 1.0);
+	}
+	gl_FragColor = vec4(res, 1.0);
+}
+"#;
@@ -0,0 +1,197 @@
+//! Practice aids, which work by putting back an earlier GameState; nothing here is fit for records.
+
+use std::collections::VecDeque;
+
+use crate::tetris;
+
+/// Everything practice mode can take back; keeps rewind and undo from handing out states the other has moved past.
+#[derive(Debug,Default)]
+pub struct Practice {
+	pub rewind: Rewind,
+	pub undo: Undo,
+	/// Pieces to drill, over and over; empty for random pieces. Survives restarts; see start().
+	pub sequence: Vec<tetris::Piece>,
+}
+
+impl Practice {
+	pub fn new() -> Practice {
+		Self::default()
+	}
+
+	/// Call after GameState::reset().
+	pub fn start(&mut self, game_state: &mut tetris::GameState) {
+		self.forget_history();
+		game_state.set_piece_sequence(self.sequence.clone());
+	}
+
+	/// Rewind and undo start over from here, e.g. after pasting in a position.
+	pub fn forget_history(&mut self) {
+		self.rewind.clear();
+		self.undo.clear();
+	}
+
+	/// Adds to the sequence, starting it over from the next piece.
+	pub fn add_to_sequence(&mut self, piece: tetris::Piece, game_state: &mut tetris::GameState) {
+		self.sequence.push(piece);
+		game_state.set_piece_sequence(self.sequence.clone());
+	}
+
+	/// Back to random pieces.
+	pub fn clear_sequence(&mut self, game_state: &mut tetris::GameState) {
+		self.sequence.clear();
+		game_state.set_piece_sequence(Vec::new());
+	}
+
+	/// Call after every GameState::tick().
+	pub fn observe(&mut self, game_state: &tetris::GameState) {
+		self.rewind.observe(game_state);
+		self.undo.observe(game_state);
+	}
+
+	pub fn rewind(&mut self, game_state: &mut tetris::GameState) -> bool {
+		let did = self.rewind.rewind(game_state);
+		if did {
+			self.undo.forget_after(game_state);
+		}
+		did
+	}
+
+	pub fn undo(&mut self, game_state: &mut tetris::GameState) -> bool {
+		let did = self.undo.undo(game_state);
+		if did {
+			self.rewind.forget_after(game_state.ticks);
+		}
+		did
+	}
+}
+
+/// Ring buffer of recent snapshots, a few per second, for going back to before a misdrop.
+/// Only the newest is kept whole; the stack seldom changes in a fraction of a second, so the rest are deltas.
+#[derive(Debug,Default)]
+pub struct Rewind {
+	newest: Option<Box<tetris::GameState>>,
+	/// Oldest first; the last turns newest into the snapshot before it, and so on back.
+	older: VecDeque<tetris::Delta>,
+}
+
+impl Rewind {
+	// HARDCODE
+	const TICKS_PER_SNAPSHOT: u64 = 6;
+	const SECONDS_KEPT: u64 = 10;
+	/// How far back each rewind() goes.
+	pub const SECONDS_PER_REWIND: u64 = 3;
+
+	pub fn new() -> Rewind {
+		Self::default()
+	}
+
+	pub fn clear(&mut self) {
+		self.newest = None;
+		self.older.clear();
+	}
+
+	/// Call after every GameState::tick().
+	pub fn observe(&mut self, game_state: &tetris::GameState) {
+		if !game_state.ticks.is_multiple_of(Self::TICKS_PER_SNAPSHOT) || self.newest.as_ref().is_some_and(|s| s.ticks == game_state.ticks) {
+			return;
+		}
+		if let Some(newest) = self.newest.replace(Box::new(game_state.snapshot())) {
+			self.older.push_back(newest.delta(game_state));
+		}
+		let capacity = (Self::SECONDS_KEPT * tetris::TICKS_PER_SECOND as u64 / Self::TICKS_PER_SNAPSHOT) as usize;
+		while self.older.len() + 1 > capacity {
+			self.older.pop_front();
+		}
+	}
+
+	/// Back SECONDS_PER_REWIND, or as far as the buffer goes; false if there is nothing older to go back to.
+	/// Snapshots newer than the one restored are gone, so rewinding again goes further back.
+	pub fn rewind(&mut self, game_state: &mut tetris::GameState) -> bool {
+		let target = game_state.ticks.saturating_sub(Self::SECONDS_PER_REWIND * tetris::TICKS_PER_SECOND as u64);
+		self.step_back_to(target);
+		let Some(snapshot) = self.newest.as_ref().filter(|s| s.ticks < game_state.ticks)
+			else { return false; };
+		*game_state = (**snapshot).clone();
+		// Those went out when the snapshot was fresh.
+		game_state.drain_events().for_each(drop);
+		true
+	}
+
+	fn forget_after(&mut self, ticks: u64) {
+		self.step_back_to(ticks);
+		if self.newest.as_ref().is_some_and(|s| s.ticks > ticks) {
+			self.newest = None;
+		}
+	}
+
+	/// Drops snapshots newer than `ticks`, but not the oldest.
+	fn step_back_to(&mut self, ticks: u64) {
+		let Some(newest) = self.newest.as_mut()
+			else { return; };
+		while newest.ticks > ticks {
+			let Some(delta) = self.older.pop_back()
+				else { break; };
+			newest.apply_delta(&delta);
+		}
+	}
+}
+
+/// Snapshots from as each recent piece spawned, for taking back the last one placed.
+#[derive(Debug,Default)]
+pub struct Undo {
+	snapshots: VecDeque<tetris::GameState>,
+	/// As of the last observe(), so spawns stand out.
+	was_falling: bool,
+}
+
+impl Undo {
+	const PIECES_KEPT: usize = 100; // HARDCODE
+
+	pub fn new() -> Undo {
+		Self::default()
+	}
+
+	pub fn clear(&mut self) {
+		self.snapshots.clear();
+		self.was_falling = false;
+	}
+
+	/// Call after every GameState::tick().
+	pub fn observe(&mut self, game_state: &tetris::GameState) {
+		let is_falling = is_falling(game_state);
+		if is_falling && !self.was_falling {
+			self.snapshots.push_back(game_state.clone());
+			while self.snapshots.len() > Self::PIECES_KEPT {
+				self.snapshots.pop_front();
+			}
+		}
+		self.was_falling = is_falling;
+	}
+
+	/// Back to when the last piece to lock spawned, with the board, score, and queue as they were then;
+	/// false if it is too long ago.
+	pub fn undo(&mut self, game_state: &mut tetris::GameState) -> bool {
+		// Still falling, so the last one to lock is the one before.
+		if is_falling(game_state) {
+			if self.snapshots.len() < 2 {
+				return false;
+			}
+			self.snapshots.pop_back();
+		}
+		let Some(snapshot) = self.snapshots.back()
+			else { return false; };
+		*game_state = snapshot.clone();
+		game_state.drain_events().for_each(drop);
+		self.was_falling = true;
+		true
+	}
+
+	fn forget_after(&mut self, game_state: &tetris::GameState) {
+		self.snapshots.retain(|s| s.ticks <= game_state.ticks);
+		self.was_falling = is_falling(game_state);
+	}
+}
+
+fn is_falling(game_state: &tetris::GameState) -> bool {
+	matches!(game_state.phase, tetris::Phase::Falling) && game_state.current_piece.is_some()
+}
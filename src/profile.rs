@@ -0,0 +1,65 @@
+//! Named local players sharing one install, each with their own config.txt, achievements, and records, so one
+//! player's games don't overwrite another's bests.
+//!
+//! A profile's files live under `profiles/<name>/`. Playing as a guest, with no profile picked, uses the top-level
+//! files, as before there were profiles; a profile without a config.txt of its own starts from that one.
+
+use std::sync::Mutex;
+
+use crate::log;
+use crate::storage;
+
+/// Every profile's name, one per line, oldest first.
+const PROFILES_FILE: &str = "profiles.txt";
+/// HARDCODE Long enough for a name, short enough for a menu button.
+pub const MAX_NAME_CHARS: usize = 16;
+
+static CURRENT: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn list() -> Vec<String> {
+	storage::load(PROFILES_FILE)
+		.unwrap_or_default()
+		.lines()
+		.filter_map(valid_name)
+		.collect()
+}
+
+/// `text` trimmed, if it's fit to name a profile and so a directory: letters, digits, spaces, `-`, and `_`,
+/// at most MAX_NAME_CHARS.
+pub fn valid_name(text: &str) -> Option<String> {
+	let name = text.trim();
+	let fits = !name.is_empty()
+		&& name.chars().count() <= MAX_NAME_CHARS
+		&& name.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'));
+	fits.then(|| name.to_string())
+}
+
+/// Adds a profile called `name`, or finds the one already called that; returns its name as stored.
+pub fn create(name: &str) -> Result<String, String> {
+	let name = valid_name(name).ok_or_else(|| format!("`{name}` won't do; use up to {MAX_NAME_CHARS} letters, digits, spaces, - or _"))?;
+	let mut profiles = list();
+	if let Some(existing) = profiles.iter().find(|p| p.eq_ignore_ascii_case(&name)) {
+		return Ok(existing.clone());
+	}
+	profiles.push(name.clone());
+	storage::save(PROFILES_FILE, &(profiles.join("\n") + "\n")).map_err(|e| format!("Could not save profiles: {e}"))?;
+	log::info!("Created profile {name}");
+	Ok(name)
+}
+
+/// Plays as `name` from now on, or as a guest for None; reload whatever was loaded through file().
+pub fn select(name: Option<&str>) {
+	*CURRENT.lock().expect("Should not be poisoned") = name.map(str::to_string);
+}
+
+pub fn current() -> Option<String> {
+	CURRENT.lock().expect("Should not be poisoned").clone()
+}
+
+/// Where the current profile keeps the file that a guest keeps at `name`.
+pub fn file(name: &str) -> String {
+	match current() {
+		Some(profile) => format!("profiles/{profile}/{name}"),
+		None => name.to_string(),
+	}
+}
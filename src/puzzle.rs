@@ -0,0 +1,198 @@
+use crate::tetris;
+
+/// Puzzle stacks have no piece to take a color from.
+pub const STACK_HUE: f32 = 0.6;
+
+#[derive(Clone,Debug)]
+pub enum Goal {
+	/// Clear at least this many rows in total.
+	ClearRows(u32),
+	/// Clear at least this many rows with a single piece, e.g. 4 for a quad.
+	ClearRowsAtOnce(u32),
+	/// Leave nothing on the board.
+	ClearBoard,
+}
+
+#[derive(Clone,Debug)]
+pub struct Puzzle {
+	pub name: String,
+	pub goal: Goal,
+	/// Lose once this many pieces lock without meeting the goal.
+	pub piece_limit: Option<u32>,
+	/// Top to bottom; sits on the floor of the well.
+	pub stack: Vec<Vec<Option<tetris::CellKind>>>,
+}
+
+impl Puzzle {
+	/// Format: `key: value` lines, a blank line, then the stack drawn with `.` for empty and anything else for filled.
+	/// `G`, `*`, `F`, and `B` fill with garbage, bomb, frozen, and bedrock cells; see tetris::CellKind::from_char().
+	///
+	/// ```text
+	/// name: Warm-up
+	/// goal: rows 1
+	/// pieces: 2
+	///
+	/// ###..###
+	/// ```
+	///
+	/// Goals are `rows N`, `at-once N`, and `board`.
+	pub fn parse(text: &str) -> Result<Puzzle, String> {
+		let mut name = None;
+		let mut goal = None;
+		let mut piece_limit = None;
+		let mut lines = text.lines();
+		for line in lines.by_ref() {
+			let line = line.trim();
+			if line.is_empty() {
+				break;
+			}
+			let Some((key, value)) = line.split_once(':')
+				else { return Err(format!("Expected `key: value`, got `{line}`")); };
+			let value = value.trim();
+			match key.trim() {
+				"name" => name = Some(value.to_string()),
+				"goal" => goal = Some(parse_goal(value)?),
+				"pieces" => piece_limit = Some(value.parse().map_err(|_| format!("Bad piece count `{value}`"))?),
+				other => return Err(format!("Unknown key `{other}`")),
+			}
+		}
+		let stack: Vec<Vec<Option<tetris::CellKind>>> = lines
+			.map(str::trim)
+			.filter(|line| !line.is_empty())
+			.map(|line| line.chars().map(tetris::CellKind::from_char).collect())
+			.collect();
+		Ok(Self {
+			name: name.ok_or("Missing `name`")?,
+			goal: goal.ok_or("Missing `goal`")?,
+			piece_limit,
+			stack,
+		})
+	}
+
+	/// What parse() reads.
+	pub fn to_text(&self) -> String {
+		let goal = match self.goal {
+			Goal::ClearRows(n) => format!("rows {n}"),
+			Goal::ClearRowsAtOnce(n) => format!("at-once {n}"),
+			Goal::ClearBoard => "board".to_string(),
+		};
+		let mut text = format!("name: {}\ngoal: {goal}\n", self.name);
+		if let Some(limit) = self.piece_limit {
+			text += &format!("pieces: {limit}\n");
+		}
+		text.push('\n');
+		for row in self.stack.iter() {
+			text.extend(row.iter().map(|kind| kind.map_or('.', tetris::CellKind::to_char)));
+			text.push('\n');
+		}
+		text
+	}
+
+	pub fn description(&self) -> String {
+		let goal = match self.goal {
+			Goal::ClearRows(1) => "Clear 1 row".to_string(),
+			Goal::ClearRows(n) => format!("Clear {n} rows"),
+			Goal::ClearRowsAtOnce(n) => format!("Clear {n} rows at once"),
+			Goal::ClearBoard => "Clear the board".to_string(),
+		};
+		match self.piece_limit {
+			Some(1) => format!("{goal} with 1 piece"),
+			Some(n) => format!("{goal} with {n} pieces"),
+			None => goal,
+		}
+	}
+
+	/// Restart the game with this puzzle's stack in place.
+	pub fn set_up(&self, game_state: &mut tetris::GameState) {
+		game_state.reset();
+		let width = game_state.cell_matrix_width;
+		let height = game_state.cell_matrix.len();
+		for (i, row) in self.stack.iter().rev().enumerate().take(height) {
+			let y = height - 1 - i;
+			for (x, &kind) in row.iter().enumerate().take(width) {
+				if let Some(kind) = kind {
+					game_state.set_cell(x, y, Some(tetris::Cell::with_kind(STACK_HUE, kind)));
+				}
+			}
+		}
+	}
+}
+
+fn parse_goal(text: &str) -> Result<Goal, String> {
+	let mut words = text.split_whitespace();
+	let kind = words.next().unwrap_or_default();
+	let mut count = || words.next()
+		.and_then(|n| n.parse().ok())
+		.ok_or(format!("Goal `{text}` needs a count"));
+	match kind {
+		"rows" => Ok(Goal::ClearRows(count()?)),
+		"at-once" => Ok(Goal::ClearRowsAtOnce(count()?)),
+		"board" => Ok(Goal::ClearBoard),
+		_ => Err(format!("Unknown goal `{text}`")),
+	}
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Outcome {
+	Won,
+	Lost,
+}
+
+/// Progress through one attempt at a puzzle; feed it every game event.
+#[derive(Debug)]
+pub struct PuzzleRun {
+	pub puzzle: Puzzle,
+	pub pieces_used: u32,
+	rows_cleared: u32,
+	most_rows_at_once: u32,
+	pub outcome: Option<Outcome>,
+}
+
+impl PuzzleRun {
+	pub fn new(puzzle: Puzzle) -> PuzzleRun {
+		Self {
+			puzzle,
+			pieces_used: 0,
+			rows_cleared: 0,
+			most_rows_at_once: 0,
+			outcome: None,
+		}
+	}
+
+	pub fn observe(&mut self, event: &tetris::Event, game_state: &tetris::GameState) {
+		if self.outcome.is_some() {
+			return;
+		}
+		match event {
+			tetris::Event::PieceLocked => {
+				self.pieces_used += 1;
+				// Otherwise, wait for the rows to clear before judging.
+				if !matches!(game_state.phase, tetris::Phase::LineClear { .. }) {
+					self.judge(game_state);
+				}
+			}
+			tetris::Event::RowsCleared(n) => {
+				self.rows_cleared += n;
+				self.most_rows_at_once = self.most_rows_at_once.max(*n);
+				self.judge(game_state);
+			}
+			tetris::Event::GravityChanged(_) | tetris::Event::ItemUsed(_) | tetris::Event::Exploded { .. } | tetris::Event::Danger(_) | tetris::Event::LevelUp(_) => {}
+			tetris::Event::ToppedOut(_) => self.outcome = Some(Outcome::Lost),
+		}
+	}
+
+	fn judge(&mut self, game_state: &tetris::GameState) {
+		let is_done = match self.puzzle.goal {
+			Goal::ClearRows(n) => self.rows_cleared >= n,
+			Goal::ClearRowsAtOnce(n) => self.most_rows_at_once >= n,
+			// Bedrock can't be cleared, so it doesn't count.
+			Goal::ClearBoard => game_state.cell_matrix.iter()
+				.all(|row| row.cells.iter().all(|c| c.as_ref().is_none_or(|c| c.kind == tetris::CellKind::Bedrock))),
+		};
+		if is_done {
+			self.outcome = Some(Outcome::Won);
+		} else if self.puzzle.piece_limit.is_some_and(|limit| self.pieces_used >= limit) {
+			self.outcome = Some(Outcome::Lost);
+		}
+	}
+}
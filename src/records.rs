@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+
+use crate::log;
+use crate::profile;
+use crate::storage;
+use crate::tetris;
+
+const DAILY_FILE: &str = "daily.txt";
+
+/// Best score for each daily challenge, keyed by days since the Unix epoch.
+#[derive(Debug,Default)]
+pub struct DailyRecords {
+	best: BTreeMap<u64, u32>,
+}
+
+impl DailyRecords {
+	/// One `day score` pair per line; unreadable lines are skipped.
+	pub fn load() -> DailyRecords {
+		let best = storage::load(&profile::file(DAILY_FILE))
+			.unwrap_or_default()
+			.lines()
+			.filter_map(|line| {
+				let (day, score) = line.split_once(' ')?;
+				Some((day.parse().ok()?, score.parse().ok()?))
+			})
+			.collect();
+		Self { best }
+	}
+
+	pub fn best(&self, day: u64) -> Option<u32> {
+		self.best.get(&day).copied()
+	}
+
+	/// Returns whether this beat the previous best.
+	pub fn submit(&mut self, day: u64, score: u32) -> bool {
+		if self.best(day).is_some_and(|best| best >= score) {
+			return false;
+		}
+		self.best.insert(day, score);
+		let contents: String = self.best.iter()
+			.map(|(day, score)| format!("{day} {score}\n"))
+			.collect();
+		if let Err(e) = storage::save(&profile::file(DAILY_FILE), &contents) {
+			log::error!("Could not save daily records: {e}");
+		}
+		true
+	}
+}
+
+const RESULTS_FILE: &str = "results.txt";
+
+/// One finished game.
+#[derive(Clone,Debug)]
+pub struct GameResult {
+	/// E.g. `marathon` or `daily-20000`; see mode_kind().
+	pub mode: String,
+	pub width: usize,
+	pub height: usize,
+	pub score: u32,
+	pub seed: u64,
+	/// Seconds since the Unix epoch.
+	pub finished_at: u64,
+	/// Storage name of the recorded replay, if any.
+	pub replay: Option<String>,
+	/// How long the game went on; see GameState::ticks. 0 in results saved before this was.
+	pub ticks: u64,
+}
+
+impl GameResult {
+	/// `daily-20000` -> `daily`
+	pub fn mode_kind(&self) -> &str {
+		self.mode.split('-').next().unwrap_or_default()
+	}
+
+	pub fn board_size(&self) -> String {
+		format!("{}x{}", self.width, self.height)
+	}
+
+	fn to_line(&self) -> String {
+		format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n", self.mode, self.width, self.height, self.score, self.seed, self.finished_at, self.replay.as_deref().unwrap_or_default(), self.ticks)
+	}
+
+	fn from_line(line: &str) -> Option<GameResult> {
+		let mut fields = line.split('\t');
+		Some(Self {
+			mode: fields.next()?.to_string(),
+			width: fields.next()?.parse().ok()?,
+			height: fields.next()?.parse().ok()?,
+			score: fields.next()?.parse().ok()?,
+			seed: fields.next()?.parse().ok()?,
+			finished_at: fields.next()?.parse().ok()?,
+			replay: fields.next().filter(|name| !name.is_empty()).map(str::to_string),
+			ticks: fields.next().and_then(|ticks| ticks.parse().ok()).unwrap_or(0),
+		})
+	}
+}
+
+/// Every finished game, best first.
+#[derive(Debug,Default)]
+pub struct LocalResults {
+	pub results: Vec<GameResult>,
+}
+
+impl LocalResults {
+	/// One tab-separated GameResult per line; unreadable lines are skipped.
+	pub fn load() -> LocalResults {
+		let mut results: Vec<GameResult> = storage::load(&profile::file(RESULTS_FILE))
+			.unwrap_or_default()
+			.lines()
+			.filter_map(GameResult::from_line)
+			.collect();
+		results.sort_by_key(|r| std::cmp::Reverse(r.score));
+		Self { results }
+	}
+
+	pub fn add(&mut self, result: GameResult) {
+		let i = self.results.partition_point(|r| r.score >= result.score);
+		self.results.insert(i, result);
+		let contents: String = self.results.iter().map(GameResult::to_line).collect();
+		if let Err(e) = storage::save(&profile::file(RESULTS_FILE), &contents) {
+			log::error!("Could not save results: {e}");
+		}
+	}
+
+	/// None matches everything.
+	pub fn filtered<'a>(&'a self, mode_kind: Option<&'a str>, board_size: Option<&'a str>) -> impl Iterator<Item = &'a GameResult> {
+		self.results.iter()
+			.filter(move |r| mode_kind.is_none_or(|m| r.mode_kind() == m))
+			.filter(move |r| board_size.is_none_or(|s| r.board_size() == s))
+	}
+
+	/// Sorted, without duplicates; for cycling through filters.
+	pub fn mode_kinds(&self) -> Vec<String> {
+		let mut kinds: Vec<String> = self.results.iter().map(|r| r.mode_kind().to_string()).collect();
+		kinds.sort();
+		kinds.dedup();
+		kinds
+	}
+
+	pub fn board_sizes(&self) -> Vec<String> {
+		let mut sizes: Vec<String> = self.results.iter().map(GameResult::board_size).collect();
+		sizes.sort();
+		sizes.dedup();
+		sizes
+	}
+}
+
+/// Game time as `m:ss.cc`.
+pub fn format_ticks(ticks: u64) -> String {
+	let centiseconds = ticks * 100 / tetris::TICKS_PER_SECOND as u64;
+	format!("{}:{:02}.{:02}", centiseconds / 6000, centiseconds / 100 % 60, centiseconds % 100)
+}
+
+/// `YYYY-MM-DD`, UTC.
+pub fn date_string(unix_secs: u64) -> String {
+	// Howard Hinnant's civil_from_days
+	let z = (unix_secs / 86_400) as i64 + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + i64::from(month <= 2);
+	format!("{year:04}-{month:02}-{day:02}")
+}
+
+const CHEESE_FILE: &str = "cheese.txt";
+
+/// Fastest cheese race for each number of garbage rows dug; see the cheese module.
+#[derive(Debug,Default)]
+pub struct CheeseRecord {
+	/// In game ticks, keyed by cheese::CheeseRace::target.
+	best: BTreeMap<u32, u64>,
+}
+
+impl CheeseRecord {
+	/// One `rows ticks` pair per line; unreadable lines are skipped.
+	pub fn load() -> CheeseRecord {
+		let best = storage::load(&profile::file(CHEESE_FILE))
+			.unwrap_or_default()
+			.lines()
+			.filter_map(|line| {
+				let (rows, ticks) = line.split_once(' ')?;
+				Some((rows.parse().ok()?, ticks.parse().ok()?))
+			})
+			.collect();
+		Self { best }
+	}
+
+	pub fn best_ticks(&self, rows: u32) -> Option<u64> {
+		self.best.get(&rows).copied()
+	}
+
+	/// Returns whether this beat the previous best.
+	pub fn submit(&mut self, rows: u32, ticks: u64) -> bool {
+		if self.best_ticks(rows).is_some_and(|best| best <= ticks) {
+			return false;
+		}
+		self.best.insert(rows, ticks);
+		let contents: String = self.best.iter()
+			.map(|(rows, ticks)| format!("{rows} {ticks}\n"))
+			.collect();
+		if let Err(e) = storage::save(&profile::file(CHEESE_FILE), &contents) {
+			log::error!("Could not save cheese records: {e}");
+		}
+		true
+	}
+}
+
+const SPRINT_FILE: &str = "sprint.txt";
+const SPRINT_REPLAY_FILE: &str = "sprint-best.replay";
+
+/// Fastest sprint, and the replay that set it for racing against.
+#[derive(Debug,Default)]
+pub struct SprintRecord {
+	/// In game ticks.
+	pub best_ticks: Option<u64>,
+}
+
+impl SprintRecord {
+	pub fn load() -> SprintRecord {
+		let best_ticks = storage::load(&profile::file(SPRINT_FILE)).and_then(|text| text.trim().parse().ok());
+		Self { best_ticks }
+	}
+
+	/// Returns whether this beat the previous best.
+	pub fn submit(&mut self, ticks: u64, replay_text: &str) -> bool {
+		if self.best_ticks.is_some_and(|best| best <= ticks) {
+			return false;
+		}
+		self.best_ticks = Some(ticks);
+		let result = storage::save(&profile::file(SPRINT_FILE), &format!("{ticks}\n"))
+			.and_then(|()| storage::save(&profile::file(SPRINT_REPLAY_FILE), replay_text));
+		if let Err(e) = result {
+			log::error!("Could not save sprint record: {e}");
+		}
+		true
+	}
+
+	pub fn best_replay(&self) -> Option<String> {
+		storage::load(&profile::file(SPRINT_REPLAY_FILE))
+	}
+}
@@ -0,0 +1,372 @@
+//! Recorded runs: the seed and settings a game started with, plus every action and the tick it happened on.
+//!
+//! The file is plain text so it survives being pasted into chat:
+//!
+//! ```text
+//! tetris-clone-rust replay 2
+//! mode marathon
+//! seed 1234
+//! board 8 24
+//! timing 10 1 10 20
+//! randomizer 4 4
+//! ruleset classic
+//! level 5
+//! queue 0 0
+//! lock 1 0 8
+//! mirror 1
+//! pieces classic
+//! actions 12L 0R 40c 3D 5U
+//! checksum 6c62272e07bb0142
+//! ```
+//!
+//! The `ruleset` line is left out for the standard rules, the `level` line for games that started at level 0,
+//! the `queue` line, previews then 1 or 0 for hold, for the default tetris::QueueOptions, the `lock` line, 1 or 0
+//! for soft drop locking and for rotation resetting the lock delay, then any reset limit, for the default
+//! tetris::LockOptions, the `mirror` line unless pieces come in mirrored, and the `pieces` line for polyominoes.
+//!
+//! Each action is the number of ticks since the previous action, then one letter; see action_code().
+//!
+//! Version 1 files play the same, but their checksums are of less of the game and can't be checked.
+
+use crate::ruleset;
+use crate::tetris;
+
+const MAGIC: &str = "tetris-clone-rust replay";
+const VERSION: u32 = 2;
+/// Before GameState::checksum() covered the falling piece, the queue, and the rest.
+const VERSION_WITHOUT_CHECKSUM: u32 = 1;
+
+#[derive(Clone,Debug)]
+pub struct Replay {
+	/// As in records::GameResult::mode.
+	pub mode: String,
+	pub seed: u64,
+	pub width: usize,
+	pub height: usize,
+	pub timing: tetris::Timing,
+	pub randomizer_strategy: tetris::RandomizerStrategy,
+	/// See ruleset::NAMES.
+	pub ruleset: String,
+	/// See GameState::starting_level.
+	pub starting_level: u32,
+	pub queue_options: tetris::QueueOptions,
+	pub lock_options: tetris::LockOptions,
+	/// See GameState::mirror_pieces.
+	pub mirror_pieces: bool,
+	pub piece_set: tetris::PieceSet,
+	/// Tick each action was applied before, in order.
+	pub actions: Vec<(u64, tetris::Action)>,
+	/// GameState::checksum() once the game ended; None while still recording.
+	pub checksum: Option<u64>,
+}
+
+impl Replay {
+	/// Call right after the game is reset and seeded, before its first tick.
+	pub fn new(mode: String, game_state: &tetris::GameState) -> Replay {
+		Self {
+			mode,
+			seed: game_state.seed,
+			width: game_state.cell_matrix_width,
+			height: game_state.cell_matrix.len() - game_state.hidden_rows,
+			timing: game_state.timing,
+			randomizer_strategy: game_state.randomizer_strategy(),
+			ruleset: game_state.ruleset_name().to_string(),
+			starting_level: game_state.starting_level,
+			queue_options: game_state.queue_options,
+			lock_options: game_state.lock_options,
+			mirror_pieces: game_state.mirror_pieces,
+			piece_set: game_state.piece_set,
+			actions: Vec::new(),
+			checksum: None,
+		}
+	}
+
+	pub fn record(&mut self, tick: u64, action: tetris::Action) {
+		self.actions.push((tick, action));
+	}
+
+	pub fn finish(&mut self, game_state: &tetris::GameState) {
+		self.checksum = Some(game_state.checksum());
+	}
+
+	/// A fresh game with the same settings and seed as the recording.
+	pub fn new_game_state(&self) -> tetris::GameState {
+		let mut game_state = tetris::GameState::new(self.height, self.width, self.randomizer_strategy, self.timing);
+		game_state.set_ruleset(ruleset::by_name(&self.ruleset).expect("parse() should have checked the ruleset"));
+		game_state.starting_level = self.starting_level;
+		game_state.queue_options = self.queue_options;
+		game_state.lock_options = self.lock_options;
+		game_state.mirror_pieces = self.mirror_pieces;
+		game_state.piece_set = self.piece_set;
+		game_state.reset();
+		game_state.reseed(self.seed);
+		game_state
+	}
+
+	/// FNV-1a of the serialized replay, for telling runs apart.
+	pub fn hash(&self) -> u64 {
+		self.to_text().bytes()
+			.fold(0xcbf29ce484222325_u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+	}
+
+	pub fn to_text(&self) -> String {
+		let (history, rerolls) = match self.randomizer_strategy {
+			tetris::RandomizerStrategy::Memoryless => (0, 0),
+			tetris::RandomizerStrategy::History { size, rerolls } => (size, rerolls),
+		};
+		let t = &self.timing;
+		let mut previous_tick = 0;
+		let actions: Vec<String> = self.actions.iter()
+			.map(|&(tick, action)| {
+				let delta = tick - previous_tick;
+				previous_tick = tick;
+				format!("{delta}{}", action_code(action))
+			})
+			.collect();
+		let mut text = format!("{MAGIC} {VERSION}\n");
+		text += &format!("mode {}\n", self.mode);
+		text += &format!("seed {}\n", self.seed);
+		text += &format!("board {} {}\n", self.width, self.height);
+		text += &format!("timing {} {} {} {}\n", t.ticks_per_drop_slow, t.ticks_per_drop_fast, t.entry_delay, t.line_clear_delay);
+		text += &format!("randomizer {history} {rerolls}\n");
+		if self.ruleset != "standard" {
+			text += &format!("ruleset {}\n", self.ruleset);
+		}
+		if self.starting_level != 0 {
+			text += &format!("level {}\n", self.starting_level);
+		}
+		if self.queue_options != tetris::QueueOptions::default() {
+			text += &format!("queue {} {}\n", self.queue_options.previews, u8::from(self.queue_options.hold));
+		}
+		if self.lock_options != tetris::LockOptions::default() {
+			let l = &self.lock_options;
+			text += &format!("lock {} {}", u8::from(l.soft_drop_locks), u8::from(l.rotation_resets));
+			if let Some(max_resets) = l.max_resets {
+				text += &format!(" {max_resets}");
+			}
+			text.push('\n');
+		}
+		if self.mirror_pieces {
+			text += "mirror 1\n";
+		}
+		if let Some(speed) = t.fall_speed {
+			text += &format!("fall {}\n", speed.0);
+		}
+		if self.piece_set != tetris::PieceSet::Polyominoes {
+			text += &format!("pieces {}\n", self.piece_set.name());
+		}
+		text += &format!("actions {}\n", actions.join(" "));
+		if let Some(checksum) = self.checksum {
+			text += &format!("checksum {checksum:016x}\n");
+		}
+		text
+	}
+
+	pub fn parse(text: &str) -> Result<Replay, String> {
+		let mut lines = text.lines();
+		let header = lines.next().unwrap_or_default();
+		let version = header.strip_prefix(MAGIC)
+			.ok_or("Not a replay file")?
+			.trim();
+		let checksum_counts = match version.parse() {
+			Ok(VERSION) => true,
+			Ok(VERSION_WITHOUT_CHECKSUM) => false,
+			_ => return Err(format!("Unsupported replay version {version}")),
+		};
+		let mut replay = Replay {
+			mode: String::new(),
+			seed: 0,
+			width: 0,
+			height: 0,
+			timing: tetris::Timing { ticks_per_drop_slow: 0, ticks_per_drop_fast: 0, entry_delay: 0, line_clear_delay: 0, fall_speed: None },
+			randomizer_strategy: tetris::RandomizerStrategy::Memoryless,
+			ruleset: "standard".to_string(),
+			starting_level: 0,
+			queue_options: tetris::QueueOptions::default(),
+			lock_options: tetris::LockOptions::default(),
+			mirror_pieces: false,
+			piece_set: tetris::PieceSet::Polyominoes,
+			actions: Vec::new(),
+			checksum: None,
+		};
+		for line in lines {
+			let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+			let numbers = || -> Result<Vec<u64>, String> {
+				value.split_whitespace()
+					.map(|n| n.parse().map_err(|_| format!("Bad number in `{line}`")))
+					.collect()
+			};
+			match key {
+				"mode" => replay.mode = value.to_string(),
+				"seed" => replay.seed = value.parse().map_err(|_| format!("Bad seed `{value}`"))?,
+				"board" => {
+					let [width, height] = numbers()?[..]
+						else { return Err(format!("Expected width and height in `{line}`")); };
+					(replay.width, replay.height) = (width as usize, height as usize);
+				}
+				"timing" => {
+					let [slow, fast, entry, line_clear] = numbers()?[..]
+						else { return Err(format!("Expected four durations in `{line}`")); };
+					replay.timing = tetris::Timing {
+						ticks_per_drop_slow: slow as u32,
+						ticks_per_drop_fast: fast as u32,
+						entry_delay: entry as u32,
+						line_clear_delay: line_clear as u32,
+						fall_speed: replay.timing.fall_speed,
+					};
+				}
+				"randomizer" => {
+					let [size, rerolls] = numbers()?[..]
+						else { return Err(format!("Expected history size and rerolls in `{line}`")); };
+					replay.randomizer_strategy = if size == 0 {
+						tetris::RandomizerStrategy::Memoryless
+					} else {
+						tetris::RandomizerStrategy::History { size: size as usize, rerolls: rerolls as u32 }
+					};
+				}
+				"ruleset" => {
+					if ruleset::by_name(value).is_none() {
+						return Err(format!("Unknown ruleset `{value}`"));
+					}
+					replay.ruleset = value.to_string();
+				}
+				"level" => replay.starting_level = value.parse().map_err(|_| format!("Bad level `{value}`"))?,
+				"queue" => {
+					let [previews, hold] = numbers()?[..]
+						else { return Err(format!("Expected previews and hold in `{line}`")); };
+					replay.queue_options = tetris::QueueOptions { previews: (previews as usize).min(tetris::MAX_PREVIEW_PIECES), hold: hold != 0 };
+				}
+				"lock" => {
+					let (soft_drop_locks, rotation_resets, max_resets) = match numbers()?[..] {
+						[soft_drop_locks, rotation_resets] => (soft_drop_locks, rotation_resets, None),
+						[soft_drop_locks, rotation_resets, max_resets] => (soft_drop_locks, rotation_resets, Some(max_resets as u32)),
+						_ => return Err(format!("Expected soft drop locking, rotation resets, and maybe a reset limit in `{line}`")),
+					};
+					replay.lock_options = tetris::LockOptions { soft_drop_locks: soft_drop_locks != 0, rotation_resets: rotation_resets != 0, max_resets };
+				}
+				"mirror" => replay.mirror_pieces = value.trim() != "0",
+				"fall" => replay.timing.fall_speed = Some(tetris::FallSpeed(value.parse().map_err(|_| format!("Bad fall speed `{value}`"))?)),
+				"pieces" => replay.piece_set = tetris::PieceSet::parse(value).ok_or_else(|| format!("Unknown piece set `{value}`"))?,
+				"actions" => {
+					let mut tick: u64 = 0;
+					for token in value.split_whitespace() {
+						// Pasted files may end a token in anything, not just one byte.
+						let (last, _) = token.char_indices().last().expect("split_whitespace() has no empty tokens");
+						let (delta, code) = token.split_at(last);
+						tick = delta.parse::<u64>().ok()
+							.and_then(|delta| tick.checked_add(delta))
+							.ok_or_else(|| format!("Bad action `{token}`"))?;
+						let action = action_from_code(code).ok_or_else(|| format!("Bad action `{token}`"))?;
+						replay.actions.push((tick, action));
+					}
+				}
+				"checksum" => {
+					let checksum = u64::from_str_radix(value, 16).map_err(|_| format!("Bad checksum `{value}`"))?;
+					replay.checksum = checksum_counts.then_some(checksum);
+				}
+				"" => {}
+				other => return Err(format!("Unknown replay field `{other}`")),
+			}
+		}
+		if replay.width == 0 || replay.height == 0 {
+			return Err("Missing board size".to_string());
+		}
+		Ok(replay)
+	}
+}
+
+/// One letter per action; netplay writes them the same way.
+pub fn action_code(action: tetris::Action) -> char {
+	match action {
+		tetris::Action::MoveLeft => 'L',
+		tetris::Action::MoveRight => 'R',
+		tetris::Action::RotateClockwise => 'w',
+		tetris::Action::RotateCounterClockwise => 'c',
+		tetris::Action::SoftDropStart => 'D',
+		tetris::Action::SoftDropStop => 'U',
+		tetris::Action::Hold => 'H',
+		tetris::Action::HardDrop => 'V',
+		tetris::Action::SonicDrop => 'S',
+	}
+}
+
+pub fn action_from_code(code: &str) -> Option<tetris::Action> {
+	match code {
+		"L" => Some(tetris::Action::MoveLeft),
+		"R" => Some(tetris::Action::MoveRight),
+		"w" => Some(tetris::Action::RotateClockwise),
+		"c" => Some(tetris::Action::RotateCounterClockwise),
+		"D" => Some(tetris::Action::SoftDropStart),
+		"U" => Some(tetris::Action::SoftDropStop),
+		"H" => Some(tetris::Action::Hold),
+		"V" => Some(tetris::Action::HardDrop),
+		"S" => Some(tetris::Action::SonicDrop),
+		_ => None,
+	}
+}
+
+/// Feeds a replay's actions back into a game, tick by tick.
+#[derive(Debug)]
+pub struct Playback {
+	pub replay: Replay,
+	next: usize,
+}
+
+impl Playback {
+	pub fn new(replay: Replay) -> Playback {
+		Self { replay, next: 0 }
+	}
+
+	pub fn rewind(&mut self) {
+		self.next = 0;
+	}
+
+	/// Actions due before the given tick.
+	pub fn actions_due(&mut self, tick: u64) -> Vec<tetris::Action> {
+		let mut due = Vec::new();
+		while let Some(&(t, action)) = self.replay.actions.get(self.next) {
+			if t > tick {
+				break;
+			}
+			due.push(action);
+			self.next += 1;
+		}
+		due
+	}
+
+	/// None if the replay has no checksum to compare against.
+	pub fn is_in_sync(&self, game_state: &tetris::GameState) -> Option<bool> {
+		self.replay.checksum.map(|checksum| checksum == game_state.checksum())
+	}
+}
+
+/// A recording played back alongside a live game, one tick per live tick.
+#[derive(Debug)]
+pub struct Ghost {
+	pub game_state: tetris::GameState,
+	playback: Playback,
+}
+
+impl Ghost {
+	pub fn new(replay: Replay) -> Ghost {
+		Self {
+			game_state: replay.new_game_state(),
+			playback: Playback::new(replay),
+		}
+	}
+
+	/// Back to the start of the recording.
+	pub fn restart(&mut self) {
+		self.game_state = self.playback.replay.new_game_state();
+		self.playback.rewind();
+	}
+
+	pub fn tick(&mut self) {
+		for action in self.playback.actions_due(self.game_state.ticks) {
+			self.game_state.apply(action);
+		}
+		self.game_state.tick();
+		// Nobody is listening.
+		self.game_state.drain_events().for_each(drop);
+	}
+}
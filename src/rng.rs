@@ -1,18 +1,145 @@
-use std::fmt::Debug;
+//! Randomness for game logic. Everything a game draws comes from its own seeded generator, through integer
+//! arithmetic or float operations IEEE 754 rounds the same everywhere, so a seed and the same inputs play out the
+//! same on native and in the browser; replays, ghosts, and netplay depend on it. macroquad's global generator is
+//! for nobody: its state is shared, so a draw for an effect would shift every draw after it.
 
-use macroquad::rand::RandomRange;
+use std::fmt::Debug;
 
-#[derive(Debug,Default)]
+/// PCG32, like macroquad's global generator, but each instance has its own state so games can be seeded independently.
+#[derive(Clone,Debug,Default)]
 pub struct RandomNumberGenerator {
+	state: u64,
 }
 
 impl RandomNumberGenerator {
+	const INCREMENT: u64 = 1442695040888963407;
+	const MULTIPLIER: u64 = 6364136223846793005;
+
+	pub fn new(seed: u64) -> RandomNumberGenerator {
+		let mut rng = Self { state: 0 };
+		rng.next_u32();
+		rng.state = rng.state.wrapping_add(seed);
+		rng.next_u32();
+		rng
+	}
+
+	/// Everything it will draw follows from this; see from_state().
+	pub fn state(&self) -> u64 {
+		self.state
+	}
+
+	/// Picks up where the one whose state() this was left off.
+	pub fn from_state(state: u64) -> RandomNumberGenerator {
+		Self { state }
+	}
+
+	pub fn next_u32(&mut self) -> u32 {
+		let old = self.state;
+		self.state = old.wrapping_mul(Self::MULTIPLIER).wrapping_add(Self::INCREMENT);
+		let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+		let rot = (old >> 59) as u32;
+		xorshifted.rotate_right(rot)
+	}
+
 	/// Half-open
 	pub fn uniform<T>(&mut self, lower: T, upper: T) -> T
 	where
-		T: RandomRange,
+		T: Uniform,
 	{
-		macroquad::rand::gen_range(lower, upper)
+		T::sample(lower, upper, self.next_u32())
+	}
+
+	/// Index `i` comes up with probability `weights[i] / sum(weights)`; None if nothing has positive weight.
+	/// Negative and NaN weights count as zero.
+	pub fn weighted_index(&mut self, weights: &[f64]) -> Option<usize> {
+		let weight = |w: f64| if w > 0.0 { w } else { 0.0 };
+		let total: f64 = weights.iter().copied().map(weight).sum();
+		if total <= 0.0 {
+			return None;
+		}
+		let mut target = self.uniform(0.0, total);
+		for (i, w) in weights.iter().copied().map(weight).enumerate() {
+			if target < w {
+				return Some(i);
+			}
+			target -= w;
+		}
+		// Rounding left us just past the end; the last positive weight is the closest.
+		weights.iter().rposition(|&w| weight(w) > 0.0)
+	}
+
+	/// Like weighted_index(), but hands back the item.
+	pub fn choose_weighted<'a, T>(&mut self, items: &'a [(T, f64)]) -> Option<&'a T> {
+		let weights: Vec<f64> = items.iter().map(|(_item, w)| *w).collect();
+		self.weighted_index(&weights).map(|i| &items[i].0)
+	}
+
+	pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+		if items.is_empty() {
+			return None;
+		}
+		Some(&items[self.uniform(0, items.len())])
+	}
+
+	/// Fisher-Yates; every permutation is equally likely.
+	pub fn shuffle<T>(&mut self, items: &mut [T]) {
+		for i in (1 .. items.len()).rev() {
+			let j = self.uniform(0, i + 1);
+			items.swap(i, j);
+		}
+	}
 
+	/// Gaussian, via Box-Muller; uses two draws per sample. ln() and cos() may round differently from one platform
+	/// to the next, so this is for training, never for anything a game decides.
+	pub fn normal(&mut self, mean: f64, std_dev: f64) -> f64 {
+		// Keep u1 away from zero so ln() stays finite.
+		let u1 = 1.0 - self.uniform(0.0, 1.0);
+		let u2 = self.uniform(0.0, 1.0);
+		let z = (-2.0 * f64::ln(u1)).sqrt() * f64::cos(std::f64::consts::TAU * u2);
+		mean + std_dev * z
 	}
 }
+
+pub trait Uniform {
+	/// Maps a draw from all of `u32` onto [lower, upper), evenly.
+	fn sample(lower: Self, upper: Self, bits: u32) -> Self;
+}
+
+macro_rules! impl_uniform_float {
+	($($t:ty),*) => {
+		$(
+			impl Uniform for $t {
+				fn sample(lower: Self, upper: Self, bits: u32) -> Self {
+					// Dividing by a power of two is exact.
+					let t = bits as f64 / (u32::MAX as f64 + 1.0);
+					(lower as f64 + (upper as f64 - lower as f64) * t) as Self
+				}
+			}
+		)*
+	}
+}
+impl_uniform_float!(f32, f64);
+
+macro_rules! impl_uniform_integer {
+	($($t:ty),*) => {
+		$(
+			impl Uniform for $t {
+				/// Fixed point, no floats; the same picks the float version made for ranges under 2^21.
+				fn sample(lower: Self, upper: Self, bits: u32) -> Self {
+					let range = upper as i128 - lower as i128;
+					(lower as i128 + ((range * bits as i128) >> 32)) as Self
+				}
+			}
+		)*
+	}
+}
+impl_uniform_integer!(i32, u32, u64, usize);
+
+/// Everyone playing on the same UTC day gets the same seed.
+pub fn daily_seed(day: u64) -> u64 {
+	// splitmix64, so neighboring days look unrelated
+	let mut z = day.wrapping_add(0x9E3779B97F4A7C15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+	z ^ (z >> 31)
+}
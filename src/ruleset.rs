@@ -0,0 +1,476 @@
+//! The rules that differ between styles of play, behind one trait so every mode shares GameState.
+//!
+//! + `standard`: this game's own rules; score is rows cleared
+//! + `classic`: gravity speeds up every ten rows, and multi-row clears score much more
+//! + `guideline`: like classic, plus a lock delay that moving or rotating can reset, and wall kicks
+//! + `cascade`: after a clear, every cell falls on its own, which can set off further clears
+//! + `sticky`: after a clear, each piece's remains fall together, which can set off further clears
+//! + `sideways`: pieces fall towards the right wall, and full columns clear
+//! + `rotating`: every ten rows the well turns a quarter, and everything resettles under the new gravity
+//! + `flipping`: every five rows gravity flips between down and up, and the stack falls to the floor or the ceiling
+//! + `items`: some pieces carry a power-up in one cell, which goes off when its row clears
+//! + `bombs`: now and then the next piece is a bomb, which blows a 3x3 hole where it lands
+//!
+//! Rulesets only ever see the board turned so that gravity points down; see GameState::gravity.
+
+use std::fmt::Debug;
+
+use crate::tetris::{CellKind, Gravity, KickTable, Piece, Row, Timing};
+
+pub const NAMES: [&str; 10] = ["standard", "classic", "guideline", "cascade", "sticky", "sideways", "rotating", "flipping", "items", "bombs"];
+
+pub fn by_name(name: &str) -> Option<Box<dyn Ruleset>> {
+	match name {
+		"standard" => Some(Box::new(Standard)),
+		"classic" => Some(Box::new(Classic)),
+		"guideline" => Some(Box::new(Guideline)),
+		"cascade" => Some(Box::new(Cascade)),
+		"sticky" => Some(Box::new(Sticky)),
+		"sideways" => Some(Box::new(Sideways)),
+		"rotating" => Some(Box::new(Rotating)),
+		"flipping" => Some(Box::new(Flipping)),
+		"items" => Some(Box::new(Items)),
+		"bombs" => Some(Box::new(Bombs)),
+		_ => None,
+	}
+}
+
+/// Rulesets keep no state of their own, so the name is enough to make another.
+impl Clone for Box<dyn Ruleset> {
+	fn clone(&self) -> Self {
+		by_name(self.name()).expect("Should be a listed ruleset")
+	}
+}
+
+/// Defaults are the standard rules.
+pub trait Ruleset: Debug + Send {
+	/// Stable; written to replays. See NAMES.
+	fn name(&self) -> &'static str;
+
+	/// Levels go up every ten rows.
+	fn level(&self, rows_cleared: u32) -> u32 {
+		rows_cleared / 10
+	}
+
+	fn ticks_per_drop(&self, timing: &Timing, is_soft_dropping: bool, _level: u32) -> u32 {
+		if is_soft_dropping { timing.ticks_per_drop_fast } else { timing.ticks_per_drop_slow }
+	}
+
+	/// Ticks a piece may rest on the stack before it locks; 0 locks as soon as it can't fall.
+	fn lock_delay(&self) -> u32 {
+		0
+	}
+
+	/// How many moves or rotations may restart the lock delay, per piece.
+	fn max_lock_resets(&self) -> u32 {
+		0
+	}
+
+	/// Where to try `piece` when it can't turn in place; none by default, so it just doesn't turn.
+	fn kick_table(&self, _piece: &Piece) -> KickTable {
+		KickTable::default()
+	}
+
+	/// Which way pieces fall once this many rows are gone; checked after every clear.
+	fn gravity(&self, _rows_cleared: u32) -> Gravity {
+		Gravity::Down
+	}
+
+	/// Where a new piece's center of mass starts; `height` includes the hidden rows.
+	fn spawn_xy(&self, piece: &Piece, gravity: Gravity, width: usize, height: usize, hidden_rows: usize) -> (i32, i32) {
+		let cells = || piece.iter_global_space((0, 0));
+		let min_x = cells().map(|(_c, x, _y)| x).min().expect("Should have cells");
+		let max_x = cells().map(|(_c, x, _y)| x).max().expect("Should have cells");
+		let min_y = cells().map(|(_c, _x, y)| y).min().expect("Should have cells");
+		let max_y = cells().map(|(_c, _x, y)| y).max().expect("Should have cells");
+		let middle_y = ((hidden_rows + height) / 2) as i32;
+		// HARDCODE Should these be random?
+		match gravity {
+			// Sit just above the visible field, poking into it if taller than the hidden rows.
+			Gravity::Down => (width as i32 / 2, (hidden_rows as i32 - 1 - max_y).max(min_y.abs())),
+			// Otherwise flush against the wall opposite gravity.
+			Gravity::Up => (width as i32 / 2, height as i32 - 1 - max_y),
+			Gravity::Left => (width as i32 - 1 - max_x, middle_y),
+			Gravity::Right => (-min_x, middle_y),
+		}
+	}
+
+	/// Rows to flash before clear_rows() takes them.
+	fn finished_rows(&self, cell_matrix: &[Row]) -> Vec<usize> {
+		full_rows(cell_matrix)
+	}
+
+	/// Clear whatever is finished and let the rest fall; returns how many rows went.
+	fn clear_rows(&self, cell_matrix: &mut [Row]) -> u32 {
+		clear_naive(cell_matrix)
+	}
+
+	/// Odds of each new piece carrying a tetris::Item; 0 never touches the RNG, so replays stay as they were.
+	fn item_chance(&self) -> f64 {
+		0.0
+	}
+
+	/// Odds of each new piece being a tetris::PieceKind::Bomb instead; ditto.
+	fn bomb_chance(&self) -> f64 {
+		0.0
+	}
+
+	/// Points for clearing `rows` rows with one piece at `level`.
+	fn score(&self, rows: u32, _level: u32) -> u32 {
+		rows
+	}
+
+	/// What a game starting at `level` has already scored, so starting higher doesn't cost points: as though the
+	/// ten rows a level took had been cleared as singles at level 0.
+	fn starting_score(&self, level: u32) -> u32 {
+		level * 10 * self.score(1, 0)
+	}
+
+	/// Extra points when the last `combo` pieces in a row all cleared something.
+	fn combo_bonus(&self, _combo: u32, _level: u32) -> u32 {
+		0
+	}
+
+	/// Extra points for a difficult clear right after another, on top of its `points` from score().
+	fn back_to_back_bonus(&self, _points: u32) -> u32 {
+		0
+	}
+
+	/// Extra points for a piece that locked `ticks` after it spawned, at `level`.
+	fn speed_bonus(&self, _ticks: u64, _level: u32) -> u32 {
+		0
+	}
+
+	/// Extra points for a hard drop of `cells` cells.
+	fn hard_drop_bonus(&self, _cells: u32) -> u32 {
+		0
+	}
+}
+
+/// HARDCODE Clears of this many rows keep a back-to-back streak going; anything less breaks it.
+pub const DIFFICULT_CLEAR_ROWS: u32 = 4;
+/// HARDCODE Pieces locked sooner than this after spawning earn a speed bonus, under rules that give one; a second.
+pub const FAST_PLACEMENT_TICKS: u64 = 60;
+
+#[derive(Debug)]
+pub struct Standard;
+
+impl Ruleset for Standard {
+	fn name(&self) -> &'static str {
+		"standard"
+	}
+}
+
+/// Shared by classic and guideline: one tick faster per level, down to one tick per row.
+fn leveled_ticks_per_drop(timing: &Timing, is_soft_dropping: bool, level: u32) -> u32 {
+	let slow = timing.ticks_per_drop_slow.saturating_sub(level).max(1);
+	if is_soft_dropping { timing.ticks_per_drop_fast.min(slow) } else { slow }
+}
+
+#[derive(Debug)]
+pub struct Classic;
+
+impl Ruleset for Classic {
+	fn name(&self) -> &'static str {
+		"classic"
+	}
+
+	fn ticks_per_drop(&self, timing: &Timing, is_soft_dropping: bool, level: u32) -> u32 {
+		leveled_ticks_per_drop(timing, is_soft_dropping, level)
+	}
+
+	fn score(&self, rows: u32, level: u32) -> u32 {
+		// Pieces here can be five cells tall, so there is one more step than usual.
+		let base = [0, 40, 100, 300, 1200, 3000];
+		base[(rows as usize).min(base.len() - 1)] * (level + 1)
+	}
+}
+
+#[derive(Debug)]
+pub struct Guideline;
+
+impl Ruleset for Guideline {
+	fn name(&self) -> &'static str {
+		"guideline"
+	}
+
+	fn ticks_per_drop(&self, timing: &Timing, is_soft_dropping: bool, level: u32) -> u32 {
+		leveled_ticks_per_drop(timing, is_soft_dropping, level)
+	}
+
+	fn lock_delay(&self) -> u32 {
+		30 // HARDCODE Half a second
+	}
+
+	fn max_lock_resets(&self) -> u32 {
+		15
+	}
+
+	fn kick_table(&self, piece: &Piece) -> KickTable {
+		KickTable::for_piece(piece)
+	}
+
+	fn score(&self, rows: u32, level: u32) -> u32 {
+		let base = [0, 100, 300, 500, 800, 1200];
+		base[(rows as usize).min(base.len() - 1)] * (level + 1)
+	}
+
+	fn combo_bonus(&self, combo: u32, level: u32) -> u32 {
+		50 * combo.saturating_sub(1) * (level + 1)
+	}
+
+	fn back_to_back_bonus(&self, points: u32) -> u32 {
+		points / 2
+	}
+
+	/// Up to 20 a piece per level, less the longer it took.
+	fn speed_bonus(&self, ticks: u64, level: u32) -> u32 {
+		let ticks_spare = FAST_PLACEMENT_TICKS.saturating_sub(ticks) as u32;
+		20 * ticks_spare * (level + 1) / FAST_PLACEMENT_TICKS as u32
+	}
+
+	fn hard_drop_bonus(&self, cells: u32) -> u32 {
+		2 * cells
+	}
+}
+
+#[derive(Debug)]
+pub struct Cascade;
+
+impl Ruleset for Cascade {
+	fn name(&self) -> &'static str {
+		"cascade"
+	}
+
+	fn clear_rows(&self, cell_matrix: &mut [Row]) -> u32 {
+		clear_and_settle(cell_matrix, settle_cells)
+	}
+}
+
+#[derive(Debug)]
+pub struct Sticky;
+
+impl Ruleset for Sticky {
+	fn name(&self) -> &'static str {
+		"sticky"
+	}
+
+	fn clear_rows(&self, cell_matrix: &mut [Row]) -> u32 {
+		clear_and_settle(cell_matrix, settle_groups)
+	}
+}
+
+#[derive(Debug)]
+pub struct Sideways;
+
+impl Ruleset for Sideways {
+	fn name(&self) -> &'static str {
+		"sideways"
+	}
+
+	fn gravity(&self, _rows_cleared: u32) -> Gravity {
+		Gravity::Right
+	}
+}
+
+#[derive(Debug)]
+pub struct Rotating;
+
+impl Ruleset for Rotating {
+	fn name(&self) -> &'static str {
+		"rotating"
+	}
+
+	fn gravity(&self, rows_cleared: u32) -> Gravity {
+		// Clockwise, as seen by the stack.
+		let turns = [Gravity::Down, Gravity::Left, Gravity::Up, Gravity::Right];
+		turns[(rows_cleared / 10) as usize % turns.len()] // HARDCODE
+	}
+}
+
+#[derive(Debug)]
+pub struct Flipping;
+
+impl Ruleset for Flipping {
+	fn name(&self) -> &'static str {
+		"flipping"
+	}
+
+	fn gravity(&self, rows_cleared: u32) -> Gravity {
+		let flips = [Gravity::Down, Gravity::Up];
+		flips[(rows_cleared / 5) as usize % flips.len()] // HARDCODE
+	}
+}
+
+#[derive(Debug)]
+pub struct Items;
+
+impl Ruleset for Items {
+	fn name(&self) -> &'static str {
+		"items"
+	}
+
+	fn item_chance(&self) -> f64 {
+		0.2 // HARDCODE
+	}
+}
+
+#[derive(Debug)]
+pub struct Bombs;
+
+impl Ruleset for Bombs {
+	fn name(&self) -> &'static str {
+		"bombs"
+	}
+
+	fn bomb_chance(&self) -> f64 {
+		0.05 // HARDCODE
+	}
+}
+
+/// Every cell filled, and none of them bedrock.
+pub fn is_full(row: &Row) -> bool {
+	!row.is_empty() && row.cells.iter().all(|c| c.as_ref().is_some_and(|c| c.kind != CellKind::Bedrock))
+}
+
+pub fn full_rows(cell_matrix: &[Row]) -> Vec<usize> {
+	cell_matrix.iter()
+		.enumerate()
+		.filter(|(_i, row)| is_full(row))
+		.map(|(i, _row)| i)
+		.collect()
+}
+
+/// Full rows vanish and everything above moves down by as many rows; nothing else moves.
+pub fn clear_naive(cell_matrix: &mut [Row]) -> u32 {
+	let mut cleared = 0;
+	// clear and drop rows; is bubble-sort in slow motion
+	for i_row in 0 .. cell_matrix.len() {
+		{
+			let row = &mut cell_matrix[i_row];
+			if row.is_empty() {
+				continue;
+			}
+			if is_full(row) {
+				row.reset();
+				cleared += 1;
+			}
+		}
+		// drop higher cells
+		if cell_matrix[i_row].is_empty() {
+			for i_row in (1 ..= i_row).rev() {
+				let i_above = i_row - 1;
+				if !cell_matrix[i_above].is_empty() {
+					cell_matrix.swap(i_row, i_row-1);
+				} else {
+					break;
+				}
+			}
+		}
+	}
+	cleared
+}
+
+/// Empty the full rows, let things fall with `settle`, and repeat until nothing else fills up.
+fn clear_and_settle(cell_matrix: &mut [Row], settle: fn(&mut [Row])) -> u32 {
+	let mut cleared = 0;
+	loop {
+		let rows = full_rows(cell_matrix);
+		if rows.is_empty() {
+			return cleared;
+		}
+		for &y in rows.iter() {
+			cell_matrix[y].reset();
+		}
+		cleared += rows.len() as u32;
+		settle(cell_matrix);
+	}
+}
+
+/// Every cell falls to the lowest empty spot in its column, except frozen and bedrock cells, which hold up whatever lands on them.
+pub fn settle_cells(cell_matrix: &mut [Row]) {
+	let width = cell_matrix.first().map_or(0, |row| row.cells.len());
+	for x in 0 .. width {
+		let mut floor = cell_matrix.len();
+		for y in (0 .. cell_matrix.len()).rev() {
+			let Some(cell) = cell_matrix[y].cells[x].take()
+				else { continue; };
+			if is_fixed(cell.kind) {
+				floor = y;
+			} else {
+				floor -= 1;
+			}
+			cell_matrix[floor].cells[x] = Some(cell);
+		}
+	}
+	cell_matrix.iter_mut().for_each(Row::recount);
+}
+
+fn is_fixed(kind: CellKind) -> bool {
+	matches!(kind, CellKind::Frozen | CellKind::Bedrock)
+}
+
+/// Cells stick to touching cells of their own piece, i.e. to what's left of it, and each group falls as one; cells of
+/// no piece, like garbage, fall alone.
+fn settle_groups(cell_matrix: &mut [Row]) {
+	let height = cell_matrix.len();
+	let width = cell_matrix.first().map_or(0, |row| row.cells.len());
+	loop {
+		// Label groups by flood fill.
+		let mut group = vec![vec![usize::MAX; width]; height];
+		let mut groups: Vec<Vec<(usize, usize)>> = Vec::new();
+		for y in 0 .. height {
+			for x in 0 .. width {
+				if group[y][x] != usize::MAX {
+					continue;
+				}
+				let Some(first) = cell_matrix[y].cells[x].as_ref()
+					else { continue; };
+				let id = groups.len();
+				let mut members = Vec::new();
+				let mut stack = vec![(x, y)];
+				group[y][x] = id;
+				while let Some((cx, cy)) = stack.pop() {
+					members.push((cx, cy));
+					let neighbors = [(cx.wrapping_sub(1), cy), (cx + 1, cy), (cx, cy.wrapping_sub(1)), (cx, cy + 1)];
+					for (nx, ny) in neighbors {
+						if nx >= width || ny >= height || group[ny][nx] != usize::MAX {
+							continue;
+						}
+						if cell_matrix[ny].cells[nx].as_ref().is_some_and(|c| c.same_piece(first)) {
+							group[ny][nx] = id;
+							stack.push((nx, ny));
+						}
+					}
+				}
+				groups.push(members);
+			}
+		}
+		// Move every group that can fall by one row, lowest first so stacks fall together.
+		let mut order: Vec<usize> = (0 .. groups.len()).collect();
+		order.sort_by_key(|&id| std::cmp::Reverse(groups[id].iter().map(|&(_x, y)| y).max().unwrap_or(0)));
+		let mut moved = false;
+		for id in order {
+			let can_fall = groups[id].iter().all(|&(x, y)| {
+				let is_fixed = cell_matrix[y].cells[x].as_ref().is_some_and(|c| is_fixed(c.kind));
+				!is_fixed && y + 1 < height && (cell_matrix[y + 1].cells[x].is_none() || group[y + 1][x] == id)
+			});
+			if !can_fall {
+				continue;
+			}
+			let mut members = groups[id].clone();
+			members.sort_by_key(|&(_x, y)| std::cmp::Reverse(y));
+			for &(x, y) in members.iter() {
+				cell_matrix[y + 1].cells[x] = cell_matrix[y].cells[x].take();
+				group[y + 1][x] = id;
+				group[y][x] = usize::MAX;
+			}
+			groups[id] = members.iter().map(|&(x, y)| (x, y + 1)).collect();
+			moved = true;
+		}
+		cell_matrix.iter_mut().for_each(Row::recount);
+		if !moved {
+			return;
+		}
+	}
+}
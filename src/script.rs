@@ -0,0 +1,359 @@
+//! House rules from `rules.txt`, one event handler per line:
+//!
+//! ```text
+//! # Quads are worth double.
+//! on rows_cleared if n >= 4: bonus n
+//! # Every tenth piece brings a row of garbage.
+//! on piece_locked if pieces % 10 == 0: garbage 1
+//! # Speed up with every row.
+//! on rows_cleared: drop_interval 10 - rows / 10
+//! ```
+//!
+//! Events are `piece_locked`, `rows_cleared`, and `topped_out`.
+//! Conditions and arguments are integer expressions with `+ - * / %`, comparisons, `&& || !`, and parentheses over:
+//! + `n`: rows cleared by this event, else 0
+//! + `rows`, `pieces`, `ticks`, `bonus`: totals so far this game
+//!
+//! Commands:
+//! + `bonus <points>`: add to the bonus score shown next to the rows cleared
+//! + `garbage <rows>`: push up garbage, at most 4 rows at once
+//! + `drop_interval <ticks>`: ticks per row while not soft dropping, 1 to 600
+//!
+//! That is the whole API: no loops, no storage, no network, and nothing else of the game is reachable, so a rules file from anywhere is safe to load.
+//! Replays don't record the rules, so they only play back in sync under the same rules.
+
+use crate::garbage;
+use crate::log;
+use crate::storage;
+use crate::tetris;
+
+const RULES_FILE: &str = "rules.txt";
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+enum Trigger {
+	PieceLocked,
+	RowsCleared,
+	ToppedOut,
+}
+
+#[derive(Clone,Copy,Debug)]
+enum Var {
+	N,
+	Rows,
+	Pieces,
+	Ticks,
+	Bonus,
+}
+
+#[derive(Clone,Copy,Debug)]
+enum BinaryOp {
+	Add, Sub, Mul, Div, Rem,
+	Eq, Ne, Lt, Le, Gt, Ge,
+	And, Or,
+}
+
+#[derive(Clone,Debug)]
+enum Expr {
+	Number(i64),
+	Var(Var),
+	Neg(Box<Expr>),
+	Not(Box<Expr>),
+	Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone,Debug)]
+enum Command {
+	Bonus(Expr),
+	Garbage(Expr),
+	DropInterval(Expr),
+}
+
+#[derive(Clone,Debug)]
+struct Handler {
+	trigger: Trigger,
+	condition: Option<Expr>,
+	command: Command,
+}
+
+/// Parsed rules; see the module docs for the language.
+#[derive(Clone,Debug,Default)]
+pub struct Script {
+	handlers: Vec<Handler>,
+}
+
+impl Script {
+	/// No file means no rules; a broken file is logged and ignored as a whole.
+	pub fn load() -> Script {
+		let Some(text) = storage::load(RULES_FILE)
+			else { return Script::default(); };
+		match Script::parse(&text) {
+			Ok(script) => {
+				log::info!("Loaded {} rules from {RULES_FILE}", script.handlers.len());
+				script
+			}
+			Err(e) => {
+				log::error!("Ignoring {RULES_FILE}: {e}");
+				Script::default()
+			}
+		}
+	}
+
+	pub fn parse(text: &str) -> Result<Script, String> {
+		let mut handlers = Vec::new();
+		for (i, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap_or_default().trim();
+			if line.is_empty() {
+				continue;
+			}
+			let handler = parse_handler(line).map_err(|e| format!("line {}: {e}", i + 1))?;
+			handlers.push(handler);
+		}
+		Ok(Script { handlers })
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.handlers.is_empty()
+	}
+}
+
+/// A script's state over one game.
+#[derive(Debug)]
+pub struct ScriptRun {
+	script: Script,
+	pub bonus: i64,
+	pieces: u32,
+	garbage: garbage::RandomPerRow,
+	/// From before the rules first changed it, to put back for the next game.
+	original_drop_interval: Option<u32>,
+}
+
+impl ScriptRun {
+	pub fn new(script: Script) -> ScriptRun {
+		Self { script, bonus: 0, pieces: 0, garbage: garbage::RandomPerRow::default(), original_drop_interval: None }
+	}
+
+	/// Call whenever a new game begins; undoes what the rules did to the last one's timing.
+	pub fn start_game(&mut self, game_state: &mut tetris::GameState) {
+		self.bonus = 0;
+		self.pieces = 0;
+		if let Some(ticks) = self.original_drop_interval.take() {
+			game_state.timing.ticks_per_drop_slow = ticks;
+		}
+	}
+
+	pub fn observe(&mut self, event: &tetris::Event, game_state: &mut tetris::GameState) {
+		let (trigger, n) = match event {
+			tetris::Event::PieceLocked => {
+				self.pieces += 1;
+				(Trigger::PieceLocked, 0)
+			}
+			&tetris::Event::RowsCleared(n) => (Trigger::RowsCleared, n as i64),
+			tetris::Event::GravityChanged(_) | tetris::Event::ItemUsed(_) | tetris::Event::Exploded { .. } | tetris::Event::Danger(_) | tetris::Event::LevelUp(_) => return,
+			tetris::Event::ToppedOut(_) => (Trigger::ToppedOut, 0),
+		};
+		for handler in self.script.handlers.iter() {
+			if handler.trigger != trigger {
+				continue;
+			}
+			let vars = Vars { n, rows: game_state.rows_cleared as i64, pieces: self.pieces as i64, ticks: game_state.ticks as i64, bonus: self.bonus };
+			if handler.condition.as_ref().is_some_and(|c| c.eval(&vars) == 0) {
+				continue;
+			}
+			match &handler.command {
+				Command::Bonus(points) => self.bonus = self.bonus.saturating_add(points.eval(&vars)),
+				Command::Garbage(rows) => {
+					let rows = rows.eval(&vars).clamp(0, 4) as usize; // HARDCODE
+					if rows > 0 && game_state.is_alive {
+						game_state.add_garbage(rows, &mut self.garbage);
+					}
+				}
+				Command::DropInterval(ticks) => {
+					self.original_drop_interval.get_or_insert(game_state.timing.ticks_per_drop_slow);
+					game_state.timing.ticks_per_drop_slow = ticks.eval(&vars).clamp(1, 600) as u32;
+				}
+			}
+		}
+	}
+}
+
+struct Vars {
+	n: i64,
+	rows: i64,
+	pieces: i64,
+	ticks: i64,
+	bonus: i64,
+}
+
+impl Expr {
+	/// Booleans are 0 and 1; dividing by zero gives 0 rather than a crash.
+	fn eval(&self, vars: &Vars) -> i64 {
+		match self {
+			Expr::Number(x) => *x,
+			Expr::Var(Var::N) => vars.n,
+			Expr::Var(Var::Rows) => vars.rows,
+			Expr::Var(Var::Pieces) => vars.pieces,
+			Expr::Var(Var::Ticks) => vars.ticks,
+			Expr::Var(Var::Bonus) => vars.bonus,
+			Expr::Neg(e) => e.eval(vars).wrapping_neg(),
+			Expr::Not(e) => i64::from(e.eval(vars) == 0),
+			Expr::Binary(op, a, b) => {
+				let (a, b) = (a.eval(vars), b.eval(vars));
+				match op {
+					BinaryOp::Add => a.wrapping_add(b),
+					BinaryOp::Sub => a.wrapping_sub(b),
+					BinaryOp::Mul => a.wrapping_mul(b),
+					BinaryOp::Div => a.checked_div(b).unwrap_or(0),
+					BinaryOp::Rem => a.checked_rem(b).unwrap_or(0),
+					BinaryOp::Eq => i64::from(a == b),
+					BinaryOp::Ne => i64::from(a != b),
+					BinaryOp::Lt => i64::from(a < b),
+					BinaryOp::Le => i64::from(a <= b),
+					BinaryOp::Gt => i64::from(a > b),
+					BinaryOp::Ge => i64::from(a >= b),
+					BinaryOp::And => i64::from(a != 0 && b != 0),
+					BinaryOp::Or => i64::from(a != 0 || b != 0),
+				}
+			}
+		}
+	}
+}
+
+/// `on <event> [if <expr>]: <command> <expr>`
+fn parse_handler(line: &str) -> Result<Handler, String> {
+	let (head, body) = line.split_once(':').ok_or("Expected `:` after the event")?;
+	let mut head_words = head.split_whitespace();
+	if head_words.next() != Some("on") {
+		return Err("Expected a line to start with `on`".to_string());
+	}
+	let trigger = match head_words.next() {
+		Some("piece_locked") => Trigger::PieceLocked,
+		Some("rows_cleared") => Trigger::RowsCleared,
+		Some("topped_out") => Trigger::ToppedOut,
+		Some(other) => return Err(format!("Unknown event `{other}`")),
+		None => return Err("Expected an event after `on`".to_string()),
+	};
+	let condition = match head_words.next() {
+		None => None,
+		Some("if") => {
+			let condition_text = head.split_once(" if ").map(|(_, c)| c).unwrap_or_default();
+			Some(parse_expr(condition_text)?)
+		}
+		Some(other) => return Err(format!("Expected `if` or `:`, not `{other}`")),
+	};
+	let body = body.trim();
+	let (name, argument) = body.split_once(char::is_whitespace).unwrap_or((body, ""));
+	let argument = parse_expr(argument)?;
+	let command = match name {
+		"bonus" => Command::Bonus(argument),
+		"garbage" => Command::Garbage(argument),
+		"drop_interval" => Command::DropInterval(argument),
+		other => return Err(format!("Unknown command `{other}`")),
+	};
+	Ok(Handler { trigger, condition, command })
+}
+
+#[derive(Clone,Debug,PartialEq)]
+enum Token {
+	Number(i64),
+	Ident(String),
+	Op(&'static str),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+	const OPS: [&str; 18] = ["&&", "||", "==", "!=", "<=", ">=", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")", "=", "&"];
+	let mut tokens = Vec::new();
+	let mut rest = text.trim_start();
+	while !rest.is_empty() {
+		let c = rest.chars().next().unwrap_or_default();
+		if c.is_ascii_digit() {
+			let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+			tokens.push(Token::Number(rest[.. end].parse().map_err(|_| format!("Number too big: {}", &rest[.. end]))?));
+			rest = &rest[end ..];
+		} else if c.is_ascii_alphabetic() || c == '_' {
+			let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+			tokens.push(Token::Ident(rest[.. end].to_string()));
+			rest = &rest[end ..];
+		} else if let Some(op) = OPS.iter().find(|op| rest.starts_with(**op)) {
+			if matches!(*op, "=" | "&") {
+				return Err(format!("Unexpected `{op}`; did you mean `{op}{op}`?"));
+			}
+			tokens.push(Token::Op(op));
+			rest = &rest[op.len() ..];
+		} else {
+			return Err(format!("Unexpected `{c}`"));
+		}
+		rest = rest.trim_start();
+	}
+	Ok(tokens)
+}
+
+fn parse_expr(text: &str) -> Result<Expr, String> {
+	let tokens = tokenize(text)?;
+	if tokens.is_empty() {
+		return Err("Expected an expression".to_string());
+	}
+	let mut parser = Parser { tokens, next: 0 };
+	let expr = parser.binary(0)?;
+	match parser.tokens.get(parser.next) {
+		None => Ok(expr),
+		Some(t) => Err(format!("Unexpected {t:?}")),
+	}
+}
+
+struct Parser {
+	tokens: Vec<Token>,
+	next: usize,
+}
+
+impl Parser {
+	/// Loosest first.
+	const PRECEDENCE: [&'static [(&'static str, BinaryOp)]; 5] = [
+		&[("||", BinaryOp::Or)],
+		&[("&&", BinaryOp::And)],
+		&[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne), ("<", BinaryOp::Lt), ("<=", BinaryOp::Le), (">", BinaryOp::Gt), (">=", BinaryOp::Ge)],
+		&[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+		&[("*", BinaryOp::Mul), ("/", BinaryOp::Div), ("%", BinaryOp::Rem)],
+	];
+
+	/// Left-associative at every level.
+	fn binary(&mut self, level: usize) -> Result<Expr, String> {
+		let Some(ops) = Self::PRECEDENCE.get(level)
+			else { return self.unary(); };
+		let mut lhs = self.binary(level + 1)?;
+		while let Some(Token::Op(symbol)) = self.tokens.get(self.next) {
+			let Some(&(_, op)) = ops.iter().find(|(s, _)| s == symbol)
+				else { break; };
+			self.next += 1;
+			let rhs = self.binary(level + 1)?;
+			lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+		}
+		Ok(lhs)
+	}
+
+	fn unary(&mut self) -> Result<Expr, String> {
+		let token = self.tokens.get(self.next).cloned().ok_or("Expression ends too soon")?;
+		self.next += 1;
+		match token {
+			Token::Number(x) => Ok(Expr::Number(x)),
+			Token::Ident(name) => match name.as_str() {
+				"n" => Ok(Expr::Var(Var::N)),
+				"rows" => Ok(Expr::Var(Var::Rows)),
+				"pieces" => Ok(Expr::Var(Var::Pieces)),
+				"ticks" => Ok(Expr::Var(Var::Ticks)),
+				"bonus" => Ok(Expr::Var(Var::Bonus)),
+				other => Err(format!("Unknown variable `{other}`")),
+			},
+			Token::Op("-") => Ok(Expr::Neg(Box::new(self.unary()?))),
+			Token::Op("!") => Ok(Expr::Not(Box::new(self.unary()?))),
+			Token::Op("(") => {
+				let inner = self.binary(0)?;
+				if self.tokens.get(self.next) != Some(&Token::Op(")")) {
+					return Err("Expected `)`".to_string());
+				}
+				self.next += 1;
+				Ok(inner)
+			}
+			Token::Op(op) => Err(format!("Unexpected `{op}`")),
+		}
+	}
+}
@@ -0,0 +1,102 @@
+//! Many independent games stepped side by side across threads, for bots, training, and generator experiments.
+
+use crate::frontend::Frontend;
+use crate::tetris;
+
+/// A bare callback as a Frontend, for policies that only pick actions.
+#[derive(Debug)]
+pub struct Policy<F>(pub F);
+
+impl<F> Frontend for Policy<F>
+where
+	F: FnMut(&tetris::GameState) -> Vec<tetris::Action>,
+{
+	fn poll_input(&mut self, game_state: &tetris::GameState) -> Vec<tetris::Action> {
+		(self.0)(game_state)
+	}
+
+	fn render(&mut self, _game_state: &tetris::GameState) {}
+
+	fn play_event(&mut self, _event: &tetris::Event) {}
+}
+
+/// One game and whoever is playing it.
+#[derive(Debug)]
+pub struct Lane<P> {
+	pub game_state: tetris::GameState,
+	pub player: P,
+	pub pieces_locked: u32,
+	pub top_out: Option<tetris::TopOut>,
+}
+
+impl<P: Frontend> Lane<P> {
+	/// `game_state` should already be reset and seeded.
+	pub fn new(game_state: tetris::GameState, player: P) -> Lane<P> {
+		Self { game_state, player, pieces_locked: 0, top_out: None }
+	}
+
+	pub fn tick(&mut self) {
+		for action in self.player.poll_input(&self.game_state) {
+			self.game_state.apply(action);
+		}
+		self.game_state.tick();
+		self.player.render(&self.game_state);
+		for event in self.game_state.drain_events() {
+			self.player.play_event(&event);
+			match event {
+				tetris::Event::PieceLocked => self.pieces_locked += 1,
+				tetris::Event::ToppedOut(cause) => self.top_out = Some(cause),
+				tetris::Event::RowsCleared(_) | tetris::Event::GravityChanged(_) | tetris::Event::ItemUsed(_) | tetris::Event::Exploded { .. } | tetris::Event::Danger(_) | tetris::Event::LevelUp(_) => {}
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct Simulation<P> {
+	pub lanes: Vec<Lane<P>>,
+	/// Defaults to what the OS says is available.
+	pub threads: usize,
+}
+
+impl<P: Frontend + Send> Simulation<P> {
+	pub fn new(lanes: Vec<Lane<P>>) -> Simulation<P> {
+		let threads = std::thread::available_parallelism().map_or(1, std::num::NonZeroUsize::get);
+		Self { lanes, threads }
+	}
+
+	/// Advance every live game by up to `ticks` ticks.
+	pub fn step(&mut self, ticks: u64) {
+		self.for_each_lane(|lane| {
+			for _ in 0 .. ticks {
+				if !lane.game_state.is_alive {
+					break;
+				}
+				lane.tick();
+			}
+		});
+	}
+
+	/// Play every game until it tops out or `is_done` says to stop.
+	pub fn run(&mut self, is_done: impl Fn(&Lane<P>) -> bool + Sync) {
+		self.for_each_lane(|lane| {
+			while lane.game_state.is_alive && !is_done(lane) {
+				lane.tick();
+			}
+		});
+	}
+
+	pub fn all_done(&self) -> bool {
+		self.lanes.iter().all(|lane| !lane.game_state.is_alive)
+	}
+
+	fn for_each_lane(&mut self, f: impl Fn(&mut Lane<P>) + Sync) {
+		let chunk_size = self.lanes.len().div_ceil(self.threads.max(1)).max(1);
+		std::thread::scope(|scope| {
+			for chunk in self.lanes.chunks_mut(chunk_size) {
+				let f = &f;
+				scope.spawn(move || chunk.iter_mut().for_each(f));
+			}
+		});
+	}
+}
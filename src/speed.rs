@@ -0,0 +1,68 @@
+//! How fast the game runs; presets for practice, which the gui can switch between mid-game.
+
+use crate::tetris;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Preset {
+	Easy,
+	Normal,
+	Hard,
+	Insane,
+}
+
+impl Preset {
+	pub const ALL: [Preset; 4] = [Preset::Easy, Preset::Normal, Preset::Hard, Preset::Insane];
+
+	pub fn parse(text: &str) -> Option<Preset> {
+		Self::ALL.into_iter().find(|p| p.name().eq_ignore_ascii_case(text))
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Preset::Easy => "easy",
+			Preset::Normal => "normal",
+			Preset::Hard => "hard",
+			Preset::Insane => "insane",
+		}
+	}
+
+	/// Game ticks per real second; Normal runs at the nominal tetris::TICKS_PER_SECOND.
+	pub fn ticks_per_second(self) -> u32 {
+		// HARDCODE
+		match self {
+			Preset::Easy => 45,
+			Preset::Normal => tetris::TICKS_PER_SECOND,
+			Preset::Hard => tetris::TICKS_PER_SECOND,
+			Preset::Insane => 90,
+		}
+	}
+
+	/// Base gravity; see Timing::ticks_per_drop_slow.
+	pub fn ticks_per_drop(self) -> u32 {
+		// HARDCODE
+		match self {
+			Preset::Easy => 20,
+			Preset::Normal => 10,
+			Preset::Hard => 5,
+			Preset::Insane => 2,
+		}
+	}
+
+	/// Takes effect from the next tick, even mid-game; replaces any fall speed in G.
+	pub fn apply(self, timing: &mut tetris::Timing) {
+		timing.ticks_per_drop_slow = self.ticks_per_drop();
+		timing.fall_speed = None;
+	}
+
+	/// Stops at Easy.
+	pub fn slower(self) -> Preset {
+		let i = Self::ALL.iter().position(|&p| p == self).expect("Should be listed");
+		Self::ALL[i.saturating_sub(1)]
+	}
+
+	/// Stops at Insane.
+	pub fn faster(self) -> Preset {
+		let i = Self::ALL.iter().position(|&p| p == self).expect("Should be listed");
+		Self::ALL[(i + 1).min(Self::ALL.len() - 1)]
+	}
+}
@@ -0,0 +1,94 @@
+//! Small named text files that outlive the game: under the user data directory natively, in `localStorage` in the browser.
+
+#[cfg(not(target_family="wasm"))]
+fn path(name: &str) -> std::path::PathBuf {
+	let base = std::env::var_os("XDG_DATA_HOME")
+		.map(std::path::PathBuf::from)
+		.or_else(|| std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".local").join("share")))
+		.or_else(|| std::env::var_os("APPDATA").map(std::path::PathBuf::from))
+		.unwrap_or_default();
+	base.join("tetris-clone-rust").join(name)
+}
+
+#[cfg(not(target_family="wasm"))]
+pub fn load(name: &str) -> Option<String> {
+	std::fs::read_to_string(path(name)).ok()
+}
+
+#[cfg(not(target_family="wasm"))]
+pub fn save(name: &str, contents: &str) -> std::io::Result<()> {
+	let path = path(name);
+	if let Some(dir) = path.parent() {
+		std::fs::create_dir_all(dir)?;
+	}
+	std::fs::write(path, contents)
+}
+
+/// For writing as it goes, e.g. a log; replaces any existing file.
+#[cfg(not(target_family="wasm"))]
+pub fn create(name: &str) -> std::io::Result<std::fs::File> {
+	let path = path(name);
+	if let Some(dir) = path.parent() {
+		std::fs::create_dir_all(dir)?;
+	}
+	std::fs::File::create(path)
+}
+
+// Matches www/index.html -> register_plugin
+#[cfg(target_family="wasm")]
+extern "C" {
+	/// Byte length of the stored text, or -1 if there is none; the text waits for storage_load_read().
+	fn storage_load(name: *const u8, name_len: usize) -> i32;
+	fn storage_load_read(buffer: *mut u8);
+	/// 1 on success, 0 if the browser refused (quota, privacy mode, ...).
+	fn storage_save(name: *const u8, name_len: usize, contents: *const u8, contents_len: usize) -> u32;
+}
+
+/// Kept in `localStorage`, so it lasts as long as the site's data does.
+#[cfg(target_family="wasm")]
+pub fn load(name: &str) -> Option<String> {
+	let len = unsafe { storage_load(name.as_ptr(), name.len()) };
+	let len = usize::try_from(len).ok()?;
+	let mut buffer = vec![0_u8; len];
+	unsafe { // SAFETY: buffer is exactly as long as the JS side reported
+		storage_load_read(buffer.as_mut_ptr());
+	}
+	String::from_utf8(buffer).ok()
+}
+
+#[cfg(target_family="wasm")]
+pub fn save(name: &str, contents: &str) -> std::io::Result<()> {
+	let saved = unsafe { // SAFETY: the JS side copies both buffers before returning
+		storage_save(name.as_ptr(), name.len(), contents.as_ptr(), contents.len())
+	};
+	match saved {
+		1 => Ok(()),
+		_ => Err(std::io::Error::other("Browser refused to store data")),
+	}
+}
+
+/// localStorage has no streams; log files and the like aren't worth emulating.
+#[cfg(target_family="wasm")]
+pub fn create(_name: &str) -> std::io::Result<std::io::Sink> {
+	Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "No files in the browser"))
+}
+
+/// Hand a file to the player: into the working directory natively, or as a download in the browser.
+#[cfg(not(target_family="wasm"))]
+pub fn export(file_name: &str, contents: &[u8]) -> std::io::Result<()> {
+	std::fs::write(file_name, contents)
+}
+
+// Matches www/index.html -> register_plugin
+#[cfg(target_family="wasm")]
+extern "C" {
+	fn download_file(name: *const u8, name_len: usize, contents: *const u8, contents_len: usize);
+}
+
+#[cfg(target_family="wasm")]
+pub fn export(file_name: &str, contents: &[u8]) -> std::io::Result<()> {
+	unsafe { // SAFETY: the JS side copies both buffers before returning
+		download_file(file_name.as_ptr(), file_name.len(), contents.as_ptr(), contents.len());
+	}
+	Ok(())
+}
@@ -0,0 +1,217 @@
+//! Optional cloud sync of a profile's files, so records and settings follow the player between machines and the
+//! web build. Off unless `sync_url` is set; see config::Config.
+//!
+//! Protocol, relative to the configured endpoint, with `token` and `profile` (empty for a guest) on every request:
+//! + `GET files?token=...&profile=...` answers with one `name<TAB>modified<TAB>contents` line per file it has,
+//!   `modified` in seconds since the Unix epoch and `contents` percent-encoded
+//! + `POST files` with form fields `token`, `profile`, `name`, `modified`, and `contents`
+//!
+//! Files merge whole, by timestamp: whichever side changed a file last wins it. Storage keeps no timestamps of its
+//! own, so MANIFEST_FILE remembers each file's hash as of the last sync, and a file whose hash has moved on since
+//! counts as changed at the time of this sync. A file this machine never synced counts as older than the cloud's copy,
+//! so turning sync on pulls first rather than pushing the new machine's defaults over everything.
+
+use crate::http;
+use crate::log;
+use crate::profile;
+use crate::storage;
+
+/// Per profile, like the files it covers; `name<TAB>modified<TAB>hash` lines.
+const MANIFEST_FILE: &str = "sync.txt";
+/// Per profile; see profile::file().
+pub const SYNCED_FILES: [&str; 8] = ["config.txt", "achievements.txt", "daily.txt", "results.txt", "sprint.txt", "sprint-best.replay", "campaign.txt", "cheese.txt"];
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Status {
+	Idle,
+	Syncing,
+	Offline,
+}
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+struct Remote {
+	name: String,
+	modified: u64,
+	contents: String,
+}
+
+#[derive(Debug)]
+pub struct SyncClient {
+	endpoint: String,
+	token: String,
+	pub status: Status,
+	/// Which profile the sync in flight is for, and when it started; seconds since the Unix epoch.
+	pending_pull: Option<(Option<String>, u64, http::PendingResponse)>,
+	pending_pushes: Vec<(String, http::PendingResponse)>,
+}
+
+impl SyncClient {
+	pub fn new(endpoint: &str, token: &str) -> SyncClient {
+		Self {
+			endpoint: endpoint.trim_end_matches('/').to_string(),
+			token: token.to_string(),
+			status: Status::Idle,
+			pending_pull: None,
+			pending_pushes: Vec::new(),
+		}
+	}
+
+	/// Starts a sync of the current profile, unless one is in flight.
+	pub fn sync(&mut self, now_s: f64) {
+		if self.pending_pull.is_some() {
+			return;
+		}
+		let profile = profile::current();
+		let url = format!("{}/files?{}", self.endpoint, http::form_encode(&[("token", &self.token), ("profile", profile.as_deref().unwrap_or_default())]));
+		self.pending_pull = Some((profile, now_s as u64, http::get(&url)));
+		self.status = Status::Syncing;
+	}
+
+	/// Call once per frame. True once a sync has changed files here, which then want loading again.
+	pub fn poll(&mut self) -> bool {
+		let mut pulled = false;
+		let mut failed = false;
+		if let Some(result) = self.pending_pull.as_mut().and_then(|(_profile, _started, pending)| pending.poll()) {
+			let (profile, started, _pending) = self.pending_pull.take().expect("Should be pending");
+			match result {
+				// They may have picked somebody else meanwhile; that profile's turn comes with the next sync.
+				Ok(_) if profile != profile::current() => {}
+				Ok(body) => pulled = self.merge(&parse_files(&body), started),
+				Err(e) => {
+					log::warn!("Sync unavailable: {e}");
+					failed = true;
+				}
+			}
+		}
+		self.pending_pushes.retain_mut(|(name, pending)| match pending.poll() {
+			None => true,
+			Some(Ok(_)) => false,
+			Some(Err(e)) => {
+				// The manifest already counts it as synced, so it goes up again only once it changes again.
+				log::warn!("Could not push {name}: {e}");
+				failed = true;
+				false
+			}
+		});
+		self.status = match self.status {
+			_ if failed => Status::Offline,
+			Status::Syncing if self.pending_pull.is_none() && self.pending_pushes.is_empty() => Status::Idle,
+			status => status,
+		};
+		pulled
+	}
+
+	/// Takes whichever copy of each file changed last, and saves the manifest; true if any file here changed.
+	fn merge(&mut self, remote: &[Remote], now: u64) -> bool {
+		let mut manifest = load_manifest();
+		let mut pulled = false;
+		for name in SYNCED_FILES {
+			let local = storage::load(&profile::file(name));
+			let hash = local.as_deref().map(hash_text);
+			let last = manifest.iter().find(|(n, _modified, _hash)| n == name).map(|&(_, modified, hash)| (modified, hash));
+			let theirs = remote.iter().find(|r| r.name == name);
+			let local_modified = match (last, hash) {
+				(_, None) => None,
+				(Some((modified, last_hash)), Some(hash)) if last_hash == hash => Some(modified),
+				(None, Some(_)) if theirs.is_some() => Some(0),
+				_ => Some(now),
+			};
+			let (modified, hash) = match (local_modified, theirs) {
+				(Some(ours), Some(theirs)) if theirs.modified > ours => (theirs.modified, self.pull(theirs, &mut pulled)),
+				(None, Some(theirs)) => (theirs.modified, self.pull(theirs, &mut pulled)),
+				(Some(ours), Some(theirs)) if theirs.modified == ours => (ours, hash.unwrap_or_default()),
+				(Some(ours), _) => {
+					self.push(name, ours, local.as_deref().unwrap_or_default());
+					(ours, hash.unwrap_or_default())
+				}
+				(None, None) => continue,
+			};
+			manifest.retain(|(n, _modified, _hash)| n != name);
+			manifest.push((name.to_string(), modified, hash));
+		}
+		let text: String = manifest.iter().map(|(name, modified, hash)| format!("{name}\t{modified}\t{hash:016x}\n")).collect();
+		if let Err(e) = storage::save(&profile::file(MANIFEST_FILE), &text) {
+			log::error!("Could not save the sync manifest: {e}");
+		}
+		pulled
+	}
+
+	/// Returns the hash of what was pulled.
+	fn pull(&self, remote: &Remote, pulled: &mut bool) -> u64 {
+		match storage::save(&profile::file(&remote.name), &remote.contents) {
+			Ok(()) => {
+				log::info!("Pulled {}", remote.name);
+				*pulled = true;
+			}
+			Err(e) => log::error!("Could not save pulled {}: {e}", remote.name),
+		}
+		hash_text(&remote.contents)
+	}
+
+	fn push(&mut self, name: &str, modified: u64, contents: &str) {
+		let profile = profile::current().unwrap_or_default();
+		let body = http::form_encode(&[
+			("token", &self.token),
+			("profile", &profile),
+			("name", name),
+			("modified", &modified.to_string()),
+			("contents", contents),
+		]);
+		self.pending_pushes.push((name.to_string(), http::post(&format!("{}/files", self.endpoint), &body)));
+	}
+}
+
+/// Unreadable lines are skipped, and so count as never synced.
+fn load_manifest() -> Vec<(String, u64, u64)> {
+	storage::load(&profile::file(MANIFEST_FILE))
+		.unwrap_or_default()
+		.lines()
+		.filter_map(|line| {
+			let [name, modified, hash] = line.split('\t').collect::<Vec<_>>()[..]
+				else { return None; };
+			Some((name.to_string(), modified.parse().ok()?, u64::from_str_radix(hash, 16).ok()?))
+		})
+		.collect()
+}
+
+/// Only files this side knows to sync; anything else on the server is left alone.
+fn parse_files(body: &str) -> Vec<Remote> {
+	body.lines()
+		.filter_map(|line| {
+			let [name, modified, contents] = line.split('\t').collect::<Vec<_>>()[..]
+				else { return None; };
+			SYNCED_FILES.contains(&name).then_some(())?;
+			Some(Remote { name: name.to_string(), modified: modified.trim().parse().ok()?, contents: percent_decode(contents)? })
+		})
+		.collect()
+}
+
+/// The inverse of http::percent_encode(); None for a stray `%` or text that isn't UTF-8.
+fn percent_decode(text: &str) -> Option<String> {
+	let bytes = text.as_bytes();
+	let mut decoded = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'%' => {
+				let hex = std::str::from_utf8(bytes.get(i + 1 .. i + 3)?).ok()?;
+				decoded.push(u8::from_str_radix(hex, 16).ok()?);
+				i += 3;
+			}
+			b'+' => {
+				decoded.push(b' ');
+				i += 1;
+			}
+			b => {
+				decoded.push(b);
+				i += 1;
+			}
+		}
+	}
+	String::from_utf8(decoded).ok()
+}
+
+/// FNV-1a, as replay::Replay::hash() does it.
+fn hash_text(text: &str) -> u64 {
+	text.bytes().fold(0xcbf29ce484222325_u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
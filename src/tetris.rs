@@ -1,32 +1,161 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, VecDeque};
 
+use crate::difficulty;
+use crate::garbage::GarbageGenerator;
+use crate::log;
 use crate::rng;
+use crate::ruleset::{self, Ruleset};
 
-#[derive(Debug)]
+/// Nominal; frontends call GameState::tick() about this often.
+pub const TICKS_PER_SECOND: u32 = 60;
+/// Rows above the visible field where pieces spawn.
+const HIDDEN_ROWS: usize = 2;
+/// Garbage cells have no piece to take a color from.
+const GARBAGE_HUE: f32 = 0.0;
+/// Ditto bombs, which frontends draw differently anyway.
+const BOMB_HUE: f32 = 0.0;
+/// Ditto cells read from text; see GameState::from_ascii() and Piece::from_cells().
+const ASCII_HUE: f32 = 0.6;
+/// HARDCODE How far ahead the next queue shows, by default; see QueueOptions.
+pub const PREVIEW_PIECES: usize = 3;
+/// HARDCODE Ditto, at most.
+pub const MAX_PREVIEW_PIECES: usize = 6;
+/// HARDCODE The stack is in danger once it leaves fewer than this many rows free; see GameState::is_in_danger().
+pub const DANGER_ROWS: usize = 4;
+/// HARDCODE Sizes of the pieces Piece::generate_new() rolls.
+pub const MIN_PIECE_CELLS: usize = 3;
+pub const MAX_PIECE_CELLS: usize = 5;
+
+#[derive(Clone,Debug)]
 pub struct GameState {
 	rng: Box<rng::RandomNumberGenerator>,
+	/// What rng was last seeded with.
+	pub seed: u64,
+	randomizer: Randomizer,
+	/// What pieces are rolled from; takes effect from the next reset().
+	pub piece_set: PieceSet,
+	pub timing: Timing,
+	/// Gravity, locking, clearing, scoring, and spawning; see set_ruleset().
+	ruleset: Box<dyn Ruleset>,
+	/// Adjusts the ruleset's gravity and lock delay, and how much of the next queue shows; takes full effect
+	/// from the next reset(), which also lays down its starting garbage.
+	pub difficulty: difficulty::Preset,
+	/// Takes effect right away.
+	pub queue_options: QueueOptions,
+	/// Ditto.
+	pub lock_options: LockOptions,
+	/// A challenge: each new piece has even odds of coming in mirrored; takes effect from the next piece.
+	pub mirror_pieces: bool,
+	/// The level before any rows are cleared; takes effect from the next reset(), which also awards
+	/// Ruleset::starting_score() for it.
+	pub starting_level: u32,
+	pub phase: Phase,
+	/// Which way pieces fall; the ruleset's choice at reset().
+	pub gravity: Gravity,
+	/// Time already spent falling by one cell-space.
+	ticks_since_drop: u32,
+	/// Ditto under Timing::fall_speed, in FallSpeed::SUBCELLS.
+	fall_progress: u32,
+	is_soft_dropping: bool,
+	/// Time the current piece has spent resting on the stack; see Ruleset::lock_delay().
+	lock_ticks: u32,
+	/// Moves and rotations that have restarted lock_ticks for the current piece.
+	lock_resets: u32,
+	/// When the current piece spawned; see Ruleset::speed_bonus().
+	spawned_at_tick: u64,
+	/// While nonzero, Item::SlowTime halves gravity.
+	pub slow_ticks_left: u32,
+	/// Item::ShrinkNextPiece went off since the last spawn.
+	pub shrink_next_piece: bool,
+	/// Already rolled, in spawn order; topped up lazily so a fresh seed draws the same pieces as before.
+	next_pieces: VecDeque<Piece>,
+	/// Set aside by Action::Hold.
+	pub held_piece: Option<Piece>,
+	/// Hold works once per spawned piece.
+	can_hold: bool,
+	/// When not empty, pieces come from here, round and round, rather than from rng; see set_piece_sequence().
+	piece_sequence: Vec<Piece>,
+	sequence_index: usize,
 	/// Indexing: cell_matrix[y].cells[x] = Some(foo_cell);
 	pub cell_matrix: Vec<Row>,
 	pub cell_matrix_width: usize,
+	/// The first this-many rows of cell_matrix are above the visible field.
+	pub hidden_rows: usize,
 	/// None during row clears
 	pub current_piece: Option<Piece>,
 	/// Global coordinates of the center of mass of this piece; may or may not have a Cell.
 	pub current_piece_mass_xy: (i32, i32),
+	/// Everyone else's piece, where more than one falls at once; see set_players(). current_piece is the first player's.
+	other_slots: Vec<Slot>,
+	/// Whose piece is in current_piece for now; see as_player().
+	active_player: usize,
 	/// Counter; never decremented.
 	pub rows_cleared: u32,
+	/// As the ruleset sees it; for the standard rules, the same as rows_cleared.
+	pub score: u32,
+	/// Where score came from.
+	pub breakdown: ScoreBreakdown,
+	/// Pieces in a row that have cleared something, counting the last one; 0 once one doesn't.
+	pub combo: u32,
+	/// Difficult clears in a row (see ruleset::DIFFICULT_CLEAR_ROWS); pieces that clear nothing don't break it.
+	pub back_to_back: u32,
+	/// Calls to tick() while alive since the last reset; the game clock, which stops whenever the frontend stops ticking, e.g. while paused.
+	pub ticks: u64,
 	pub is_alive: bool,
+	/// Drained by the frontend each frame.
+	events: Vec<Event>,
+	/// Pieces locked since the last reset; see Cell::piece.
+	pub pieces_locked: u32,
+	/// As of the last Event::Danger.
+	was_in_danger: bool,
 }
 
 impl GameState {
-	pub fn new(height: usize, width: usize) -> GameState {
+	pub fn new(height: usize, width: usize, randomizer_strategy: RandomizerStrategy, timing: Timing) -> GameState {
 		let mut gs = Self {
 			rng: Box::default(),
-			cell_matrix: (0 .. height).map(|_| Row::new(width)).collect(),
+			seed: 0,
+			randomizer: Randomizer::new(randomizer_strategy),
+			piece_set: PieceSet::default(),
+			timing,
+			ruleset: Box::new(ruleset::Standard),
+			difficulty: difficulty::Preset::Standard,
+			starting_level: 0,
+			queue_options: QueueOptions::default(),
+			lock_options: LockOptions::default(),
+			mirror_pieces: false,
+			phase: Phase::Falling,
+			gravity: Gravity::Down,
+			ticks_since_drop: 0,
+			fall_progress: 0,
+			is_soft_dropping: false,
+			lock_ticks: 0,
+			lock_resets: 0,
+			spawned_at_tick: 0,
+			slow_ticks_left: 0,
+			shrink_next_piece: false,
+			next_pieces: VecDeque::new(),
+			held_piece: None,
+			can_hold: true,
+			piece_sequence: Vec::new(),
+			sequence_index: 0,
+			cell_matrix: (0 .. height + HIDDEN_ROWS).map(|_| Row::new(width)).collect(),
 			cell_matrix_width: width,
+			hidden_rows: HIDDEN_ROWS,
 			current_piece: None, // generated below
 			current_piece_mass_xy: (0, 0), // ibid
+			other_slots: Vec::new(),
+			active_player: 0,
 			rows_cleared: 0,
+			score: 0,
+			breakdown: ScoreBreakdown::default(),
+			combo: 0,
+			back_to_back: 0,
+			ticks: 0,
 			is_alive: true,
+			events: Vec::new(),
+			pieces_locked: 0,
+			was_in_danger: false,
 		};
 		gs.queue_new_piece();
 		gs
@@ -36,127 +165,1564 @@ impl GameState {
 		self.cell_matrix.iter_mut().for_each(|row| row.reset());
 		self.current_piece = None;
 		self.current_piece_mass_xy = (0, 0);
+		self.other_slots.iter_mut().for_each(|slot| *slot = Slot::default());
+		self.phase = Phase::Entry { ticks_left: self.timing.entry_delay };
+		self.gravity = self.ruleset.gravity(0);
+		self.ticks_since_drop = 0;
+		self.fall_progress = 0;
+		self.is_soft_dropping = false;
+		self.lock_ticks = 0;
+		self.lock_resets = 0;
+		self.slow_ticks_left = 0;
+		self.shrink_next_piece = false;
+		self.next_pieces.clear();
+		self.held_piece = None;
+		self.can_hold = true;
+		self.piece_sequence.clear();
+		self.sequence_index = 0;
 		self.rows_cleared = 0;
+		self.score = self.ruleset.starting_score(self.starting_level);
+		self.breakdown = ScoreBreakdown { starting: self.score, ..ScoreBreakdown::default() };
+		self.combo = 0;
+		self.back_to_back = 0;
+		self.ticks = 0;
 		self.is_alive = true;
+		self.randomizer.history.clear();
+		self.events.clear();
+		self.pieces_locked = 0;
+		self.was_in_danger = false;
+		let garbage_rows = self.difficulty.garbage_rows();
+		if garbage_rows > 0 {
+			self.add_garbage(garbage_rows, &mut difficulty::Preset::garbage_generator());
+		}
+	}
+
+	/// Same seed, same pieces.
+	pub fn reseed(&mut self, seed: u64) {
+		self.seed = seed;
+		*self.rng = rng::RandomNumberGenerator::new(seed);
+		self.next_pieces.clear();
+	}
+
+	/// Takes effect from the next piece; call reset() to start a game under it.
+	pub fn set_ruleset(&mut self, ruleset: Box<dyn Ruleset>) {
+		self.ruleset = ruleset;
+	}
+
+	pub fn ruleset_name(&self) -> &'static str {
+		self.ruleset.name()
+	}
+
+	/// Game time so far, at the nominal TICKS_PER_SECOND rather than however fast the frontend really ticked.
+	pub fn elapsed(&self) -> std::time::Duration {
+		std::time::Duration::from_millis(self.ticks * 1000 / TICKS_PER_SECOND as u64)
+	}
+
+	/// Per the ruleset; usually one more every ten rows. Never below starting_level, which holds until the rows
+	/// cleared catch up with it.
+	pub fn level(&self) -> u32 {
+		self.ruleset.level(self.rows_cleared).max(self.starting_level)
+	}
+
+	/// Push `count` rows of garbage up from the floor of the well, whichever way gravity points, each with one hole.
+	pub fn add_garbage(&mut self, count: usize, generator: &mut dyn GarbageGenerator) {
+		let fits = self.with_gravity_frame(|_ruleset, frame| {
+			for i in 0 .. count {
+				let width = frame[0].cells.len();
+				let hole = generator.next_hole(width, i == 0) % width;
+				frame.rotate_left(1);
+				let row = frame.last_mut().expect("Should have rows");
+				let is_pushed_out = !row.is_empty;
+				*row = Row::new(width);
+				for (x, cell) in row.cells.iter_mut().enumerate() {
+					if x != hole {
+						*cell = Some(Cell::with_kind(GARBAGE_HUE, CellKind::Garbage));
+					}
+				}
+				row.is_empty = false;
+				if is_pushed_out {
+					return false;
+				}
+			}
+			true
+		});
+		if !fits {
+			self.top_out(TopOut::PushedOut);
+			return;
+		}
+		let (dx, dy) = self.gravity.delta();
+		let (height, width) = (self.cell_matrix.len(), self.cell_matrix_width);
+		let shift = |line: usize, d: i32, lines: usize| line.checked_add_signed(-d as isize * count as isize).filter(|&l| l < lines);
+		if let Phase::LineClear { rows, columns, .. } = &mut self.phase {
+			*rows = rows.iter().filter_map(|&r| shift(r, dy, height)).collect();
+			*columns = columns.iter().filter_map(|&c| shift(c, dx, width)).collect();
+		}
+		// Rise with the stack rather than sinking into it.
+		for player in 0 .. self.players() {
+			if !self.as_player(player, |gs| gs.back_off((-dx, -dy))) {
+				self.top_out(TopOut::PushedOut);
+				return;
+			}
+		}
+		self.update_danger();
+		self.debug_validate();
+	}
+
+	/// How far the visible stack reaches up from the floor, in cells along gravity; out of stack_room().
+	pub fn stack_height(&self) -> usize {
+		self.surface().into_iter().max().unwrap_or(0)
+	}
+
+	/// The stack's skyline: each column's height as stack_height() measures it, left to right; rows, top to bottom,
+	/// under sideways gravity.
+	pub fn surface(&self) -> Vec<usize> {
+		let visible = &self.cell_matrix[self.hidden_rows ..];
+		let (rows, columns) = (visible.len(), self.cell_matrix_width);
+		let mut surface = vec![0; if self.gravity.is_vertical() { columns } else { rows }];
+		for (y, row) in visible.iter().enumerate() {
+			for (x, _cell) in row.cells.iter().enumerate().filter(|(_x, c)| c.is_some()) {
+				let (line, height) = match self.gravity {
+					Gravity::Down => (x, rows - y),
+					Gravity::Up => (x, y + 1),
+					Gravity::Right => (y, columns - x),
+					Gravity::Left => (y, x + 1),
+				};
+				surface[line] = surface[line].max(height);
+			}
+		}
+		surface
+	}
+
+	/// The most stack_height() can be: the visible field, along gravity.
+	pub fn stack_room(&self) -> usize {
+		if self.gravity.is_vertical() { self.cell_matrix.len() - self.hidden_rows } else { self.cell_matrix_width }
+	}
+
+	/// Fewer than DANGER_ROWS left free; Event::Danger says when this changes.
+	pub fn is_in_danger(&self) -> bool {
+		self.stack_room().saturating_sub(self.stack_height()) < DANGER_ROWS
+	}
+
+	/// Once the stack has settled, between pieces.
+	fn update_danger(&mut self) {
+		let is_in_danger = self.is_alive && self.is_in_danger();
+		if is_in_danger != self.was_in_danger {
+			self.was_in_danger = is_in_danger;
+			self.events.push(Event::Danger(is_in_danger));
+		}
+	}
+
+	/// The visible stack, one line per row from the top, in CellKind::to_char() characters; `.` for empty.
+	/// The falling piece is left out.
+	pub fn to_ascii(&self) -> String {
+		let mut text = String::new();
+		for row in self.cell_matrix.iter().skip(self.hidden_rows) {
+			text.extend(row.cells.iter().map(|c| c.as_ref().map_or('.', |c| c.kind.to_char())));
+			text.push('\n');
+		}
+		text
+	}
+
+	/// A fresh game, sized to fit, with to_ascii()'s stack in place; blank lines are ignored.
+	/// Random pieces and default timing; change them as usual.
+	pub fn from_ascii(text: &str) -> Result<GameState, String> {
+		let lines: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+		let width = lines.first().ok_or("No rows")?.chars().count();
+		if let Some(line) = lines.iter().find(|line| line.chars().count() != width) {
+			return Err(format!("Row `{line}` is not {width} cells wide"));
+		}
+		let mut game_state = GameState::new(lines.len(), width, RandomizerStrategy::Memoryless, Timing::default());
+		for (y, line) in lines.iter().enumerate() {
+			for (x, c) in line.chars().enumerate() {
+				if let Some(kind) = CellKind::from_char(c) {
+					game_state.set_cell(x, y + game_state.hidden_rows, Some(Cell::with_kind(ASCII_HUE, kind)));
+				}
+			}
+		}
+		Ok(game_state)
+	}
+
+	/// Keeps the per-row bookkeeping in sync; prefer this over poking cell_matrix directly.
+	pub fn set_cell(&mut self, x: usize, y: usize, cell: Option<Cell>) {
+		let row = &mut self.cell_matrix[y];
+		row.cells[x] = cell;
+		row.recount();
+	}
+
+	pub fn drain_events(&mut self) -> std::vec::Drain<'_, Event> {
+		self.events.drain(..)
+	}
+
+	/// Gravity and locking for the current piece, for one tick of Phase::Falling.
+	fn fall_step(&mut self) {
+		let lock_delay = self.lock_delay();
+		if lock_delay > 0 && self.is_resting() {
+			self.lock_ticks += 1;
+			if self.lock_ticks >= lock_delay || (self.is_soft_dropping && self.lock_options.soft_drop_locks) {
+				self.lock_current_piece();
+			}
+			return;
+		}
+		if let Some(speed) = self.timing.fall_speed {
+			let mut subcells = speed.0;
+			if self.is_soft_dropping {
+				subcells = subcells.max(FallSpeed::SUBCELLS / self.timing.ticks_per_drop_fast.max(1));
+			}
+			if self.slow_ticks_left > 0 {
+				subcells /= 2;
+			}
+			self.fall(subcells);
+			return;
+		}
+		self.ticks_since_drop += 1;
+		let mut ticks_per_drop = self.ruleset.ticks_per_drop(&self.timing, self.is_soft_dropping, self.level());
+		if !self.is_soft_dropping {
+			ticks_per_drop = self.difficulty.ticks_per_drop(ticks_per_drop, self.level());
+		}
+		if self.slow_ticks_left > 0 {
+			ticks_per_drop *= 2;
+		}
+		if self.ticks_since_drop >= ticks_per_drop {
+			self.ticks_since_drop = 0;
+			self.try_drop_current_piece();
+		}
+	}
+
+	/// Advance the game by one tick.
+	pub fn tick(&mut self) {
+		self.advance();
+		self.debug_validate();
+	}
+
+	/// See tick().
+	fn advance(&mut self) {
+		if !self.is_alive {
+			return;
+		}
+		self.ticks += 1;
+		self.slow_ticks_left = self.slow_ticks_left.saturating_sub(1);
+		match &mut self.phase {
+			Phase::Falling => {
+				self.fall_step();
+				for player in 1 .. self.players() {
+					if !matches!(self.phase, Phase::Falling) || !self.is_alive {
+						break;
+					}
+					self.as_player(player, Self::fall_step);
+				}
+			}
+			Phase::LineClear { ticks_left, .. } => {
+				if *ticks_left > 0 {
+					*ticks_left -= 1;
+				} else {
+					let level = self.level();
+					let mut count = self.clear_lines();
+					let gravity = self.ruleset.gravity(self.rows_cleared + count);
+					if gravity != self.gravity {
+						log::debug!("Tick {}: gravity {:?} -> {gravity:?}", self.ticks, self.gravity);
+						self.gravity = gravity;
+						// Everything falls the new way, which may well fill more lines.
+						self.with_gravity_frame(|_ruleset, frame| ruleset::settle_cells(frame));
+						count += self.clear_lines();
+						self.events.push(Event::GravityChanged(gravity));
+					}
+					self.rows_cleared += count;
+					self.score_clear(count, level);
+					self.events.push(Event::RowsCleared(count));
+					if self.level() > level {
+						self.events.push(Event::LevelUp(self.level()));
+					}
+					// Pieces still in the air may now overlap what moved.
+					if self.players() > 1 {
+						let (dx, dy) = self.gravity.delta();
+						for player in 0 .. self.players() {
+							if !self.as_player(player, |gs| gs.back_off((-dx, -dy))) {
+								self.top_out(TopOut::PushedOut);
+								return;
+							}
+						}
+					}
+					log::trace!("Tick {}: line clear -> entry", self.ticks);
+					self.phase = Phase::Entry { ticks_left: self.timing.entry_delay };
+					self.update_danger();
+				}
+			}
+			Phase::Entry { ticks_left } => {
+				if *ticks_left > 0 {
+					*ticks_left -= 1;
+				} else {
+					// Whoever's piece didn't lock keeps it.
+					for player in 0 .. self.players() {
+						self.as_player(player, |gs| if gs.current_piece.is_none() { gs.queue_new_piece(); });
+					}
+					log::trace!("Tick {}: entry -> falling", self.ticks);
+					self.phase = Phase::Falling;
+				}
+			}
+		}
+	}
+
+	pub fn set_soft_drop(&mut self, is_soft_dropping: bool) {
+		self.is_soft_dropping = is_soft_dropping;
+	}
+
+	/// Everything a player can do; replays are a list of these.
+	pub fn apply(&mut self, action: Action) {
+		match action {
+			Action::MoveLeft => { self.try_leftright_current_piece(true); }
+			Action::MoveRight => { self.try_leftright_current_piece(false); }
+			Action::RotateClockwise => { self.try_rotate_current_piece(true); }
+			Action::RotateCounterClockwise => { self.try_rotate_current_piece(false); }
+			Action::SoftDropStart => self.set_soft_drop(true),
+			Action::SoftDropStop => self.set_soft_drop(false),
+			Action::Hold => { self.try_hold(); }
+			Action::HardDrop => { self.hard_drop(); }
+			Action::SonicDrop => { self.sonic_drop(); }
+		}
+		self.debug_validate();
+	}
+
+	/// Checks what should always hold between ticks: every row as wide as the board and right about whether it's
+	/// empty, and every falling piece on the board and clear of the stack and each other, unless that's how the game
+	/// topped out.
+	pub fn validate(&self) -> Result<(), String> {
+		if self.cell_matrix.len() <= self.hidden_rows {
+			return Err(format!("{} rows, but {} of them hidden", self.cell_matrix.len(), self.hidden_rows));
+		}
+		for (y, row) in self.cell_matrix.iter().enumerate() {
+			if row.cells.len() != self.cell_matrix_width {
+				return Err(format!("Row {y} is {} cells wide; the board is {}", row.cells.len(), self.cell_matrix_width));
+			}
+			if row.is_empty != row.cells.iter().all(Option::is_none) {
+				return Err(format!("Row {y} says it's {}empty, but it isn't", if row.is_empty { "" } else { "not " }));
+			}
+		}
+		if !self.is_alive {
+			return Ok(());
+		}
+		let mut taken = BTreeSet::new();
+		let current = self.current_piece.as_ref().map(|p| (p, self.current_piece_mass_xy));
+		for (p, xy) in current.into_iter().chain(self.other_pieces()) {
+			for (_c, x, y) in p.iter_global_space(xy) {
+				if x < 0 || y < 0 || x as usize >= self.cell_matrix_width || y as usize >= self.cell_matrix.len() {
+					return Err(format!("A falling piece has a cell off the board at ({x}, {y})"));
+				}
+				if self.cell_matrix[y as usize].cells[x as usize].is_some() {
+					return Err(format!("A falling piece overlaps the stack at ({x}, {y})"));
+				}
+				if !taken.insert((x, y)) {
+					return Err(format!("Two falling pieces overlap at ({x}, {y})"));
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Panics on anything validate() finds, in debug builds only; release builds skip the check.
+	fn debug_validate(&self) {
+		if cfg!(debug_assertions) {
+			if let Err(e) = self.validate() {
+				panic!("Tick {}: {e}", self.ticks);
+			}
+		}
+	}
+
+	/// What spawns after the current piece, soonest first; see preview_count().
+	pub fn next_pieces(&self) -> impl Iterator<Item = &Piece> {
+		self.next_pieces.iter().take(self.preview_count())
+	}
+
+	/// How far ahead the next queue shows: as far as the queue options say, as the difficulty adjusts it.
+	pub fn preview_count(&self) -> usize {
+		self.difficulty.preview_pieces(self.queue_options.previews)
+	}
+
+	/// Goes ahead of everything already in the next queue.
+	pub fn push_next_piece(&mut self, p: Piece) {
+		self.next_pieces.push_front(p);
+	}
+
+	/// Replaces the whole next queue, e.g. to set up a position; random pieces follow these, or the sequence if one is set.
+	pub fn replace_next_pieces(&mut self, pieces: Vec<Piece>) {
+		self.next_pieces = pieces.into();
+	}
+
+	/// Puts `p` in play at the spawn point right away, skipping any entry delay; hold works again.
+	pub fn spawn(&mut self, p: Piece) {
+		self.phase = Phase::Falling;
+		self.can_hold = true;
+		self.spawn_piece(p);
+		self.debug_validate();
+	}
+
+	/// Pieces repeat this sequence, starting from the next one, until reset(); empty goes back to random pieces.
+	pub fn set_piece_sequence(&mut self, sequence: Vec<Piece>) {
+		self.piece_sequence = sequence;
+		self.sequence_index = 0;
+		self.next_pieces.clear();
+	}
+
+	/// Drops the current piece as far as it goes, whichever way gravity points, and locks it there; returns how many cells it fell.
+	pub fn hard_drop(&mut self) -> u32 {
+		if !matches!(self.phase, Phase::Falling) {
+			return 0;
+		}
+		let mut cells = 0;
+		while self.current_piece.is_some() && self.try_drop_current_piece() {
+			cells += 1;
+		}
+		let bonus = self.ruleset.hard_drop_bonus(cells);
+		self.score += bonus;
+		self.breakdown.hard_drops += bonus;
+		cells
+	}
+
+	/// Like hard_drop(), but the piece stays in play on the stack, and earns nothing; returns how far it fell.
+	pub fn sonic_drop(&mut self) -> u32 {
+		let Some(p) = self.current_piece.as_ref().filter(|_p| matches!(self.phase, Phase::Falling))
+			else { return 0; };
+		let (dx, dy) = self.gravity.delta();
+		let (mut x, mut y) = self.current_piece_mass_xy;
+		let mut cells = 0;
+		while self.can_place(p, (x + dx, y + dy)) {
+			(x, y) = (x + dx, y + dy);
+			cells += 1;
+		}
+		self.current_piece_mass_xy = (x, y);
+		cells
+	}
+
+	/// Swaps the current piece for the held one, or for the next one if nothing is held yet.
+	pub fn try_hold(&mut self) -> bool {
+		if !self.can_hold() {
+			return false;
+		}
+		let Some(current) = self.current_piece.take()
+			else { return false; };
+		self.can_hold = false;
+		let p = match self.held_piece.replace(current) {
+			Some(held) => held,
+			None => self.pop_next_piece(),
+		};
+		self.spawn_piece(p);
+		true
+	}
+
+	/// Whether Action::Hold would do anything now.
+	pub fn can_hold(&self) -> bool {
+		self.queue_options.hold && self.can_hold && matches!(self.phase, Phase::Falling)
 	}
 
+	pub fn randomizer_strategy(&self) -> RandomizerStrategy {
+		self.randomizer.strategy
+	}
+
+	/// FNV-1a over everything that should match between two runs of the same replay.
+	pub fn checksum(&self) -> u64 {
+		let mut hash = 0xcbf29ce484222325_u64;
+		let mut feed = |bytes: &[u8]| {
+			for b in bytes {
+				hash = (hash ^ *b as u64).wrapping_mul(0x100000001b3);
+			}
+		};
+		feed(&self.seed.to_le_bytes());
+		feed(&self.ticks.to_le_bytes());
+		feed(&self.rows_cleared.to_le_bytes());
+		feed(&self.score.to_le_bytes());
+		for row in self.cell_matrix.iter() {
+			for cell in row.cells.iter() {
+				match cell {
+					Some(c) => {
+						feed(&c.hue.to_bits().to_le_bytes());
+						feed(&[c.kind as u8]);
+					}
+					None => feed(&[0xff]),
+				}
+			}
+		}
+		match &self.phase {
+			Phase::Falling => feed(&[0]),
+			Phase::LineClear { ticks_left, rows, columns } => {
+				feed(&[1]);
+				feed(&ticks_left.to_le_bytes());
+				rows.iter().chain(columns.iter()).for_each(|i| feed(&(*i as u32).to_le_bytes()));
+			}
+			Phase::Entry { ticks_left } => {
+				feed(&[2]);
+				feed(&ticks_left.to_le_bytes());
+			}
+		}
+		let mut feed_piece = |piece: Option<&Piece>, (x, y): (i32, i32)| {
+			let Some(piece) = piece
+				else { return feed(&[0xff]); };
+			feed(&[piece.kind as u8]);
+			feed(&x.to_le_bytes());
+			feed(&y.to_le_bytes());
+			for c in piece.cells.iter() {
+				feed(&c.cell.hue.to_bits().to_le_bytes());
+				feed(&c.x.to_le_bytes());
+				feed(&c.y.to_le_bytes());
+			}
+		};
+		feed_piece(self.current_piece.as_ref(), self.current_piece_mass_xy);
+		for slot in self.other_slots.iter() {
+			feed_piece(slot.piece.as_ref(), slot.mass_xy);
+		}
+		feed_piece(self.held_piece.as_ref(), (0, 0));
+		for piece in self.next_pieces.iter() {
+			feed_piece(Some(piece), (0, 0));
+		}
+		hash
+	}
+
+	/// A copy to take deltas against later; see delta().
+	pub fn snapshot(&self) -> GameState {
+		self.clone()
+	}
+
+	/// What turns `base`, an earlier or later state of the same game, into this one: the rows that differ, the
+	/// falling piece, the queue and hold box if they changed, and a few numbers. Much smaller than a snapshot while
+	/// the stack sits still. For one player; other players' pieces stay as they are in the base.
+	pub fn delta(&self, base: &GameState) -> Delta {
+		let rows = self.cell_matrix.iter().enumerate()
+			.filter(|&(y, row)| base.cell_matrix.get(y) != Some(row))
+			.map(|(y, row)| (y, row.clone()))
+			.collect();
+		let changed = |ours: &VecDeque<Piece>, theirs: &VecDeque<Piece>| ours.len() != theirs.len() || ours.iter().zip(theirs).any(|(a, b)| a.to_text() != b.to_text());
+		let held_changed = self.held_piece.as_ref().map(Piece::to_text) != base.held_piece.as_ref().map(Piece::to_text);
+		Delta {
+			height: self.cell_matrix.len(),
+			width: self.cell_matrix_width,
+			rows,
+			piece: self.current_piece.clone(),
+			mass_xy: self.current_piece_mass_xy,
+			held_piece: held_changed.then(|| self.held_piece.clone()),
+			next_pieces: changed(&self.next_pieces, &base.next_pieces).then(|| self.next_pieces.iter().cloned().collect()),
+			history: (self.randomizer.history != base.randomizer.history).then(|| self.randomizer.history.iter().cloned().collect()),
+			rng_state: self.rng.state(),
+			phase: self.phase.clone(),
+			gravity: self.gravity,
+			counters: self.counters(),
+		}
+	}
+
+	/// Call on the `base` that `delta` came from; see delta(). Events waiting to be drained are dropped, since they
+	/// belong to the base's moment rather than the delta's.
+	pub fn apply_delta(&mut self, delta: &Delta) {
+		self.cell_matrix_width = delta.width;
+		self.cell_matrix.resize_with(delta.height, || Row::new(delta.width));
+		for (y, row) in delta.rows.iter() {
+			self.cell_matrix[*y] = row.clone();
+		}
+		self.current_piece = delta.piece.clone();
+		self.current_piece_mass_xy = delta.mass_xy;
+		if let Some(held_piece) = delta.held_piece.as_ref() {
+			self.held_piece = held_piece.clone();
+		}
+		if let Some(next_pieces) = delta.next_pieces.as_ref() {
+			self.next_pieces = next_pieces.iter().cloned().collect();
+		}
+		if let Some(history) = delta.history.as_ref() {
+			self.randomizer.history = history.iter().cloned().collect();
+		}
+		*self.rng = rng::RandomNumberGenerator::from_state(delta.rng_state);
+		self.phase = delta.phase.clone();
+		self.gravity = delta.gravity;
+		self.set_counters(delta.counters);
+		self.events.clear();
+		self.debug_validate();
+	}
+
+	/// The numbers a Delta carries, in a fixed order; set_counters() takes them back.
+	fn counters(&self) -> [u64; COUNTERS] {
+		let b = &self.breakdown;
+		[
+			self.ticks, self.spawned_at_tick, self.score as u64, self.rows_cleared as u64, self.combo as u64,
+			self.back_to_back as u64, self.ticks_since_drop as u64, self.fall_progress as u64, self.lock_ticks as u64,
+			self.lock_resets as u64, self.slow_ticks_left as u64, self.sequence_index as u64, self.pieces_locked as u64,
+			b.starting as u64, b.clears as u64, b.combos as u64, b.back_to_back as u64, b.speed as u64, b.hard_drops as u64,
+			self.is_alive as u64, self.is_soft_dropping as u64, self.shrink_next_piece as u64, self.can_hold as u64,
+			self.was_in_danger as u64,
+		]
+	}
+
+	fn set_counters(&mut self, counters: [u64; COUNTERS]) {
+		let [
+			ticks, spawned_at_tick, score, rows_cleared, combo,
+			back_to_back, ticks_since_drop, fall_progress, lock_ticks,
+			lock_resets, slow_ticks_left, sequence_index, pieces_locked,
+			starting, clears, combos, back_to_back_score, speed, hard_drops,
+			is_alive, is_soft_dropping, shrink_next_piece, can_hold,
+			was_in_danger,
+		] = counters;
+		(self.ticks, self.spawned_at_tick) = (ticks, spawned_at_tick);
+		(self.score, self.rows_cleared, self.combo, self.back_to_back) = (score as u32, rows_cleared as u32, combo as u32, back_to_back as u32);
+		(self.ticks_since_drop, self.fall_progress, self.lock_ticks, self.lock_resets) = (ticks_since_drop as u32, fall_progress as u32, lock_ticks as u32, lock_resets as u32);
+		(self.slow_ticks_left, self.sequence_index, self.pieces_locked) = (slow_ticks_left as u32, sequence_index as usize, pieces_locked as u32);
+		self.breakdown = ScoreBreakdown { starting: starting as u32, clears: clears as u32, combos: combos as u32, back_to_back: back_to_back_score as u32, speed: speed as u32, hard_drops: hard_drops as u32 };
+		(self.is_alive, self.is_soft_dropping, self.shrink_next_piece, self.can_hold, self.was_in_danger) = (is_alive != 0, is_soft_dropping != 0, shrink_next_piece != 0, can_hold != 0, was_in_danger != 0);
+	}
+
+	fn top_out(&mut self, cause: TopOut) {
+		log::debug!("Tick {}: topped out by {cause:?}", self.ticks);
+		self.is_alive = false;
+		self.events.push(Event::ToppedOut(cause));
+	}
+
+	/// In place if it fits, or else at the first of the ruleset's kicks that does.
 	pub fn try_rotate_current_piece(&mut self, clockwise: bool) -> bool {
 		if let Some(p_old) = self.current_piece.as_ref() {
 			let p_new = p_old.rotated(clockwise);
-			let dst = self.current_piece_mass_xy;
-			if self.can_place(&p_new, dst) {
-				self.current_piece = Some(p_new);
-				return true;
+			let kicks = self.ruleset.kick_table(p_old);
+			let (x, y) = self.current_piece_mass_xy;
+			let (dx, dy) = self.gravity.delta();
+			for (across, up) in std::iter::once((0, 0)).chain(kicks.offsets(clockwise)) {
+				let dst = if self.gravity.is_vertical() { (x + across, y) } else { (x, y + across) };
+				let dst = (dst.0 - up * dx, dst.1 - up * dy);
+				if self.can_place(&p_new, dst) {
+					self.current_piece = Some(p_new);
+					self.current_piece_mass_xy = dst;
+					if self.lock_options.rotation_resets {
+						self.restart_lock_delay();
+					}
+					return true;
+				}
 			}
 		}
 		false
 	}
 
+	/// Across gravity; under sideways gravity, left is up.
 	pub fn try_leftright_current_piece(&mut self, leftwards: bool) -> bool {
 		if let Some(p) = self.current_piece.as_ref() {
 			let direction = if leftwards { -1 } else { 1 };
-			let dst = (self.current_piece_mass_xy.0 + direction, self.current_piece_mass_xy.1);
+			let (x, y) = self.current_piece_mass_xy;
+			let dst = if self.gravity.is_vertical() { (x + direction, y) } else { (x, y + direction) };
 			if self.can_place(p, dst) {
 				self.current_piece_mass_xy = dst;
+				self.restart_lock_delay();
 				return true;
 			}
 		}
 		false
 	}
 
-	pub fn try_drop_current_piece(&mut self) -> bool {
+	/// Whether the current piece is sitting on the stack or the floor.
+	fn is_resting(&self) -> bool {
+		let Some(p) = self.current_piece.as_ref()
+			else { return false; };
+		let ((x, y), (dx, dy)) = (self.current_piece_mass_xy, self.gravity.delta());
+		!self.can_place(p, (x + dx, y + dy))
+	}
+
+	/// The ruleset's, unless the difficulty says otherwise.
+	fn lock_delay(&self) -> u32 {
+		self.difficulty.lock_delay().unwrap_or_else(|| self.ruleset.lock_delay())
+	}
+
+	/// A move or rotation buys the piece more time on the stack, a limited number of times.
+	fn restart_lock_delay(&mut self) {
+		let max_lock_resets = self.lock_options.max_resets.unwrap_or_else(|| {
+			if self.difficulty.lock_delay().is_some() { difficulty::MAX_LOCK_RESETS } else { self.ruleset.max_lock_resets() }
+		});
+		if self.lock_ticks > 0 && self.lock_resets < max_lock_resets {
+			self.lock_ticks = 0;
+			self.lock_resets += 1;
+		}
+	}
+
+	fn try_drop_current_piece(&mut self) -> bool {
 		if let Some(p) = self.current_piece.as_ref() {
-			let dst = (self.current_piece_mass_xy.0, self.current_piece_mass_xy.1 + 1);
+			let ((x, y), (dx, dy)) = (self.current_piece_mass_xy, self.gravity.delta());
+			let dst = (x + dx, y + dy);
 			if self.can_place(p, dst) {
 				self.current_piece_mass_xy = dst;
 				true
 			} else {
-				self.commit_current_piece();
-				self.clear_finished_rows();
+				self.lock_current_piece();
 				false
 			}
 		} else {
-			self.queue_new_piece();
 			false
 		}
 	}
 
+	/// Down by as many whole cells as `subcells` and what was left over from before add up to.
+	fn fall(&mut self, subcells: u32) {
+		self.fall_progress = self.fall_progress.saturating_add(subcells);
+		let mut has_fallen = false;
+		while self.fall_progress >= FallSpeed::SUBCELLS {
+			self.fall_progress -= FallSpeed::SUBCELLS;
+			// Landing and locking take a tick each, as at lower speeds.
+			if (has_fallen && self.is_resting()) || !self.try_drop_current_piece() {
+				self.fall_progress = 0;
+				return;
+			}
+			has_fallen = true;
+		}
+	}
+
+	fn lock_current_piece(&mut self) {
+		let Some(p) = self.current_piece.as_ref()
+			else { return; };
+		let kind = p.kind;
+		let hidden_rows = self.hidden_rows as i32;
+		// Otherwise pieces never enter the hidden rows, so only block outs end the game.
+		let is_lock_out = self.gravity == Gravity::Down
+			&& p.iter_global_space(self.current_piece_mass_xy).all(|(_c, _x, y)| y < hidden_rows);
+		self.commit_current_piece();
+		if is_lock_out {
+			self.top_out(TopOut::LockOut);
+			return;
+		}
+		self.events.push(Event::PieceLocked);
+		let bonus = self.ruleset.speed_bonus(self.ticks.saturating_sub(self.spawned_at_tick), self.level());
+		self.score += bonus;
+		self.breakdown.speed += bonus;
+		if kind == PieceKind::Bomb {
+			self.explode(self.current_piece_mass_xy);
+		}
+		// Something interesting happened, so we want to slow down enough to see it.
+		self.is_soft_dropping = false;
+		let lines = self.with_gravity_frame(|ruleset, frame| ruleset.finished_rows(frame));
+		log::trace!("Tick {}: locked at {:?}, finishing lines {lines:?}", self.ticks, self.current_piece_mass_xy);
+		let (width, height) = (self.cell_matrix_width, self.cell_matrix.len());
+		let (mut rows, mut columns) = (Vec::new(), Vec::new());
+		for line in lines {
+			match self.gravity {
+				Gravity::Down => rows.push(line),
+				Gravity::Up => rows.push(height - 1 - line),
+				Gravity::Right => columns.push(line),
+				Gravity::Left => columns.push(width - 1 - line),
+			}
+		}
+		self.phase = if rows.is_empty() && columns.is_empty() {
+			self.combo = 0;
+			Phase::Entry { ticks_left: self.timing.entry_delay }
+		} else {
+			Phase::LineClear { ticks_left: self.timing.line_clear_delay, rows, columns }
+		};
+		// Rows about to clear don't count.
+		if let Phase::Entry { .. } = self.phase {
+			self.update_danger();
+		}
+	}
+
+	fn score_clear(&mut self, rows: u32, level: u32) {
+		if rows == 0 {
+			self.combo = 0;
+			return;
+		}
+		self.combo += 1;
+		let points = self.ruleset.score(rows, level);
+		let combo_bonus = self.ruleset.combo_bonus(self.combo, level);
+		self.score += points + combo_bonus;
+		self.breakdown.clears += points;
+		self.breakdown.combos += combo_bonus;
+		if rows < ruleset::DIFFICULT_CLEAR_ROWS {
+			self.back_to_back = 0;
+			return;
+		}
+		self.back_to_back += 1;
+		if self.back_to_back > 1 {
+			let bonus = self.ruleset.back_to_back_bonus(points);
+			self.score += bonus;
+			self.breakdown.back_to_back += bonus;
+		}
+	}
+
 	fn commit_current_piece(&mut self) {
 		if let Some(p) = self.current_piece.take() {
+			self.pieces_locked += 1;
 			for (c, x, y) in p.iter_global_space(self.current_piece_mass_xy) {
 				// SAFETY: called .can_place() before this method
 				let (x, y) = (x as usize, y as usize);
-				self.cell_matrix[y].cells[x] = Some(c.clone());
+				self.cell_matrix[y].cells[x] = Some(Cell { piece: self.pieces_locked, ..c.clone() });
 				self.cell_matrix[y].is_empty = false;
 			}
 		}
 	}
 
-	fn clear_finished_rows(&mut self) {
-		// clear and drop rows; is bubble-sort in slow motion
-		let mut anything_changed = false;
-		for i_row in 0 .. self.cell_matrix.len() {
-			{
-				let row = &mut self.cell_matrix[i_row];
-				if row.is_empty {
-					continue;
-				}
-				if row.cells.iter().all(Option::is_some) {
-					row.cells.iter_mut().for_each(|c| { c.take(); });
-					self.rows_cleared += 1;
-					row.is_empty = true;
-					anything_changed = true;
-				}
-			}
-			// drop higher cells
-			if self.cell_matrix[i_row].is_empty {
-				for i_row in (1 ..= i_row).rev() {
-					let i_above = i_row - 1;
-					if !self.cell_matrix[i_above].is_empty {
-						self.cell_matrix.swap(i_row, i_row-1);
-					} else {
-						break;
-					}
-				}
-			}
+	fn queue_new_piece(&mut self) {
+		self.can_hold = true;
+		let mut p = self.pop_next_piece();
+		// Here rather than when rolled, so the preview doesn't give it away.
+		if self.mirror_pieces && self.rng.uniform(0, 2) == 0 {
+			p = p.mirrored();
+		}
+		self.spawn_piece(p);
+	}
+
+	fn pop_next_piece(&mut self) -> Piece {
+		// Rolled as far ahead as shows; the order they come in is the same either way.
+		while self.next_pieces.len() <= PREVIEW_PIECES.max(self.preview_count()) {
+			let p = self.roll_piece();
+			self.next_pieces.push_back(p);
 		}
-		// FIXME handle multi-clears; maybe take everything above the cleared line and bundle it into a super-Piece?
-		// loop until no more clears
-		if anything_changed {
-			self.clear_finished_rows();
+		let p = self.next_pieces.pop_front().expect("Should have topped up");
+		if std::mem::take(&mut self.shrink_next_piece) {
+			return p.shrunk(2); // HARDCODE
 		}
+		p
 	}
 
-	fn queue_new_piece(&mut self) {
-		let p: Piece = Piece::generate_new(&mut self.rng);
-		let clearance = p.iter_global_space((0, 0)).map(|(_c, _x, y)| y).min()
-			.expect("Should have cells")
-			.abs();
-		let init_xy = (self.cell_matrix_width as i32 / 2, clearance); // HARDCODE Should this be random?
+	fn roll_piece(&mut self) -> Piece {
+		if !self.piece_sequence.is_empty() {
+			let p = self.piece_sequence[self.sequence_index % self.piece_sequence.len()].clone();
+			self.sequence_index += 1;
+			return p;
+		}
+		let mut p: Piece = Piece::generate_new(&mut self.rng, &mut self.randomizer, self.piece_set);
+		let bomb_chance = self.ruleset.bomb_chance();
+		if bomb_chance > 0.0 && self.rng.uniform(0.0, 1.0) < bomb_chance {
+			p = Piece::bomb();
+		}
+		let item_chance = self.ruleset.item_chance();
+		if item_chance > 0.0 && self.rng.uniform(0.0, 1.0) < item_chance {
+			let item = *self.rng.choose(&Item::ALL).expect("Should have items");
+			let i = self.rng.uniform(0, p.cells.len());
+			p.cells[i].cell.item = Some(item);
+		}
+		p
+	}
+
+	fn spawn_piece(&mut self, p: Piece) {
+		let mut init_xy = self.ruleset.spawn_xy(&p, self.gravity, self.cell_matrix_width, self.cell_matrix.len(), self.hidden_rows);
+		// Each player in a lane of their own, the first on the left.
+		let players = self.players() as i32;
+		if players > 1 && self.gravity.is_vertical() {
+			let (width, lane) = (self.cell_matrix_width as i32, self.cell_matrix_width as i32 / players);
+			init_xy.0 += lane * self.active_player as i32 + lane / 2 - width / 2;
+		}
+		self.lock_ticks = 0;
+		self.lock_resets = 0;
+		self.fall_progress = 0;
+		self.spawned_at_tick = self.ticks;
 		if !self.can_place(&p, init_xy) {
-			self.is_alive = false;
+			self.top_out(TopOut::BlockOut);
 			return;
 		}
 		self.current_piece = Some(p);
 		self.current_piece_mass_xy = init_xy;
 	}
 
-	fn can_place(&self, p: &Piece, (global_x, global_y): (i32, i32)) -> bool {
+	/// Empties the 3x3 cells around `(x, y)`, bar any bedrock; whatever was above stays put.
+	fn explode(&mut self, (x, y): (i32, i32)) {
+		log::debug!("Tick {}: bomb at {:?}", self.ticks, (x, y));
+		for yy in (y - 1).max(0) ..= y + 1 {
+			for xx in (x - 1).max(0) ..= x + 1 {
+				let Some(row) = self.cell_matrix.get_mut(yy as usize)
+					else { continue; };
+				if let Some(cell) = row.cells.get_mut(xx as usize).filter(|c| !c.as_ref().is_some_and(|c| c.kind == CellKind::Bedrock)) {
+					*cell = None;
+				}
+				row.recount();
+			}
+		}
+		self.events.push(Event::Exploded { x, y });
+	}
+
+	/// Until nothing more fills up, in case the ruleset leaves that to us; returns how many went.
+	/// Items in the lines that were full beforehand go off.
+	fn clear_lines(&mut self) -> u32 {
+		let mut count = 0;
+		loop {
+			let (cleared, items) = self.with_gravity_frame(|ruleset, frame| {
+				let items: Vec<(Item, usize)> = ruleset.finished_rows(frame).into_iter()
+					.flat_map(|y| frame[y].cells.iter().enumerate()
+						.filter_map(|(x, c)| Some((c.as_ref()?.item?, x)))
+						.collect::<Vec<_>>())
+					.collect();
+				let cleared = ruleset.clear_rows(frame);
+				// Across the lines, so along gravity.
+				for &(_item, x) in items.iter().filter(|(item, _x)| *item == Item::ClearColumn) {
+					frame.iter_mut().for_each(|row| { row.cells[x] = None; row.recount(); });
+				}
+				(cleared, items)
+			});
+			for (item, _x) in items {
+				log::debug!("Tick {}: {item:?} went off", self.ticks);
+				match item {
+					Item::ClearColumn => {}
+					Item::SlowTime => self.slow_ticks_left = 10 * TICKS_PER_SECOND, // HARDCODE
+					Item::ShrinkNextPiece => self.shrink_next_piece = true,
+				}
+				self.events.push(Event::ItemUsed(item));
+			}
+			if cleared == 0 {
+				return count;
+			}
+			count += cleared;
+		}
+	}
+
+	/// Hand the ruleset the board turned so that gravity points down, then turn it back.
+	/// Under sideways or upwards gravity the hidden rows are left out; nothing goes there.
+	fn with_gravity_frame<R>(&mut self, f: impl FnOnce(&dyn Ruleset, &mut [Row]) -> R) -> R {
+		if self.gravity == Gravity::Down {
+			return f(&*self.ruleset, &mut self.cell_matrix);
+		}
+		let (width, hidden_rows) = (self.cell_matrix_width, self.hidden_rows);
+		let height = self.cell_matrix.len() - hidden_rows;
+		let gravity = self.gravity;
+		// Frame (x, y) -> board (x, y), hidden rows included.
+		let to_board = |fx: usize, fy: usize| match gravity {
+			Gravity::Down => (fx, fy),
+			Gravity::Up => (fx, hidden_rows + height - 1 - fy),
+			Gravity::Right => (fy, hidden_rows + fx),
+			Gravity::Left => (width - 1 - fy, hidden_rows + fx),
+		};
+		let (frame_width, frame_height) = if gravity.is_vertical() { (width, height) } else { (height, width) };
+		let mut frame: Vec<Row> = (0 .. frame_height)
+			.map(|fy| {
+				let mut row = Row::new(frame_width);
+				for fx in 0 .. frame_width {
+					let (x, y) = to_board(fx, fy);
+					row.cells[fx] = self.cell_matrix[y].cells[x].take();
+				}
+				row.recount();
+				row
+			})
+			.collect();
+		let result = f(&*self.ruleset, &mut frame);
+		for (fy, row) in frame.iter_mut().enumerate() {
+			for fx in 0 .. frame_width {
+				let (x, y) = to_board(fx, fy);
+				self.cell_matrix[y].cells[x] = row.cells[fx].take();
+			}
+		}
+		self.cell_matrix.iter_mut().for_each(Row::recount);
+		result
+	}
+
+	/// Whether `p` fits on the board with its center of mass at these global coordinates.
+	pub fn can_place(&self, p: &Piece, (global_x, global_y): (i32, i32)) -> bool {
+		// Only pieces falling down come from the hidden rows.
+		let min_y = if self.gravity == Gravity::Down { 0 } else { self.hidden_rows as i32 };
 		p.iter_global_space((global_x, global_y))
 			.all(|(_c, x, y)| {
-				if x < 0 || y < 0 {
+				if x < 0 || y < min_y {
 					return false;
 				}
 				let Some(row) = self.cell_matrix.get(y as usize)
 					else { return false; };
 				let Some(cell) = row.cells.get(x as usize)
 					else { return false; };
-				cell.is_none()
+				cell.is_none() && !self.is_other_piece_at((x, y))
 			})
 	}
+
+	/// Whether another player's piece has a cell here; see set_players().
+	fn is_other_piece_at(&self, xy: (i32, i32)) -> bool {
+		self.other_pieces().any(|(p, mass_xy)| p.iter_global_space(mass_xy).any(|(_c, x, y)| (x, y) == xy))
+	}
+
+	/// Moves the current piece `(dx, dy)` at a time until it overlaps nothing; false if it runs off the board first.
+	fn back_off(&mut self, (dx, dy): (i32, i32)) -> bool {
+		let Some(p) = self.current_piece.as_ref()
+			else { return true; };
+		let (mut x, mut y) = self.current_piece_mass_xy;
+		while !self.can_place(p, (x, y)) {
+			(x, y) = (x + dx, y + dy);
+			if x < 0 || y < 0 || x as usize >= self.cell_matrix_width || y as usize >= self.cell_matrix.len() {
+				return false;
+			}
+		}
+		self.current_piece_mass_xy = (x, y);
+		true
+	}
+
+	/// Empties the board at a new width, e.g. for more players; call reset() to start a game on it.
+	pub fn set_width(&mut self, width: usize) {
+		self.cell_matrix = (0 .. self.cell_matrix.len()).map(|_| Row::new(width)).collect();
+		self.cell_matrix_width = width;
+		self.current_piece = None;
+		self.other_slots.iter_mut().for_each(|slot| slot.piece = None);
+	}
+
+	/// `count` players, each with a falling piece of their own, spawning side by side; they share everything else.
+	/// Takes effect from the next reset(); 1 for the usual game.
+	pub fn set_players(&mut self, count: usize) {
+		self.other_slots = vec![Slot::default(); count.saturating_sub(1)];
+	}
+
+	pub fn players(&self) -> usize {
+		1 + self.other_slots.len()
+	}
+
+	/// Every player's piece but the first's, which is current_piece, and where each is; see set_players().
+	pub fn other_pieces(&self) -> impl Iterator<Item = (&Piece, (i32, i32))> {
+		self.other_slots.iter().filter_map(|slot| Some((slot.piece.as_ref()?, slot.mass_xy)))
+	}
+
+	/// apply() for `player`'s piece, 0 being the first player's; does nothing for players past players().
+	pub fn apply_as(&mut self, player: usize, action: Action) {
+		if player < self.players() {
+			self.as_player(player, |gs| gs.apply(action));
+		}
+	}
+
+	/// Runs `f` with `player`'s piece, and everything that goes with it, swapped into current_piece and the rest.
+	fn as_player<R>(&mut self, player: usize, f: impl FnOnce(&mut Self) -> R) -> R {
+		if player == 0 {
+			return f(self);
+		}
+		self.swap_slot(player);
+		self.active_player = player;
+		let result = f(self);
+		self.active_player = 0;
+		self.swap_slot(player);
+		result
+	}
+
+	fn swap_slot(&mut self, player: usize) {
+		let slot = &mut self.other_slots[player - 1];
+		std::mem::swap(&mut self.current_piece, &mut slot.piece);
+		std::mem::swap(&mut self.current_piece_mass_xy, &mut slot.mass_xy);
+		std::mem::swap(&mut self.ticks_since_drop, &mut slot.ticks_since_drop);
+		std::mem::swap(&mut self.fall_progress, &mut slot.fall_progress);
+		std::mem::swap(&mut self.is_soft_dropping, &mut slot.is_soft_dropping);
+		std::mem::swap(&mut self.lock_ticks, &mut slot.lock_ticks);
+		std::mem::swap(&mut self.lock_resets, &mut slot.lock_resets);
+		std::mem::swap(&mut self.spawned_at_tick, &mut slot.spawned_at_tick);
+		std::mem::swap(&mut self.can_hold, &mut slot.can_hold);
+	}
 }
 
-#[derive(Debug)]
+/// A falling piece and what goes with it, for the players besides the first; see GameState::set_players().
+#[derive(Clone,Debug,Default)]
+struct Slot {
+	piece: Option<Piece>,
+	mass_xy: (i32, i32),
+	ticks_since_drop: u32,
+	fall_progress: u32,
+	is_soft_dropping: bool,
+	lock_ticks: u32,
+	lock_resets: u32,
+	spawned_at_tick: u64,
+	can_hold: bool,
+}
+
+/// What the player gets to see and do with pieces to come; classic purists play with neither.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct QueueOptions {
+	/// Up to MAX_PREVIEW_PIECES, before the difficulty adjusts it.
+	pub previews: usize,
+	/// Whether Action::Hold does anything.
+	pub hold: bool,
+}
+
+impl Default for QueueOptions {
+	fn default() -> Self {
+		Self { previews: PREVIEW_PIECES, hold: true }
+	}
+}
+
+/// How long a piece gets on the stack before it locks, past the lock delay itself; competitive players like these
+/// set their own way.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct LockOptions {
+	/// Whether a piece soft dropped onto the stack locks at once, rather than waiting out the lock delay.
+	pub soft_drop_locks: bool,
+	/// Whether rotating restarts the lock delay like moving does.
+	pub rotation_resets: bool,
+	/// Replaces the ruleset's or difficulty's limit on lock delay restarts per piece.
+	pub max_resets: Option<u32>,
+}
+
+impl Default for LockOptions {
+	fn default() -> Self {
+		Self { soft_drop_locks: false, rotation_resets: true, max_resets: None }
+	}
+}
+
+/// Durations, in game ticks.
+#[derive(Clone,Copy,Debug)]
+pub struct Timing {
+	/// Time to fall by one cell-space.
+	pub ticks_per_drop_slow: u32,
+	/// Ditto, while soft dropping.
+	pub ticks_per_drop_fast: u32,
+	/// From a piece locking (or its rows clearing) to the next piece spawning; a.k.a. ARE.
+	pub entry_delay: u32,
+	/// How long full rows linger before they disappear.
+	pub line_clear_delay: u32,
+	/// Replaces ticks_per_drop_slow, and the ruleset's and difficulty's gravity curves, with one speed for every level;
+	/// soft drop still goes at least as fast as ticks_per_drop_fast.
+	pub fall_speed: Option<FallSpeed>,
+}
+
+/// Normal speed; see speed::Preset.
+impl Default for Timing {
+	fn default() -> Self {
+		Self { ticks_per_drop_slow: 10, ticks_per_drop_fast: 1, entry_delay: 10, line_clear_delay: 20, fall_speed: None }
+	}
+}
+
+impl Timing {
+	/// The four durations in field order, then FallSpeed's subcells if there's a fall speed, spaced out.
+	pub fn to_text(&self) -> String {
+		let mut text = format!("{} {} {} {}", self.ticks_per_drop_slow, self.ticks_per_drop_fast, self.entry_delay, self.line_clear_delay);
+		if let Some(FallSpeed(subcells)) = self.fall_speed {
+			text += &format!(" {subcells}");
+		}
+		text
+	}
+
+	pub fn parse(text: &str) -> Result<Timing, String> {
+		let numbers: Vec<u32> = text.split_whitespace()
+			.map(|n| n.parse().map_err(|_| format!("Bad number in timing `{text}`")))
+			.collect::<Result<_, _>>()?;
+		let (slow, fast, entry, line_clear, fall_speed) = match numbers[..] {
+			[slow, fast, entry, line_clear] => (slow, fast, entry, line_clear, None),
+			[slow, fast, entry, line_clear, subcells] if subcells > 0 => (slow, fast, entry, line_clear, Some(FallSpeed(subcells))),
+			_ => return Err(format!("Expected four durations and maybe a fall speed in timing `{text}`")),
+		};
+		Ok(Self { ticks_per_drop_slow: slow, ticks_per_drop_fast: fast, entry_delay: entry, line_clear_delay: line_clear, fall_speed })
+	}
+}
+
+/// Cells fallen per tick, the G of faster games' speed charts: 1G is a cell every tick, and 20G drops straight to
+/// the floor. Fixed point, in SUBCELLS, so every machine falls alike.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub struct FallSpeed(pub u32);
+
+impl FallSpeed {
+	pub const SUBCELLS: u32 = 1 << 16;
+
+	pub fn from_g(g: f64) -> FallSpeed {
+		Self((g * Self::SUBCELLS as f64).round().clamp(1.0, u32::MAX as f64) as u32)
+	}
+
+	pub fn from_cells_per_second(cells: f64) -> FallSpeed {
+		Self::from_g(cells / TICKS_PER_SECOND as f64)
+	}
+
+	pub fn g(self) -> f64 {
+		self.0 as f64 / Self::SUBCELLS as f64
+	}
+
+	pub fn cells_per_second(self) -> f64 {
+		self.g() * TICKS_PER_SECOND as f64
+	}
+
+	/// In G with a `G` after, like `20G` or `0.05g`, or else in cells per second, like `2.5`.
+	pub fn parse(text: &str) -> Option<FallSpeed> {
+		let text = text.trim();
+		let (number, is_g) = match text.strip_suffix(['G', 'g']) {
+			Some(number) => (number, true),
+			None => (text, false),
+		};
+		let value: f64 = number.trim().parse().ok().filter(|v: &f64| v.is_finite() && *v > 0.0)?;
+		Some(if is_g { Self::from_g(value) } else { Self::from_cells_per_second(value) })
+	}
+}
+
+/// What GameState::tick() is currently doing; frontends render each differently.
+#[derive(Clone,Debug)]
+pub enum Phase {
+	/// The current piece falls under gravity.
+	Falling,
+	/// These rows (or, under sideways gravity, columns) are full and about to disappear.
+	LineClear { ticks_left: u32, rows: Vec<usize>, columns: Vec<usize> },
+	/// Waiting to spawn the next piece.
+	Entry { ticks_left: u32 },
+}
+
+/// Which way pieces fall and the stack settles; lines clear across it.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Gravity {
+	Down,
+	Up,
+	Left,
+	Right,
+}
+
+impl Gravity {
+	/// One cell-space this way.
+	pub fn delta(self) -> (i32, i32) {
+		match self {
+			Gravity::Down => (0, 1),
+			Gravity::Up => (0, -1),
+			Gravity::Left => (-1, 0),
+			Gravity::Right => (1, 0),
+		}
+	}
+
+	/// Whether full rows clear, rather than full columns.
+	pub fn is_vertical(self) -> bool {
+		matches!(self, Gravity::Down | Gravity::Up)
+	}
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum TopOut {
+	/// A new piece overlapped the stack as it spawned.
+	BlockOut,
+	/// A piece locked without any of its cells reaching the visible field.
+	LockOut,
+	/// Incoming garbage shoved the stack out the top of the well.
+	PushedOut,
+}
+
+/// GameState::score by where it came from; the parts add up to it.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct ScoreBreakdown {
+	/// See Ruleset::starting_score().
+	pub starting: u32,
+	pub clears: u32,
+	pub combos: u32,
+	pub back_to_back: u32,
+	/// See Ruleset::speed_bonus().
+	pub speed: u32,
+	pub hard_drops: u32,
+}
+
+impl ScoreBreakdown {
+	/// Labeled, in the order they're listed.
+	pub fn parts(&self) -> [(&'static str, u32); 6] {
+		[
+			("Head start", self.starting),
+			("Clears", self.clears),
+			("Combos", self.combos),
+			("Back-to-back", self.back_to_back),
+			("Speed", self.speed),
+			("Hard drops", self.hard_drops),
+		]
+	}
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Action {
+	MoveLeft,
+	MoveRight,
+	RotateClockwise,
+	RotateCounterClockwise,
+	SoftDropStart,
+	SoftDropStop,
+	Hold,
+	/// All the way down, locking at once.
+	HardDrop,
+	/// All the way down, but locking only as it would have on landing, which leaves the lock delay for last moves.
+	SonicDrop,
+}
+
+/// Things the frontend may want to react to; see GameState::drain_events().
+#[derive(Clone,Debug)]
+pub enum Event {
+	/// The current piece became part of the stack.
+	PieceLocked,
+	/// After the line clear delay; how many rows disappeared.
+	RowsCleared(u32),
+	/// After RowsCleared; the stack has already resettled.
+	GravityChanged(Gravity),
+	/// Before RowsCleared; the effect has already happened.
+	ItemUsed(Item),
+	/// After RowsCleared; the new level.
+	LevelUp(u32),
+	/// A bomb went off, centered on this cell; after PieceLocked.
+	Exploded { x: i32, y: i32 },
+	/// The stack came within DANGER_ROWS of the top, or went back below; see GameState::is_in_danger().
+	Danger(bool),
+	ToppedOut(TopOut),
+}
+
+/// See GameState::delta(). The pieces beside the falling one, and the randomizer's memory, only come along when
+/// they changed; settings, the ruleset, and events never do.
+#[derive(Clone,Debug)]
+pub struct Delta {
+	/// Of cell_matrix.
+	height: usize,
+	width: usize,
+	/// By index into cell_matrix.
+	rows: Vec<(usize, Row)>,
+	piece: Option<Piece>,
+	mass_xy: (i32, i32),
+	held_piece: Option<Option<Piece>>,
+	next_pieces: Option<Vec<Piece>>,
+	history: Option<Vec<Vec<(i32, i32)>>>,
+	rng_state: u64,
+	phase: Phase,
+	gravity: Gravity,
+	/// See GameState::counters().
+	counters: [u64; COUNTERS],
+}
+
+/// How many numbers GameState::counters() has.
+const COUNTERS: usize = 24;
+
+impl Delta {
+	/// Of the state this delta leads to.
+	pub fn ticks(&self) -> u64 {
+		self.counters[0]
+	}
+
+	/// How many rows it carries; the bulk of its size.
+	pub fn changed_rows(&self) -> usize {
+		self.rows.len()
+	}
+
+	/// A line per part, for the wire: `delta` with the board's height and width, `row` with its index and each cell
+	/// as Cell::to_text() or `.`, `piece` with where and Piece::to_text(), then `held`, `next`, and `history` if they
+	/// changed, `rng`, `phase`, `gravity`, and `counters`. `-` stands for no piece; `|` separates pieces and shapes.
+	pub fn to_text(&self) -> String {
+		let piece_text = |piece: Option<&Piece>| piece.map_or("-".to_string(), Piece::to_text);
+		let mut text = format!("delta {} {}\n", self.height, self.width);
+		for (y, row) in self.rows.iter() {
+			let cells: Vec<String> = row.cells.iter().map(|c| c.as_ref().map_or(".".to_string(), Cell::to_text)).collect();
+			text += &format!("row {y} {}\n", cells.join(" "));
+		}
+		text += &format!("piece {} {} {}\n", self.mass_xy.0, self.mass_xy.1, piece_text(self.piece.as_ref()));
+		if let Some(held) = self.held_piece.as_ref() {
+			text += &format!("held {}\n", piece_text(held.as_ref()));
+		}
+		if let Some(next) = self.next_pieces.as_ref() {
+			text += &format!("next {}\n", next.iter().map(Piece::to_text).collect::<Vec<_>>().join(" | "));
+		}
+		if let Some(history) = self.history.as_ref() {
+			let shapes: Vec<String> = history.iter()
+				.map(|shape| shape.iter().map(|(x, y)| format!("{x},{y}")).collect::<Vec<_>>().join(" "))
+				.collect();
+			text += &format!("history {}\n", shapes.join(" | "));
+		}
+		text += &format!("rng {:016x}\n", self.rng_state);
+		text += &match &self.phase {
+			Phase::Falling => "phase falling\n".to_string(),
+			Phase::LineClear { ticks_left, rows, columns } => {
+				let list = |lines: &[usize]| if lines.is_empty() { "-".to_string() } else { lines.iter().map(usize::to_string).collect::<Vec<_>>().join(",") };
+				format!("phase clear {ticks_left} {} {}\n", list(rows), list(columns))
+			}
+			Phase::Entry { ticks_left } => format!("phase entry {ticks_left}\n"),
+		};
+		let gravity = match self.gravity {
+			Gravity::Down => "down",
+			Gravity::Up => "up",
+			Gravity::Left => "left",
+			Gravity::Right => "right",
+		};
+		text += &format!("gravity {gravity}\n");
+		text += &format!("counters {}\n", self.counters.map(|n| n.to_string()).join(" "));
+		text
+	}
+
+	pub fn parse(text: &str) -> Result<Delta, String> {
+		let piece = |text: &str| if text == "-" { Ok(None) } else { Piece::parse(text).map(Some) };
+		let number = |line: &str, text: &str| text.parse::<u64>().map_err(|_| format!("Bad number in `{line}`"));
+		let mut lines = text.lines();
+		let header = lines.next().unwrap_or_default();
+		let [Some(height), Some(width)] = header.strip_prefix("delta ").unwrap_or_default().split(' ').map(|n| n.parse::<usize>().ok()).collect::<Vec<_>>()[..]
+			else { return Err(format!("Not a delta: `{header}`")); };
+		let mut delta = Delta {
+			height,
+			width,
+			rows: Vec::new(),
+			piece: None,
+			mass_xy: (0, 0),
+			held_piece: None,
+			next_pieces: None,
+			history: None,
+			rng_state: 0,
+			phase: Phase::Falling,
+			gravity: Gravity::Down,
+			counters: [0; COUNTERS],
+		};
+		for line in lines {
+			let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+			match key {
+				"row" => {
+					let mut fields = value.split(' ');
+					let y = number(line, fields.next().unwrap_or_default())? as usize;
+					let cells: Vec<Option<Cell>> = fields
+						.map(|cell| if cell == "." { Ok(None) } else { Cell::parse(cell).map(Some) })
+						.collect::<Result<_, _>>()?;
+					if y >= height || cells.len() != width {
+						return Err(format!("Row doesn't fit the board in `{line}`"));
+					}
+					let mut row = Row { cells, is_empty: true };
+					row.recount();
+					delta.rows.push((y, row));
+				}
+				"piece" => {
+					let [x, y, rest] = value.splitn(3, ' ').collect::<Vec<_>>()[..]
+						else { return Err(format!("Expected where and which piece in `{line}`")); };
+					let (Ok(x), Ok(y)) = (x.parse(), y.parse())
+						else { return Err(format!("Bad position in `{line}`")); };
+					delta.mass_xy = (x, y);
+					delta.piece = piece(rest)?;
+				}
+				"held" => delta.held_piece = Some(piece(value)?),
+				"next" => delta.next_pieces = Some(value.split(" | ").filter(|p| !p.is_empty()).map(Piece::parse).collect::<Result<_, _>>()?),
+				"history" => {
+					let shape = |text: &str| text.split(' ')
+						.map(|xy| {
+							let (x, y) = xy.split_once(',').ok_or_else(|| format!("Bad shape in `{line}`"))?;
+							Ok((x.parse().map_err(|_| format!("Bad shape in `{line}`"))?, y.parse().map_err(|_| format!("Bad shape in `{line}`"))?))
+						})
+						.collect::<Result<Vec<(i32, i32)>, String>>();
+					delta.history = Some(value.split(" | ").filter(|s| !s.is_empty()).map(shape).collect::<Result<_, _>>()?);
+				}
+				"rng" => delta.rng_state = u64::from_str_radix(value, 16).map_err(|_| format!("Bad rng state in `{line}`"))?,
+				"phase" => {
+					let fields: Vec<&str> = value.split(' ').collect();
+					let list = |text: &str| if text == "-" { Ok(Vec::new()) } else { text.split(',').map(|n| number(line, n).map(|n| n as usize)).collect() };
+					delta.phase = match fields[..] {
+						["falling"] => Phase::Falling,
+						["clear", ticks_left, rows, columns] => Phase::LineClear { ticks_left: number(line, ticks_left)? as u32, rows: list(rows)?, columns: list(columns)? },
+						["entry", ticks_left] => Phase::Entry { ticks_left: number(line, ticks_left)? as u32 },
+						_ => return Err(format!("Bad phase in `{line}`")),
+					};
+				}
+				"gravity" => delta.gravity = match value {
+					"down" => Gravity::Down,
+					"up" => Gravity::Up,
+					"left" => Gravity::Left,
+					"right" => Gravity::Right,
+					_ => return Err(format!("Bad gravity in `{line}`")),
+				},
+				"counters" => {
+					let counters: Vec<u64> = value.split(' ').map(|n| number(line, n)).collect::<Result<_, _>>()?;
+					delta.counters = counters.try_into().map_err(|_| format!("Expected {COUNTERS} counters in `{line}`"))?;
+				}
+				"" => {}
+				other => return Err(format!("Unknown delta field `{other}`")),
+			}
+		}
+		Ok(delta)
+	}
+}
+
+/// What Piece::generate_new() rolls from.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum PieceSet {
+	/// Every polyomino of MIN_PIECE_CELLS to MAX_PIECE_CELLS cells, each piece in a random hue.
+	#[default]
+	Polyominoes,
+	/// The seven tetrominoes, each in its usual color; see Shape.
+	Classic,
+}
+
+impl PieceSet {
+	pub fn parse(text: &str) -> Option<PieceSet> {
+		match text.to_ascii_lowercase().as_str() {
+			"polyominoes" => Some(PieceSet::Polyominoes),
+			"classic" => Some(PieceSet::Classic),
+			_ => None,
+		}
+	}
+
+	/// Stable; written to replays.
+	pub fn name(self) -> &'static str {
+		match self {
+			PieceSet::Polyominoes => "polyominoes",
+			PieceSet::Classic => "classic",
+		}
+	}
+}
+
+/// The tetrominoes of PieceSet::Classic; cells remember which one they came from.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Shape {
+	I,
+	O,
+	T,
+	S,
+	Z,
+	J,
+	L,
+}
+
+impl Shape {
+	pub const ALL: [Shape; 7] = [Shape::I, Shape::O, Shape::T, Shape::S, Shape::Z, Shape::J, Shape::L];
+
+	/// Flat side down, y growing downward; in an order where each cell touches one before it, so Piece::shrunk() still works.
+	fn cells(self) -> [(i32, i32); 4] {
+		match self {
+			Shape::I => [(0, 0), (-1, 0), (1, 0), (2, 0)],
+			Shape::O => [(0, 0), (1, 0), (0, -1), (1, -1)],
+			Shape::T => [(0, 0), (-1, 0), (1, 0), (0, -1)],
+			Shape::S => [(0, 0), (-1, 0), (0, -1), (1, -1)],
+			Shape::Z => [(0, 0), (1, 0), (0, -1), (-1, -1)],
+			Shape::J => [(0, 0), (-1, 0), (1, 0), (-1, -1)],
+			Shape::L => [(0, 0), (-1, 0), (1, 0), (1, -1)],
+		}
+	}
+
+	/// What it looks like in a mirror: S and Z swap, as do J and L; the rest look the same.
+	pub fn mirrored(self) -> Shape {
+		match self {
+			Shape::S => Shape::Z,
+			Shape::Z => Shape::S,
+			Shape::J => Shape::L,
+			Shape::L => Shape::J,
+			Shape::I | Shape::O | Shape::T => self,
+		}
+	}
+
+	/// Of the usual color: I cyan, O yellow, T purple, S green, Z red, J blue, L orange. Frontends with a palette
+	/// of their own can go by the shape instead.
+	pub fn hue(self) -> f32 {
+		// HARDCODE
+		match self {
+			Shape::I => 0.5,
+			Shape::O => 1.0 / 6.0,
+			Shape::T => 0.8,
+			Shape::S => 1.0 / 3.0,
+			Shape::Z => 0.0,
+			Shape::J => 2.0 / 3.0,
+			Shape::L => 1.0 / 12.0,
+		}
+	}
+}
+
+/// How each new piece relates to the ones before it.
+#[derive(Clone,Copy,Debug)]
+pub enum RandomizerStrategy {
+	/// Every piece is independent of the ones before it.
+	Memoryless,
+	/// Like TGM's 4-history: while the new shape matches one of the last `size` shapes, re-roll up to `rerolls` times.
+	History { size: usize, rerolls: u32 },
+}
+
+impl RandomizerStrategy {
+	/// `memoryless`, or `history <size> <rerolls>`; a size of 0 is memoryless too.
+	pub fn parse(text: &str) -> Option<RandomizerStrategy> {
+		let mut words = text.split_whitespace();
+		let strategy = match words.next()?.to_ascii_lowercase().as_str() {
+			"memoryless" => RandomizerStrategy::Memoryless,
+			"history" => {
+				let size = words.next()?.parse::<usize>().ok()?;
+				let rerolls = words.next()?.parse::<u32>().ok()?;
+				if size == 0 { RandomizerStrategy::Memoryless } else { RandomizerStrategy::History { size, rerolls } }
+			},
+			_ => return None,
+		};
+		words.next().is_none().then_some(strategy)
+	}
+}
+
+#[derive(Clone,Debug)]
+pub struct Randomizer {
+	pub strategy: RandomizerStrategy,
+	/// Shape keys of recent pieces; newest at the back.
+	history: VecDeque<Vec<(i32, i32)>>,
+}
+
+impl Randomizer {
+	pub fn new(strategy: RandomizerStrategy) -> Randomizer {
+		Self { strategy, history: VecDeque::new() }
+	}
+
+	fn remember(&mut self, shape: Vec<(i32, i32)>) {
+		let RandomizerStrategy::History { size, .. } = self.strategy
+			else { return; };
+		self.history.push_back(shape);
+		while self.history.len() > size {
+			self.history.pop_front();
+		}
+	}
+}
+
+#[derive(Clone,Debug,PartialEq)]
 pub struct Row {
 	pub cells: Vec<Option<Cell>>,
 	is_empty: bool,
@@ -170,10 +1736,19 @@ impl Row {
 		}
 	}
 
-	fn reset(&mut self) {
+	pub fn reset(&mut self) {
 		self.cells.iter_mut().for_each(|c| *c = None);
 		self.is_empty = true;
 	}
+
+	pub fn is_empty(&self) -> bool {
+		self.is_empty
+	}
+
+	/// After editing cells directly.
+	pub fn recount(&mut self) {
+		self.is_empty = self.cells.iter().all(Option::is_none);
+	}
 }
 
 #[derive(Clone,Debug)]
@@ -183,6 +1758,54 @@ pub struct Piece {
 	// Origin for cell positions
 	pub center_of_mass_x: i32,
 	pub center_of_mass_y: i32,
+	pub kind: PieceKind,
+}
+
+/// Where to try a piece that can't turn where it is, in order; the first spot it fits wins. Offsets are (across, up)
+/// relative to gravity, across as in GameState::try_leftright_current_piece(), so one table serves every gravity.
+#[derive(Clone,Debug,Default,PartialEq,Eq)]
+pub struct KickTable {
+	/// For turning clockwise; counter-clockwise tries these mirrored.
+	pub clockwise: Vec<(i32, i32)>,
+}
+
+impl KickTable {
+	/// HARDCODE No kick lifts a piece more than this, so turning can't climb far up the stack.
+	pub const MAX_FLOOR_KICK: i32 = 2;
+
+	/// Derived from how far the piece reaches from its center, so any generated shape gets kicks to match its
+	/// size, and pieces of the same reach share a table: sideways by one cell, then further, as far as it reaches;
+	/// then up off the floor; then up and to either side.
+	pub fn for_piece(piece: &Piece) -> KickTable {
+		let reach = piece.cells.iter()
+			.map(|c| (c.x - piece.center_of_mass_x).abs().max((c.y - piece.center_of_mass_y).abs()))
+			.max()
+			.unwrap_or(0);
+		let mut clockwise = Vec::new();
+		for d in 1 ..= reach {
+			clockwise.extend([(-d, 0), (d, 0)]);
+		}
+		for up in 1 ..= reach.min(Self::MAX_FLOOR_KICK) {
+			clockwise.push((0, up));
+		}
+		if reach > 0 {
+			clockwise.extend([(-1, 1), (1, 1)]);
+		}
+		Self { clockwise }
+	}
+
+	pub fn offsets(&self, clockwise: bool) -> impl Iterator<Item = (i32, i32)> + '_ {
+		self.clockwise.iter().map(move |&(across, up)| if clockwise { (across, up) } else { (-across, up) })
+	}
+}
+
+/// What a piece does once it locks.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum PieceKind {
+	/// Becomes part of the stack.
+	Normal,
+	/// One cell that empties the 3x3 area around it, itself included; see Ruleset::bomb_chance().
+	Bomb,
 }
 
 pub struct PieceGlobalSpaceIter<'a> {
@@ -206,14 +1829,34 @@ impl <'a> Iterator for PieceGlobalSpaceIter<'a> {
 
 impl Piece {
 	const OFFSETS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
-	fn generate_new(rng: &mut rng::RandomNumberGenerator) -> Piece {
+	pub fn generate_new(rng: &mut rng::RandomNumberGenerator, randomizer: &mut Randomizer, piece_set: PieceSet) -> Piece {
+		let rerolls = match randomizer.strategy {
+			RandomizerStrategy::Memoryless => 0,
+			RandomizerStrategy::History { rerolls, .. } => rerolls,
+		};
+		let generate = |rng: &mut rng::RandomNumberGenerator| match piece_set {
+			PieceSet::Polyominoes => Self::generate_unrestricted(rng),
+			PieceSet::Classic => Self::classic(*rng.choose(&Shape::ALL).expect("Should have shapes")),
+		};
+		let mut p = generate(rng);
+		for _ in 0 .. rerolls {
+			if !randomizer.history.contains(&p.shape_key()) {
+				break;
+			}
+			p = generate(rng);
+		}
+		randomizer.remember(p.shape_key());
+		p
+	}
+
+	fn generate_unrestricted(rng: &mut rng::RandomNumberGenerator) -> Piece {
 		// Idea: randomly attach each new cell to an empty site on the existing piece's perimeter.
 		let hue: f32 = rng.uniform(0.0, 1.0);
 		// Why limit ourselves to just *tetr*-is?
-		let size = rng.uniform(3, 6);
+		let size = rng.uniform(MIN_PIECE_CELLS, MAX_PIECE_CELLS + 1);
 		// This is biased towards T- and L-shaped pieces; is that a good thing?
 		let mut cells = vec![CellWithRelativePosition { cell: Cell::new(hue), x: 0, y: 0, }];
-		let mut sites = HashSet::from(Self::OFFSETS);
+		let mut sites = BTreeSet::from(Self::OFFSETS);
 		for _ in 1 .. size {
 			let idx = rng.uniform(0, sites.len());
 			let &(x, y) = sites.iter().nth(idx)
@@ -227,16 +1870,99 @@ impl Piece {
 				}
 			}
 		}
-		let (center_of_mass_x, center_of_mass_y) = {
-			let (x, y) = cells.iter()
-				.fold((0, 0), |(acc_x, acc_y), c| (acc_x + c.x, acc_y + c.y));
-			let m = cells.len() as f32;
-			((x as f32 / m).round() as i32, (y as f32 / m).round() as i32)
-		};
-		Self { cells, center_of_mass_x, center_of_mass_y }
+		let (center_of_mass_x, center_of_mass_y) = center_of_mass(&cells);
+		Self { cells, center_of_mass_x, center_of_mass_y, kind: PieceKind::Normal }
+	}
+
+	/// One of each shape generate_new() can roll, up to rotation and reflection; smallest first, hues spread around the wheel.
+	pub fn all_shapes() -> Vec<Piece> {
+		// Cells in the order they were attached, like generate_unrestricted(), so shrunk() still works.
+		let mut shapes: Vec<Vec<(i32, i32)>> = vec![vec![(0, 0)]];
+		let mut all = Vec::new();
+		for size in 2 ..= MAX_PIECE_CELLS {
+			let mut keys = BTreeSet::new();
+			let mut grown = Vec::new();
+			for shape in shapes.iter() {
+				for &(x, y) in shape.iter() {
+					for xy in Self::OFFSETS.iter().map(|(dx, dy)| (x + dx, y + dy)) {
+						if shape.contains(&xy) {
+							continue;
+						}
+						let mut bigger = shape.clone();
+						bigger.push(xy);
+						if keys.insert(canonical_shape(&bigger)) {
+							grown.push(bigger);
+						}
+					}
+				}
+			}
+			shapes = grown;
+			if size >= MIN_PIECE_CELLS {
+				all.extend(shapes.iter().cloned());
+			}
+		}
+		let count = all.len();
+		all.into_iter().enumerate()
+			.map(|(i, shape)| {
+				let hue = i as f32 / count as f32;
+				let cells: Vec<CellWithRelativePosition> = shape.into_iter()
+					.map(|(x, y)| CellWithRelativePosition { cell: Cell::new(hue), x, y })
+					.collect();
+				let (center_of_mass_x, center_of_mass_y) = center_of_mass(&cells);
+				Self { cells, center_of_mass_x, center_of_mass_y, kind: PieceKind::Normal }
+			})
+			.collect()
 	}
 
-	fn rotated(&self, clockwise: bool) -> Piece {
+	/// A piece from the cells `(x, y)`, as all_shapes() colors that shape; None unless they are all connected.
+	/// Cells are put in the order they connect, starting from the first, so shrunk() still works.
+	pub fn from_cells(xys: &[(i32, i32)]) -> Option<Piece> {
+		let mut ordered: Vec<(i32, i32)> = xys.first().copied().into_iter().collect();
+		let mut i = 0;
+		while i < ordered.len() {
+			let (x, y) = ordered[i];
+			for xy in Self::OFFSETS.iter().map(|(dx, dy)| (x + dx, y + dy)) {
+				if xys.contains(&xy) && !ordered.contains(&xy) {
+					ordered.push(xy);
+				}
+			}
+			i += 1;
+		}
+		if ordered.is_empty() || ordered.len() != xys.len() {
+			return None;
+		}
+		let key = canonical_shape(&ordered);
+		let hue = Self::all_shapes().iter()
+			.find(|p| p.shape_key() == key)
+			.map_or(ASCII_HUE, |p| p.cells[0].cell.hue);
+		let cells: Vec<CellWithRelativePosition> = ordered.into_iter()
+			.map(|(x, y)| CellWithRelativePosition { cell: Cell::new(hue), x, y })
+			.collect();
+		let (center_of_mass_x, center_of_mass_y) = center_of_mass(&cells);
+		Some(Self { cells, center_of_mass_x, center_of_mass_y, kind: PieceKind::Normal })
+	}
+
+	pub fn classic(shape: Shape) -> Piece {
+		let cells: Vec<CellWithRelativePosition> = shape.cells().into_iter()
+			.map(|(x, y)| CellWithRelativePosition { cell: Cell { shape: Some(shape), ..Cell::new(shape.hue()) }, x, y })
+			.collect();
+		let (center_of_mass_x, center_of_mass_y) = center_of_mass(&cells);
+		Self { cells, center_of_mass_x, center_of_mass_y, kind: PieceKind::Normal }
+	}
+
+	pub fn bomb() -> Piece {
+		let cells = vec![CellWithRelativePosition { cell: Cell::with_kind(BOMB_HUE, CellKind::Bomb), x: 0, y: 0 }];
+		Self { cells, center_of_mass_x: 0, center_of_mass_y: 0, kind: PieceKind::Bomb }
+	}
+
+	/// The first `size` cells; still connected, since each cell was attached to the ones before it.
+	pub fn shrunk(&self, size: usize) -> Piece {
+		let cells: Vec<CellWithRelativePosition> = self.cells.iter().take(size.max(1)).cloned().collect();
+		let (center_of_mass_x, center_of_mass_y) = center_of_mass(&cells);
+		Self { cells, center_of_mass_x, center_of_mass_y, kind: self.kind }
+	}
+
+	pub fn rotated(&self, clockwise: bool) -> Piece {
 		let cells = self.cells.iter()
 			.map(|p| {
 				let v = (p.x - self.center_of_mass_x, p.y - self.center_of_mass_y);
@@ -252,13 +1978,101 @@ impl Piece {
 		Self { cells, ..*self }
 	}
 
-	pub fn iter_global_space(&self, xy: (i32, i32)) -> PieceGlobalSpaceIter {
+	/// Flipped left to right about its center; a classic piece becomes its mirror image, and takes that one's color.
+	pub fn mirrored(&self) -> Piece {
+		let cells = self.cells.iter()
+			.map(|p| {
+				let mut cell = p.cell.clone();
+				if let Some(shape) = cell.shape.map(Shape::mirrored) {
+					(cell.shape, cell.hue) = (Some(shape), shape.hue());
+				}
+				CellWithRelativePosition { cell, x: 2 * self.center_of_mass_x - p.x, y: p.y }
+			})
+			.collect();
+		Self { cells, ..*self }
+	}
+
+	/// Identical for pieces that are the same up to rotation and reflection, so S/Z and J/L count as "highly similar".
+	pub fn shape_key(&self) -> Vec<(i32, i32)> {
+		let cells: Vec<(i32, i32)> = self.cells.iter().map(|c| (c.x, c.y)).collect();
+		canonical_shape(&cells)
+	}
+
+	pub fn iter_global_space(&self, xy: (i32, i32)) -> PieceGlobalSpaceIter<'_> {
 		PieceGlobalSpaceIter {
 			piece: self,
 			iter_cells: self.cells.iter(),
 			global_xy: xy,
 		}
 	}
+
+	/// Everything about it, unlike notation's shapes: `normal` or `bomb`, the center of mass, then each cell as
+	/// `x,y,` and Cell::to_text(); spaced out, e.g. `normal 0 1 0,0,#,3e800000,-,-,0 0,1,#,3e800000,-,-,0`.
+	pub fn to_text(&self) -> String {
+		let kind = match self.kind {
+			PieceKind::Normal => "normal",
+			PieceKind::Bomb => "bomb",
+		};
+		let mut text = format!("{kind} {} {}", self.center_of_mass_x, self.center_of_mass_y);
+		for c in self.cells.iter() {
+			text += &format!(" {},{},{}", c.x, c.y, c.cell.to_text());
+		}
+		text
+	}
+
+	pub fn parse(text: &str) -> Result<Piece, String> {
+		let mut fields = text.split_whitespace();
+		let kind = match fields.next() {
+			Some("normal") => PieceKind::Normal,
+			Some("bomb") => PieceKind::Bomb,
+			_ => return Err(format!("Expected `normal` or `bomb` to start piece `{text}`")),
+		};
+		let mut number = || fields.next().and_then(|n| n.parse::<i32>().ok()).ok_or_else(|| format!("Bad center of mass in piece `{text}`"));
+		let (center_of_mass_x, center_of_mass_y) = (number()?, number()?);
+		let cells = fields
+			.map(|field| {
+				let [x, y, cell] = field.splitn(3, ',').collect::<Vec<_>>()[..]
+					else { return Err(format!("Bad cell `{field}` in piece `{text}`")); };
+				let (Ok(x), Ok(y)) = (x.parse(), y.parse())
+					else { return Err(format!("Bad cell `{field}` in piece `{text}`")); };
+				Ok(CellWithRelativePosition { cell: Cell::parse(cell)?, x, y })
+			})
+			.collect::<Result<Vec<_>, String>>()?;
+		if cells.is_empty() {
+			return Err(format!("No cells in piece `{text}`"));
+		}
+		Ok(Self { cells, center_of_mass_x, center_of_mass_y, kind })
+	}
+}
+
+/// See Piece::shape_key().
+fn canonical_shape(cells: &[(i32, i32)]) -> Vec<(i32, i32)> {
+	let mut best: Option<Vec<(i32, i32)>> = None;
+	for mirror in [false, true] {
+		let mut v: Vec<(i32, i32)> = cells.iter()
+			.map(|&(x, y)| if mirror { (-x, y) } else { (x, y) })
+			.collect();
+		for _ in 0 .. 4 {
+			v.iter_mut().for_each(|xy| *xy = rotate_2d(true, *xy));
+			let min_x = v.iter().map(|xy| xy.0).min().unwrap_or(0);
+			let min_y = v.iter().map(|xy| xy.1).min().unwrap_or(0);
+			let mut normalized: Vec<(i32, i32)> = v.iter().map(|(x, y)| (x - min_x, y - min_y)).collect();
+			normalized.sort();
+			if best.as_ref().is_none_or(|b| normalized < *b) {
+				best = Some(normalized);
+			}
+		}
+	}
+	best.unwrap_or_default()
+}
+
+/// Rounded half away from zero, in integers, so pieces turn about the same cell on every platform.
+fn center_of_mass(cells: &[CellWithRelativePosition]) -> (i32, i32) {
+	let (x, y) = cells.iter()
+		.fold((0, 0), |(acc_x, acc_y), c| (acc_x + c.x, acc_y + c.y));
+	let m = cells.len().max(1) as i32;
+	let round = |sum: i32| sum.signum() * ((2 * sum.abs() + m) / (2 * m));
+	(round(x), round(y))
 }
 
 fn rotate_2d(clockwise: bool, (x, y): (i32, i32)) -> (i32, i32) {
@@ -277,13 +2091,231 @@ pub struct CellWithRelativePosition {
 }
 
 
-#[derive(Clone,Debug)]
+#[derive(Clone,Debug,PartialEq)]
 pub struct Cell {
+	/// Meaningless for most kinds other than Normal, which frontends draw in their own colors.
 	pub hue: f32,
+	pub kind: CellKind,
+	/// Goes off when its line clears; only some rulesets hand these out.
+	pub item: Option<Item>,
+	/// Which of PieceSet::Classic's pieces it was part of, if any.
+	pub shape: Option<Shape>,
+	/// Which piece it locked as part of, counting from 1 since the game started; 0 until it locks, and for cells
+	/// that never were part of a piece, like garbage. Cells of one piece stay alike through clears and resettling.
+	pub piece: u32,
 }
 
 impl Cell {
 	pub fn new(hue: f32) -> Cell {
-		Self { hue }
+		Self::with_kind(hue, CellKind::Normal)
+	}
+
+	pub fn with_kind(hue: f32, kind: CellKind) -> Cell {
+		Self { hue, kind, item: None, shape: None, piece: 0 }
+	}
+
+	/// Both locked as part of the same piece.
+	pub fn same_piece(&self, other: &Cell) -> bool {
+		self.piece != 0 && self.piece == other.piece
+	}
+
+	/// CellKind::to_char(), the hue's bits in hex so it comes back exactly, the shape and item by name or `-`, and
+	/// the piece number; comma-separated.
+	pub fn to_text(&self) -> String {
+		let shape = self.shape.map_or("-".to_string(), |shape| format!("{shape:?}"));
+		let item = self.item.map_or("-".to_string(), |item| format!("{item:?}"));
+		format!("{},{:08x},{shape},{item},{}", self.kind.to_char(), self.hue.to_bits(), self.piece)
+	}
+
+	pub fn parse(text: &str) -> Result<Cell, String> {
+		let [kind, hue, shape, item, piece] = text.split(',').collect::<Vec<_>>()[..]
+			else { return Err(format!("Expected five fields in cell `{text}`")); };
+		let mut chars = kind.chars();
+		let (Some(kind), None) = (chars.next().and_then(CellKind::from_char), chars.next())
+			else { return Err(format!("Bad kind in cell `{text}`")); };
+		let hue = u32::from_str_radix(hue, 16).map(f32::from_bits).map_err(|_| format!("Bad hue in cell `{text}`"))?;
+		let shape = match shape {
+			"-" => None,
+			name => Some(*Shape::ALL.iter().find(|s| format!("{s:?}") == name).ok_or_else(|| format!("Bad shape in cell `{text}`"))?),
+		};
+		let item = match item {
+			"-" => None,
+			name => Some(*Item::ALL.iter().find(|i| format!("{i:?}") == name).ok_or_else(|| format!("Bad item in cell `{text}`"))?),
+		};
+		let piece = piece.parse().map_err(|_| format!("Bad piece number in cell `{text}`"))?;
+		Ok(Self { hue, kind, item, shape, piece })
+	}
+}
+
+/// Where a cell came from, and how it behaves.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum CellKind {
+	/// Part of a piece.
+	Normal,
+	/// Pushed up from below; see GameState::add_garbage().
+	Garbage,
+	/// The one cell of a PieceKind::Bomb.
+	Bomb,
+	/// Clears as usual, but stays put when the stack resettles; see ruleset::settle_cells().
+	Frozen,
+	/// Never clears; a row with any in it is never full.
+	Bedrock,
+}
+
+impl CellKind {
+	/// For GameState::to_ascii() and puzzle files.
+	pub fn to_char(self) -> char {
+		match self {
+			CellKind::Normal => '#',
+			CellKind::Garbage => 'G',
+			CellKind::Bomb => '*',
+			CellKind::Frozen => 'F',
+			CellKind::Bedrock => 'B',
+		}
+	}
+
+	/// None for `.`; anything unrecognized is a normal cell, so boards can be drawn with any character.
+	pub fn from_char(c: char) -> Option<CellKind> {
+		match c {
+			'.' => None,
+			'G' => Some(CellKind::Garbage),
+			'*' => Some(CellKind::Bomb),
+			'F' => Some(CellKind::Frozen),
+			'B' => Some(CellKind::Bedrock),
+			_ => Some(CellKind::Normal),
+		}
+	}
+}
+
+/// Power-ups carried by single cells; see Ruleset::item_chance().
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Item {
+	/// Empties the item's column, or its row under sideways gravity.
+	ClearColumn,
+	/// Halves gravity for ten seconds.
+	SlowTime,
+	/// The next piece has only two cells.
+	ShrinkNextPiece,
+}
+
+impl Item {
+	pub const ALL: [Item; 3] = [Item::ClearColumn, Item::SlowTime, Item::ShrinkNextPiece];
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Every piece set, many pieces each, as the game would deal them.
+	fn generated_pieces() -> Vec<Piece> {
+		let mut rng = rng::RandomNumberGenerator::new(1);
+		let mut pieces = Vec::new();
+		for piece_set in [PieceSet::Polyominoes, PieceSet::Classic] {
+			let mut randomizer = Randomizer::new(RandomizerStrategy::Memoryless);
+			for _ in 0 .. 500 {
+				pieces.push(Piece::generate_new(&mut rng, &mut randomizer, piece_set));
+			}
+		}
+		pieces
+	}
+
+	#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+	enum Wall {
+		Left,
+		Right,
+		Floor,
+	}
+
+	/// An empty guideline game with `piece` falling up against `wall`, halfway up the board or across it.
+	fn game_against(piece: &Piece, wall: Wall) -> GameState {
+		let mut game_state = GameState::new(20, 10, RandomizerStrategy::Memoryless, Timing::default());
+		game_state.set_ruleset(Box::new(ruleset::Guideline));
+		game_state.reset();
+		let xs = piece.cells.iter().map(|c| c.x - piece.center_of_mass_x);
+		let ys = piece.cells.iter().map(|c| c.y - piece.center_of_mass_y);
+		let (width, height) = (game_state.cell_matrix_width as i32, game_state.cell_matrix.len() as i32);
+		let middle = game_state.hidden_rows as i32 + 8;
+		game_state.current_piece_mass_xy = match wall {
+			Wall::Left => (-xs.min().unwrap(), middle),
+			Wall::Right => (width - 1 - xs.max().unwrap(), middle),
+			Wall::Floor => (width / 2, height - 1 - ys.max().unwrap()),
+		};
+		game_state.current_piece = Some(piece.clone());
+		assert!(game_state.can_place(piece, game_state.current_piece_mass_xy), "{piece:?} should start on the board");
+		game_state
+	}
+
+	#[test]
+	fn pieces_and_timing_round_trip_as_text() {
+		for piece in generated_pieces().into_iter().chain([Piece::bomb()]) {
+			let text = piece.to_text();
+			assert_eq!(Piece::parse(&text).map(|p| p.to_text()), Ok(text));
+		}
+		for timing in [Timing::default(), Timing { fall_speed: Some(FallSpeed::from_g(20.0)), ..Timing::default() }] {
+			let text = timing.to_text();
+			assert_eq!(Timing::parse(&text).map(|t| t.to_text()), Ok(text));
+		}
+	}
+
+	#[test]
+	fn deltas_lead_both_ways_and_survive_text() {
+		let mut game_state = GameState::new(20, 10, RandomizerStrategy::History { size: 4, rerolls: 4 }, Timing::default());
+		game_state.set_ruleset(Box::new(ruleset::Guideline));
+		game_state.reset();
+		game_state.reseed(3);
+		let actions = [Action::MoveLeft, Action::RotateClockwise, Action::Hold, Action::HardDrop, Action::MoveRight];
+		let mut earlier = game_state.snapshot();
+		for tick in 0 .. 2000 {
+			if tick % 7 == 0 {
+				game_state.apply(actions[tick / 7 % actions.len()]);
+			}
+			game_state.tick();
+			if !game_state.is_alive {
+				break;
+			}
+			if tick % 25 == 0 {
+				for (from, to) in [(&earlier, &game_state), (&game_state, &earlier)] {
+					let delta = Delta::parse(&to.delta(from).to_text()).expect("Should parse its own text");
+					let mut applied = from.clone();
+					applied.apply_delta(&delta);
+					let mut expected = to.clone();
+					expected.events.clear();
+					assert_eq!(format!("{applied:?}"), format!("{expected:?}"), "at tick {tick}");
+					assert_eq!(applied.delta(to).changed_rows(), 0);
+				}
+				earlier = game_state.snapshot();
+			}
+		}
+	}
+
+	#[test]
+	fn kicks_stay_low() {
+		for piece in generated_pieces() {
+			let kicks = KickTable::for_piece(&piece);
+			assert!(kicks.clockwise.iter().all(|&(_across, up)| up <= KickTable::MAX_FLOOR_KICK), "{kicks:?}");
+		}
+	}
+
+	#[test]
+	fn pieces_turn_against_walls_and_floor() {
+		for piece in generated_pieces() {
+			let kicks = KickTable::for_piece(&piece);
+			for clockwise in [true, false] {
+				for wall in [Wall::Left, Wall::Right, Wall::Floor] {
+					let mut game_state = game_against(&piece, wall);
+					let (x, y) = game_state.current_piece_mass_xy;
+					let turned = piece.rotated(clockwise);
+					let fits = std::iter::once((0, 0)).chain(kicks.offsets(clockwise))
+						.any(|(across, up)| game_state.can_place(&turned, (x + across, y - up)));
+					// Alone against one wall, there's always room to the side.
+					if wall != Wall::Floor {
+						assert!(fits, "{piece:?} against {wall:?}");
+					}
+					assert_eq!(game_state.try_rotate_current_piece(clockwise), fits, "{piece:?} against {wall:?}");
+					let (_x, new_y) = game_state.current_piece_mass_xy;
+					assert!(y - new_y <= KickTable::MAX_FLOOR_KICK, "{piece:?} against {wall:?} climbed {}", y - new_y);
+				}
+			}
+		}
 	}
 }
@@ -0,0 +1,121 @@
+//! How the well looks and sounds at each level; like the arcade games, every level up brings a new look.
+
+/// Colors are HSL, each 0 to 1, for `color::hsl_to_rgb()` and friends.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Theme {
+	pub background: [f32; 3],
+	pub grid: [f32; 3],
+	/// Index of the music track; the embedding page plays `assets/music/<n>.ogg` when told over the bridge.
+	/// Native builds have no audio yet.
+	pub music: u32,
+}
+
+/// HARDCODE Level 0 keeps the original black background and gray grid.
+pub fn for_level(level: u32) -> Theme {
+	if level == 0 {
+		return Theme { background: [0.0, 0.0, 0.0], grid: [0.0, 0.0, 0.31], music: 0 };
+	}
+	// Golden-ratio steps around the color wheel never quite repeat and keep neighbors apart.
+	let hue = (level as f32 * 0.618_034).fract();
+	// Darker and more saturated as things speed up, topping out at level 20.
+	let intensity = (level as f32 / 20.0).min(1.0);
+	Theme {
+		background: [hue, 0.3 + 0.4 * intensity, 0.08 + 0.04 * intensity],
+		grid: [hue, 0.4 + 0.4 * intensity, 0.35],
+		music: (level / 5).min(MUSIC_TRACKS - 1),
+	}
+}
+
+/// One more track every five levels.
+pub const MUSIC_TRACKS: u32 = 4;
+
+/// How the empty well is marked out; the `grid` key in config.txt.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum GridStyle {
+	Off,
+	/// A line every four columns.
+	Sparse,
+	/// A line between every pair of rows and of columns.
+	Lattice,
+	/// A faint square in every cell.
+	Outlines,
+}
+
+impl GridStyle {
+	pub fn parse(text: &str) -> Option<GridStyle> {
+		match text.to_ascii_lowercase().as_str() {
+			"off" => Some(GridStyle::Off),
+			"sparse" => Some(GridStyle::Sparse),
+			"lattice" => Some(GridStyle::Lattice),
+			"outlines" => Some(GridStyle::Outlines),
+			_ => None,
+		}
+	}
+}
+
+/// How each cell of the stack and the falling piece is drawn; the `blocks` key in config.txt.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum BlockStyle {
+	/// One color per cell; or the block texture, if there is one.
+	#[default]
+	Flat,
+	/// Lit from the top left: a light edge above and to the left, a shadow below and to the right.
+	Beveled,
+	/// Flat, with no seams between cells of the same piece, so each locked piece keeps its outline.
+	Connected,
+}
+
+impl BlockStyle {
+	pub fn parse(text: &str) -> Option<BlockStyle> {
+		match text.to_ascii_lowercase().as_str() {
+			"flat" => Some(BlockStyle::Flat),
+			"beveled" => Some(BlockStyle::Beveled),
+			"connected" => Some(BlockStyle::Connected),
+			_ => None,
+		}
+	}
+}
+
+/// What shows behind the well and HUD, for capturing the window into streaming software; the `capture` key in
+/// config.txt, or `--capture` on the command line. Anything but Off also leaves out the animated background,
+/// the danger tint, and hints meant for the player.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum Capture {
+	#[default]
+	Off,
+	/// Clear, where the window system and capture software support it.
+	Transparent,
+	/// Solid CHROMA_KEY, for keying out where they don't.
+	ChromaKey,
+}
+
+/// HARDCODE Pure green, as keyers expect; RGB, each 0 to 1.
+pub const CHROMA_KEY: [f32; 3] = [0.0, 1.0, 0.0];
+
+impl Capture {
+	pub fn parse(text: &str) -> Option<Capture> {
+		match text.to_ascii_lowercase().as_str() {
+			"off" => Some(Capture::Off),
+			"transparent" => Some(Capture::Transparent),
+			"chroma" | "chroma-key" => Some(Capture::ChromaKey),
+			_ => None,
+		}
+	}
+}
+
+/// Full-screen effects over everything drawn; each its own key in config.txt, all off by default.
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct Effects {
+	/// Dark lines across the screen, like an old CRT.
+	pub scanlines: bool,
+	/// A glow around anything bright for a moment after rows clear.
+	pub bloom: bool,
+	/// Darker toward the corners.
+	pub vignette: bool,
+}
+
+impl Effects {
+	pub fn any(self) -> bool {
+		self.scanlines || self.bloom || self.vignette
+	}
+}
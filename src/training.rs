@@ -0,0 +1,133 @@
+//! `--train`: evolve GreedyBot's weights over headless self-play, then save the best for bot::Weights::load().
+//!
+//! ```text
+//! tetris-clone-rust --train --generations 20 --population 16 --games 8 --seed 1 --max-pieces 300
+//! ```
+//!
+//! Every candidate in a generation plays the same seeds, so they are judged on the same pieces.
+//! Fitness is mean rows cleared.
+
+use crate::bot;
+use crate::headless;
+use crate::log;
+use crate::rng;
+use crate::simulation::{Lane, Simulation};
+
+#[derive(Clone,Debug)]
+pub struct Settings {
+	/// Board, timing, games per candidate, first seed, and piece limit; the bot name is ignored.
+	pub games: headless::Settings,
+	pub generations: u32,
+	pub population: usize,
+	/// Standard deviation of the noise added to each (normalized) weight.
+	pub mutation: f64,
+}
+
+impl Settings {
+	pub fn parse(args: &[String]) -> Result<Settings, String> {
+		let mut settings = Settings {
+			games: headless::Settings::default(),
+			generations: 10,
+			population: 16,
+			mutation: 0.2,
+		};
+		let mut rest = Vec::new();
+		let mut args = args.iter();
+		while let Some(flag) = args.next() {
+			let value = args.next().ok_or_else(|| format!("Missing value for {flag}"))?;
+			match flag.as_str() {
+				"--generations" => settings.generations = value.parse().map_err(|_| format!("Bad number `{value}` for {flag}"))?,
+				"--population" => settings.population = value.parse().map_err(|_| format!("Bad number `{value}` for {flag}"))?,
+				"--mutation" => settings.mutation = value.parse().map_err(|_| format!("Bad number `{value}` for {flag}"))?,
+				_ => rest.extend([flag.clone(), value.clone()]),
+			}
+		}
+		settings.games = headless::Settings::parse(&rest)?;
+		if settings.population < 2 {
+			return Err("Need a population of at least 2".to_string());
+		}
+		Ok(settings)
+	}
+}
+
+/// Mean rows cleared by each candidate over the same games.
+pub fn evaluate(settings: &headless::Settings, candidates: &[bot::Weights], seed: u64) -> Vec<f64> {
+	let games = settings.games.max(1) as u64;
+	let lanes = candidates.iter()
+		.flat_map(|weights| (0 .. games).map(move |i| (*weights, seed.wrapping_add(i))))
+		.map(|(weights, seed)| {
+			Lane::new(settings.new_game_state(seed), headless::BotFrontend { bot: Box::new(bot::GreedyBot::new(weights)), training_log: None })
+		})
+		.collect();
+	let mut simulation = Simulation::new(lanes);
+	simulation.run(|lane| lane.pieces_locked >= settings.max_pieces);
+	simulation.lanes.chunks(games as usize)
+		.map(|lanes| lanes.iter().map(|lane| lane.game_state.rows_cleared as f64).sum::<f64>() / games as f64)
+		.collect()
+}
+
+/// Unit length; the bot only compares scores, so only direction matters.
+fn normalized(weights: [f32; bot::Weights::COUNT]) -> [f32; bot::Weights::COUNT] {
+	let length = weights.iter().map(|w| w * w).sum::<f32>().sqrt();
+	if length == 0.0 {
+		return weights;
+	}
+	weights.map(|w| w / length)
+}
+
+/// Returns the best weights and their fitness, after saving them.
+pub fn train(settings: &Settings) -> (bot::Weights, f64) {
+	let mut rng = rng::RandomNumberGenerator::new(settings.games.seed);
+	// Start from whatever is current, plus random strangers.
+	let mut population: Vec<bot::Weights> = vec![bot::Weights::load()];
+	while population.len() < settings.population {
+		let random = [(); bot::Weights::COUNT].map(|()| rng.uniform(-1.0_f32, 1.0));
+		population.push(bot::Weights::from_array(normalized(random)));
+	}
+	let mut best = (population[0], f64::MIN);
+	for generation in 0 .. settings.generations {
+		// Fresh seeds each generation, so nobody overfits to one set of pieces.
+		let seed = settings.games.seed.wrapping_add(generation as u64 * settings.games.games as u64);
+		let fitness = evaluate(&settings.games, &population, seed);
+		let mut ranked: Vec<(bot::Weights, f64)> = population.iter().copied().zip(fitness).collect();
+		ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+		if ranked[0].1 > best.1 {
+			best = ranked[0];
+		}
+		log::info!("Generation {generation}: best {:.2}, median {:.2}, weights {:?}",
+			ranked[0].1, ranked[ranked.len() / 2].1, ranked[0].0.to_array());
+		// Keep the top quarter as-is; breed the rest from tournaments.
+		let elite = (settings.population / 4).max(1);
+		let mut next: Vec<bot::Weights> = ranked.iter().take(elite).map(|(w, _f)| *w).collect();
+		while next.len() < settings.population {
+			let mut tournament = || {
+				let contenders = [(); 3].map(|()| rng.uniform(0, ranked.len()));
+				ranked[contenders.into_iter().min().unwrap_or(0)]
+			};
+			let ((a, fitness_a), (b, fitness_b)) = (tournament(), tournament());
+			// Lean towards the fitter parent.
+			let share_a = if fitness_a + fitness_b > 0.0 { fitness_a / (fitness_a + fitness_b) } else { 0.5 };
+			let (a, b) = (a.to_array(), b.to_array());
+			let mut child = [0.0; bot::Weights::COUNT];
+			for i in 0 .. bot::Weights::COUNT {
+				child[i] = a[i] * share_a as f32 + b[i] * (1.0 - share_a) as f32
+					+ rng.normal(0.0, settings.mutation) as f32;
+			}
+			next.push(bot::Weights::from_array(normalized(child)));
+		}
+		population = next;
+	}
+	if let Err(e) = best.0.save() {
+		log::error!("Could not save bot weights: {e}");
+	}
+	best
+}
+
+/// Entry point for `--train`; `args` are whatever followed it. Progress goes to the log.
+pub fn run(args: &[String]) -> Result<String, String> {
+	let settings = Settings::parse(args)?;
+	let (weights, fitness) = train(&settings);
+	Ok(format!(
+		"{{\"fitness\":{fitness:.3},\"aggregate_height\":{},\"holes\":{},\"bumpiness\":{},\"rows_cleared\":{}}}",
+		weights.aggregate_height, weights.holes, weights.bumpiness, weights.rows_cleared))
+}
@@ -0,0 +1,57 @@
+//! Short animations for frontends: a value easing from one number to another over a while.
+//! Times are seconds on whatever clock the caller likes, e.g. macroquad's `get_time()`.
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Easing {
+	Linear,
+	/// Fast, then settling in.
+	OutCubic,
+	/// Overshoots a little, then settles back; good for a pulse.
+	OutBack,
+}
+
+impl Easing {
+	/// `t` from 0 to 1.
+	pub fn apply(self, t: f32) -> f32 {
+		match self {
+			Easing::Linear => t,
+			Easing::OutCubic => 1.0 - (1.0 - t).powi(3),
+			Easing::OutBack => {
+				let c = 1.70158; // HARDCODE The usual ten percent overshoot
+				let u = t - 1.0;
+				1.0 + (c + 1.0) * u.powi(3) + c * u.powi(2)
+			}
+		}
+	}
+}
+
+#[derive(Clone,Copy,Debug)]
+pub struct Tween {
+	pub from: f32,
+	pub to: f32,
+	pub start: f64,
+	pub duration: f64,
+	pub easing: Easing,
+}
+
+impl Tween {
+	pub fn new(from: f32, to: f32, start: f64, duration: f64, easing: Easing) -> Tween {
+		Self { from, to, start, duration, easing }
+	}
+
+	/// Fraction of the way through, clamped to 0 to 1.
+	pub fn progress(&self, now: f64) -> f32 {
+		if self.duration <= 0.0 {
+			return 1.0;
+		}
+		((now - self.start) / self.duration).clamp(0.0, 1.0) as f32
+	}
+
+	pub fn value(&self, now: f64) -> f32 {
+		self.from + (self.to - self.from) * self.easing.apply(self.progress(now))
+	}
+
+	pub fn is_done(&self, now: f64) -> bool {
+		now >= self.start + self.duration
+	}
+}
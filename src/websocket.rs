@@ -0,0 +1,225 @@
+//! Just enough of RFC 6455 for text messages: the opening handshake and unfragmented frames.
+//! Native only; the browser has its own WebSocket, see netplay::Connection.
+
+use std::io::{Read, Write};
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+#[derive(Clone,Debug,PartialEq,Eq)]
+pub enum Frame {
+	Text(String),
+	Ping(Vec<u8>),
+	Pong,
+	Close,
+}
+
+/// Connects to `url`, e.g. `ws://example.com:9001/room`; only `ws://`, so no TLS.
+#[cfg(not(target_family="wasm"))]
+pub fn connect(url: &str) -> Result<std::net::TcpStream, String> {
+	let rest = url.strip_prefix("ws://")
+		.ok_or_else(|| format!("Only ws:// URLs are supported natively, not {url}"))?;
+	let (host, path) = match rest.find('/') {
+		Some(i) => (&rest[.. i], &rest[i ..]),
+		None => (rest, "/"),
+	};
+	let address = if host.contains(':') { host.to_string() } else { format!("{host}:80") };
+	let mut stream = std::net::TcpStream::connect(&address).map_err(|e| e.to_string())?;
+	stream.set_nodelay(true).map_err(|e| e.to_string())?;
+	client_handshake(&mut stream, host, path)?;
+	Ok(stream)
+}
+
+/// Sends the upgrade request and checks the server's answer.
+pub fn client_handshake(stream: &mut (impl Read + Write), host: &str, path: &str) -> Result<(), String> {
+	let mut nonce = [0_u8; 16];
+	getrandom::getrandom(&mut nonce).map_err(|e| e.to_string())?;
+	let key = base64(&nonce);
+	let request = format!(
+		"GET {path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n");
+	stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+	let head = read_head(stream).map_err(|e| e.to_string())?;
+	let status = head.split_whitespace().nth(1).unwrap_or_default();
+	if status != "101" {
+		return Err(format!("Server refused the upgrade with HTTP status {status}"));
+	}
+	if header(&head, "Sec-WebSocket-Accept") != Some(accept_key(&key).as_str()) {
+		return Err("Server sent the wrong Sec-WebSocket-Accept".to_string());
+	}
+	Ok(())
+}
+
+/// Answers an upgrade request whose head was already read with read_head().
+pub fn server_handshake(stream: &mut impl Write, head: &str) -> Result<(), String> {
+	if header(head, "Sec-WebSocket-Version") != Some("13") {
+		stream.write_all(b"HTTP/1.1 426 Upgrade Required\r\nSec-WebSocket-Version: 13\r\nContent-Length: 0\r\n\r\n")
+			.map_err(|e| e.to_string())?;
+		return Err("Client wants an unsupported WebSocket version".to_string());
+	}
+	let key = header(head, "Sec-WebSocket-Key").ok_or("Client sent no Sec-WebSocket-Key")?;
+	let response = format!(
+		"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+		accept_key(key));
+	stream.write_all(response.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// The request or response head, up to and including the blank line; byte by byte, so nothing after it is lost.
+pub fn read_head(stream: &mut impl Read) -> std::io::Result<String> {
+	let mut head = Vec::new();
+	let mut byte = [0_u8; 1];
+	while !head.ends_with(b"\r\n\r\n") {
+		stream.read_exact(&mut byte)?;
+		head.push(byte[0]);
+		if head.len() > 8192 { // HARDCODE
+			return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Header too long"));
+		}
+	}
+	Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+/// Value of the first header called `name`, ignoring case.
+pub fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+	head.lines()
+		.filter_map(|line| line.split_once(':'))
+		.find(|(key, _value)| key.trim().eq_ignore_ascii_case(name))
+		.map(|(_key, value)| value.trim())
+}
+
+/// What the server answers a Sec-WebSocket-Key with.
+pub fn accept_key(key: &str) -> String {
+	base64(&sha1(format!("{key}{ACCEPT_GUID}").as_bytes()))
+}
+
+/// Clients must mask what they send; servers must not.
+pub fn write_frame(stream: &mut impl Write, frame: &Frame, is_client: bool) -> std::io::Result<()> {
+	let (opcode, payload): (u8, &[u8]) = match frame {
+		Frame::Text(text) => (0x1, text.as_bytes()),
+		Frame::Ping(data) => (0x9, data),
+		Frame::Pong => (0xA, &[]),
+		Frame::Close => (0x8, &[]),
+	};
+	let mut bytes = vec![0x80 | opcode];
+	let mask_bit = if is_client { 0x80 } else { 0 };
+	match payload.len() {
+		len @ 0 ..= 125 => bytes.push(mask_bit | len as u8),
+		len @ 126 ..= 0xFFFF => {
+			bytes.push(mask_bit | 126);
+			bytes.extend((len as u16).to_be_bytes());
+		}
+		len => {
+			bytes.push(mask_bit | 127);
+			bytes.extend((len as u64).to_be_bytes());
+		}
+	}
+	if is_client {
+		let mut mask = [0_u8; 4];
+		getrandom::getrandom(&mut mask).map_err(|e| std::io::Error::other(e.to_string()))?;
+		bytes.extend(mask);
+		bytes.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+	} else {
+		bytes.extend(payload);
+	}
+	stream.write_all(&bytes)
+}
+
+/// Blocks until a whole frame arrives; fragmented messages are refused rather than reassembled.
+pub fn read_frame(stream: &mut impl Read) -> std::io::Result<Frame> {
+	let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string());
+	let mut head = [0_u8; 2];
+	stream.read_exact(&mut head)?;
+	if head[0] & 0x80 == 0 {
+		return Err(invalid("Fragmented frames are not supported"));
+	}
+	let opcode = head[0] & 0x0F;
+	let is_masked = head[1] & 0x80 != 0;
+	let len = match head[1] & 0x7F {
+		126 => {
+			let mut len = [0_u8; 2];
+			stream.read_exact(&mut len)?;
+			u16::from_be_bytes(len) as u64
+		}
+		127 => {
+			let mut len = [0_u8; 8];
+			stream.read_exact(&mut len)?;
+			u64::from_be_bytes(len)
+		}
+		len => len as u64,
+	};
+	if len > 1 << 20 { // HARDCODE
+		return Err(invalid("Frame too big"));
+	}
+	let mut mask = [0_u8; 4];
+	if is_masked {
+		stream.read_exact(&mut mask)?;
+	}
+	let mut payload = vec![0_u8; len as usize];
+	stream.read_exact(&mut payload)?;
+	if is_masked {
+		payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i % 4]);
+	}
+	match opcode {
+		0x1 => String::from_utf8(payload).map(Frame::Text).map_err(|_| invalid("Text frame is not UTF-8")),
+		0x8 => Ok(Frame::Close),
+		0x9 => Ok(Frame::Ping(payload)),
+		0xA => Ok(Frame::Pong),
+		_ => Err(invalid("Only text frames are supported")),
+	}
+}
+
+pub fn base64(bytes: &[u8]) -> String {
+	const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut text = String::new();
+	for chunk in bytes.chunks(3) {
+		let n = chunk.iter().enumerate().fold(0_u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+		for i in 0 .. 4 {
+			if i <= chunk.len() {
+				text.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+			} else {
+				text.push('=');
+			}
+		}
+	}
+	text
+}
+
+/// Only for the handshake, which is where RFC 6455 asks for it; not for anything secret.
+pub fn sha1(bytes: &[u8]) -> [u8; 20] {
+	let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+	let mut message = bytes.to_vec();
+	message.push(0x80);
+	while message.len() % 64 != 56 {
+		message.push(0);
+	}
+	message.extend((bytes.len() as u64 * 8).to_be_bytes());
+	for block in message.chunks(64) {
+		let mut w = [0_u32; 80];
+		for (i, word) in block.chunks(4).enumerate() {
+			w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+		}
+		for i in 16 .. 80 {
+			w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+		}
+		let [mut a, mut b, mut c, mut d, mut e] = h;
+		for (i, &word) in w.iter().enumerate() {
+			let (f, k) = match i {
+				0 ..= 19 => ((b & c) | (!b & d), 0x5A827999),
+				20 ..= 39 => (b ^ c ^ d, 0x6ED9EBA1),
+				40 ..= 59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+				_ => (b ^ c ^ d, 0xCA62C1D6),
+			};
+			let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+			e = d;
+			d = c;
+			c = b.rotate_left(30);
+			b = a;
+			a = temp;
+		}
+		for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+			*h = h.wrapping_add(x);
+		}
+	}
+	let mut digest = [0_u8; 20];
+	for (i, word) in h.iter().enumerate() {
+		digest[i * 4 .. i * 4 + 4].copy_from_slice(&word.to_be_bytes());
+	}
+	digest
+}